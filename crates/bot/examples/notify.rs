@@ -0,0 +1,79 @@
+//! A notification bot, for example for CI: every line on stdin is sent to the subscribers.
+//!
+//! Contacts subscribe by sending `!subscribe` and unsubscribe with `!unsubscribe`.
+//!
+//! ```sh
+//! ./build.sh 2>&1 | cargo run -p sremp-bot --example notify -- 0.0.0.0:4000
+//! ```
+
+use std::{
+    io::BufRead,
+    sync::mpsc::{Receiver, TryRecvError},
+    time::Duration,
+};
+
+use sremp_bot::{Bot, BotRunner, Context};
+use sremp_client::domain::known_identities::SharedContact;
+use sremp_core::{
+    chat::messages::SharedMessage,
+    identity::{Trust, UserIdentity},
+};
+
+struct Notify {
+    lines: Receiver<String>,
+}
+
+impl Bot for Notify {
+    fn on_message(&mut self, ctx: &mut Context, from: &SharedContact, msg: &SharedMessage) {
+        match msg.text.trim() {
+            "!subscribe" => {
+                ctx.trust(from.id(), Trust::Trusted);
+                ctx.send(from.id(), "You will be notified");
+            }
+            "!unsubscribe" => {
+                ctx.trust(from.id(), Trust::Unknown);
+                ctx.send(from.id(), "You will not be notified anymore");
+            }
+            _ => ctx.send(from.id(), "Send !subscribe or !unsubscribe"),
+        }
+    }
+
+    fn on_tick(&mut self, ctx: &mut Context) {
+        loop {
+            match self.lines.try_recv() {
+                Ok(line) => ctx.broadcast(line),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    ctx.stop();
+                    break;
+                }
+            }
+        }
+    }
+}
+
+fn main() {
+    let addr = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "0.0.0.0:4000".to_string())
+        .parse()
+        .expect("the first argument must be a socket address to listen on");
+
+    let (tx, lines) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    let user = UserIdentity::create("notify").expect("could not create the identity");
+    BotRunner::new(Notify { lines }, user)
+        .expect("could not mark the identity as a machine account")
+        .listen(addr)
+        .every(Duration::from_secs(1))
+        .run()
+        .expect("the bot has failed");
+}
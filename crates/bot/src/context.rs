@@ -0,0 +1,127 @@
+use std::{fmt::Display, net::SocketAddr, sync::Arc};
+
+use async_channel::Sender;
+use sremp_client::{
+    domain::{UiCommand, known_identities::KnownIdentities, outbox::DeliveryState},
+    error::ClientResult,
+};
+use sremp_core::{
+    chat::messages::Message,
+    identity::{ContactId, Trust, UserIdentity},
+};
+
+/// What a [`Bot`](crate::Bot) knows and can do while it handles an event.
+///
+/// Commands are queued and sent to the [`ClientDomain`](sremp_client::domain::ClientDomain)
+/// after the handler has returned.
+#[derive(Debug)]
+pub struct Context {
+    user: Arc<UserIdentity>,
+    contacts: KnownIdentities,
+    queued: Vec<UiCommand>,
+    /// Messages of the bot that have not been sent or given up yet
+    unsent: usize,
+    stopped: bool,
+}
+
+impl Context {
+    pub(crate) fn new(user: Arc<UserIdentity>) -> Self {
+        Self {
+            user,
+            contacts: KnownIdentities::new(),
+            queued: Vec::new(),
+            unsent: 0,
+            stopped: false,
+        }
+    }
+
+    /// The identity of the bot
+    #[inline]
+    pub fn user(&self) -> &UserIdentity {
+        &self.user
+    }
+
+    #[inline]
+    pub fn id(&self) -> ContactId {
+        self.user.identity.id()
+    }
+
+    /// All contacts the bot has met, with the trust it has given them
+    #[inline]
+    pub fn contacts(&self) -> &KnownIdentities {
+        &self.contacts
+    }
+
+    pub(crate) fn set_contacts(&mut self, contacts: KnownIdentities) {
+        self.contacts = contacts;
+    }
+
+    /// Sends a chat message to the contact, it waits in the outbox if they are not connected.
+    pub fn send(&mut self, to: ContactId, text: impl Display) {
+        let msg = Message::new(text, chrono::Utc::now(), self.id());
+        self.unsent += 1;
+        self.command(UiCommand::SendMessage(to, msg.into()));
+    }
+
+    /// Sends a chat message to every contact the bot trusts, like a notification.
+    pub fn broadcast(&mut self, text: impl Display) {
+        let text = text.to_string();
+        let trusted: Vec<ContactId> = self
+            .contacts
+            .values()
+            .filter(|c| matches!(c.trust, Trust::Trusted | Trust::Verified))
+            .map(|c| c.id())
+            .collect();
+        for to in trusted {
+            self.send(to, &text);
+        }
+    }
+
+    pub fn trust(&mut self, cid: ContactId, trust: Trust) {
+        self.command(UiCommand::TrustContact(cid, trust));
+    }
+
+    pub fn connect(&mut self, addr: SocketAddr) {
+        self.command(UiCommand::Connect(addr));
+    }
+
+    pub fn disconnect(&mut self, addr: SocketAddr) {
+        self.command(UiCommand::Disconnect(addr));
+    }
+
+    /// Queues any other [`UiCommand`].
+    pub fn command(&mut self, command: UiCommand) {
+        self.queued.push(command);
+    }
+
+    /// Stops the bot once the current handler has returned.
+    pub fn stop(&mut self) {
+        self.stopped = true;
+    }
+
+    #[inline]
+    pub(crate) fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    #[inline]
+    pub(crate) fn unsent(&self) -> usize {
+        self.unsent
+    }
+
+    /// Keeps track of the messages of the bot that still wait in the outbox.
+    pub(crate) fn delivery_state_changed(&mut self, state: DeliveryState) {
+        if state != DeliveryState::Pending {
+            self.unsent = self.unsent.saturating_sub(1);
+        }
+    }
+
+    /// Sends the queued commands.
+    pub(crate) async fn flush(&mut self, commands: &Sender<UiCommand>) -> ClientResult<()> {
+        for command in self.queued.drain(..) {
+            log::debug!("Bot command: {command}");
+            commands.send(command).await?;
+        }
+        Ok(())
+    }
+}
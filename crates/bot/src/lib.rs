@@ -0,0 +1,75 @@
+//! Headless SDK for bots and other machine accounts on SREMP.
+//!
+//! A bot implements the [`Bot`] trait and is run by a [`BotRunner`], which starts the
+//! [`NetworkDomain`](sremp_core::domain::NetworkDomain) and the
+//! [`ClientDomain`](sremp_client::domain::ClientDomain) without any GUI and calls the bot for
+//! each event. The bot answers through the [`Context`], for example by replying to a message or
+//! trusting a contact. The identity of a bot is marked as a machine account, so the clients of
+//! humans can show that they are talking to a bot.
+//!
+//! ```no_run
+//! use sremp_bot::{Bot, BotRunner, Context};
+//! use sremp_client::domain::known_identities::SharedContact;
+//! use sremp_core::{chat::messages::SharedMessage, identity::UserIdentity};
+//!
+//! struct Echo;
+//!
+//! impl Bot for Echo {
+//!     fn on_message(&mut self, ctx: &mut Context, from: &SharedContact, msg: &SharedMessage) {
+//!         ctx.send(from.id(), &msg.text);
+//!     }
+//! }
+//!
+//! let user = UserIdentity::create("echo").unwrap();
+//! BotRunner::new(Echo, user)
+//!     .unwrap()
+//!     .listen("0.0.0.0:4000".parse().unwrap())
+//!     .run()
+//!     .unwrap();
+//! ```
+
+use std::net::SocketAddr;
+
+use sremp_client::domain::{UiEvent, known_identities::SharedContact, tofu::PeerStatus};
+use sremp_core::chat::messages::SharedMessage;
+
+mod context;
+pub use context::*;
+
+mod runner;
+pub use runner::*;
+
+/// The behaviour of a bot.
+///
+/// All methods are called one after another from the same task, the commands a method queues on
+/// the [`Context`] are sent once it returns. Methods should return quickly, longer work belongs
+/// in a task of its own that is checked in [`on_tick`](Self::on_tick).
+pub trait Bot {
+    /// Called once the identity of the bot is set, before any other event.
+    fn on_start(&mut self, _ctx: &mut Context) {}
+
+    /// A connection with `contact` is ready.
+    ///
+    /// By default, connections of rejected contacts are closed and everyone else is left
+    /// [`Trust::Unknown`](sremp_core::identity::Trust::Unknown).
+    fn on_connect(
+        &mut self,
+        ctx: &mut Context,
+        remote: SocketAddr,
+        _contact: &SharedContact,
+        status: &PeerStatus,
+    ) {
+        if *status == PeerStatus::Rejected {
+            ctx.disconnect(remote);
+        }
+    }
+
+    /// A chat message from `from` has arrived.
+    fn on_message(&mut self, ctx: &mut Context, from: &SharedContact, msg: &SharedMessage);
+
+    /// Called periodically if the [`BotRunner`] has a schedule, see [`BotRunner::every`].
+    fn on_tick(&mut self, _ctx: &mut Context) {}
+
+    /// Any other [`UiEvent`] of the [`ClientDomain`](sremp_client::domain::ClientDomain).
+    fn on_event(&mut self, _ctx: &mut Context, _event: &UiEvent) {}
+}
@@ -0,0 +1,194 @@
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use async_channel::Sender;
+use sremp_client::{
+    domain::{ClientDomain, UI_COMMAND_CAPACITY, UI_EVENT_BULK_CAPACITY, UiCommand, UiEvent},
+    error::ClientResult,
+};
+use sremp_core::{
+    current_function,
+    domain::{
+        NET_COMMAND_CAPACITY, NET_EVENT_BULK_CAPACITY, NetworkDomain,
+        priority::{PriorityReceiver, priority_channel},
+    },
+    error::CoreError,
+    identity::{Flags, UserIdentity},
+};
+use tokio::time::{Instant, Interval};
+
+use crate::{Bot, Context};
+
+/// How long a bot that has stopped waits for its last messages to be sent
+pub const STOP_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+/// Runs a [`Bot`] with its identity, the addresses it listens on and connects to, and its
+/// schedule.
+#[derive(Debug)]
+pub struct BotRunner<B: Bot> {
+    bot: B,
+    user: UserIdentity,
+    listen: Vec<SocketAddr>,
+    connect: Vec<SocketAddr>,
+    period: Option<Duration>,
+}
+
+impl<B: Bot> BotRunner<B> {
+    /// Creates a [`BotRunner`] for `bot`, which uses the identity `user`.
+    ///
+    /// The identity is marked as a machine account, see [`Flags::is_machine_account`].
+    pub fn new(bot: B, mut user: UserIdentity) -> ClientResult<Self> {
+        let flags = user.identity.flags();
+        if !flags.is_machine_account {
+            let mut key = user.identity_private_key().clone();
+            user.identity.set_flags(
+                Flags {
+                    is_machine_account: true,
+                    ..flags
+                },
+                &mut key,
+            )?;
+        }
+        Ok(Self {
+            bot,
+            user,
+            listen: Vec::new(),
+            connect: Vec::new(),
+            period: None,
+        })
+    }
+
+    /// Listens for peers on `addr` once the bot has started.
+    pub fn listen(mut self, addr: SocketAddr) -> Self {
+        self.listen.push(addr);
+        self
+    }
+
+    /// Connects to `addr` once the bot has started.
+    pub fn connect(mut self, addr: SocketAddr) -> Self {
+        self.connect.push(addr);
+        self
+    }
+
+    /// Calls [`Bot::on_tick`] every `period`, the first time one `period` after the start.
+    pub fn every(mut self, period: Duration) -> Self {
+        self.period = Some(period);
+        self
+    }
+
+    /// The identity of the bot, as marked by [`new`](Self::new)
+    #[inline]
+    pub fn user(&self) -> &UserIdentity {
+        &self.user
+    }
+
+    /// Starts the domains on a new runtime and runs the bot on it until it stops.
+    pub fn run(self) -> ClientResult<B> {
+        let mut rt = tokio::runtime::Runtime::new().map_err(CoreError::from)?;
+
+        let (net_command_tx, net_command_rx) = async_channel::bounded(NET_COMMAND_CAPACITY);
+        let (net_event_tx, net_event_rx) = priority_channel(NET_EVENT_BULK_CAPACITY);
+        let (ui_command_tx, ui_command_rx) = async_channel::bounded(UI_COMMAND_CAPACITY);
+        let (ui_event_tx, ui_event_rx) = priority_channel(UI_EVENT_BULK_CAPACITY);
+
+        NetworkDomain::new().start(net_command_rx, net_event_tx, &mut rt)?;
+        ClientDomain::new().start(
+            net_command_tx,
+            net_event_rx,
+            ui_command_rx,
+            ui_event_tx,
+            &mut rt,
+        )?;
+
+        rt.block_on(self.drive(ui_command_tx, ui_event_rx))
+    }
+
+    /// Runs the bot against a [`ClientDomain`] that was started elsewhere, until it stops.
+    ///
+    /// Once the bot has stopped, messages that are still unsent get up to [`STOP_GRACE_PERIOD`]
+    /// to leave. Returns the bot, so its state can be inspected afterwards.
+    pub async fn drive(
+        mut self,
+        commands: Sender<UiCommand>,
+        events: PriorityReceiver<UiEvent>,
+    ) -> ClientResult<B> {
+        log::trace!("{}", current_function!());
+        let user = Arc::new(self.user);
+        commands
+            .send(UiCommand::SetIdentity(Some(user.clone())))
+            .await?;
+        for addr in self.listen {
+            commands.send(UiCommand::StartListener(addr)).await?;
+        }
+        for addr in self.connect {
+            commands.send(UiCommand::Connect(addr)).await?;
+        }
+        log::info!("Bot {} has started", user.identity.username());
+
+        let mut ctx = Context::new(user);
+        self.bot.on_start(&mut ctx);
+        ctx.flush(&commands).await?;
+
+        let mut schedule = self
+            .period
+            .map(|period| tokio::time::interval_at(Instant::now() + period, period));
+        while !ctx.is_stopped() {
+            tokio::select! {
+                evt = events.recv() => {
+                    let evt = evt.map_err(CoreError::from)?;
+                    dispatch(&mut self.bot, &mut ctx, evt);
+                }
+                _ = tick(&mut schedule) => self.bot.on_tick(&mut ctx),
+            }
+            ctx.flush(&commands).await?;
+        }
+
+        let flushed = tokio::time::timeout(STOP_GRACE_PERIOD, async {
+            while ctx.unsent() > 0 {
+                let evt = events.recv().await.map_err(CoreError::from)?;
+                if let UiEvent::DeliveryStateChanged(_, _, state) = evt {
+                    ctx.delivery_state_changed(state);
+                }
+            }
+            ClientResult::Ok(())
+        })
+        .await;
+        match flushed {
+            Ok(result) => result?,
+            Err(_) => log::warn!("Bot has stopped with {} unsent messages", ctx.unsent()),
+        }
+        log::info!("Bot {} has stopped", ctx.user().identity.username());
+        Ok(self.bot)
+    }
+}
+
+/// Waits for the next tick of the schedule, forever if there is none.
+async fn tick(schedule: &mut Option<Interval>) {
+    match schedule {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Calls the handler of the bot that fits the event.
+fn dispatch(bot: &mut impl Bot, ctx: &mut Context, event: UiEvent) {
+    match event {
+        UiEvent::SetKnownIdentities(contacts) => ctx.set_contacts(contacts),
+        UiEvent::DeliveryStateChanged(cid, msg, state) => {
+            ctx.delivery_state_changed(state);
+            bot.on_event(ctx, &UiEvent::DeliveryStateChanged(cid, msg, state));
+        }
+        UiEvent::ConnectionEstablished(remote, cid, status) => {
+            match ctx.contacts().get(&cid).cloned() {
+                Some(contact) => bot.on_connect(ctx, remote, &contact, &status),
+                None => log::warn!("Connection established with unknown contact {cid}"),
+            }
+        }
+        UiEvent::IncomingMessage(_remote, cid, msg) => match ctx.contacts().get(&cid).cloned() {
+            Some(contact) => bot.on_message(ctx, &contact, &msg),
+            None => log::warn!("Message from unknown contact {cid}"),
+        },
+        other => bot.on_event(ctx, &other),
+    }
+}
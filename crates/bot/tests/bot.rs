@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+use sremp_bot::{Bot, BotRunner, Context};
+use sremp_client::domain::{UiCommand, UiEvent, known_identities::SharedContact};
+use sremp_core::{
+    chat::messages::SharedMessage,
+    identity::{Trust, UserIdentity},
+};
+use sremp_sim::Simulation;
+
+const BOT: [u8; 4] = [10, 0, 0, 1];
+const ALICE: [u8; 4] = [10, 0, 0, 2];
+
+/// Trusts whoever says hello, echoes everything and stops after three ticks
+#[derive(Default)]
+struct Echo {
+    ticks: u32,
+}
+
+impl Bot for Echo {
+    fn on_message(&mut self, ctx: &mut Context, from: &SharedContact, msg: &SharedMessage) {
+        if msg.text == "hello" {
+            ctx.trust(from.id(), Trust::Trusted);
+        }
+        ctx.send(from.id(), format!("echo: {}", msg.text));
+    }
+
+    fn on_tick(&mut self, ctx: &mut Context) {
+        self.ticks += 1;
+        ctx.broadcast(format!("tick {}", self.ticks));
+        if self.ticks == 3 {
+            ctx.stop();
+        }
+    }
+}
+
+#[test]
+fn bot_replies_runs_on_schedule_and_is_a_machine_account() {
+    let mut sim = Simulation::new(1);
+    let (commands, events) = sim.spawn_client(BOT);
+    let runner = BotRunner::new(Echo::default(), UserIdentity::create("echo").unwrap())
+        .unwrap()
+        .listen((BOT, 4000).into())
+        .every(Duration::from_secs(10));
+    let bot_id = runner.user().identity.id();
+    let alice = sim.spawn_node(ALICE, "alice");
+
+    let bot = sim.run(async {
+        let bot = tokio::spawn(runner.drive(commands, events));
+        // give the bot a moment to start listening
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        alice.command(UiCommand::Connect((BOT, 4000).into())).await;
+        alice.connected_to(&bot_id).await;
+        let known = alice.history().into_iter().rev().find_map(|e| match e {
+            UiEvent::SetKnownIdentities(known) => known.get(&bot_id).cloned(),
+            _ => None,
+        });
+        assert!(known.unwrap().flags().is_machine_account);
+
+        alice
+            .command(UiCommand::SendMessage(
+                bot_id.clone(),
+                alice.message("hello"),
+            ))
+            .await;
+        let mut texts = Vec::new();
+        while texts.len() < 4 {
+            texts.push(
+                alice
+                    .wait_for(|e| match e {
+                        UiEvent::IncomingMessage(_, _, msg) => Some(msg.text.clone()),
+                        _ => None,
+                    })
+                    .await,
+            );
+        }
+        assert_eq!(texts, ["echo: hello", "tick 1", "tick 2", "tick 3"]);
+        bot.await.unwrap().unwrap()
+    });
+    assert_eq!(bot.ticks, 3);
+}
@@ -0,0 +1,753 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::Display,
+    net::SocketAddr,
+    path::PathBuf,
+    sync::Arc,
+};
+
+use async_channel::Sender;
+use sremp_client::domain::{
+    UiCommand, UiEvent, chats::Chats, known_identities::KnownIdentities, outbox::DeliveryState,
+    tofu::PeerStatus,
+};
+use sremp_core::{
+    chat::messages::{Message, SharedMessage},
+    domain::priority::PriorityReceiver,
+    identity::{ContactId, IdentityStatement, RecoveryPhrase, Trust, UserIdentity},
+};
+
+use crate::command::{Command, HELP};
+
+/// How many notices are kept for the full-screen mode
+const MAX_NOTICES: usize = 500;
+
+/// An action that waits for the next line the user enters
+#[derive(Debug, Clone)]
+pub(crate) enum Prompt {
+    ExportPassphrase(PathBuf),
+    ImportPassphrase(PathBuf),
+    /// The recovery phrase of an identity to restore with this username
+    RecoveryPhrase(String),
+    /// The passphrase for the identity of a new device with this name
+    DevicePassphrase(String, PathBuf),
+    /// Whether to revoke the identity for this reason
+    ConfirmRevocation(String),
+}
+
+/// The state of the terminal client, shared by the REPL and the full-screen mode.
+///
+/// Like the UI domain of `sremp-gtk`, it only mirrors what the
+/// [`ClientDomain`](sremp_client::domain::ClientDomain) tells it and sends [`UiCommand`]s.
+#[derive(Debug)]
+pub(crate) struct App {
+    commands: Sender<UiCommand>,
+    events: PriorityReceiver<UiEvent>,
+    user: Option<Arc<UserIdentity>>,
+    contacts: KnownIdentities,
+    chats: Chats,
+    connections: HashMap<ContactId, HashSet<SocketAddr>>,
+    selected: Option<ContactId>,
+    listener: Option<SocketAddr>,
+    /// What the client has told the user, the newest last
+    notices: VecDeque<String>,
+    /// Number of notices at the end of `notices` that were not taken yet
+    unread: usize,
+    prompt: Option<Prompt>,
+    quit: bool,
+}
+
+impl App {
+    pub(crate) fn new(commands: Sender<UiCommand>, events: PriorityReceiver<UiEvent>) -> Self {
+        Self {
+            commands,
+            events,
+            user: None,
+            contacts: KnownIdentities::new(),
+            chats: Chats::new(),
+            connections: HashMap::new(),
+            selected: None,
+            listener: None,
+            notices: VecDeque::new(),
+            unread: 0,
+            prompt: None,
+            quit: false,
+        }
+    }
+
+    /// Waits for the next event of the client domain, [`None`] if it has stopped.
+    pub(crate) async fn next_event(&self) -> Option<UiEvent> {
+        self.events.recv().await.ok()
+    }
+
+    #[inline]
+    pub(crate) fn user(&self) -> Option<&UserIdentity> {
+        self.user.as_deref()
+    }
+
+    #[inline]
+    pub(crate) fn listener(&self) -> Option<SocketAddr> {
+        self.listener
+    }
+
+    #[inline]
+    pub(crate) fn selected(&self) -> Option<&ContactId> {
+        self.selected.as_ref()
+    }
+
+    /// Whether the next line is a secret, like a passphrase, that should not be shown
+    #[inline]
+    pub(crate) fn wants_secret(&self) -> bool {
+        self.prompt.is_some()
+    }
+
+    #[inline]
+    pub(crate) fn should_quit(&self) -> bool {
+        self.quit
+    }
+
+    pub(crate) fn notices(&self) -> std::collections::vec_deque::Iter<'_, String> {
+        self.notices.iter()
+    }
+
+    /// The notices that were added since the last call
+    pub(crate) fn take_unread(&mut self) -> Vec<String> {
+        let unread = self
+            .notices
+            .iter()
+            .skip(self.notices.len() - self.unread)
+            .cloned()
+            .collect();
+        self.unread = 0;
+        unread
+    }
+
+    fn notice(&mut self, text: impl Display) {
+        if self.notices.len() == MAX_NOTICES {
+            self.notices.pop_front();
+        }
+        self.notices.push_back(printable(&text.to_string()));
+        self.unread = (self.unread + 1).min(self.notices.len());
+    }
+
+    async fn send_cmd(&mut self, command: UiCommand) {
+        if let Err(e) = self.commands.send(command).await {
+            log::error!("Could not send a command to the client domain: {e}");
+            self.notice("The client has stopped, please restart it");
+            self.quit = true;
+        }
+    }
+
+    /// Contacts other than the user, sorted by username. Their position, starting at 1, is the
+    /// number the user can refer to them by.
+    pub(crate) fn contact_list(&self) -> Vec<ContactId> {
+        let own = self.user.as_ref().map(|u| u.identity.id());
+        let mut list: Vec<_> = self
+            .contacts
+            .values()
+            .filter(|c| Some(c.id()) != own)
+            .collect();
+        list.sort_by(|a, b| a.username().cmp(b.username()).then(a.id().cmp(&b.id())));
+        list.into_iter().map(|c| c.id()).collect()
+    }
+
+    /// Contacts with a chat, in the order of [`contact_list`](Self::contact_list), with their
+    /// number
+    pub(crate) fn chat_list(&self) -> Vec<(usize, ContactId)> {
+        self.contact_list()
+            .into_iter()
+            .enumerate()
+            .filter(|(_, cid)| self.chats.contains_key(cid))
+            .map(|(i, cid)| (i + 1, cid))
+            .collect()
+    }
+
+    pub(crate) fn contact_name(&self, cid: &ContactId) -> String {
+        if self.user.as_ref().is_some_and(|u| u.identity.id() == *cid) {
+            return "you".to_string();
+        }
+        match self.contacts.get(cid) {
+            Some(contact) if contact.flags().is_machine_account => {
+                format!("{} [bot]", contact.username())
+            }
+            Some(contact) => contact.username().to_string(),
+            None => cid.to_string().chars().take(8).collect(),
+        }
+    }
+
+    pub(crate) fn is_connected(&self, cid: &ContactId) -> bool {
+        self.connections.get(cid).is_some_and(|c| !c.is_empty())
+    }
+
+    /// Finds a contact by its number, its username or the start of its identity.
+    fn find_contact(&self, reference: &str) -> Result<ContactId, String> {
+        let list = self.contact_list();
+        if let Ok(number) = reference.parse::<usize>() {
+            return number
+                .checked_sub(1)
+                .and_then(|i| list.get(i))
+                .cloned()
+                .ok_or_else(|| format!("There is no contact number {number}, see /contacts"));
+        }
+        let by_name: Vec<_> = list
+            .iter()
+            .filter(|cid| self.contacts[*cid].username() == reference)
+            .collect();
+        if let [cid] = by_name[..] {
+            return Ok(cid.clone());
+        }
+        let prefix = reference.to_uppercase();
+        let by_id: Vec<_> = list
+            .iter()
+            .filter(|cid| prefix.len() >= 4 && cid.to_string().starts_with(&prefix))
+            .collect();
+        match (by_name.len(), &by_id[..]) {
+            (_, [cid]) => Ok((*cid).clone()),
+            (0, []) => Err(format!("No contact is called {reference}, see /contacts")),
+            _ => Err(format!(
+                "{reference} is ambiguous, use the number from /contacts"
+            )),
+        }
+    }
+
+    /// The latest `count` messages of the current chat, oldest first
+    pub(crate) fn current_messages(&self, count: usize) -> &[SharedMessage] {
+        let Some(chat) = self.selected.as_ref().and_then(|cid| self.chats.get(cid)) else {
+            return &[];
+        };
+        let messages = chat.messages();
+        &messages[messages.len().saturating_sub(count)..]
+    }
+
+    pub(crate) fn format_message(&self, msg: &Message) -> String {
+        format!(
+            "[{}] {}: {}",
+            msg.meta
+                .time_received
+                .with_timezone(&chrono::Local)
+                .format("%H:%M"),
+            self.contact_name(&msg.meta.author_id),
+            msg.text
+        )
+    }
+
+    /// Selects the chat `offset` places after the current one in the chat list.
+    pub(crate) fn select_relative(&mut self, offset: isize) {
+        let chats = self.chat_list();
+        if chats.is_empty() {
+            return;
+        }
+        let current = self
+            .selected
+            .as_ref()
+            .and_then(|selected| chats.iter().position(|(_, cid)| cid == selected));
+        let len = isize::try_from(chats.len()).unwrap_or(isize::MAX);
+        let next = match current {
+            Some(i) => (isize::try_from(i).unwrap_or(0) + offset).rem_euclid(len),
+            None if offset < 0 => len - 1,
+            None => 0,
+        };
+        self.selected = usize::try_from(next)
+            .ok()
+            .and_then(|i| chats.get(i))
+            .map(|(_, cid)| cid.clone());
+    }
+
+    pub(crate) async fn handle_event(&mut self, event: UiEvent) {
+        match event {
+            UiEvent::IdentitySet(user) => {
+                match &user {
+                    Some(user) => self.notice(format!(
+                        "Your identity is {} ({})",
+                        user.identity.username(),
+                        user.identity.id()
+                    )),
+                    None => self.notice("You have no identity"),
+                }
+                self.user = user;
+            }
+            UiEvent::SetKnownIdentities(contacts) => self.contacts = contacts,
+            UiEvent::LoadedChats(chats) => self.chats = chats,
+            UiEvent::ListenerStarted(addr) => {
+                self.listener = Some(addr);
+                self.notice(format!("Listening on {addr}"));
+            }
+            UiEvent::ListenerStopped => {
+                self.listener = None;
+                self.notice("Stopped listening");
+            }
+            UiEvent::ConnectionEstablished(remote, cid, status) => {
+                self.connections
+                    .entry(cid.clone())
+                    .or_default()
+                    .insert(remote);
+                self.peer_connected(remote, cid, status).await;
+            }
+            UiEvent::ConnectionLost(remote, cid) => {
+                if let Some(remotes) = self.connections.get_mut(&cid) {
+                    remotes.remove(&remote);
+                }
+                self.notice(format!(
+                    "{} ({remote}) has disconnected",
+                    self.contact_name(&cid)
+                ));
+            }
+            UiEvent::ConnectionFailed(remote, reason) => {
+                self.notice(format!("Could not connect to {remote}: {reason}"))
+            }
+            UiEvent::IncomingMessage(_remote, cid, msg) => {
+                self.chats
+                    .entry(cid.clone())
+                    .or_default()
+                    .add_message(msg.clone());
+                self.notice(self.format_message(&msg));
+            }
+            UiEvent::DeliveryStateChanged(cid, msg, state) => {
+                if state == DeliveryState::Failed {
+                    self.notice(format!(
+                        "Could not deliver \"{}\" to {}, open the chat and use /retry",
+                        msg.text,
+                        self.contact_name(&cid)
+                    ))
+                }
+            }
+            UiEvent::ContactTyping(..) | UiEvent::ConnectionStats(_) => (),
+            other => self.notice(other),
+        }
+    }
+
+    async fn peer_connected(&mut self, remote: SocketAddr, cid: ContactId, status: PeerStatus) {
+        let name = self.contact_name(&cid);
+        let number = self
+            .contact_list()
+            .iter()
+            .position(|c| *c == cid)
+            .map_or(0, |i| i + 1);
+        match status {
+            PeerStatus::Trusted => {
+                self.notice(format!("{name} ({remote}) has connected"));
+                self.send_cmd(UiCommand::StartChat(cid)).await;
+            }
+            PeerStatus::Rejected => {
+                self.notice(format!(
+                    "Closing the connection of rejected {name} ({remote})"
+                ));
+                self.send_cmd(UiCommand::Disconnect(remote)).await;
+            }
+            PeerStatus::New => self.notice(format!(
+                "New peer {name} ({cid}) has connected from {remote}. \
+                Decide with /trust {number} trusted or /trust {number} rejected"
+            )),
+            PeerStatus::KeyChanged {
+                address_of,
+                name_of,
+            } => {
+                self.notice(format!(
+                    "WARNING: POSSIBLE IMPERSONATION by {name} ({cid}) from {remote}"
+                ));
+                if let Some(other) = address_of {
+                    self.notice(format!(
+                        "  A different identity answered on this address before: {} ({other})",
+                        self.contact_name(&other)
+                    ));
+                }
+                for other in name_of {
+                    self.notice(format!(
+                        "  The name looks like that of {} ({other})",
+                        self.contact_name(&other)
+                    ));
+                }
+                self.notice(format!(
+                    "  Only /trust {number} trusted if you have confirmed it another way, \
+                    otherwise /trust {number} rejected"
+                ));
+            }
+        }
+    }
+
+    /// Handles a line the user has entered.
+    pub(crate) async fn handle_line(&mut self, line: String) {
+        if let Some(prompt) = self.prompt.take() {
+            self.answer_prompt(prompt, line.trim_end_matches(['\r', '\n']))
+                .await;
+            return;
+        }
+        if line.trim().is_empty() {
+            return;
+        }
+        match line.parse::<Command>() {
+            Ok(command) => self.execute(command).await,
+            Err(e) => self.notice(e),
+        }
+    }
+
+    async fn answer_prompt(&mut self, prompt: Prompt, answer: &str) {
+        match prompt {
+            Prompt::ExportPassphrase(path) => {
+                let Some(user) = &self.user else {
+                    self.notice("You have no identity to export");
+                    return;
+                };
+                let result = user
+                    .export(answer)
+                    .map_err(|e| e.to_string())
+                    .and_then(|data| std::fs::write(&path, data).map_err(|e| e.to_string()));
+                match result {
+                    Ok(()) => self.notice(format!("Saved your identity to {}", path.display())),
+                    Err(e) => self.notice(format!("Could not export your identity: {e}")),
+                }
+            }
+            Prompt::ImportPassphrase(path) => {
+                let result = std::fs::read(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|data| {
+                        UserIdentity::import(&data, answer).map_err(|e| e.to_string())
+                    });
+                match result {
+                    Ok(user) => {
+                        self.notice(format!("Loaded the identity from {}", path.display()));
+                        self.send_cmd(UiCommand::SetIdentity(Some(Arc::new(user))))
+                            .await;
+                    }
+                    Err(e) => self.notice(format!("Could not import the identity: {e}")),
+                }
+            }
+            Prompt::RecoveryPhrase(username) => {
+                let result = answer
+                    .parse::<RecoveryPhrase>()
+                    .and_then(|phrase| UserIdentity::recover(&phrase, &username));
+                match result {
+                    Ok(user) => {
+                        self.notice(format!(
+                            "Restored your identity {}, devices have to be added again",
+                            user.id()
+                        ));
+                        self.send_cmd(UiCommand::SetIdentity(Some(Arc::new(user))))
+                            .await;
+                    }
+                    Err(e) => self.notice(format!("Could not restore the identity: {e}")),
+                }
+            }
+            Prompt::DevicePassphrase(name, path) => {
+                let Some(user) = &self.user else {
+                    self.notice("You have no identity to add a device to");
+                    return;
+                };
+                let mut updated = (**user).clone();
+                let result = updated
+                    .new_device(&name)
+                    .and_then(|device| device.export(answer))
+                    .map_err(|e| e.to_string())
+                    .and_then(|data| std::fs::write(&path, data).map_err(|e| e.to_string()));
+                match result {
+                    Ok(()) => {
+                        self.notice(format!(
+                            "Saved the identity of {name} to {}, load it there with \
+                            /identity import",
+                            path.display()
+                        ));
+                        self.send_cmd(UiCommand::SetIdentity(Some(Arc::new(updated))))
+                            .await;
+                    }
+                    Err(e) => self.notice(format!("Could not add the device: {e}")),
+                }
+            }
+            Prompt::ConfirmRevocation(reason) => {
+                if answer.trim() != "yes" {
+                    self.notice("Your identity was not revoked");
+                    return;
+                }
+                let Some(user) = &self.user else {
+                    self.notice("You have no identity to revoke");
+                    return;
+                };
+                match user.revoke(&reason) {
+                    Ok(revocation) => {
+                        self.notice(
+                            "Revoked your identity, create a new one with /identity create",
+                        );
+                        self.send_cmd(UiCommand::PublishStatement(Arc::new(
+                            IdentityStatement::Revocation(revocation),
+                        )))
+                        .await;
+                    }
+                    Err(e) => self.notice(format!("Could not revoke your identity: {e}")),
+                }
+            }
+        }
+    }
+
+    async fn execute(&mut self, command: Command) {
+        match command {
+            Command::Help => {
+                for line in HELP.lines() {
+                    self.notice(line);
+                }
+            }
+            Command::Quit => self.quit = true,
+            Command::CreateIdentity(username) => match UserIdentity::create(&username) {
+                Ok(user) => {
+                    self.send_cmd(UiCommand::SetIdentity(Some(Arc::new(user))))
+                        .await
+                }
+                Err(e) => self.notice(format!("Could not create the identity: {e}")),
+            },
+            Command::CreateRecoverableIdentity(username) => {
+                match UserIdentity::create_recoverable(&username) {
+                    Ok((user, phrase)) => {
+                        self.notice(
+                            "Write down these words and keep them safe, they restore your \
+                            identity and are not shown again:",
+                        );
+                        self.notice(format!("  {phrase}"));
+                        self.send_cmd(UiCommand::SetIdentity(Some(Arc::new(user))))
+                            .await
+                    }
+                    Err(e) => self.notice(format!("Could not create the identity: {e}")),
+                }
+            }
+            Command::RecoverIdentity(username) => {
+                self.notice("Enter the 24 words of your recovery phrase:");
+                self.prompt = Some(Prompt::RecoveryPhrase(username));
+            }
+            Command::ShowIdentity => match &self.user {
+                Some(user) => {
+                    let text = format!(
+                        "You are {} ({}), {}",
+                        user.identity.username(),
+                        user.identity.id(),
+                        match self.listener {
+                            Some(addr) => format!("listening on {addr}"),
+                            None => "not listening".to_string(),
+                        }
+                    );
+                    self.notice(text);
+                }
+                None => self.notice("You have no identity yet, use /identity create <username>"),
+            },
+            Command::SucceedIdentity => {
+                let Some(user) = &self.user else {
+                    self.notice("You have no identity to replace");
+                    return;
+                };
+                match user.create_successor() {
+                    Ok((successor, succession)) => {
+                        self.notice(format!(
+                            "Your new identity is {}, contacts follow it once they learn of it",
+                            successor.id()
+                        ));
+                        // contacts must learn of the succession before the successor connects
+                        self.send_cmd(UiCommand::PublishStatement(Arc::new(
+                            IdentityStatement::Succession(succession),
+                        )))
+                        .await;
+                        self.send_cmd(UiCommand::SetIdentity(Some(Arc::new(successor))))
+                            .await;
+                    }
+                    Err(e) => self.notice(format!("Could not create the new identity: {e}")),
+                }
+            }
+            Command::RevokeIdentity(reason) => {
+                if self.user.is_none() {
+                    self.notice("You have no identity to revoke");
+                    return;
+                }
+                self.notice("Contacts will refuse your identity for good, type yes to revoke it:");
+                self.prompt = Some(Prompt::ConfirmRevocation(reason));
+            }
+            Command::ExportIdentity(path) => {
+                if self.user.is_none() {
+                    self.notice("You have no identity to export");
+                    return;
+                }
+                self.notice("Enter a passphrase to encrypt the identity with:");
+                self.prompt = Some(Prompt::ExportPassphrase(path));
+            }
+            Command::ImportIdentity(path) => {
+                self.notice("Enter the passphrase of the identity:");
+                self.prompt = Some(Prompt::ImportPassphrase(path));
+            }
+            Command::Devices => match &self.user {
+                Some(user) if user.identity.devices().is_empty() => {
+                    self.notice("Your identity has no other devices, add one with /device add")
+                }
+                Some(user) => {
+                    let devices: Vec<String> = user
+                        .identity
+                        .devices()
+                        .iter()
+                        .map(|d| format!("  {} (added {})", d.name(), d.created()))
+                        .collect();
+                    self.notice("The other devices of your identity:");
+                    for device in devices {
+                        self.notice(device);
+                    }
+                }
+                None => self.notice("You have no identity yet, use /identity create <username>"),
+            },
+            Command::AddDevice(name, path) => {
+                let Some(user) = &self.user else {
+                    self.notice("You have no identity to add a device to");
+                    return;
+                };
+                if user.identity.devices().iter().any(|d| d.name() == name) {
+                    self.notice(format!("There is a device named {name} already"));
+                    return;
+                }
+                self.notice("Enter a passphrase to encrypt the identity of the device with:");
+                self.prompt = Some(Prompt::DevicePassphrase(name, path));
+            }
+            Command::RemoveDevice(name) => {
+                let Some(user) = &self.user else {
+                    self.notice("You have no identity to remove a device from");
+                    return;
+                };
+                let Some(device) = user.identity.devices().iter().find(|d| d.name() == name) else {
+                    self.notice(format!("There is no device named {name}, see /device list"));
+                    return;
+                };
+                let mut updated = (**user).clone();
+                let mut key = updated.identity_key.clone();
+                match updated
+                    .identity
+                    .remove_device(&device.noise_key(), &mut key)
+                {
+                    Ok(_) => {
+                        self.notice(format!(
+                            "Removed {name}, contacts refuse it once they have the new version"
+                        ));
+                        self.send_cmd(UiCommand::SetIdentity(Some(Arc::new(updated))))
+                            .await;
+                    }
+                    Err(e) => self.notice(format!("Could not remove the device: {e}")),
+                }
+            }
+            Command::Listen(addr) => self.send_cmd(UiCommand::StartListener(addr)).await,
+            Command::StopListening => self.send_cmd(UiCommand::StopListener).await,
+            Command::Connect(addr) => {
+                if self.user.is_none() {
+                    self.notice("Create an identity first, with /identity create <username>");
+                    return;
+                }
+                self.notice(format!("Connecting to {addr}"));
+                self.send_cmd(UiCommand::Connect(addr)).await;
+            }
+            Command::Disconnect(addr) => self.send_cmd(UiCommand::Disconnect(addr)).await,
+            Command::Contacts => {
+                let list = self.contact_list();
+                if list.is_empty() {
+                    self.notice("You do not know anyone yet");
+                }
+                for (i, cid) in list.iter().enumerate() {
+                    let text = format!(
+                        "{:>3}. {} ({cid}) {}{}",
+                        i + 1,
+                        self.contact_name(cid),
+                        self.contacts[cid].trust,
+                        if self.is_connected(cid) {
+                            ", connected"
+                        } else {
+                            ""
+                        }
+                    );
+                    self.notice(text);
+                }
+            }
+            Command::Trust(reference, trust) => {
+                let cid = match self.find_contact(&reference) {
+                    Ok(cid) => cid,
+                    Err(e) => return self.notice(e),
+                };
+                self.send_cmd(UiCommand::TrustContact(cid.clone(), trust))
+                    .await;
+                self.notice(format!("{} is now {trust}", self.contact_name(&cid)));
+                match trust {
+                    Trust::Trusted => {
+                        self.send_cmd(UiCommand::StartChat(cid.clone())).await;
+                        self.selected = Some(cid);
+                    }
+                    Trust::Rejected => {
+                        for remote in self.connections.remove(&cid).unwrap_or_default() {
+                            self.send_cmd(UiCommand::Disconnect(remote)).await;
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            Command::Chats => {
+                let list = self.chat_list();
+                if list.is_empty() {
+                    self.notice("There are no chats yet, use /open <contact>");
+                }
+                for (number, cid) in list {
+                    let text = format!(
+                        "{}{number:>3}. {} ({} messages)",
+                        if self.selected.as_ref() == Some(&cid) {
+                            ">"
+                        } else {
+                            " "
+                        },
+                        self.contact_name(&cid),
+                        self.chats[&cid].messages().len()
+                    );
+                    self.notice(text);
+                }
+            }
+            Command::Open(reference) => match self.find_contact(&reference) {
+                Ok(cid) => {
+                    self.notice(format!("Chatting with {}", self.contact_name(&cid)));
+                    self.send_cmd(UiCommand::StartChat(cid.clone())).await;
+                    self.selected = Some(cid);
+                }
+                Err(e) => self.notice(e),
+            },
+            Command::Read(count) => {
+                if self.selected.is_none() {
+                    return self.notice("Open a chat first, with /open <contact>");
+                }
+                let lines: Vec<String> = self
+                    .current_messages(count)
+                    .iter()
+                    .map(|msg| self.format_message(msg))
+                    .collect();
+                if lines.is_empty() {
+                    self.notice("There are no messages yet");
+                }
+                for line in lines {
+                    self.notice(line);
+                }
+            }
+            Command::Retry => match self.selected.clone() {
+                Some(cid) => self.send_cmd(UiCommand::RetryMessages(cid)).await,
+                None => self.notice("Open a chat first, with /open <contact>"),
+            },
+            Command::Send(text) => {
+                let (Some(user), Some(cid)) = (&self.user, &self.selected) else {
+                    return self.notice(
+                        "Create an identity and open a chat first, see /help. \
+                        Commands start with /",
+                    );
+                };
+                let msg: SharedMessage =
+                    Message::new(text, chrono::Utc::now(), user.identity.id()).into();
+                let cid = cid.clone();
+                self.chats
+                    .entry(cid.clone())
+                    .or_default()
+                    .add_message(msg.clone());
+                self.send_cmd(UiCommand::SendMessage(cid, msg)).await;
+            }
+        }
+    }
+}
+
+/// Replaces control characters, so that a peer can not send escape sequences to the terminal.
+pub(crate) fn printable(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\n' | '\t' => ' ',
+            c if c.is_control() => char::REPLACEMENT_CHARACTER,
+            c => c,
+        })
+        .collect()
+}
@@ -0,0 +1,274 @@
+use std::{net::SocketAddr, path::PathBuf, str::FromStr};
+
+use sremp_core::identity::Trust;
+
+/// Something the user has typed: a command that starts with `/`, or a message to the current
+/// chat. A message that starts with `/` is written as `//`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Command {
+    Help,
+    Quit,
+    CreateIdentity(String),
+    /// Creates an identity that can be restored from a recovery phrase
+    CreateRecoverableIdentity(String),
+    /// Restores an identity from its recovery phrase, the phrase is asked for next
+    RecoverIdentity(String),
+    ShowIdentity,
+    /// Replaces the identity with a new one and tells the contacts
+    SucceedIdentity,
+    /// Revokes the identity for this reason, after the user has confirmed it
+    RevokeIdentity(String),
+    ExportIdentity(PathBuf),
+    ImportIdentity(PathBuf),
+    Devices,
+    /// Certifies a new device and saves its identity to the file, encrypted with a passphrase
+    AddDevice(String, PathBuf),
+    RemoveDevice(String),
+    Listen(SocketAddr),
+    StopListening,
+    Connect(SocketAddr),
+    Disconnect(SocketAddr),
+    Contacts,
+    Trust(String, Trust),
+    Chats,
+    Open(String),
+    Read(usize),
+    Retry,
+    Send(String),
+}
+
+/// Number of messages `/read` shows if no count is given
+const DEFAULT_READ_COUNT: usize = 20;
+
+pub(crate) const HELP: &str = "Commands:
+  /identity create <username>   create a new identity
+  /identity recoverable <username>
+                                create a new identity that can be restored from 24 words
+  /identity recover <username>  restore an identity from its 24 words
+  /identity show                show your identity
+  /identity succeed             replace your identity with a new one, contacts follow it
+  /identity revoke <reason>     tell contacts to stop trusting your identity, for good
+  /identity export <file>       save your identity, encrypted with a passphrase
+  /identity import <file>       load an identity saved with /identity export
+  /device list                  list the other devices of your identity
+  /device add <name> <file>     add a device, load the file on it with /identity import
+  /device remove <name>         remove a device, contacts stop accepting it
+  /listen <address>             accept connections, like /listen 0.0.0.0:4000
+  /unlisten                     stop accepting connections
+  /connect <address>            connect to a peer
+  /disconnect <address>         close a connection
+  /contacts                     list known contacts and their trust
+  /trust <contact> <level>      trust a contact: trusted, rejected or unknown
+  /chats                        list chats
+  /open <contact>               open the chat with a contact
+  /read [count]                 show the latest messages of the current chat
+  /retry                        send failed messages of the current chat again
+  /send <text>                  send a message, same as typing text without /
+  /quit                         exit
+A <contact> is a number from /contacts or /chats, a username or the start of an identity.";
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        if let Some(text) = line.strip_prefix("//") {
+            return Ok(Self::Send(format!("/{text}")));
+        }
+        let Some(command) = line.strip_prefix('/') else {
+            return Ok(Self::Send(line.to_string()));
+        };
+        let (name, rest) = command
+            .trim()
+            .split_once(char::is_whitespace)
+            .unwrap_or((command.trim(), ""));
+        let rest = rest.trim();
+        let addr = |rest: &str| {
+            rest.parse::<SocketAddr>()
+                .map_err(|e| format!("\"{rest}\" is not an address like 10.0.0.1:4000: {e}"))
+        };
+        let required = |what: &str| {
+            if rest.is_empty() {
+                Err(format!("/{name} needs {what}, see /help"))
+            } else {
+                Ok(rest.to_string())
+            }
+        };
+        Ok(match name {
+            "help" | "h" | "?" => Self::Help,
+            "quit" | "q" | "exit" => Self::Quit,
+            "identity" | "id" => {
+                let (sub, arg) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                let arg = arg.trim();
+                match (sub, arg.is_empty()) {
+                    ("show" | "", _) => Self::ShowIdentity,
+                    ("create", false) => Self::CreateIdentity(arg.to_string()),
+                    ("recoverable", false) => Self::CreateRecoverableIdentity(arg.to_string()),
+                    ("recover", false) => Self::RecoverIdentity(arg.to_string()),
+                    ("succeed", _) => Self::SucceedIdentity,
+                    ("revoke", false) => Self::RevokeIdentity(arg.to_string()),
+                    ("export", false) => Self::ExportIdentity(arg.into()),
+                    ("import", false) => Self::ImportIdentity(arg.into()),
+                    ("create" | "recoverable" | "recover", true) => {
+                        return Err(format!("/identity {sub} needs a username"));
+                    }
+                    ("export" | "import", true) => {
+                        return Err(format!("/identity {sub} needs a file"));
+                    }
+                    ("revoke", true) => return Err("/identity revoke needs a reason".into()),
+                    (other, _) => return Err(format!("Unknown subcommand /identity {other}")),
+                }
+            }
+            "device" | "devices" => {
+                let (sub, arg) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                let arg = arg.trim();
+                match (sub, arg.is_empty()) {
+                    ("list" | "", _) => Self::Devices,
+                    ("add", false) => {
+                        let (name, file) = arg
+                            .rsplit_once(char::is_whitespace)
+                            .ok_or("/device add needs a name and a file")?;
+                        Self::AddDevice(name.trim().to_string(), file.into())
+                    }
+                    ("remove", false) => Self::RemoveDevice(arg.to_string()),
+                    ("add", true) => return Err("/device add needs a name and a file".into()),
+                    ("remove", true) => return Err("/device remove needs a name".into()),
+                    (other, _) => return Err(format!("Unknown subcommand /device {other}")),
+                }
+            }
+            "listen" => Self::Listen(addr(&required("an address")?)?),
+            "unlisten" => Self::StopListening,
+            "connect" | "c" => Self::Connect(addr(&required("an address")?)?),
+            "disconnect" => Self::Disconnect(addr(&required("an address")?)?),
+            "contacts" => Self::Contacts,
+            "trust" => {
+                let rest = required("a contact and a trust level")?;
+                let (who, level) = rest
+                    .rsplit_once(char::is_whitespace)
+                    .ok_or("/trust needs a contact and a trust level, see /help")?;
+                let trust = match level.to_lowercase().as_str() {
+                    "trusted" | "trust" | "yes" => Trust::Trusted,
+                    "rejected" | "reject" | "no" => Trust::Rejected,
+                    "unknown" => Trust::Unknown,
+                    other => {
+                        return Err(format!(
+                            "\"{other}\" is not a trust level, use trusted, rejected or unknown"
+                        ));
+                    }
+                };
+                Self::Trust(who.trim().to_string(), trust)
+            }
+            "chats" => Self::Chats,
+            "open" | "o" => Self::Open(required("a contact")?),
+            "read" | "r" => Self::Read(if rest.is_empty() {
+                DEFAULT_READ_COUNT
+            } else {
+                rest.parse()
+                    .map_err(|_| format!("\"{rest}\" is not a number of messages"))?
+            }),
+            "retry" => Self::Retry,
+            "send" | "s" => Self::Send(required("a text")?),
+            other => return Err(format!("Unknown command /{other}, see /help")),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Result<Command, String> {
+        line.parse()
+    }
+
+    #[test]
+    fn text_is_sent_and_double_slash_escapes() {
+        assert_eq!(parse("hello\n"), Ok(Command::Send("hello".into())));
+        assert_eq!(parse("//shrug\r\n"), Ok(Command::Send("/shrug".into())));
+        assert_eq!(parse("///"), Ok(Command::Send("//".into())));
+        assert_eq!(
+            parse("/send  /me waves "),
+            Ok(Command::Send("/me waves".into()))
+        );
+    }
+
+    #[test]
+    fn trust_takes_the_last_word_as_level() {
+        assert_eq!(
+            parse("/trust Alice from work  Trusted"),
+            Ok(Command::Trust("Alice from work".into(), Trust::Trusted))
+        );
+        assert_eq!(
+            parse("/trust 3 no"),
+            Ok(Command::Trust("3".into(), Trust::Rejected))
+        );
+        assert!(parse("/trust bob maybe").unwrap_err().contains("maybe"));
+        assert!(parse("/trust bob").is_err());
+    }
+
+    #[test]
+    fn missing_arguments_are_errors() {
+        for line in [
+            "/trust",
+            "/open",
+            "/send",
+            "/listen",
+            "/connect ",
+            "/disconnect",
+            "/identity create",
+            "/identity recoverable",
+            "/identity recover",
+            "/identity export",
+            "/identity import  ",
+            "/identity revoke ",
+            "/device add phone",
+            "/device remove",
+        ] {
+            assert!(parse(line).is_err(), "{line}");
+        }
+        assert_eq!(
+            parse("/identity create"),
+            Err("/identity create needs a username".into())
+        );
+        assert_eq!(
+            parse("/open"),
+            Err("/open needs a contact, see /help".into())
+        );
+    }
+
+    #[test]
+    fn arguments_are_parsed() {
+        assert_eq!(
+            parse("/connect 10.0.0.1:4000"),
+            Ok(Command::Connect(([10, 0, 0, 1], 4000).into()))
+        );
+        assert!(parse("/connect localhost").is_err());
+        assert_eq!(parse("/read"), Ok(Command::Read(DEFAULT_READ_COUNT)));
+        assert_eq!(parse("/r 5"), Ok(Command::Read(5)));
+        assert!(parse("/read many").is_err());
+        assert_eq!(
+            parse("/id create Alice Smith"),
+            Ok(Command::CreateIdentity("Alice Smith".into()))
+        );
+        assert_eq!(
+            parse("/identity recover Alice"),
+            Ok(Command::RecoverIdentity("Alice".into()))
+        );
+        assert_eq!(parse("/identity"), Ok(Command::ShowIdentity));
+        assert_eq!(parse("/id succeed"), Ok(Command::SucceedIdentity));
+        assert_eq!(
+            parse("/identity revoke lost my laptop"),
+            Ok(Command::RevokeIdentity("lost my laptop".into()))
+        );
+        assert_eq!(
+            parse("/device add work phone  phone.sremp"),
+            Ok(Command::AddDevice(
+                "work phone".into(),
+                "phone.sremp".into()
+            ))
+        );
+        assert_eq!(parse("/devices"), Ok(Command::Devices));
+        assert!(parse("/identity delete").is_err());
+        assert!(parse("/frobnicate").is_err());
+    }
+}
@@ -0,0 +1,149 @@
+//! Terminal client for SREMP.
+//!
+//! By default, the client reads commands and messages line by line, like a REPL. With `--tui`,
+//! it shows the chat list and the current chat side by side in a full-screen mode.
+
+use std::path::PathBuf;
+
+use sremp_client::domain::{ClientDomain, UI_COMMAND_CAPACITY, UI_EVENT_BULK_CAPACITY, UiEvent};
+use sremp_core::domain::{
+    NET_COMMAND_CAPACITY, NET_EVENT_BULK_CAPACITY, NetworkDomain,
+    priority::{PriorityReceiver, priority_channel},
+};
+
+use crate::app::App;
+
+mod app;
+mod command;
+mod repl;
+#[cfg(unix)]
+mod term;
+#[cfg(unix)]
+mod tui;
+
+const USAGE: &str = "Usage: sremp-cli [--tui] [--state <path>]
+
+  --tui             show the chat list and the current chat side by side
+  --state <path>    where the identity, contacts, chats and unsent messages are kept across
+                    restarts
+  --help            show this help
+
+Type /help in the client for its commands.";
+
+fn main() -> std::process::ExitCode {
+    let mut tui = false;
+    let mut state: Option<PathBuf> = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tui" => tui = true,
+            "--state" => match args.next() {
+                Some(path) => state = Some(path.into()),
+                None => {
+                    eprintln!("--state needs a path\n\n{USAGE}");
+                    return std::process::ExitCode::FAILURE;
+                }
+            },
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                return std::process::ExitCode::SUCCESS;
+            }
+            other => {
+                eprintln!("Unknown argument: {other}\n\n{USAGE}");
+                return std::process::ExitCode::FAILURE;
+            }
+        }
+    }
+    setup_logging(tui);
+
+    let mut rt = tokio::runtime::Runtime::new().expect("could not create tokio runtime");
+
+    let (net_command_tx, net_command_rx) = async_channel::bounded(NET_COMMAND_CAPACITY);
+    let (net_event_tx, net_event_rx) = priority_channel(NET_EVENT_BULK_CAPACITY);
+
+    let (ui_command_tx, ui_command_rx) = async_channel::bounded(UI_COMMAND_CAPACITY);
+    let (ui_event_tx, ui_event_rx) = priority_channel(UI_EVENT_BULK_CAPACITY);
+
+    NetworkDomain::new()
+        .start(net_command_rx, net_event_tx, &mut rt)
+        .expect("could not start network domain");
+    let client = match state {
+        Some(path) => match ClientDomain::load(&path) {
+            Ok(client) => client,
+            Err(e) => {
+                eprintln!("Could not load the state from {}: {e}", path.display());
+                return std::process::ExitCode::FAILURE;
+            }
+        },
+        None => ClientDomain::new(),
+    };
+    let client = client
+        .start(
+            net_command_tx,
+            net_event_rx,
+            ui_command_rx,
+            ui_event_tx,
+            &mut rt,
+        )
+        .expect("could not start application domain");
+
+    let app = App::new(ui_command_tx.clone(), ui_event_rx.clone());
+    let result = if tui {
+        run_tui(&rt, app)
+    } else {
+        rt.block_on(repl::run(app))
+    };
+    // the client domain saves its state once it sees that no more commands come
+    ui_command_tx.close();
+    rt.block_on(wait_for_client(client, ui_event_rx));
+    match result {
+        Ok(()) => std::process::ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::ExitCode::FAILURE
+        }
+    }
+}
+
+/// Waits until the client domain has stopped. Its last events are taken meanwhile, so it does not
+/// wait for a frontend that is gone.
+async fn wait_for_client(
+    client: tokio::task::JoinHandle<sremp_client::error::ClientResult<()>>,
+    events: PriorityReceiver<UiEvent>,
+) {
+    let drain = async { while events.recv().await.is_ok() {} };
+    tokio::select! {
+        biased;
+        stopped = client => {
+            if let Ok(Err(e)) = stopped {
+                log::error!("The client domain has failed: {e}");
+            }
+        }
+        () = drain => (),
+    }
+}
+
+#[cfg(unix)]
+fn run_tui(rt: &tokio::runtime::Runtime, app: App) -> std::io::Result<()> {
+    rt.block_on(tui::run(app))
+}
+
+#[cfg(not(unix))]
+fn run_tui(_rt: &tokio::runtime::Runtime, _app: App) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "the full-screen mode is only available on unix",
+    ))
+}
+
+/// Logs to stderr, which would garble the full-screen mode, so it only logs there if `RUST_LOG`
+/// asks for it.
+fn setup_logging(tui: bool) {
+    let mut l = env_logger::builder();
+    l.filter_level(if tui {
+        log::LevelFilter::Off
+    } else {
+        log::LevelFilter::Warn
+    });
+    l.parse_default_env().init();
+}
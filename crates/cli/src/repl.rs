@@ -0,0 +1,68 @@
+//! The default frontend: reads a line at a time from stdin and prints what happens.
+
+use std::io::{self, BufRead, Write};
+
+use crate::app::App;
+
+/// Reads lines from stdin until it is closed or the user quits.
+pub(crate) async fn run(mut app: App) -> io::Result<()> {
+    let lines = spawn_line_reader();
+    println!("Welcome to SREMP, type /help for the commands");
+
+    while !app.should_quit() {
+        tokio::select! {
+            event = app.next_event() => match event {
+                Some(event) => app.handle_event(event).await,
+                None => {
+                    log::error!("The client domain has stopped");
+                    break;
+                }
+            },
+            line = lines.recv() => match line {
+                Ok(line) => app.handle_line(line).await,
+                // stdin was closed
+                Err(_) => break,
+            },
+        }
+
+        let mut stdout = io::stdout().lock();
+        for notice in app.take_unread() {
+            writeln!(stdout, "{notice}")?;
+        }
+        stdout.flush()?;
+        set_echo(!app.wants_secret());
+    }
+    set_echo(true);
+    Ok(())
+}
+
+/// Reads stdin on its own thread, since reading it blocks.
+fn spawn_line_reader() -> async_channel::Receiver<String> {
+    let (tx, rx) = async_channel::unbounded();
+    std::thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            match line {
+                Ok(line) => {
+                    if tx.send_blocking(line).is_err() {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    log::error!("Could not read from stdin: {e}");
+                    break;
+                }
+            }
+        }
+    });
+    rx
+}
+
+/// Hides a passphrase while it is typed. Does nothing if stdin is not a terminal.
+fn set_echo(echo: bool) {
+    #[cfg(unix)]
+    if let Err(e) = crate::term::set_echo(echo) {
+        log::debug!("Could not change the echo of the terminal: {e}");
+    }
+    #[cfg(not(unix))]
+    let _ = echo;
+}
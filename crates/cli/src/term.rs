@@ -0,0 +1,69 @@
+//! The little terminal handling the client needs, on top of termios.
+
+use std::io;
+
+/// Puts the terminal on stdin into raw mode and restores it when dropped.
+pub(crate) struct RawMode {
+    original: libc::termios,
+}
+
+impl RawMode {
+    pub(crate) fn enable() -> io::Result<Self> {
+        let original = get_attrs()?;
+        let mut raw = original;
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN);
+        raw.c_iflag &= !(libc::IXON | libc::ICRNL);
+        raw.c_cc[libc::VMIN] = 1;
+        raw.c_cc[libc::VTIME] = 0;
+        set_attrs(&raw)?;
+        Ok(Self { original })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        if let Err(e) = set_attrs(&self.original) {
+            log::error!("Could not restore the terminal: {e}");
+        }
+    }
+}
+
+/// Turns the echo of typed characters on stdin on or off, for entering a passphrase.
+pub(crate) fn set_echo(echo: bool) -> io::Result<()> {
+    let mut attrs = get_attrs()?;
+    if echo {
+        attrs.c_lflag |= libc::ECHO;
+    } else {
+        attrs.c_lflag &= !libc::ECHO;
+    }
+    set_attrs(&attrs)
+}
+
+/// Columns and rows of the terminal on stdout
+pub(crate) fn size() -> io::Result<(usize, usize)> {
+    // SAFETY: winsize is plain old data, all zeroes is a valid value
+    let mut ws: libc::winsize = unsafe { std::mem::zeroed() };
+    // SAFETY: TIOCGWINSZ only writes a winsize to the pointer, which is valid for the call
+    if unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut ws) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok((usize::from(ws.ws_col), usize::from(ws.ws_row)))
+}
+
+fn get_attrs() -> io::Result<libc::termios> {
+    // SAFETY: termios is plain old data, all zeroes is a valid value
+    let mut attrs: libc::termios = unsafe { std::mem::zeroed() };
+    // SAFETY: tcgetattr only writes a termios to the pointer, which is valid for the call
+    if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut attrs) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(attrs)
+}
+
+fn set_attrs(attrs: &libc::termios) -> io::Result<()> {
+    // SAFETY: tcsetattr only reads the termios behind the pointer, which is valid for the call
+    if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSAFLUSH, attrs) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
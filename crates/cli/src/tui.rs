@@ -0,0 +1,220 @@
+//! The full-screen frontend: the chat list on the left, the current chat on the right.
+
+use std::{
+    fmt::Write as _,
+    io::{self, Read, Write},
+    time::Duration,
+};
+
+use crate::{
+    app::{App, printable},
+    term,
+};
+
+/// How often the screen is drawn again without an event, to follow a resized terminal
+const REDRAW_INTERVAL: Duration = Duration::from_millis(500);
+/// Widest the chat list gets
+const MAX_LIST_WIDTH: usize = 28;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Key {
+    Char(char),
+    Enter,
+    Backspace,
+    Up,
+    Down,
+    Quit,
+}
+
+/// Shows the client on the alternate screen until the user quits.
+pub(crate) async fn run(mut app: App) -> io::Result<()> {
+    let _raw = term::RawMode::enable()?;
+    let keys = spawn_key_reader();
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b[?1049h")?;
+
+    let mut input = String::new();
+    let mut redraw = tokio::time::interval(REDRAW_INTERVAL);
+    while !app.should_quit() {
+        draw(&app, &input, &mut stdout)?;
+        tokio::select! {
+            event = app.next_event() => match event {
+                Some(event) => app.handle_event(event).await,
+                None => break,
+            },
+            key = keys.recv() => match key {
+                Ok(Key::Char(c)) => input.push(c),
+                Ok(Key::Backspace) => {
+                    input.pop();
+                }
+                Ok(Key::Enter) => app.handle_line(std::mem::take(&mut input)).await,
+                Ok(Key::Up) => app.select_relative(-1),
+                Ok(Key::Down) => app.select_relative(1),
+                Ok(Key::Quit) | Err(_) => break,
+            },
+            _ = redraw.tick() => (),
+        }
+        // the full-screen mode shows notices in the status line instead
+        app.take_unread();
+    }
+
+    write!(stdout, "\x1b[?1049l")?;
+    stdout.flush()
+}
+
+/// Reads keys on their own thread, since reading stdin blocks.
+fn spawn_key_reader() -> async_channel::Receiver<Key> {
+    let (tx, rx) = async_channel::unbounded();
+    std::thread::spawn(move || {
+        let mut buf = [0; 64];
+        loop {
+            let n = match io::stdin().lock().read(&mut buf) {
+                Ok(0) => 0,
+                Ok(n) => n,
+                Err(e) => {
+                    log::error!("Could not read from stdin: {e}");
+                    0
+                }
+            };
+            let keys = if n == 0 {
+                vec![Key::Quit]
+            } else {
+                parse_keys(&String::from_utf8_lossy(&buf[..n]))
+            };
+            for key in keys {
+                if tx.send_blocking(key).is_err() || key == Key::Quit {
+                    return;
+                }
+            }
+        }
+    });
+    rx
+}
+
+fn parse_keys(chunk: &str) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut chars = chunk.chars();
+    while let Some(c) = chars.next() {
+        keys.push(match c {
+            '\r' | '\n' => Key::Enter,
+            '\x7f' | '\x08' => Key::Backspace,
+            // Ctrl-C and Ctrl-D
+            '\x03' | '\x04' => Key::Quit,
+            '\x1b' => match (chars.next(), chars.next()) {
+                (Some('['), Some('A')) => Key::Up,
+                (Some('['), Some('B')) => Key::Down,
+                _ => continue,
+            },
+            c if c.is_control() => continue,
+            c => Key::Char(c),
+        });
+    }
+    keys
+}
+
+fn draw(app: &App, input: &str, out: &mut impl Write) -> io::Result<()> {
+    let (width, height) = term::size().unwrap_or((80, 24));
+    if width < 20 || height < 5 {
+        write!(out, "\x1b[2J\x1b[Hterminal too small")?;
+        return out.flush();
+    }
+    let list_width = MAX_LIST_WIDTH.min(width / 3);
+    let pane_width = width - list_width - 1;
+    let pane_height = height - 3;
+
+    let mut screen = String::from("\x1b[?25l\x1b[H");
+    let title = match (app.user(), app.listener()) {
+        (None, _) => " SREMP - no identity, see /help".to_string(),
+        (Some(user), None) => format!(" SREMP - {}", user.username()),
+        (Some(user), Some(addr)) => format!(" SREMP - {} - listening on {addr}", user.username()),
+    };
+    line(&mut screen, &title, width, true);
+
+    let chats: Vec<String> = app
+        .chat_list()
+        .into_iter()
+        .map(|(number, cid)| {
+            let marker = if app.selected() == Some(&cid) {
+                '>'
+            } else {
+                ' '
+            };
+            let online = if app.is_connected(&cid) { '*' } else { ' ' };
+            format!("{marker}{number:>2} {online}{}", app.contact_name(&cid))
+        })
+        .collect();
+
+    let mut pane: Vec<String> = match app.selected() {
+        Some(_) => app
+            .current_messages(pane_height)
+            .iter()
+            .flat_map(|msg| wrap(&app.format_message(msg), pane_width))
+            .collect(),
+        None => app
+            .notices()
+            .rev()
+            .take(pane_height)
+            .rev()
+            .flat_map(|notice| wrap(notice, pane_width))
+            .collect(),
+    };
+    pane.drain(..pane.len().saturating_sub(pane_height));
+
+    for row in 0..pane_height {
+        let left = chats.get(row).map_or("", String::as_str);
+        let right = pane.get(row).map_or("", String::as_str);
+        let selected = left.starts_with('>');
+        cell(&mut screen, left, list_width, selected);
+        screen.push('│');
+        line(&mut screen, right, pane_width, false);
+    }
+
+    let status = app.notices().next_back().map_or("", String::as_str);
+    line(&mut screen, status, width, true);
+
+    let shown: String = if app.wants_secret() {
+        "*".repeat(input.chars().count())
+    } else {
+        input.to_string()
+    };
+    let visible = width - 3;
+    let skip = shown.chars().count().saturating_sub(visible);
+    let shown: String = shown.chars().skip(skip).collect();
+    let _ = write!(screen, "\x1b[K> {}", printable(&shown));
+    screen.push_str("\x1b[?25h");
+
+    out.write_all(screen.as_bytes())?;
+    out.flush()
+}
+
+/// Writes `text` into a cell of `width` columns, cut or padded
+fn cell(screen: &mut String, text: &str, width: usize, inverse: bool) {
+    let text: String = printable(text).chars().take(width).collect();
+    let padding = width - text.chars().count();
+    if inverse {
+        screen.push_str("\x1b[7m");
+    }
+    screen.push_str(&text);
+    screen.push_str(&" ".repeat(padding));
+    if inverse {
+        screen.push_str("\x1b[0m");
+    }
+}
+
+/// Like [`cell`], but ends the row
+fn line(screen: &mut String, text: &str, width: usize, inverse: bool) {
+    cell(screen, text, width, inverse);
+    screen.push_str("\r\n");
+}
+
+/// Splits `text` into rows of at most `width` characters
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.is_empty() {
+        return vec![String::new()];
+    }
+    chars
+        .chunks(width.max(1))
+        .map(|row| row.iter().collect())
+        .collect()
+}
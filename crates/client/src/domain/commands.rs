@@ -2,7 +2,9 @@ use std::{fmt::Display, net::SocketAddr, sync::Arc};
 
 use sremp_core::{
     chat::messages::SharedMessage,
-    identity::{ContactId, Trust, UserIdentity},
+    identity::{
+        ContactId, IdentityStatement, PairingCode, Trust, UserIdentity, invitation::Invitation,
+    },
 };
 
 #[derive(Debug, Clone)]
@@ -12,10 +14,35 @@ pub enum UiCommand {
     SendMessage(ContactId, SharedMessage),
     StartChat(ContactId),
     TrustContact(ContactId, Trust),
+    /// Shows the short authentication string of a connection to the contact on both sides,
+    /// answered with [`UiEvent::VerificationStarted`](crate::domain::UiEvent::VerificationStarted)
+    StartVerification(ContactId),
+    /// The user has compared the short authentication string, `true` if it matched
+    ConfirmVerification(ContactId, bool),
+    /// Aborts the verification of the contact without changing its trust
+    CancelVerification(ContactId),
+    /// Pairs with the contact using a code that both users enter, see
+    /// [`Pairing`](sremp_core::identity::Pairing). Also the answer to
+    /// [`UiEvent::PairingRequested`](crate::domain::UiEvent::PairingRequested).
+    StartPairing(ContactId, PairingCode),
+    /// Aborts the pairing with the contact without changing its trust
+    CancelPairing(ContactId),
+    /// Attempts to send the messages to the contact that have failed again
+    RetryMessages(ContactId),
+    /// Sends a statement about an identity of the user to all peers, now and whenever they connect
+    PublishStatement(Arc<IdentityStatement>),
+    /// Publishes an [`Endorsement`](sremp_core::identity::Endorsement) of the contact by the
+    /// user, `true` to vouch for it, `false` to withdraw an earlier endorsement
+    Endorse(ContactId, bool),
     StartListener(SocketAddr),
     StopListener,
     Connect(SocketAddr),
+    /// Adds the identity of the invitation to the known identities and connects to its
+    /// endpoints in order until one works. Peers with another identity are refused.
+    AcceptInvitation(Arc<Invitation>),
     Disconnect(SocketAddr),
+    /// Answered with [`UiEvent::ConnectionStats`](crate::domain::UiEvent::ConnectionStats)
+    QueryConnectionStats,
 }
 
 impl Display for UiCommand {
@@ -25,13 +52,32 @@ impl Display for UiCommand {
             "{}",
             match self {
                 Self::Connect(addr) => format!("Connect to {addr}"),
+                Self::AcceptInvitation(invitation) => format!(
+                    "Accept invitation of {} ({})",
+                    invitation.contact_id(),
+                    invitation.username()
+                ),
                 Self::Disconnect(addr) => format!("Disconnect from {addr}"),
                 Self::StartChat(id) => format!("Create new chat with {id}"),
                 Self::SendMessage(id, _msg) => format!("Send Message to {id}"),
                 Self::TrustContact(id, trust) => format!("Set trust of {id} to {trust}"),
+                Self::StartVerification(id) => format!("Start verification of {id}"),
+                Self::ConfirmVerification(id, matched) => format!(
+                    "Short authentication string of {id} {}",
+                    if *matched { "matched" } else { "did not match" }
+                ),
+                Self::CancelVerification(id) => format!("Cancel verification of {id}"),
+                Self::StartPairing(id, _code) => format!("Start pairing with {id}"),
+                Self::CancelPairing(id) => format!("Cancel pairing with {id}"),
+                Self::RetryMessages(id) => format!("Retry failed messages to {id}"),
+                Self::PublishStatement(statement) => format!("Publish {statement}"),
+                Self::Endorse(id, true) => format!("Vouch for {id}"),
+                Self::Endorse(id, false) => format!("Withdraw the endorsement of {id}"),
                 Self::StartListener(addr) =>
                     format!("Start listening for incoming connection on {addr}"),
                 Self::StopListener => "Stop listening for incoming connections".to_string(),
+                Self::QueryConnectionStats =>
+                    "Query statistics of the active connections".to_string(),
                 Self::SetIdentity(id) => {
                     if let Some(id) = id {
                         format!(
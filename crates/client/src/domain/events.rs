@@ -2,18 +2,39 @@ use std::{fmt::Display, net::SocketAddr, sync::Arc};
 
 use sremp_core::{
     chat::messages::SharedMessage,
-    identity::{ContactId, UserIdentity},
+    domain::priority::Priority,
+    identity::{ContactId, IdentityStatement, ShortAuthString, Trust, UserIdentity},
+    net::connection::stats::ConnectionStatsSnapshot,
 };
 
-use crate::domain::{chats::Chats, known_identities::KnownIdentities};
+use crate::domain::{
+    chats::Chats, known_identities::KnownIdentities, outbox::DeliveryState, tofu::PeerStatus,
+};
 
 #[derive(Debug, Clone)]
 #[allow(clippy::large_enum_variant)]
 pub enum UiEvent {
-    ConnectionEstablished(SocketAddr, ContactId),
+    /// A connection is ready, the [`PeerStatus`] tells whether the user should be asked to trust
+    /// the contact
+    ConnectionEstablished(SocketAddr, ContactId, PeerStatus),
     ConnectionLost(SocketAddr, ContactId),
     IncomingMessage(SocketAddr, ContactId, SharedMessage),
-    MessageSent(SocketAddr, ContactId, SharedMessage),
+    /// A message of the user to the contact has reached a new [`DeliveryState`]
+    DeliveryStateChanged(ContactId, SharedMessage, DeliveryState),
+    ContactTyping(ContactId, bool),
+    /// The user should compare these words with the contact and confirm whether they match
+    VerificationStarted(ContactId, ShortAuthString),
+    /// Both sides have answered, the contact now has this [`Trust`]
+    VerificationFinished(ContactId, Trust),
+    /// The verification was aborted by either side or its connection was lost
+    VerificationCancelled(ContactId),
+    /// The contact has started a pairing, the user should enter the code they were told
+    PairingRequested(ContactId),
+    /// Both sides have confirmed, `true` if they entered the same code and the contact is now
+    /// [`Trust::Verified`]
+    PairingFinished(ContactId, bool),
+    /// The pairing was aborted by either side or its connection was lost
+    PairingCancelled(ContactId),
     ConnectionReset(SocketAddr),
     ConnectionFailed(SocketAddr, String),
     ListenerStarted(SocketAddr),
@@ -21,6 +42,19 @@ pub enum UiEvent {
     IdentitySet(Option<Arc<UserIdentity>>),
     LoadedChats(Chats),
     SetKnownIdentities(KnownIdentities),
+    ConnectionStats(Vec<ConnectionStatsSnapshot>),
+    /// A statement the user wanted to publish is invalid and was not sent, with the reason
+    StatementRejected(Arc<IdentityStatement>, String),
+}
+
+impl Priority for UiEvent {
+    /// Chat traffic is bulk, everything else is control
+    fn is_bulk(&self) -> bool {
+        matches!(
+            self,
+            Self::IncomingMessage(..) | Self::DeliveryStateChanged(..) | Self::ContactTyping(..)
+        )
+    }
 }
 
 impl Display for UiEvent {
@@ -29,12 +63,29 @@ impl Display for UiEvent {
             f,
             "{}",
             match self {
-                Self::ConnectionEstablished(addr, id) =>
-                    format!("Connection established with {addr} ({id})"),
+                Self::ConnectionEstablished(addr, id, status) =>
+                    format!("Connection established with {addr} ({id}), the peer is {status}"),
                 Self::ConnectionLost(addr, id) => format!("Peer {addr} ({id}) has disconnected"),
                 Self::IncomingMessage(addr, id, _msg) =>
                     format!("Message received from {addr} ({id})"),
-                Self::MessageSent(addr, id, _msg) => format!("Message sent to {addr} ({id})"),
+                Self::DeliveryStateChanged(id, _msg, state) =>
+                    format!("Message to {id} is now {state}"),
+                Self::ContactTyping(id, typing) => {
+                    if *typing {
+                        format!("{id} is typing")
+                    } else {
+                        format!("{id} stopped typing")
+                    }
+                }
+                Self::VerificationStarted(id, _sas) => format!("Verification of {id} has started"),
+                Self::VerificationFinished(id, trust) =>
+                    format!("Verification of {id} has finished, the contact is {trust}"),
+                Self::VerificationCancelled(id) => format!("Verification of {id} was cancelled"),
+                Self::PairingRequested(id) => format!("{id} asks to pair"),
+                Self::PairingFinished(id, true) => format!("Paired with {id}"),
+                Self::PairingFinished(id, false) =>
+                    format!("Pairing with {id} failed, the codes did not match"),
+                Self::PairingCancelled(id) => format!("Pairing with {id} was cancelled"),
                 Self::ConnectionFailed(addr, reason) =>
                     format!("Connection to {addr} attempt was aborted: {reason}"),
                 Self::ListenerStarted(addr) =>
@@ -56,6 +107,10 @@ impl Display for UiEvent {
                 Self::LoadedChats(chats) => format!("Loaded {} chats", chats.len()),
                 Self::SetKnownIdentities(kid) =>
                     format!("Set known identities for UI ({} identities)", kid.len()),
+                Self::ConnectionStats(stats) =>
+                    format!("Statistics of {} active connections", stats.len()),
+                Self::StatementRejected(statement, reason) =>
+                    format!("{statement} was not published: {reason}"),
             }
         )
     }
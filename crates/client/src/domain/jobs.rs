@@ -1,16 +1,23 @@
 use std::{net::SocketAddr, sync::Arc};
 
 use sremp_core::{
-    chat::messages::SharedMessage,
+    chat::messages::{Message, SharedMessage},
     current_function,
     domain::{NetworkCommand, NetworkEvent},
-    error::{CoreError, CoreResult},
-    identity::{ContactId, ContactIdentity, UserIdentity},
+    error::CoreError,
+    identity::{
+        ContactId, ContactIdentity, IdentityStatement, Pairing, PairingCode, PairingMessage,
+        SasMessage, ShortAuthString, Trust, UserIdentity, invitation::Invitation,
+    },
+    net::envelope::Envelope,
 };
 
 use crate::{
-    domain::{ClientDomain, UiCommand, UiEvent, known_identities::SharedContact},
-    error::{ClientError, ClientResult},
+    domain::{
+        ClientDomain, UiCommand, UiEvent, known_identities::SharedContact, outbox::DeliveryState,
+        pairing::PairingState, tofu::PeerStatus, verification::Verification,
+    },
+    error::ClientResult,
 };
 
 impl ClientDomain {
@@ -21,26 +28,83 @@ impl ClientDomain {
             UiCommand::StopListener => self.listener_stop().await,
             UiCommand::StartListener(local_addr) => self.listener_start(local_addr).await,
             UiCommand::Connect(remote) => self.connect(remote).await,
+            UiCommand::AcceptInvitation(invitation) => self.accept_invitation(&invitation).await,
             UiCommand::Disconnect(remote) => self.disconnect(remote).await,
             UiCommand::SetIdentity(ident) => self.set_identity(ident).await,
             UiCommand::SendMessage(key, msg) => self.send_message(key, msg).await,
+            UiCommand::QueryConnectionStats => {
+                self.net_command_channel()
+                    .send(NetworkCommand::QueryStats)
+                    .await
+                    .map_err(CoreError::from)?;
+                Ok(())
+            }
             UiCommand::StartChat(cid) => {
                 self.chats.entry(cid).or_default();
                 self.send_ui_evt(UiEvent::LoadedChats(self.chats.clone()))
                     .await;
                 Ok(())
             }
-            UiCommand::TrustContact(cid, trust) => {
-                if let Some(contact) = self.known_identities.get(&cid) {
-                    // replace the contact with the changed one
-                    let mut nc: ContactIdentity = (**contact).clone();
-                    nc.trust = trust;
-                    self.known_identities.insert(cid, Arc::new(nc));
-                } else {
-                    log::warn!("Could not set trust for {cid}, because this is not a known contact")
+            UiCommand::RetryMessages(cid) => {
+                for msg in self.outbox.retry_failed(&cid) {
+                    self.send_ui_evt(UiEvent::DeliveryStateChanged(
+                        cid.clone(),
+                        msg,
+                        DeliveryState::Pending,
+                    ))
+                    .await
+                }
+                self.flush_outbox(&cid).await;
+                Ok(())
+            }
+            UiCommand::PublishStatement(statement) => {
+                self.publish_statement(statement).await;
+                Ok(())
+            }
+            UiCommand::Endorse(cid, vouch) => {
+                self.endorse(cid, vouch).await;
+                Ok(())
+            }
+            UiCommand::StartVerification(cid) => {
+                self.start_verification(cid).await;
+                Ok(())
+            }
+            UiCommand::ConfirmVerification(cid, matched) => {
+                self.confirm_verification(cid, matched).await;
+                Ok(())
+            }
+            UiCommand::CancelVerification(cid) => {
+                if let Some(verification) = self.verifications.remove(&cid) {
+                    self.send_net_cmd(NetworkCommand::SendEnvelope(
+                        verification.remote,
+                        cid,
+                        Envelope::Verification(SasMessage::Cancel).into(),
+                    ))
+                    .await
+                }
+                Ok(())
+            }
+            UiCommand::StartPairing(cid, code) => {
+                self.start_pairing(cid, &code).await;
+                Ok(())
+            }
+            UiCommand::CancelPairing(cid) => {
+                if let Some(pairing) = self.pairings.remove(&cid) {
+                    self.send_net_cmd(NetworkCommand::SendEnvelope(
+                        pairing.remote,
+                        cid,
+                        Envelope::Pairing(PairingMessage::Cancel).into(),
+                    ))
+                    .await
                 }
                 Ok(())
             }
+            UiCommand::TrustContact(cid, trust) => {
+                self.set_trust(cid, trust);
+                self.send_ui_evt(UiEvent::SetKnownIdentities(self.known_identities.clone()))
+                    .await;
+                Ok(())
+            }
         }
     }
 
@@ -49,33 +113,144 @@ impl ClientDomain {
         log::info!("Processing Net Event: {event}");
         match event {
             NetworkEvent::ListenerStopped => self.send_ui_evt(UiEvent::ListenerStopped).await,
-            NetworkEvent::ListenerFailed(core_error) => todo!(),
+            NetworkEvent::ListenerFailed(core_error) => {
+                log::error!("Listener failed: {core_error}");
+                self.send_ui_evt(UiEvent::ListenerStopped).await
+            }
             NetworkEvent::ListenerStarted(addr) => {
                 self.send_ui_evt(UiEvent::ListenerStarted(addr)).await
             }
             NetworkEvent::ConnectionLost(remote, key) => {
+                self.handshake_hashes.remove(&remote);
+                self.dialed.remove(&remote);
+                if self
+                    .verifications
+                    .get(&key)
+                    .is_some_and(|v| v.remote == remote)
+                {
+                    self.verifications.remove(&key);
+                    self.send_ui_evt(UiEvent::VerificationCancelled(key.clone()))
+                        .await
+                }
+                if self.pairings.get(&key).is_some_and(|p| p.remote == remote) {
+                    self.pairings.remove(&key);
+                    self.send_ui_evt(UiEvent::PairingCancelled(key.clone()))
+                        .await
+                }
+                let was_open = self
+                    .open_connections
+                    .get_mut(&key)
+                    .is_some_and(|remotes| remotes.remove(&remote));
+                if was_open {
+                    let last = self.open_connections[&key].is_empty();
+                    if last {
+                        self.open_connections.remove(&key);
+                    }
+                    for msg in self.outbox.connection_lost(&key, remote, last) {
+                        log::warn!("Giving up on a message to {key} for now");
+                        self.send_ui_evt(UiEvent::DeliveryStateChanged(
+                            key.clone(),
+                            msg,
+                            DeliveryState::Failed,
+                        ))
+                        .await
+                    }
+                    self.flush_outbox(&key).await;
+                }
                 self.send_ui_evt(UiEvent::ConnectionLost(remote, key)).await
             }
             NetworkEvent::ConnectionFailed(remote, reason) => {
+                self.dialed.remove(&remote);
+                if let Some((cid, mut endpoints)) = self
+                    .invitation_attempts
+                    .remove(&remote)
+                    .filter(|(_, endpoints)| !endpoints.is_empty())
+                {
+                    let next = endpoints.remove(0);
+                    log::info!("Could not reach {cid} on {remote}, trying {next}");
+                    self.invitation_attempts
+                        .insert(next, (cid.clone(), endpoints));
+                    self.send_net_cmd(NetworkCommand::ConnectPinned(next, cid))
+                        .await;
+                }
                 self.send_ui_evt(UiEvent::ConnectionFailed(remote, reason))
                     .await
             }
-            NetworkEvent::ConnectionEstablished(remote, iden) => {
-                self.known_identities.create_or_update(&iden)?;
+            NetworkEvent::ConnectionEstablished(remote, iden, noise_key, handshake_hash) => {
+                let trust = self.known_identities.get(&iden.id()).map(|c| c.trust);
+                if let Err(e) = self.known_identities.connected(&iden, &noise_key) {
+                    log::warn!("Refusing the connection to {} on {remote}: {e}", iden.id());
+                    self.send_net_cmd(NetworkCommand::Disconnect(remote)).await;
+                    self.send_ui_evt(UiEvent::ConnectionFailed(remote, e.to_string()))
+                        .await;
+                    return Ok(());
+                }
+                self.handshake_hashes.insert(remote, handshake_hash);
+                if self.invitation_attempts.remove(&remote).is_some() {
+                    self.dialed.insert(remote);
+                }
+                let status = self.classify_peer(remote, &iden.id(), trust);
+                if self.dialed.contains(&remote) && !matches!(status, PeerStatus::KeyChanged { .. })
+                {
+                    self.known_endpoints.insert(remote, iden.id());
+                }
+                self.open_connections
+                    .entry(iden.id())
+                    .or_default()
+                    .insert(remote);
                 self.send_ui_evt(UiEvent::SetKnownIdentities(self.known_identities.clone()))
                     .await;
-                self.send_ui_evt(UiEvent::ConnectionEstablished(remote, iden.id()))
+                self.send_ui_evt(UiEvent::ConnectionEstablished(remote, iden.id(), status))
+                    .await;
+                for statement in self.statements.clone() {
+                    self.send_net_cmd(NetworkCommand::SendEnvelope(
+                        remote,
+                        iden.id(),
+                        Envelope::IdentityStatement(statement.into()).into(),
+                    ))
                     .await
+                }
+                self.flush_outbox(&iden.id()).await
             }
-            NetworkEvent::MessageSent(remote, key, data) => {
-                todo!()
+            NetworkEvent::EnvelopeSent(remote, key, envelope) => {
+                log::trace!("{envelope} was sent to {remote} ({key})");
+                if let Some(msg) = self.outbox.confirm(&key, &envelope) {
+                    msg.flags.set_sent(true);
+                    self.send_ui_evt(UiEvent::DeliveryStateChanged(key, msg, DeliveryState::Sent))
+                        .await
+                }
+            }
+            NetworkEvent::IdentityUpdated(remote, iden) => {
+                match self.known_identities.update(&iden) {
+                    Ok(contact) => {
+                        log::info!(
+                            "{} ({remote}) is now at identity version {}",
+                            contact.id(),
+                            contact.version()
+                        );
+                        self.send_ui_evt(UiEvent::SetKnownIdentities(self.known_identities.clone()))
+                            .await
+                    }
+                    Err(e) => log::warn!("Ignoring identity update from {remote}: {e}"),
+                }
             }
-            NetworkEvent::IncomingMessage(remote, key, data) => {
-                self.incoming_message(remote, key, data).await?
+            NetworkEvent::EnvelopeQueued(remote, key, envelope) => {
+                self.outbox.queued(&key, &envelope, remote)
+            }
+            NetworkEvent::StreamBusy(remote, key, envelope) => {
+                // NOTE: the envelope is retried on the same connection
+                self.outbox.queued(&key, &envelope, remote);
+                self.deferred.push((remote, key, envelope))
+            }
+            NetworkEvent::IncomingEnvelope(remote, key, envelope) => {
+                self.incoming_envelope(remote, key, envelope).await?
             }
             NetworkEvent::ConnectionReset(remote) => {
                 self.send_ui_evt(UiEvent::ConnectionReset(remote)).await
             }
+            NetworkEvent::ConnectionStats(stats) => {
+                self.send_ui_evt(UiEvent::ConnectionStats(stats)).await
+            }
         }
         Ok(())
     }
@@ -98,8 +273,9 @@ impl ClientDomain {
         Ok(())
     }
 
-    pub(crate) async fn connect(&self, addr: SocketAddr) -> ClientResult<()> {
+    pub(crate) async fn connect(&mut self, addr: SocketAddr) -> ClientResult<()> {
         log::trace!("{}", current_function!());
+        self.dialed.insert(addr);
         self.net_command_channel()
             .send(NetworkCommand::Connect(addr))
             .await
@@ -107,6 +283,28 @@ impl ClientDomain {
         Ok(())
     }
 
+    /// Connects to the first endpoint of the invitation, the others are tried when that fails.
+    ///
+    /// Only the contact of the invitation is accepted on them, its identity is known once the
+    /// connection is established.
+    pub(crate) async fn accept_invitation(&mut self, invitation: &Invitation) -> ClientResult<()> {
+        log::trace!("{}", current_function!());
+        if let Err(e) = invitation.verify() {
+            log::warn!("Ignoring invalid invitation: {e}");
+            return Ok(());
+        }
+        let cid = invitation.contact_id();
+        let mut endpoints = invitation.endpoints().to_vec();
+        let first = endpoints.remove(0);
+        self.invitation_attempts
+            .insert(first, (cid.clone(), endpoints));
+        self.net_command_channel()
+            .send(NetworkCommand::ConnectPinned(first, cid))
+            .await
+            .map_err(CoreError::from)?;
+        Ok(())
+    }
+
     pub(crate) async fn disconnect(&self, addr: SocketAddr) -> ClientResult<()> {
         log::trace!("{}", current_function!());
         self.net_command_channel()
@@ -128,7 +326,9 @@ impl ClientDomain {
         // - We need them if the user wants to send messages to themselves as a self-contact
         if let Some(iden) = iden.clone() {
             let self_contact: SharedContact = ContactIdentity::from_user_identity(&iden).into();
-            self.known_identities.create_or_update(&self_contact)?;
+            if let Err(e) = self.known_identities.create_or_update(&self_contact) {
+                log::warn!("Not updating the own contact: {e}");
+            }
             self.send_ui_evt(UiEvent::SetKnownIdentities(self.known_identities.clone()))
                 .await;
         }
@@ -143,28 +343,551 @@ impl ClientDomain {
         Ok(())
     }
 
-    pub(crate) async fn send_message(&self, to: ContactId, msg: SharedMessage) -> ClientResult<()> {
+    pub(crate) async fn send_message(
+        &mut self,
+        to: ContactId,
+        msg: SharedMessage,
+    ) -> ClientResult<()> {
+        log::trace!("{}", current_function!());
+        self.chats
+            .entry(to.clone())
+            .or_default()
+            .add_message(msg.clone());
+        self.outbox.push(to.clone(), msg.clone());
+        self.send_ui_evt(UiEvent::DeliveryStateChanged(
+            to.clone(),
+            msg,
+            DeliveryState::Pending,
+        ))
+        .await;
+        self.flush_outbox(&to).await;
+        Ok(())
+    }
+
+    /// Hands the pending messages to `to` to the network domain, if there is a connection.
+    pub(crate) async fn flush_outbox(&mut self, to: &ContactId) {
+        if !self.open_connections.contains_key(to) {
+            // NOTE: once relays are supported, this is where messages to contacts that are
+            // offline can be handed to one
+            log::debug!("{to} is not connected, keeping their messages in the outbox");
+            return;
+        }
+        // NOTE: the network domain picks the best connection if there are several
+        for envelope in self.outbox.take_ready(to) {
+            self.send_net_cmd(NetworkCommand::SendToContact(to.clone(), envelope))
+                .await;
+        }
+    }
+
+    /// Remembers a statement about an identity of the user and sends it to all connected peers.
+    ///
+    /// A statement that does not verify is reported back with [`UiEvent::StatementRejected`].
+    pub(crate) async fn publish_statement(&mut self, statement: Arc<IdentityStatement>) {
+        log::trace!("{}", current_function!());
+        if let Err(e) = statement.verify() {
+            log::warn!("Not publishing {statement}: {e}");
+            self.send_ui_evt(UiEvent::StatementRejected(statement, e.to_string()))
+                .await;
+            return;
+        }
+        if !self.remember_statement(&statement) {
+            return;
+        }
+        let envelope: Arc<Envelope> = Envelope::IdentityStatement(statement).into();
+        for (id, remotes) in &self.open_connections {
+            for remote in remotes {
+                self.send_net_cmd(NetworkCommand::SendEnvelope(
+                    *remote,
+                    id.clone(),
+                    envelope.clone(),
+                ))
+                .await
+            }
+        }
+    }
+
+    /// Adds a statement to those that are sent to every peer that connects.
+    ///
+    /// An endorsement replaces older ones of the same endorser for the same identity. Returns
+    /// `false` if the statement is already known or outdated.
+    fn remember_statement(&mut self, statement: &IdentityStatement) -> bool {
+        if self.statements.contains(statement) {
+            return false;
+        }
+        if let IdentityStatement::Endorsement(new) = statement {
+            if self
+                .statements
+                .iter()
+                .any(|s| matches!(s, IdentityStatement::Endorsement(old) if old.supersedes(new)))
+            {
+                return false;
+            }
+            self.statements.retain(
+                |s| !matches!(s, IdentityStatement::Endorsement(old) if new.supersedes(old)),
+            );
+        }
+        self.statements.push(statement.clone());
+        true
+    }
+
+    /// Vouches for the contact `cid` with an [`Endorsement`](sremp_core::identity::Endorsement)
+    /// by the user, or withdraws it.
+    pub(crate) async fn endorse(&mut self, cid: ContactId, vouch: bool) {
+        log::trace!("{}", current_function!());
+        let Some(user) = &self.user_identity else {
+            log::warn!("Can not endorse {cid} without a user identity");
+            return;
+        };
+        if !self.known_identities.contains_key(&cid) {
+            log::warn!("Can not endorse {cid}, because this is not a known contact");
+            return;
+        }
+        let statement = match user.endorse(cid.clone(), !vouch) {
+            Ok(endorsement) => IdentityStatement::Endorsement(endorsement),
+            Err(e) => {
+                log::error!("Could not endorse {cid}: {e}");
+                return;
+            }
+        };
+        if let Err(e) = self.known_identities.apply_statement(&statement) {
+            log::warn!("Could not record {statement}: {e}");
+        }
+        self.send_ui_evt(UiEvent::SetKnownIdentities(self.known_identities.clone()))
+            .await;
+        self.publish_statement(statement.into()).await;
+    }
+
+    /// Replaces the contact with one that has the changed [`Trust`].
+    ///
+    /// Once the user trusts a contact, it is expected on the addresses the user has connected to
+    /// it on, even if another key answered there before.
+    pub(crate) fn set_trust(&mut self, cid: ContactId, trust: Trust) {
+        if let Some(contact) = self.known_identities.get(&cid) {
+            let mut nc: ContactIdentity = (**contact).clone();
+            nc.trust = trust;
+            nc.trust_inherited = false;
+            self.known_identities.insert(cid.clone(), Arc::new(nc));
+        } else {
+            log::warn!("Could not set trust for {cid}, because this is not a known contact");
+            return;
+        }
+        if matches!(trust, Trust::Trusted | Trust::Verified) {
+            for remote in self.open_connections.get(&cid).into_iter().flatten() {
+                if self.dialed.contains(remote) {
+                    self.known_endpoints.insert(*remote, cid.clone());
+                }
+            }
+        }
+    }
+
+    /// Classifies the peer `id` of a new connection on `remote`, with the [`Trust`] it had before
+    /// the connection, if it was known.
+    fn classify_peer(
+        &self,
+        remote: SocketAddr,
+        id: &ContactId,
+        trust: Option<Trust>,
+    ) -> PeerStatus {
+        match trust {
+            Some(Trust::Trusted | Trust::Verified) => return PeerStatus::Trusted,
+            Some(Trust::Rejected | Trust::Revoked) => return PeerStatus::Rejected,
+            Some(Trust::Unknown | Trust::Suspicious) | None => (),
+        }
+        // NOTE: a successor answering where its predecessor did is expected
+        let address_of = self
+            .known_endpoints
+            .get(&remote)
+            .filter(|_| self.dialed.contains(&remote))
+            .filter(|known| *known != id)
+            .filter(|known| {
+                self.known_identities
+                    .get(*known)
+                    .is_none_or(|c| c.successor.as_ref() != Some(id))
+            })
+            .cloned();
+        let name_of: Vec<ContactId> = self
+            .known_identities
+            .lookalikes(id)
+            .iter()
+            .map(|c| c.id())
+            .collect();
+        if address_of.is_none() && name_of.is_empty() {
+            PeerStatus::New
+        } else {
+            PeerStatus::KeyChanged {
+                address_of,
+                name_of,
+            }
+        }
+    }
+
+    /// The [`ShortAuthString`] of the connection on `remote` to `id`, if it is still open
+    fn short_auth_string(&self, remote: SocketAddr, id: &ContactId) -> Option<ShortAuthString> {
+        let user = self.user_identity.as_ref()?;
+        let handshake_hash = self.handshake_hashes.get(&remote)?;
+        Some(ShortAuthString::new(handshake_hash, &user.id(), id))
+    }
+
+    /// Starts the verification of `cid` over one of the connections to it.
+    pub(crate) async fn start_verification(&mut self, cid: ContactId) {
+        log::trace!("{}", current_function!());
+        let Some((remote, sas)) = self
+            .open_connections
+            .get(&cid)
+            .into_iter()
+            .flatten()
+            .find_map(|remote| Some((*remote, self.short_auth_string(*remote, &cid)?)))
+        else {
+            log::warn!("Can't verify {cid}: not connected");
+            return;
+        };
+        self.verifications
+            .insert(cid.clone(), Verification::new(remote, sas));
+        self.send_net_cmd(NetworkCommand::SendEnvelope(
+            remote,
+            cid.clone(),
+            Envelope::Verification(SasMessage::Start).into(),
+        ))
+        .await;
+        self.send_ui_evt(UiEvent::VerificationStarted(cid, sas))
+            .await
+    }
+
+    /// Tells the contact whether the words matched for the user.
+    pub(crate) async fn confirm_verification(&mut self, cid: ContactId, matched: bool) {
+        log::trace!("{}", current_function!());
+        let Some(verification) = self.verifications.get_mut(&cid) else {
+            log::warn!("There is no verification of {cid} to confirm");
+            return;
+        };
+        verification.confirm_ours(matched);
+        let remote = verification.remote;
+        self.send_net_cmd(NetworkCommand::SendEnvelope(
+            remote,
+            cid.clone(),
+            Envelope::Verification(SasMessage::Confirm(matched)).into(),
+        ))
+        .await;
+        self.finish_verification(cid).await
+    }
+
+    pub(crate) async fn incoming_verification(
+        &mut self,
+        remote: SocketAddr,
+        id: ContactId,
+        step: SasMessage,
+    ) {
         log::trace!("{}", current_function!());
-        let data: Arc<Vec<u8>> = Arc::new(msg.to_wire());
-        let remote = match self.open_connections.get(&to) {
-            Some(r) => r,
-            None => {
-                return Err(ClientError::NoConnection(to.into()));
+        match step {
+            SasMessage::Start => {
+                if self
+                    .verifications
+                    .get(&id)
+                    .is_some_and(|v| v.remote == remote)
+                {
+                    // both sides have started at the same time
+                    return;
+                }
+                let Some(sas) = self.short_auth_string(remote, &id) else {
+                    log::warn!("Can't verify {remote} ({id}), the connection is not known");
+                    return;
+                };
+                self.verifications
+                    .insert(id.clone(), Verification::new(remote, sas));
+                self.send_ui_evt(UiEvent::VerificationStarted(id, sas))
+                    .await
+            }
+            SasMessage::Confirm(matched) => {
+                let Some(verification) = self
+                    .verifications
+                    .get_mut(&id)
+                    .filter(|v| v.remote == remote)
+                else {
+                    log::warn!(
+                        "Ignoring verification answer from {remote} ({id}), none is ongoing"
+                    );
+                    return;
+                };
+                verification.confirm_theirs(matched);
+                self.finish_verification(id).await
             }
+            SasMessage::Cancel => {
+                if self
+                    .verifications
+                    .get(&id)
+                    .is_some_and(|v| v.remote == remote)
+                {
+                    self.verifications.remove(&id);
+                    self.send_ui_evt(UiEvent::VerificationCancelled(id)).await
+                }
+            }
+        }
+    }
+
+    /// Applies the outcome of the verification of `cid` once both sides have answered.
+    async fn finish_verification(&mut self, cid: ContactId) {
+        let Some(trust) = self.verifications.get(&cid).and_then(Verification::outcome) else {
+            return;
         };
-        self.send_net_cmd(NetworkCommand::SendMessage(*remote, to.clone(), data))
+        self.verifications.remove(&cid);
+        self.set_trust(cid.clone(), trust);
+        self.send_ui_evt(UiEvent::SetKnownIdentities(self.known_identities.clone()))
             .await;
-        self.send_ui_evt(UiEvent::MessageSent(*remote, to, msg))
+        self.send_ui_evt(UiEvent::VerificationFinished(cid, trust))
+            .await
+    }
+
+    /// Starts pairing with `cid` using `code`, or answers the pairing the contact has started.
+    pub(crate) async fn start_pairing(&mut self, cid: ContactId, code: &PairingCode) {
+        log::trace!("{}", current_function!());
+        let Some(user) = self.user_identity.as_ref().map(|user| user.id()) else {
+            log::warn!("Can't pair with {cid}: no user identity");
+            return;
+        };
+        // prefer the connection the contact has started the pairing on
+        let Some((remote, handshake_hash)) = self
+            .pairings
+            .get(&cid)
+            .filter(|p| !p.has_code())
+            .map(|p| p.remote)
+            .into_iter()
+            .chain(
+                self.open_connections
+                    .get(&cid)
+                    .into_iter()
+                    .flatten()
+                    .copied(),
+            )
+            .find_map(|remote| Some((remote, *self.handshake_hashes.get(&remote)?)))
+        else {
+            log::warn!("Can't pair with {cid}: not connected");
+            return;
+        };
+        let pairing = Pairing::new(code, &handshake_hash, &user, &cid);
+        let share = pairing.share();
+        let state = match self.pairings.get_mut(&cid) {
+            Some(state) if state.remote == remote && !state.has_code() => state,
+            _ => self
+                .pairings
+                .entry(cid.clone())
+                .insert_entry(PairingState::new(remote))
+                .into_mut(),
+        };
+        state.set_ours(pairing);
+        self.send_net_cmd(NetworkCommand::SendEnvelope(
+            remote,
+            cid.clone(),
+            Envelope::Pairing(PairingMessage::Start(share)).into(),
+        ))
+        .await;
+        self.advance_pairing(cid).await
+    }
+
+    pub(crate) async fn incoming_pairing(
+        &mut self,
+        remote: SocketAddr,
+        id: ContactId,
+        step: PairingMessage,
+    ) {
+        log::trace!("{}", current_function!());
+        match step {
+            PairingMessage::Start(share) => {
+                if let Some(state) = self.pairings.get_mut(&id).filter(|p| p.remote == remote) {
+                    state.set_theirs(share);
+                    self.advance_pairing(id).await
+                } else {
+                    let mut state = PairingState::new(remote);
+                    state.set_theirs(share);
+                    self.pairings.insert(id.clone(), state);
+                    self.send_ui_evt(UiEvent::PairingRequested(id)).await
+                }
+            }
+            PairingMessage::Confirm(confirmation) => {
+                let Some(state) = self.pairings.get_mut(&id).filter(|p| p.remote == remote) else {
+                    log::warn!(
+                        "Ignoring pairing confirmation from {remote} ({id}), none is ongoing"
+                    );
+                    return;
+                };
+                state.set_their_confirmation(confirmation);
+                self.advance_pairing(id).await
+            }
+            PairingMessage::Cancel => {
+                if self.pairings.get(&id).is_some_and(|p| p.remote == remote) {
+                    self.pairings.remove(&id);
+                    self.send_ui_evt(UiEvent::PairingCancelled(id)).await
+                }
+            }
+        }
+    }
+
+    /// Sends the confirmation of the user once both shares are known, and applies the outcome
+    /// of the pairing with `cid` once both confirmations are.
+    async fn advance_pairing(&mut self, cid: ContactId) {
+        let Some(state) = self.pairings.get_mut(&cid) else {
+            return;
+        };
+        let remote = state.remote;
+        if let Some((ours, theirs)) = state.shares() {
+            let keys = match ours.finish(theirs) {
+                Ok(keys) => keys,
+                Err(e) => {
+                    log::warn!("Aborting pairing with {remote} ({cid}): {e}");
+                    self.pairings.remove(&cid);
+                    self.send_net_cmd(NetworkCommand::SendEnvelope(
+                        remote,
+                        cid.clone(),
+                        Envelope::Pairing(PairingMessage::Cancel).into(),
+                    ))
+                    .await;
+                    self.send_ui_evt(UiEvent::PairingCancelled(cid)).await;
+                    return;
+                }
+            };
+            let confirmation = keys.ours();
+            state.set_keys(keys);
+            self.send_net_cmd(NetworkCommand::SendEnvelope(
+                remote,
+                cid.clone(),
+                Envelope::Pairing(PairingMessage::Confirm(confirmation)).into(),
+            ))
             .await;
+        }
+
+        let Some(paired) = self.pairings.get(&cid).and_then(PairingState::outcome) else {
+            return;
+        };
+        self.pairings.remove(&cid);
+        if paired {
+            self.set_trust(cid.clone(), Trust::Verified);
+            self.send_ui_evt(UiEvent::SetKnownIdentities(self.known_identities.clone()))
+                .await;
+        } else {
+            log::warn!("Pairing with {remote} ({cid}) failed, the codes did not match");
+        }
+        self.send_ui_evt(UiEvent::PairingFinished(cid, paired))
+            .await
+    }
+
+    /// Hands envelopes that were refused because their stream was busy to the network domain
+    /// again. Envelopes for peers that are no longer connected are dropped.
+    pub(crate) async fn process_deferred(&mut self) {
+        if self.deferred.is_empty() {
+            return;
+        }
+        log::trace!("{}", current_function!());
+        for (remote, id, envelope) in std::mem::take(&mut self.deferred) {
+            if !self
+                .open_connections
+                .get(&id)
+                .is_some_and(|remotes| remotes.contains(&remote))
+            {
+                log::warn!("Dropping deferred {envelope} for {remote} ({id}): not connected");
+                continue;
+            }
+            self.send_net_cmd(NetworkCommand::SendEnvelope(remote, id, envelope))
+                .await;
+        }
+    }
+
+    pub(crate) async fn incoming_envelope(
+        &mut self,
+        remote: SocketAddr,
+        id: ContactId,
+        envelope: Arc<Envelope>,
+    ) -> ClientResult<()> {
+        log::trace!("{}", current_function!());
+        match &*envelope {
+            Envelope::ChatMessage(data) => self.incoming_message(remote, id, data).await?,
+            Envelope::Typing(typing) => self.send_ui_evt(UiEvent::ContactTyping(id, *typing)).await,
+            Envelope::IdentityStatement(statement) => {
+                // NOTE: the user presents endorsements of their identity to every peer, so
+                // contacts that do not know the endorser yet can see them too
+                let endorser_of_user = match &**statement {
+                    IdentityStatement::Endorsement(endorsement)
+                        if self
+                            .user_identity
+                            .as_ref()
+                            .is_some_and(|user| *endorsement.endorsed() == user.id()) =>
+                    {
+                        Some(endorsement.endorser())
+                    }
+                    _ => None,
+                };
+                if let Some(endorser) = endorser_of_user {
+                    match statement.verify() {
+                        Ok(()) if !self.known_identities.contains_key(&endorser) => {
+                            log::debug!(
+                                "Ignoring {statement} from {remote} ({id}) by an unknown endorser"
+                            )
+                        }
+                        Ok(()) if self.remember_statement(statement) => {
+                            log::info!("Keeping {statement} received from {remote} ({id})")
+                        }
+                        Ok(()) => log::trace!("{statement} is already known or outdated"),
+                        Err(e) => log::warn!("Ignoring {statement} from {remote} ({id}): {e}"),
+                    }
+                    return Ok(());
+                }
+                match self.known_identities.apply_statement(statement) {
+                    Ok(true) => {
+                        log::info!("Applied {statement} received from {remote} ({id})");
+                        self.send_ui_evt(UiEvent::SetKnownIdentities(self.known_identities.clone()))
+                            .await
+                    }
+                    Ok(false) => log::trace!("{statement} was already applied"),
+                    Err(e) => log::warn!("Ignoring {statement} from {remote} ({id}): {e}"),
+                }
+            }
+            Envelope::Verification(step) => self.incoming_verification(remote, id, *step).await,
+            Envelope::Pairing(step) => self.incoming_pairing(remote, id, *step).await,
+            Envelope::Ping(_) | Envelope::Pong(_) | Envelope::IdentityUpdate(_) => {
+                log::warn!("Received transport level {envelope} from the network domain")
+            }
+            Envelope::Unknown { kind, .. } => {
+                log::warn!("Ignoring envelope of unknown kind {kind:#06x} from {remote} ({id})")
+            }
+        }
         Ok(())
     }
 
     pub(crate) async fn incoming_message(
-        &self,
+        &mut self,
         remote: SocketAddr,
         id: ContactId,
-        data: Arc<Vec<u8>>,
-    ) -> CoreResult<()> {
-        todo!()
+        data: &[u8],
+    ) -> ClientResult<()> {
+        log::trace!("{}", current_function!());
+        if self
+            .known_identities
+            .get(&id)
+            .is_some_and(|c| matches!(c.trust, Trust::Rejected | Trust::Revoked))
+        {
+            log::info!("Dropping message from rejected or revoked contact {id}");
+            return Ok(());
+        }
+
+        let msg: SharedMessage = match Message::from_wire(data) {
+            Ok(msg) => msg.into(),
+            Err(e) => {
+                log::warn!("Dropping a malformed message from {remote} ({id}): {e}");
+                return Ok(());
+            }
+        };
+        if msg.meta().author_id != id {
+            log::warn!(
+                "Dropping message from {remote} ({id}) that claims to be written by {}",
+                msg.meta().author_id
+            );
+            return Ok(());
+        }
+
+        self.chats
+            .entry(id.clone())
+            .or_default()
+            .add_message(msg.clone());
+        self.send_ui_evt(UiEvent::IncomingMessage(remote, id, msg))
+            .await;
+        Ok(())
     }
 }
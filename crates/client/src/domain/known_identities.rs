@@ -7,11 +7,12 @@ use std::{
 use serde::{Deserialize, Serialize};
 
 use sremp_core::{
-    identity::{ContactId, ContactIdentity, Identity, Trust},
+    error::CoreError,
+    identity::{ContactId, ContactIdentity, Identity, IdentityStatement, Trust, username_skeleton},
     ser_helper::*,
 };
 
-use crate::error::ClientResult;
+use crate::error::{ClientError, ClientResult};
 
 pub type SharedContact = Arc<ContactIdentity>;
 
@@ -26,12 +27,30 @@ impl KnownIdentities {
         Self::default()
     }
 
+    /// Adds the contact with `iden`, or updates the known one if `iden` is newer.
+    ///
+    /// Fails with [`CoreError::StaleIdentity`] if `iden` is older than the known version, for
+    /// example because it still lists a device that was removed since.
     pub fn create_or_update(&mut self, iden: &Identity) -> ClientResult<SharedContact> {
         let id = iden.id();
         match self.inner.entry(id.clone()) {
             Entry::Occupied(mut entry) => {
                 let mut contact: ContactIdentity = (**entry.get()).clone();
+                if iden.version() < contact.version() {
+                    return Err(CoreError::StaleIdentity {
+                        id,
+                        known: contact.version(),
+                        received: iden.version(),
+                    }
+                    .into());
+                }
                 contact.seen();
+                if iden.version() > contact.version() {
+                    match contact.check_update(iden) {
+                        Ok(()) => contact.identity = iden.clone(),
+                        Err(e) => log::warn!("Not updating the identity of {id}: {e}"),
+                    }
+                }
                 entry.insert(contact.into());
             }
             Entry::Vacant(entry) => {
@@ -41,6 +60,162 @@ impl KnownIdentities {
         };
         Ok(self.inner[&id].clone())
     }
+
+    /// Like [`create_or_update`](Self::create_or_update), for a peer that has connected with the
+    /// device key `noise_key`.
+    ///
+    /// The devices of an identity each keep their own copy of it, so a device may still present
+    /// an older version after another device has changed the identity. That version is accepted
+    /// as long as the known version still certifies `noise_key`, a removed device is refused.
+    pub fn connected(
+        &mut self,
+        iden: &Identity,
+        noise_key: &x25519_dalek::PublicKey,
+    ) -> ClientResult<SharedContact> {
+        let id = iden.id();
+        let Some(known) = self
+            .inner
+            .get(&id)
+            .filter(|known| iden.version() < known.version() && known.is_device_key(noise_key))
+        else {
+            return self.create_or_update(iden);
+        };
+        let mut contact: ContactIdentity = (**known).clone();
+        contact.seen();
+        self.inner.insert(id.clone(), contact.into());
+        Ok(self.inner[&id].clone())
+    }
+
+    /// Replaces the [`Identity`] of a known contact with a newer version of it.
+    ///
+    /// Fails if the contact is unknown or if `iden` is not a valid update, see
+    /// [`Identity::check_update`].
+    pub fn update(&mut self, iden: &Identity) -> ClientResult<SharedContact> {
+        let id = iden.id();
+        let Some(known) = self.inner.get_mut(&id) else {
+            return Err(ClientError::UnknownContact(id.into()));
+        };
+        known.check_update(iden)?;
+        let mut contact: ContactIdentity = (**known).clone();
+        contact.identity = iden.clone();
+        contact.seen();
+        *known = contact.into();
+        Ok(known.clone())
+    }
+
+    /// Applies a verified [`IdentityStatement`] to the contact it is about.
+    ///
+    /// A revocation marks the contact as [`Trust::Revoked`]. If the contact already named a
+    /// successor, the successor loses the trust it inherited, since the succession may have been
+    /// made with a stolen key. A succession does the same, and
+    /// additionally hands the trust of the contact over to its successor, unless the user has
+    /// already decided about the successor. A contact that was revoked can not get a successor
+    /// anymore, since its key may be in the wrong hands. An endorsement by a known contact is
+    /// recorded on the contact it endorses, see [`ContactIdentity::add_endorsement`].
+    ///
+    /// Returns whether anything has changed.
+    pub fn apply_statement(&mut self, statement: &IdentityStatement) -> ClientResult<bool> {
+        statement.verify()?;
+        let id = statement.subject();
+        let Some(known) = self.inner.get(&id) else {
+            return Err(ClientError::UnknownContact(id.into()));
+        };
+        let mut contact: ContactIdentity = (**known).clone();
+
+        match statement {
+            IdentityStatement::Revocation(_) => {
+                let successor_demoted = match &contact.successor {
+                    Some(successor_id) => self.demote_inherited_trust(successor_id),
+                    None => false,
+                };
+                if contact.trust == Trust::Revoked {
+                    return Ok(successor_demoted);
+                }
+                contact.trust = Trust::Revoked;
+                self.inner.insert(id, contact.into());
+            }
+            IdentityStatement::Succession(succession) => {
+                let successor_id = succession.successor().id();
+                if contact.successor.as_ref() == Some(&successor_id) {
+                    return Ok(false);
+                }
+                if contact.trust == Trust::Revoked {
+                    return Err(ClientError::ContactRevoked(id.into()));
+                }
+                // NOTE: the successor goes in first, a successor that can not be added must not
+                // leave the contact revoked without one
+                let successor = self.create_or_update(succession.successor())?;
+                if successor.trust == Trust::Unknown {
+                    let mut successor: ContactIdentity = (*successor).clone();
+                    successor.trust = contact.trust;
+                    successor.trust_inherited = true;
+                    self.inner.insert(successor_id.clone(), successor.into());
+                }
+
+                contact.trust = Trust::Revoked;
+                contact.successor = Some(successor_id);
+                self.inner.insert(id, contact.into());
+            }
+            IdentityStatement::Endorsement(endorsement) => {
+                // NOTE: anyone can endorse with a fresh key, only known endorsers are worth keeping
+                if !self.inner.contains_key(&endorsement.endorser())
+                    || !contact.add_endorsement(endorsement.clone())
+                {
+                    return Ok(false);
+                }
+                self.inner.insert(id, contact.into());
+            }
+        }
+        Ok(true)
+    }
+
+    /// Takes back the trust that the contact `id` inherited from the identity it succeeded.
+    ///
+    /// Returns whether the contact had inherited trust.
+    fn demote_inherited_trust(&mut self, id: &ContactId) -> bool {
+        let Some(known) = self.inner.get(id) else {
+            return false;
+        };
+        if !known.trust_inherited {
+            return false;
+        }
+        let mut contact: ContactIdentity = (**known).clone();
+        contact.trust = Trust::Unknown;
+        contact.trust_inherited = false;
+        self.inner.insert(id.clone(), contact.into());
+        true
+    }
+
+    /// Contacts the user trusts that vouch for `id`, see [`ContactIdentity::endorsers`].
+    pub fn vouched_for_by(&self, id: &ContactId) -> Vec<SharedContact> {
+        let Some(contact) = self.inner.get(id) else {
+            return Vec::new();
+        };
+        contact
+            .endorsers()
+            .filter_map(|endorser| self.inner.get(&endorser))
+            .filter(|endorser| matches!(endorser.trust, Trust::Trusted | Trust::Verified))
+            .cloned()
+            .collect()
+    }
+
+    /// Other contacts whose username looks like the one of `id`, see [`username_skeleton`].
+    ///
+    /// A predecessor that was succeeded by `id` is not included, it usually has the same name.
+    pub fn lookalikes(&self, id: &ContactId) -> Vec<SharedContact> {
+        let Some(contact) = self.inner.get(id) else {
+            return Vec::new();
+        };
+        let skeleton = username_skeleton(contact.username());
+        self.inner
+            .values()
+            .filter(|other| other.id() != *id)
+            .filter(|other| other.successor.as_ref() != Some(id))
+            .filter(|other| contact.successor.as_ref() != Some(&other.id()))
+            .filter(|other| username_skeleton(other.username()) == skeleton)
+            .cloned()
+            .collect()
+    }
 }
 
 impl Deref for KnownIdentities {
@@ -56,3 +231,121 @@ impl DerefMut for KnownIdentities {
         &mut self.inner
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use sremp_core::identity::UserIdentity;
+
+    use super::*;
+
+    #[test]
+    fn revocation_after_succession_demotes_the_successor() {
+        let alice = UserIdentity::create("alice").unwrap();
+        let (successor, succession) = alice.create_successor().unwrap();
+        let revocation = alice.revoke("my key was stolen").unwrap();
+
+        let mut known = KnownIdentities::new();
+        let mut contact = (*known.create_or_update(&alice.identity).unwrap()).clone();
+        contact.trust = Trust::Trusted;
+        known.insert(alice.id(), contact.into());
+
+        assert!(
+            known
+                .apply_statement(&IdentityStatement::Succession(succession))
+                .unwrap()
+        );
+        assert_eq!(known[&successor.id()].trust, Trust::Trusted);
+
+        assert!(
+            known
+                .apply_statement(&IdentityStatement::Revocation(revocation.clone()))
+                .unwrap()
+        );
+        assert_eq!(known[&alice.id()].trust, Trust::Revoked);
+        assert_eq!(known[&successor.id()].trust, Trust::Unknown);
+        assert!(
+            !known
+                .apply_statement(&IdentityStatement::Revocation(revocation))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn revocation_keeps_trust_the_user_gave_the_successor() {
+        let alice = UserIdentity::create("alice").unwrap();
+        let (successor, succession) = alice.create_successor().unwrap();
+
+        let mut known = KnownIdentities::new();
+        let mut contact = (*known.create_or_update(&alice.identity).unwrap()).clone();
+        contact.trust = Trust::Trusted;
+        known.insert(alice.id(), contact.into());
+        known
+            .apply_statement(&IdentityStatement::Succession(succession))
+            .unwrap();
+        let mut contact = (*known[&successor.id()]).clone();
+        contact.trust = Trust::Verified;
+        contact.trust_inherited = false;
+        known.insert(successor.id(), contact.into());
+
+        known
+            .apply_statement(&IdentityStatement::Revocation(
+                alice.revoke("lost my phone").unwrap(),
+            ))
+            .unwrap();
+        assert_eq!(known[&successor.id()].trust, Trust::Verified);
+    }
+
+    #[test]
+    fn failed_succession_keeps_the_contact() {
+        let alice = UserIdentity::create("alice").unwrap();
+        let (mut successor, succession) = alice.create_successor().unwrap();
+        successor
+            .identity
+            .set_username("alice2", &mut successor.identity_key.clone())
+            .unwrap();
+
+        let mut known = KnownIdentities::new();
+        let mut contact = (*known.create_or_update(&alice.identity).unwrap()).clone();
+        contact.trust = Trust::Trusted;
+        known.insert(alice.id(), contact.into());
+        // a newer version of the successor is known already, the one in the succession is stale
+        known.create_or_update(&successor.identity).unwrap();
+
+        assert!(
+            known
+                .apply_statement(&IdentityStatement::Succession(succession))
+                .is_err()
+        );
+        assert_eq!(known[&alice.id()].trust, Trust::Trusted);
+        assert_eq!(known[&alice.id()].successor, None);
+    }
+
+    #[test]
+    fn only_endorsements_by_known_contacts_are_kept() {
+        let alice = UserIdentity::create("alice").unwrap();
+        let bob = UserIdentity::create("bob").unwrap();
+        let stranger = UserIdentity::create("stranger").unwrap();
+
+        let mut known = KnownIdentities::new();
+        known.create_or_update(&bob.identity).unwrap();
+        let by_stranger = stranger.endorse(bob.id(), false).unwrap();
+        assert!(
+            !known
+                .apply_statement(&IdentityStatement::Endorsement(by_stranger))
+                .unwrap()
+        );
+        assert!(known[&bob.id()].endorsements.is_empty());
+
+        known.create_or_update(&alice.identity).unwrap();
+        let by_alice = alice.endorse(bob.id(), false).unwrap();
+        assert!(
+            known
+                .apply_statement(&IdentityStatement::Endorsement(by_alice))
+                .unwrap()
+        );
+        assert_eq!(
+            known[&bob.id()].endorsers().collect::<Vec<_>>(),
+            [alice.id()]
+        );
+    }
+}
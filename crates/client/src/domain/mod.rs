@@ -1,4 +1,10 @@
-use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+    path::PathBuf,
+    sync::Arc,
+    time::Instant,
+};
 
 use async_channel::{Receiver, Sender};
 use serde::{Deserialize, Serialize};
@@ -7,13 +13,18 @@ use tokio::{sync::RwLock, task::JoinHandle};
 mod commands;
 mod events;
 mod jobs;
+mod persist;
 
 pub use commands::UiCommand;
 pub use events::UiEvent;
 use sremp_core::{
-    domain::{NetworkCommand, NetworkEvent},
+    domain::{
+        NetworkCommand, NetworkEvent,
+        priority::{PriorityReceiver, PrioritySender},
+    },
     error::CoreError,
-    identity::{ContactId, UserIdentity},
+    identity::{ContactId, IdentityStatement, UserIdentity},
+    net::{connection::HandshakeHash, envelope::Envelope},
     ser_helper::*,
 };
 
@@ -23,18 +34,71 @@ pub mod chats;
 use chats::*;
 pub mod known_identities;
 use known_identities::*;
+pub mod outbox;
+use outbox::*;
+pub mod pairing;
+pub mod tofu;
+use pairing::*;
+pub mod verification;
+use verification::*;
 
 pub type ClientDomainSync = Arc<RwLock<ClientDomain>>;
 
 const JOB_ITERATION_INTERVAL_MS: u64 = 200;
 
+/// Capacity of the channel for [`UiCommand`]s
+pub const UI_COMMAND_CAPACITY: usize = 256;
+/// Capacity of the bulk lane of the channel for [`UiEvent`]s, see
+/// [`priority`](sremp_core::domain::priority)
+pub const UI_EVENT_BULK_CAPACITY: usize = 256;
+
+/// The state of the client and its jobs. Fields that do not outlive a run are not serialized, see
+/// [`ClientDomain::load`].
 #[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
 pub struct ClientDomain {
     pub(crate) known_identities: KnownIdentities,
     pub(crate) chats: Chats,
     #[serde(serialize_with = "ser_arc_opt", deserialize_with = "deser_arc_opt")]
     pub(crate) user_identity: Option<Arc<UserIdentity>>,
-    pub(crate) open_connections: HashMap<ContactId, SocketAddr>,
+    /// Remote addresses of the connections to each contact, a contact may be connected more than
+    /// once
+    #[serde(skip)]
+    pub(crate) open_connections: HashMap<ContactId, HashSet<SocketAddr>>,
+    /// Messages of the user that were not sent yet
+    pub(crate) outbox: Outbox,
+    /// Successions and revocations of identities of the user, sent to every peer that connects
+    pub(crate) statements: Vec<IdentityStatement>,
+    /// The contact that answered on each address the user has connected to, to notice when
+    /// another key answers there
+    pub(crate) known_endpoints: HashMap<SocketAddr, ContactId>,
+    /// Envelopes that could not be queued because their stream was busy, retried periodically
+    #[serde(skip)]
+    pub(crate) deferred: Vec<(SocketAddr, ContactId, Arc<Envelope>)>,
+    /// Hashes of the noise handshakes of the open connections
+    #[serde(skip)]
+    pub(crate) handshake_hashes: HashMap<SocketAddr, HandshakeHash>,
+    /// Connection attempts with an invitation, by the endpoint that is currently tried. Holds the
+    /// pinned contact and the endpoints to try next.
+    #[serde(skip)]
+    pub(crate) invitation_attempts: HashMap<SocketAddr, (ContactId, Vec<SocketAddr>)>,
+    /// Addresses the user has connected to, as opposed to peers that have connected to the user
+    #[serde(skip)]
+    pub(crate) dialed: HashSet<SocketAddr>,
+    /// Ongoing verifications with short authentication strings, at most one per contact
+    #[serde(skip)]
+    pub(crate) verifications: HashMap<ContactId, Verification>,
+    /// Ongoing pairings with a code, at most one per contact
+    #[serde(skip)]
+    pub(crate) pairings: HashMap<ContactId, PairingState>,
+    /// Where the state is saved, if it was loaded from there
+    #[serde(skip)]
+    state_path: Option<PathBuf>,
+    /// Whether the state has changed since it was saved
+    #[serde(skip)]
+    unsaved: bool,
+    #[serde(skip)]
+    last_save: Option<Instant>,
     #[serde(skip)]
     channels: Option<Channels>,
 }
@@ -42,9 +106,9 @@ pub struct ClientDomain {
 #[derive(Debug, Clone)]
 pub(crate) struct Channels {
     pub(crate) net_command_channel: Sender<NetworkCommand>,
-    pub(crate) net_event_channel: Receiver<NetworkEvent>,
+    pub(crate) net_event_channel: PriorityReceiver<NetworkEvent>,
     pub(crate) ui_command_channel: Receiver<UiCommand>,
-    pub(crate) ui_event_channel: Sender<UiEvent>,
+    pub(crate) ui_event_channel: PrioritySender<UiEvent>,
 }
 
 impl ClientDomain {
@@ -58,24 +122,47 @@ impl ClientDomain {
 
     async fn run(self) -> ClientResult<()> {
         let ssy = self.into_sync();
+        ssy.write().await.announce_loaded_state().await?;
+        let result = Self::process(&ssy).await;
+        ssy.write().await.save();
+        result
+    }
+
+    async fn process(ssy: &ClientDomainSync) -> ClientResult<()> {
         loop {
             let this = ssy.read().await;
+            // NOTE: commands of the user come first, so they are handled even while the peers
+            // keep us busy
             tokio::select! {
+                biased;
                 cmd = this.ui_command_channel().recv() => {
                     drop(this);
-                    let cmd = cmd.map_err(CoreError::from)?;
-                    ssy.write().await.process_ui_command(
-                        cmd,
-                    ).await?;
+                    // NOTE: the frontend closes its channel when it quits
+                    let Ok(cmd) = cmd else {
+                        return Ok(());
+                    };
+                    let mut this = ssy.write().await;
+                    // NOTE: a command that fails must not stop the domain, the next one may work
+                    if let Err(e) = this.process_ui_command(cmd).await {
+                        log::error!("Could not process a command of the user: {e}");
+                    }
+                    this.state_changed();
                 },
                 evt = this.net_event_channel().recv() => {
                     drop(this);
                     let evt = evt.map_err(CoreError::from)?;
-                    ssy.write().await.process_net_event(evt).await?;
+                    let mut this = ssy.write().await;
+                    if let Err(e) = this.process_net_event(evt).await {
+                        log::error!("Could not process a network event: {e}");
+                    }
+                    this.state_changed();
                 },
                 _ = tokio::time::sleep(tokio::time::Duration::from_millis(JOB_ITERATION_INTERVAL_MS)) => {
                     // WARN: not sure, but this might kill the execution of other branches?
-                    continue;
+                    drop(this);
+                    let mut this = ssy.write().await;
+                    this.process_deferred().await;
+                    this.save_if_due();
                 }
             };
         }
@@ -84,9 +171,9 @@ impl ClientDomain {
     pub fn start(
         mut self,
         net_command_channel: Sender<NetworkCommand>,
-        net_event_channel: Receiver<NetworkEvent>,
+        net_event_channel: PriorityReceiver<NetworkEvent>,
         ui_command_channel: Receiver<UiCommand>,
-        ui_event_channel: Sender<UiEvent>,
+        ui_event_channel: PrioritySender<UiEvent>,
         rt: &mut tokio::runtime::Runtime,
     ) -> ClientResult<JoinHandle<ClientResult<()>>> {
         self.channels = Some(Channels {
@@ -113,7 +200,7 @@ impl ClientDomain {
     }
 
     #[inline]
-    pub(crate) fn net_event_channel(&self) -> &Receiver<NetworkEvent> {
+    pub(crate) fn net_event_channel(&self) -> &PriorityReceiver<NetworkEvent> {
         &self.channels_ref().net_event_channel
     }
 
@@ -123,7 +210,7 @@ impl ClientDomain {
     }
 
     #[inline]
-    pub(crate) fn ui_event_channel(&self) -> &Sender<UiEvent> {
+    pub(crate) fn ui_event_channel(&self) -> &PrioritySender<UiEvent> {
         &self.channels_ref().ui_event_channel
     }
 
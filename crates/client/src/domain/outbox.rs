@@ -0,0 +1,300 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Display,
+    net::SocketAddr,
+    ops::Deref,
+    sync::Arc,
+};
+
+use serde::{Deserialize, Serialize};
+use sremp_core::{chat::messages::SharedMessage, identity::ContactId, net::envelope::Envelope};
+
+/// How often a message is handed to a connection before it is considered failed
+pub const MAX_DELIVERY_ATTEMPTS: u32 = 5;
+
+/// How far a message written by the user has come
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DeliveryState {
+    /// Waiting in the [`Outbox`] for a connection to the contact
+    Pending,
+    /// Written to a connection to the contact
+    Sent,
+    /// Given up after [`MAX_DELIVERY_ATTEMPTS`], until the user retries it
+    Failed,
+}
+
+/// A message in the [`Outbox`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxEntry {
+    pub message: SharedMessage,
+    /// Either [`DeliveryState::Pending`] or [`DeliveryState::Failed`], sent messages leave the
+    /// outbox
+    pub state: DeliveryState,
+    pub attempts: u32,
+    /// The current attempt, if there is one
+    #[serde(skip)]
+    in_flight: Option<InFlight>,
+}
+
+/// A message that was handed to the network domain
+#[derive(Debug, Clone)]
+struct InFlight {
+    /// Recognized when the network domain reports it as queued or sent
+    envelope: Arc<Envelope>,
+    /// The connection it was queued on, once the network domain has picked one
+    remote: Option<SocketAddr>,
+}
+
+/// Messages written by the user that were not sent yet, per contact and in the order they were
+/// written.
+///
+/// The outbox is part of the state that the [`ClientDomain`](super::ClientDomain) saves, so
+/// messages to contacts that are offline survive a restart, see
+/// [`ClientDomain::load`](super::ClientDomain::load).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Outbox {
+    inner: HashMap<ContactId, VecDeque<OutboxEntry>>,
+}
+
+impl Outbox {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Queues a message to `to` as [`DeliveryState::Pending`].
+    pub(crate) fn push(&mut self, to: ContactId, message: SharedMessage) {
+        self.inner.entry(to).or_default().push_back(OutboxEntry {
+            message,
+            state: DeliveryState::Pending,
+            attempts: 0,
+            in_flight: None,
+        });
+    }
+
+    /// Returns the envelopes of all pending messages to `to` that are not in flight yet, in
+    /// order, and counts the attempt.
+    pub(crate) fn take_ready(&mut self, to: &ContactId) -> Vec<Arc<Envelope>> {
+        let Some(entries) = self.inner.get_mut(to) else {
+            return Vec::new();
+        };
+        entries
+            .iter_mut()
+            .filter(|e| e.state == DeliveryState::Pending && e.in_flight.is_none())
+            .map(|e| {
+                let envelope: Arc<Envelope> =
+                    Envelope::ChatMessage(e.message.to_wire().into()).into();
+                e.attempts += 1;
+                e.in_flight = Some(InFlight {
+                    envelope: envelope.clone(),
+                    remote: None,
+                });
+                envelope
+            })
+            .collect()
+    }
+
+    /// Remembers the connection the envelope of a message was queued on.
+    pub(crate) fn queued(&mut self, to: &ContactId, envelope: &Arc<Envelope>, remote: SocketAddr) {
+        let Some(entries) = self.inner.get_mut(to) else {
+            return;
+        };
+        if let Some(in_flight) = entries
+            .iter_mut()
+            .filter_map(|e| e.in_flight.as_mut())
+            .find(|i| Arc::ptr_eq(&i.envelope, envelope))
+        {
+            in_flight.remote = Some(remote);
+        }
+    }
+
+    /// Removes the message whose envelope was sent and returns it.
+    pub(crate) fn confirm(
+        &mut self,
+        to: &ContactId,
+        envelope: &Arc<Envelope>,
+    ) -> Option<SharedMessage> {
+        let entries = self.inner.get_mut(to)?;
+        let idx = entries.iter().position(|e| {
+            e.in_flight
+                .as_ref()
+                .is_some_and(|i| Arc::ptr_eq(&i.envelope, envelope))
+        })?;
+        let entry = entries.remove(idx)?;
+        if entries.is_empty() {
+            self.inner.remove(to);
+        }
+        Some(entry.message)
+    }
+
+    /// The connection to `to` on `remote` was lost, so the messages queued on it were not sent.
+    ///
+    /// If it was the last connection to `to`, this goes for all messages in flight, also those
+    /// the network domain has not picked a connection for yet.
+    ///
+    /// They are attempted again on the next connection, unless they have used up their
+    /// attempts. Returns the messages that have failed because of that.
+    pub(crate) fn connection_lost(
+        &mut self,
+        to: &ContactId,
+        remote: SocketAddr,
+        last: bool,
+    ) -> Vec<SharedMessage> {
+        let Some(entries) = self.inner.get_mut(to) else {
+            return Vec::new();
+        };
+        let mut failed = Vec::new();
+        let lost = entries.iter_mut().filter(|e| {
+            e.in_flight
+                .as_ref()
+                .is_some_and(|i| last || i.remote == Some(remote))
+        });
+        for entry in lost {
+            entry.in_flight = None;
+            if entry.attempts >= MAX_DELIVERY_ATTEMPTS {
+                entry.state = DeliveryState::Failed;
+                failed.push(entry.message.clone());
+            }
+        }
+        failed
+    }
+
+    /// Makes the failed messages to `to` pending again and returns them.
+    pub(crate) fn retry_failed(&mut self, to: &ContactId) -> Vec<SharedMessage> {
+        let Some(entries) = self.inner.get_mut(to) else {
+            return Vec::new();
+        };
+        entries
+            .iter_mut()
+            .filter(|e| e.state == DeliveryState::Failed)
+            .map(|e| {
+                e.state = DeliveryState::Pending;
+                e.attempts = 0;
+                e.message.clone()
+            })
+            .collect()
+    }
+
+    /// Returns the state of a message in the outbox, messages that are not in it were either
+    /// sent or not written by the user.
+    pub fn state_of(&self, to: &ContactId, message: &SharedMessage) -> Option<DeliveryState> {
+        self.inner
+            .get(to)?
+            .iter()
+            .find(|e| e.message == *message)
+            .map(|e| e.state)
+    }
+}
+
+impl Deref for Outbox {
+    type Target = HashMap<ContactId, VecDeque<OutboxEntry>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl Display for DeliveryState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Pending => "pending",
+                Self::Sent => "sent",
+                Self::Failed => "failed",
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sremp_core::{chat::messages::Message, identity::UserIdentity};
+
+    use super::*;
+
+    fn message(text: &str) -> SharedMessage {
+        let from = UserIdentity::create("alice").unwrap().id();
+        Message::new(text, chrono::Utc::now(), from).into()
+    }
+
+    #[test]
+    fn messages_leave_in_order_once_confirmed() {
+        let bob = UserIdentity::create("bob").unwrap().id();
+        let mut outbox = Outbox::new();
+        let (first, second) = (message("first"), message("second"));
+        outbox.push(bob.clone(), first.clone());
+        outbox.push(bob.clone(), second.clone());
+
+        let ready = outbox.take_ready(&bob);
+        assert_eq!(ready.len(), 2);
+        assert!(outbox.take_ready(&bob).is_empty(), "already in flight");
+
+        assert_eq!(outbox.confirm(&bob, &ready[0]), Some(first.clone()));
+        assert_eq!(outbox.state_of(&bob, &first), None);
+        assert_eq!(outbox.state_of(&bob, &second), Some(DeliveryState::Pending));
+        assert_eq!(outbox.confirm(&bob, &ready[1]), Some(second));
+        assert!(outbox.is_empty());
+    }
+
+    fn remote(port: u16) -> SocketAddr {
+        ([10, 0, 0, 2], port).into()
+    }
+
+    #[test]
+    fn only_messages_on_the_lost_connection_are_sent_again() {
+        let bob = UserIdentity::create("bob").unwrap().id();
+        let mut outbox = Outbox::new();
+        for text in ["first", "second", "third"] {
+            outbox.push(bob.clone(), message(text));
+        }
+        let ready = outbox.take_ready(&bob);
+        outbox.queued(&bob, &ready[0], remote(1));
+        outbox.queued(&bob, &ready[1], remote(2));
+
+        // the second connection is still there
+        outbox.connection_lost(&bob, remote(1), false);
+        assert_eq!(outbox.take_ready(&bob).len(), 1);
+        assert!(outbox.confirm(&bob, &ready[1]).is_some());
+
+        // the third message was not queued on any connection yet
+        outbox.connection_lost(&bob, remote(2), true);
+        assert_eq!(outbox.take_ready(&bob).len(), 2);
+    }
+
+    #[test]
+    fn messages_fail_after_too_many_attempts() {
+        let bob = UserIdentity::create("bob").unwrap().id();
+        let mut outbox = Outbox::new();
+        let msg = message("hello");
+        outbox.push(bob.clone(), msg.clone());
+
+        for _ in 1..MAX_DELIVERY_ATTEMPTS {
+            assert_eq!(outbox.take_ready(&bob).len(), 1);
+            assert!(outbox.connection_lost(&bob, remote(1), true).is_empty());
+        }
+        assert_eq!(outbox.take_ready(&bob).len(), 1);
+        assert_eq!(outbox.connection_lost(&bob, remote(1), true).len(), 1);
+        assert_eq!(outbox.state_of(&bob, &msg), Some(DeliveryState::Failed));
+        assert!(outbox.take_ready(&bob).is_empty());
+
+        assert_eq!(outbox.retry_failed(&bob).len(), 1);
+        assert_eq!(outbox.state_of(&bob, &msg), Some(DeliveryState::Pending));
+        assert_eq!(outbox.take_ready(&bob).len(), 1);
+    }
+
+    #[test]
+    fn pending_messages_survive_serialization() {
+        let bob = UserIdentity::create("bob").unwrap().id();
+        let mut outbox = Outbox::new();
+        let msg = message("hello");
+        outbox.push(bob.clone(), msg.clone());
+        outbox.take_ready(&bob);
+
+        let outbox: Outbox =
+            rmp_serde::from_slice(&rmp_serde::to_vec_named(&outbox).unwrap()).unwrap();
+        assert_eq!(outbox.state_of(&bob, &msg), Some(DeliveryState::Pending));
+        // the attempt in flight did not finish before the restart
+        assert_eq!(outbox.clone().take_ready(&bob).len(), 1);
+    }
+}
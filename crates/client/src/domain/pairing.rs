@@ -0,0 +1,76 @@
+use std::net::SocketAddr;
+
+use sremp_core::identity::{Pairing, PairingConfirmation, PairingKeys, PairingShare};
+
+/// An ongoing pairing with a contact over one connection.
+///
+/// Either side may start, the other side is asked for the code when the first share arrives. The
+/// contact is [`Trust::Verified`](sremp_core::identity::Trust::Verified) once both
+/// confirmations match. A mismatch leaves the trust as it was, since it is usually a typo.
+#[derive(Debug)]
+pub struct PairingState {
+    /// The connection whose handshake the pairing is bound to
+    pub remote: SocketAddr,
+    ours: Option<Pairing>,
+    theirs: Option<PairingShare>,
+    keys: Option<PairingKeys>,
+    their_confirmation: Option<PairingConfirmation>,
+}
+
+impl PairingState {
+    pub fn new(remote: SocketAddr) -> Self {
+        Self {
+            remote,
+            ours: None,
+            theirs: None,
+            keys: None,
+            their_confirmation: None,
+        }
+    }
+
+    /// Whether the user has entered a code already
+    #[inline]
+    pub fn has_code(&self) -> bool {
+        self.ours.is_some()
+    }
+
+    /// Records the side of the user, made from the code they entered.
+    #[inline]
+    pub fn set_ours(&mut self, pairing: Pairing) {
+        self.ours = Some(pairing);
+    }
+
+    /// Records the share of the contact.
+    #[inline]
+    pub fn set_theirs(&mut self, share: PairingShare) {
+        self.theirs = Some(share);
+    }
+
+    /// Records the confirmation of the contact.
+    #[inline]
+    pub fn set_their_confirmation(&mut self, confirmation: PairingConfirmation) {
+        self.their_confirmation = Some(confirmation);
+    }
+
+    /// Both shares, if they are known and the confirmations were not computed yet
+    pub fn shares(&self) -> Option<(&Pairing, &PairingShare)> {
+        if self.keys.is_some() {
+            return None;
+        }
+        Some((self.ours.as_ref()?, self.theirs.as_ref()?))
+    }
+
+    #[inline]
+    pub fn set_keys(&mut self, keys: PairingKeys) {
+        self.keys = Some(keys);
+    }
+
+    /// `true` if both users entered the same code, or [`None`] while a confirmation is missing
+    pub fn outcome(&self) -> Option<bool> {
+        Some(
+            self.keys
+                .as_ref()?
+                .verify(self.their_confirmation.as_ref()?),
+        )
+    }
+}
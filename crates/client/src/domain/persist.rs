@@ -0,0 +1,130 @@
+//! Saving the state of the [`ClientDomain`] to a file, so contacts, chats, the outbox and the
+//! published identity statements survive a restart.
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use sremp_core::error::CoreError;
+
+use crate::{
+    domain::{ClientDomain, UiEvent},
+    error::ClientResult,
+};
+
+/// How long changes may wait before they are saved, so that a burst of events is saved once
+pub(crate) const SAVE_INTERVAL: Duration = Duration::from_secs(1);
+
+impl ClientDomain {
+    /// Loads the state saved at `path`, or starts with an empty state if there is no file yet.
+    ///
+    /// While the domain runs, it saves its state there again. The file holds the secret keys of
+    /// the user identity, so on unix only the user may read it.
+    pub fn load(path: impl Into<PathBuf>) -> ClientResult<Self> {
+        let path = path.into();
+        let mut domain: Self = match fs::read(&path) {
+            Ok(data) => rmp_serde::from_slice(&data).map_err(CoreError::from)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(CoreError::from(e).into()),
+        };
+        domain.state_path = Some(path);
+        Ok(domain)
+    }
+
+    /// Tells the UI and the network domain about a state that was loaded with
+    /// [`load`](Self::load).
+    pub(crate) async fn announce_loaded_state(&mut self) -> ClientResult<()> {
+        if self.state_path.is_none() {
+            return Ok(());
+        }
+        self.send_ui_evt(UiEvent::SetKnownIdentities(self.known_identities.clone()))
+            .await;
+        self.send_ui_evt(UiEvent::LoadedChats(self.chats.clone()))
+            .await;
+        if self.user_identity.is_some() {
+            self.set_identity(self.user_identity.clone()).await?;
+        }
+        Ok(())
+    }
+
+    /// Remembers that the state has changed, and saves it unless the last save was less than
+    /// [`SAVE_INTERVAL`] ago.
+    pub(crate) fn state_changed(&mut self) {
+        self.unsaved = true;
+        self.save_if_due();
+    }
+
+    /// Saves changes that are waiting since [`SAVE_INTERVAL`] or longer.
+    pub(crate) fn save_if_due(&mut self) {
+        if self
+            .last_save
+            .is_none_or(|last| last.elapsed() >= SAVE_INTERVAL)
+        {
+            self.save();
+        }
+    }
+
+    /// Saves the state now if it has changed. Errors are logged, the next change tries again.
+    pub(crate) fn save(&mut self) {
+        let Some(path) = &self.state_path else {
+            return;
+        };
+        if !self.unsaved {
+            return;
+        }
+        match write_state(path, self) {
+            Ok(()) => {
+                log::debug!("Saved the state to {}", path.display());
+                self.unsaved = false;
+            }
+            Err(e) => log::error!("Could not save the state to {}: {e}", path.display()),
+        }
+        self.last_save = Some(Instant::now());
+    }
+}
+
+/// Writes the state next to `path` first and then moves it there, so a crash while writing does
+/// not destroy the last state.
+fn write_state(path: &Path, domain: &ClientDomain) -> ClientResult<()> {
+    let data = rmp_serde::to_vec_named(domain).map_err(CoreError::from)?;
+    let tmp = path.with_extension("tmp");
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(&tmp).map_err(CoreError::from)?;
+    file.write_all(&data).map_err(CoreError::from)?;
+    file.sync_all().map_err(CoreError::from)?;
+    fs::rename(&tmp, path).map_err(CoreError::from)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use sremp_core::identity::{IdentityStatement, UserIdentity};
+
+    use super::*;
+
+    #[test]
+    fn published_statements_survive_a_restart() {
+        let path = std::env::temp_dir().join(format!("sremp-persist-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let user = UserIdentity::create("alice").unwrap();
+
+        let mut domain = ClientDomain::load(&path).unwrap();
+        domain
+            .statements
+            .push(IdentityStatement::Revocation(user.revoke("lost").unwrap()));
+        domain.state_changed();
+        assert!(!domain.unsaved);
+
+        let domain = ClientDomain::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(domain.statements.len(), 1);
+        assert_eq!(domain.statements[0].subject(), user.id());
+    }
+}
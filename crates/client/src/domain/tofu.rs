@@ -0,0 +1,51 @@
+use std::fmt::Display;
+
+use sremp_core::identity::ContactId;
+
+/// What is known about the peer of a new connection, so the frontend can decide whether to ask
+/// the user to trust it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PeerStatus {
+    /// A known contact that is [`Trust::Trusted`](sremp_core::identity::Trust::Trusted) or
+    /// [`Trust::Verified`](sremp_core::identity::Trust::Verified), there is no need to ask
+    Trusted,
+    /// A known contact that was rejected or revoked, the connection should be closed
+    Rejected,
+    /// The user has not decided about this contact yet, and nothing suggests an impersonation
+    New,
+    /// The peer presents a different key than expected, like an SSH host key that has changed.
+    /// Someone may be impersonating a known contact.
+    KeyChanged {
+        /// The contact that answered on this address before, when the user connected to it
+        address_of: Option<ContactId>,
+        /// Known contacts with a username that looks like the one of the peer
+        name_of: Vec<ContactId>,
+    },
+}
+
+impl Display for PeerStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Trusted => "trusted".to_string(),
+                Self::Rejected => "rejected".to_string(),
+                Self::New => "new".to_string(),
+                Self::KeyChanged {
+                    address_of,
+                    name_of,
+                } => {
+                    let mut reasons = Vec::new();
+                    if let Some(id) = address_of {
+                        reasons.push(format!("the address belonged to {id}"));
+                    }
+                    if !name_of.is_empty() {
+                        reasons.push(format!("the name looks like {} contacts", name_of.len()));
+                    }
+                    format!("key changed ({})", reasons.join(", "))
+                }
+            }
+        )
+    }
+}
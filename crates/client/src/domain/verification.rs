@@ -0,0 +1,48 @@
+use std::net::SocketAddr;
+
+use sremp_core::identity::{ShortAuthString, Trust};
+
+/// An ongoing verification of a contact with the [`ShortAuthString`] of one connection.
+///
+/// The contact is [`Trust::Verified`] once both users have confirmed that the words match, and
+/// [`Trust::Suspicious`] as soon as one of them says they don't.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verification {
+    /// The connection whose handshake the words are derived from
+    pub remote: SocketAddr,
+    pub sas: ShortAuthString,
+    ours: Option<bool>,
+    theirs: Option<bool>,
+}
+
+impl Verification {
+    pub fn new(remote: SocketAddr, sas: ShortAuthString) -> Self {
+        Self {
+            remote,
+            sas,
+            ours: None,
+            theirs: None,
+        }
+    }
+
+    /// Records whether the words matched for the user.
+    #[inline]
+    pub fn confirm_ours(&mut self, matched: bool) {
+        self.ours = Some(matched);
+    }
+
+    /// Records whether the words matched for the contact.
+    #[inline]
+    pub fn confirm_theirs(&mut self, matched: bool) {
+        self.theirs = Some(matched);
+    }
+
+    /// The new trust of the contact, or [`None`] while an answer is missing
+    pub fn outcome(&self) -> Option<Trust> {
+        match (self.ours, self.theirs) {
+            (Some(false), _) | (_, Some(false)) => Some(Trust::Suspicious),
+            (Some(true), Some(true)) => Some(Trust::Verified),
+            _ => None,
+        }
+    }
+}
@@ -18,6 +18,10 @@ pub enum ClientError {
     CoreError(CoreError),
     #[error("No connection exists to {}. Can't send message to them!", .0)]
     NoConnection(Arc<ContactId>),
+    #[error("{} is not a known contact", .0)]
+    UnknownContact(Arc<ContactId>),
+    #[error("{} was revoked or already has another successor", .0)]
+    ContactRevoked(Arc<ContactId>),
 }
 
 impl From<CoreError> for ClientError {
@@ -1,5 +1,6 @@
 pub mod domain;
 pub mod error;
+pub mod qr;
 
 pub fn version() -> String {
     format!("{} v{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
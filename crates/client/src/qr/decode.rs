@@ -0,0 +1,384 @@
+//! Reading the data of a sampled QR code.
+//!
+//! The layout of function patterns, format information and masks is taken from the
+//! [`qrcode`] canvas that also renders our codes, so the decoder only has to read the modules
+//! in placement order, correct errors and parse the segments. Kanji segments are not
+//! supported, we never produce them.
+
+use qrcode::{
+    EcLevel, Version,
+    bits::Bits,
+    canvas::{Canvas, MaskPattern, Module},
+};
+
+use super::detect::Grid;
+
+const EC_LEVELS: [EcLevel; 4] = [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H];
+const MASKS: [MaskPattern; 8] = [
+    MaskPattern::Checkerboard,
+    MaskPattern::HorizontalLines,
+    MaskPattern::VerticalLines,
+    MaskPattern::DiagonalLines,
+    MaskPattern::LargeCheckerboard,
+    MaskPattern::Fields,
+    MaskPattern::Diamonds,
+    MaskPattern::Meadow,
+];
+const ALPHANUMERIC: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+/// Reads the data of a sampled QR code, if it is one.
+pub(super) fn decode(grid: &Grid) -> Option<Vec<u8>> {
+    let size = i16::try_from(grid.size).ok()?;
+    if size < 21 || (size - 17) % 4 != 0 {
+        return None;
+    }
+    let version = Version::Normal((size - 17) / 4);
+
+    let mut template = Canvas::new(version, EcLevel::L);
+    template.draw_all_functional_patterns();
+
+    // The function patterns are the same for every format, only the format information
+    // differs. The format whose modules match the grid best is the one in use.
+    let mut best: Option<(usize, EcLevel, Canvas)> = None;
+    let mut functional = 0;
+    for ec_level in EC_LEVELS {
+        let mut blank = Canvas::new(version, ec_level);
+        blank.draw_all_functional_patterns();
+        for mask in MASKS {
+            let mut masked = blank.clone();
+            masked.apply_mask(mask);
+            functional = 0;
+            let mut errors = 0;
+            for y in 0..size {
+                for x in 0..size {
+                    if template.get(x, y) != Module::Empty {
+                        functional += 1;
+                        errors += usize::from(
+                            masked.get(x, y).is_dark() != grid.dark(x as usize, y as usize),
+                        );
+                    }
+                }
+            }
+            if best.as_ref().is_none_or(|(e, _, _)| errors < *e) {
+                best = Some((errors, ec_level, masked));
+            }
+        }
+    }
+    let (errors, ec_level, masked) = best?;
+    if errors > functional / 8 {
+        return None;
+    }
+
+    // unmasked data bits in placement order, two columns at a time from the right
+    let mut bits = Vec::new();
+    let mut upwards = true;
+    let mut right = size - 1;
+    while right > 0 {
+        if right == 6 {
+            right -= 1;
+        }
+        for i in 0..size {
+            let y = if upwards { size - 1 - i } else { i };
+            for x in [right, right - 1] {
+                if template.get(x, y) == Module::Empty {
+                    bits.push(grid.dark(x as usize, y as usize) != masked.get(x, y).is_dark());
+                }
+            }
+        }
+        upwards = !upwards;
+        right -= 2;
+    }
+    let codewords: Vec<u8> = bits
+        .chunks_exact(8)
+        .map(|byte| byte.iter().fold(0, |acc, &bit| acc << 1 | u8::from(bit)))
+        .collect();
+
+    let data = correct_blocks(&codewords, version, ec_level)?;
+    parse_segments(&data, version)
+}
+
+/// Splits the interleaved codewords into blocks and corrects their errors.
+fn correct_blocks(codewords: &[u8], version: Version, ec_level: EcLevel) -> Option<Vec<u8>> {
+    let data_len = Bits::new(version).max_len(ec_level).ok()? / 8;
+
+    // Interleaving puts the second byte of the first block after the first byte of all
+    // blocks, so its position is the number of blocks.
+    let mut probe = vec![0; data_len];
+    probe[1] = 1;
+    let (interleaved, ec) = qrcode::ec::construct_codewords(&probe, version, ec_level).ok()?;
+    let blocks = interleaved.iter().position(|&b| b == 1)?;
+    if data_len + ec.len() != codewords.len() {
+        return None;
+    }
+    let ec_per_block = ec.len() / blocks;
+
+    // shorter blocks come first, the longer ones have one more data codeword
+    let short_len = data_len / blocks;
+    let short_blocks = blocks - data_len % blocks;
+    let block_len = |block: usize| short_len + usize::from(block >= short_blocks);
+
+    let mut blocks_data: Vec<Vec<u8>> = (0..blocks)
+        .map(|block| Vec::with_capacity(block_len(block) + ec_per_block))
+        .collect();
+    let mut codewords = codewords.iter().copied();
+    for i in 0..=short_len {
+        for (block, data) in blocks_data.iter_mut().enumerate() {
+            if i < block_len(block) {
+                data.push(codewords.next()?);
+            }
+        }
+    }
+    for _ in 0..ec_per_block {
+        for data in blocks_data.iter_mut() {
+            data.push(codewords.next()?);
+        }
+    }
+
+    let mut data = Vec::with_capacity(data_len);
+    for mut block in blocks_data {
+        reed_solomon::correct(&mut block, ec_per_block)?;
+        block.truncate(block.len() - ec_per_block);
+        data.extend(block);
+    }
+    Some(data)
+}
+
+/// Concatenates the numeric, alphanumeric and byte segments of the data codewords.
+fn parse_segments(data: &[u8], version: Version) -> Option<Vec<u8>> {
+    let Version::Normal(version) = version else {
+        return None;
+    };
+    let size_class = match version {
+        1..=9 => 0,
+        10..=26 => 1,
+        _ => 2,
+    };
+    let mut reader = BitReader { data, pos: 0 };
+    let mut out = Vec::new();
+
+    while reader.remaining() >= 4 {
+        match reader.read(4)? {
+            0b0000 => break,
+            0b0001 => {
+                let mut len = reader.read([10, 12, 14][size_class])?;
+                while len > 0 {
+                    let (digits, bits) = match len {
+                        1 => (1, 4),
+                        2 => (2, 7),
+                        _ => (3, 10),
+                    };
+                    let value = reader.read(bits)?;
+                    if value >= 10usize.pow(digits) {
+                        return None;
+                    }
+                    out.extend(format!("{value:0width$}", width = digits as usize).bytes());
+                    len -= digits as usize;
+                }
+            }
+            0b0010 => {
+                let mut len = reader.read([9, 11, 13][size_class])?;
+                while len > 0 {
+                    if len == 1 {
+                        out.push(*ALPHANUMERIC.get(reader.read(6)?)?);
+                        break;
+                    }
+                    let value = reader.read(11)?;
+                    out.push(*ALPHANUMERIC.get(value / 45)?);
+                    out.push(*ALPHANUMERIC.get(value % 45)?);
+                    len -= 2;
+                }
+            }
+            0b0100 => {
+                let len = reader.read([8, 16, 16][size_class])?;
+                for _ in 0..len {
+                    out.push(u8::try_from(reader.read(8)?).ok()?);
+                }
+            }
+            0b0111 => {
+                // ECI designators only tell the character set, we expect UTF-8 anyway
+                let first = reader.read(8)?;
+                if first & 0x80 != 0 {
+                    reader.read(if first & 0x40 == 0 { 8 } else { 16 })?;
+                }
+            }
+            _ => return None,
+        }
+    }
+    Some(out)
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl BitReader<'_> {
+    fn remaining(&self) -> usize {
+        self.data.len() * 8 - self.pos
+    }
+
+    fn read(&mut self, bits: usize) -> Option<usize> {
+        if bits > self.remaining() {
+            return None;
+        }
+        let mut value = 0;
+        for _ in 0..bits {
+            let bit = self.data[self.pos / 8] >> (7 - self.pos % 8) & 1;
+            value = value << 1 | bit as usize;
+            self.pos += 1;
+        }
+        Some(value)
+    }
+}
+
+/// Error correction for QR code blocks, over GF(256) with the polynomial 0x11d
+mod reed_solomon {
+    #[allow(clippy::cast_possible_truncation)] // reduced by the polynomial
+    const EXP: [u8; 512] = {
+        let mut exp = [0; 512];
+        let mut x: u16 = 1;
+        let mut i = 0;
+        while i < 512 {
+            exp[i] = x as u8;
+            x <<= 1;
+            if x & 0x100 != 0 {
+                x ^= 0x11d;
+            }
+            i += 1;
+        }
+        exp
+    };
+    #[allow(clippy::cast_possible_truncation)] // exponents are below 255
+    const LOG: [u8; 256] = {
+        let mut log = [0; 256];
+        let mut i = 0;
+        while i < 255 {
+            log[EXP[i] as usize] = i as u8;
+            i += 1;
+        }
+        log
+    };
+
+    #[inline]
+    fn mul(a: u8, b: u8) -> u8 {
+        if a == 0 || b == 0 {
+            return 0;
+        }
+        EXP[LOG[a as usize] as usize + LOG[b as usize] as usize]
+    }
+
+    #[inline]
+    fn div(a: u8, b: u8) -> u8 {
+        debug_assert_ne!(b, 0);
+        if a == 0 {
+            return 0;
+        }
+        EXP[LOG[a as usize] as usize + 255 - LOG[b as usize] as usize]
+    }
+
+    #[inline]
+    fn pow_alpha(power: usize) -> u8 {
+        EXP[power % 255]
+    }
+
+    /// Evaluates a polynomial with the lowest coefficient first
+    fn eval(poly: &[u8], x: u8) -> u8 {
+        poly.iter().rev().fold(0, |acc, &c| mul(acc, x) ^ c)
+    }
+
+    /// Corrects up to `ec_len / 2` wrong codewords in `block`, the data followed by the
+    /// error correction codewords. Returns `None` if there are more errors.
+    pub(super) fn correct(block: &mut [u8], ec_len: usize) -> Option<()> {
+        let n = block.len();
+        let syndromes = syndromes_of(block, ec_len);
+        if syndromes.iter().all(|&s| s == 0) {
+            return Some(());
+        }
+
+        // Berlekamp-Massey for the error locator, lowest coefficient first
+        let mut locator = vec![1u8];
+        let mut previous = vec![1u8];
+        let mut errors = 0;
+        let mut shift = 1;
+        let mut previous_discrepancy = 1;
+        for i in 0..ec_len {
+            let mut discrepancy = syndromes[i];
+            for j in 1..=errors.min(locator.len() - 1) {
+                discrepancy ^= mul(locator[j], syndromes[i - j]);
+            }
+            if discrepancy == 0 {
+                shift += 1;
+                continue;
+            }
+            let coefficient = div(discrepancy, previous_discrepancy);
+            let old = locator.clone();
+            if locator.len() < previous.len() + shift {
+                locator.resize(previous.len() + shift, 0);
+            }
+            for (j, &p) in previous.iter().enumerate() {
+                locator[j + shift] ^= mul(coefficient, p);
+            }
+            if 2 * errors <= i {
+                errors = i + 1 - errors;
+                previous = old;
+                previous_discrepancy = discrepancy;
+                shift = 1;
+            } else {
+                shift += 1;
+            }
+        }
+        if 2 * errors > ec_len {
+            return None;
+        }
+        locator.truncate(errors + 1);
+
+        // Chien search: codeword `k` has the power `n - 1 - k`
+        let positions: Vec<usize> = (0..n)
+            .filter(|&k| eval(&locator, pow_alpha(255 - (n - 1 - k) % 255)) == 0)
+            .collect();
+        if positions.len() != errors {
+            return None;
+        }
+
+        // Forney: evaluator = syndromes * locator mod x^ec_len
+        let mut evaluator = vec![0u8; ec_len];
+        for (i, &s) in syndromes.iter().enumerate() {
+            for (j, &l) in locator.iter().enumerate() {
+                if i + j < ec_len {
+                    evaluator[i + j] ^= mul(s, l);
+                }
+            }
+        }
+        let derivative: Vec<u8> = locator
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(i, &l)| if i % 2 == 1 { l } else { 0 })
+            .collect();
+        for k in positions {
+            let x = pow_alpha(n - 1 - k);
+            let x_inv = pow_alpha(255 - (n - 1 - k) % 255);
+            let denominator = eval(&derivative, x_inv);
+            if denominator == 0 {
+                return None;
+            }
+            block[k] ^= mul(x, div(eval(&evaluator, x_inv), denominator));
+        }
+
+        syndromes_of(block, ec_len)
+            .iter()
+            .all(|&s| s == 0)
+            .then_some(())
+    }
+
+    /// Evaluates the block at the roots of the generator polynomial, all zero if it has no
+    /// errors
+    fn syndromes_of(block: &[u8], ec_len: usize) -> Vec<u8> {
+        (0..ec_len)
+            .map(|j| {
+                let x = pow_alpha(j);
+                block.iter().fold(0, |acc, &c| mul(acc, x) ^ c)
+            })
+            .collect()
+    }
+}
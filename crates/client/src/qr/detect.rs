@@ -0,0 +1,565 @@
+//! Finding a QR code in a grayscale image and sampling its modules.
+//!
+//! The three finder patterns are located by their 1:1:3:1:1 ratio of dark and light runs. If
+//! the code has a bottom right alignment pattern, it is used to correct for perspective,
+//! otherwise the grid is mapped with the finder patterns alone. Large codes photographed at
+//! a steep angle may not be read.
+#![allow(clippy::cast_possible_truncation)] // pixel positions are measured as floats
+
+/// Runs of alternating colors that make up a finder pattern, in modules
+const FINDER_RATIO: [f32; 5] = [1.0, 1.0, 3.0, 1.0, 1.0];
+/// How many of the best rated finder pattern triples are tried
+const MAX_TRIPLES: usize = 12;
+/// Larger images are scaled down before searching, photos have far more pixels than needed
+const MAX_IMAGE_SIZE: usize = 1600;
+
+/// A grayscale image with one byte per pixel, row by row
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Luma {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+/// The sampled modules of a QR code, `true` is dark
+pub(super) struct Grid {
+    pub(super) size: usize,
+    modules: Vec<bool>,
+}
+
+impl Grid {
+    #[inline]
+    pub(super) fn dark(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.size + x]
+    }
+}
+
+/// A black and white image
+struct Bitmap {
+    width: usize,
+    height: usize,
+    dark: Vec<bool>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Point {
+    x: f32,
+    y: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Finder {
+    center: Point,
+    module: f32,
+    hits: u32,
+}
+
+/// Maps module coordinates of the code to pixel coordinates of the image
+struct Homography([f64; 8]);
+
+/// Finds QR codes in `image` and samples them, the most promising first, until `read` reads
+/// one of them.
+pub(super) fn find<T>(image: &Luma, mut read: impl FnMut(&Grid) -> Option<T>) -> Option<T> {
+    let image = image.shrink(MAX_IMAGE_SIZE);
+    [Bitmap::global, Bitmap::local]
+        .into_iter()
+        .find_map(|binarize| binarize(&image).find(&mut read))
+}
+
+impl Luma {
+    /// Scales the image down by an integer factor until it fits into `max` pixels, averaging
+    /// the pixels
+    fn shrink(&self, max: usize) -> Self {
+        let factor = self.width.max(self.height).div_ceil(max).max(1);
+        let (width, height) = (self.width / factor, self.height / factor);
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = 0;
+                for dy in 0..factor {
+                    let row = (y * factor + dy) * self.width + x * factor;
+                    sum += self.pixels[row..row + factor]
+                        .iter()
+                        .map(|&p| p as usize)
+                        .sum::<usize>();
+                }
+                pixels.push((sum / (factor * factor)) as u8);
+            }
+        }
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    fn otsu_threshold(&self) -> u8 {
+        let mut histogram = [0usize; 256];
+        for &p in &self.pixels {
+            histogram[p as usize] += 1;
+        }
+        let total = self.pixels.len() as f64;
+        let sum: f64 = histogram
+            .iter()
+            .enumerate()
+            .map(|(i, &n)| i as f64 * n as f64)
+            .sum();
+
+        let (mut best, mut best_variance) = (128, 0.0);
+        let (mut weight_low, mut sum_low) = (0.0, 0.0);
+        for (i, &n) in histogram.iter().enumerate() {
+            weight_low += n as f64;
+            sum_low += i as f64 * n as f64;
+            let weight_high = total - weight_low;
+            if weight_low == 0.0 || weight_high == 0.0 {
+                continue;
+            }
+            let mean_diff = sum_low / weight_low - (sum - sum_low) / weight_high;
+            let variance = weight_low * weight_high * mean_diff * mean_diff;
+            if variance > best_variance {
+                best = i as u8;
+                best_variance = variance;
+            }
+        }
+        best
+    }
+}
+
+impl Bitmap {
+    /// One threshold for the whole image, good for screenshots and generated images
+    fn global(image: &Luma) -> Self {
+        let threshold = image.otsu_threshold();
+        Self {
+            width: image.width,
+            height: image.height,
+            dark: image.pixels.iter().map(|&p| p <= threshold).collect(),
+        }
+    }
+
+    /// Compares each pixel with the mean of its surroundings, good for photos with uneven
+    /// lighting
+    fn local(image: &Luma) -> Self {
+        let (w, h) = (image.width, image.height);
+        let mut integral = vec![0u64; (w + 1) * (h + 1)];
+        for y in 0..h {
+            let mut row = 0u64;
+            for x in 0..w {
+                row += image.pixels[y * w + x] as u64;
+                integral[(y + 1) * (w + 1) + x + 1] = integral[y * (w + 1) + x + 1] + row;
+            }
+        }
+
+        let radius = (w.min(h) / 8).max(8);
+        let mut dark = Vec::with_capacity(w * h);
+        for y in 0..h {
+            let (y0, y1) = (y.saturating_sub(radius), (y + radius + 1).min(h));
+            for x in 0..w {
+                let (x0, x1) = (x.saturating_sub(radius), (x + radius + 1).min(w));
+                let sum = integral[y1 * (w + 1) + x1] + integral[y0 * (w + 1) + x0]
+                    - integral[y0 * (w + 1) + x1]
+                    - integral[y1 * (w + 1) + x0];
+                let count = ((x1 - x0) * (y1 - y0)) as u64;
+                // a little below the mean, so that noise in even areas stays light
+                dark.push((image.pixels[y * w + x] as u64 + 4) * count < sum);
+            }
+        }
+        Self {
+            width: w,
+            height: h,
+            dark,
+        }
+    }
+
+    #[inline]
+    fn at(&self, x: usize, y: usize) -> bool {
+        self.dark[y * self.width + x]
+    }
+
+    #[inline]
+    fn sample(&self, p: Point) -> bool {
+        if p.x < 0.0 || p.y < 0.0 {
+            return false;
+        }
+        let (x, y) = (p.x as usize, p.y as usize);
+        x < self.width && y < self.height && self.at(x, y)
+    }
+
+    /// Samples the middle of a module at `p` and decides by majority, which is more
+    /// reliable than a single pixel for small modules
+    fn sample_module(&self, p: Point, step_u: Point, step_v: Point) -> bool {
+        let mut dark = 0;
+        for v in [-0.25, 0.0, 0.25] {
+            for u in [-0.25, 0.0, 0.25] {
+                dark += usize::from(self.sample(Point {
+                    x: p.x + u * step_u.x + v * step_v.x,
+                    y: p.y + u * step_u.y + v * step_v.y,
+                }));
+            }
+        }
+        dark >= 5
+    }
+
+    fn find<T>(&self, read: &mut impl FnMut(&Grid) -> Option<T>) -> Option<T> {
+        finder_triples(&self.finders())
+            .into_iter()
+            .find_map(|[tl, tr, bl]| self.grids_for(tl, tr, bl).iter().find_map(&mut *read))
+    }
+
+    /// Scans every row for the finder ratio and confirms it vertically and horizontally
+    fn finders(&self) -> Vec<Finder> {
+        let mut finders: Vec<Finder> = Vec::new();
+        for y in 0..self.height {
+            let mut runs: Vec<(usize, usize)> = Vec::new();
+            let mut start = 0;
+            for x in 1..=self.width {
+                if x == self.width || self.at(x, y) != self.at(start, y) {
+                    runs.push((start, x - start));
+                    start = x;
+                }
+            }
+
+            for window in runs.windows(5) {
+                if !self.at(window[0].0, y) {
+                    continue;
+                }
+                let lengths = [0, 1, 2, 3, 4].map(|i| window[i].1);
+                if finder_module(lengths).is_none() {
+                    continue;
+                }
+                let x = window[2].0 + window[2].1 / 2;
+                if let Some(finder) = self.confirm_finder(x, y) {
+                    add_finder(&mut finders, finder);
+                }
+            }
+        }
+        finders
+    }
+
+    fn confirm_finder(&self, x: usize, y: usize) -> Option<Finder> {
+        let (cy, module_v) = self.cross_check(x, y, false)?;
+        let (cx, module_h) = self.cross_check(x, cy as usize, true)?;
+        let (cy, module_v2) = self.cross_check(cx as usize, cy as usize, false)?;
+        Some(Finder {
+            center: Point { x: cx, y: cy },
+            module: (module_v + module_h + module_v2) / 3.0,
+            hits: 1,
+        })
+    }
+
+    /// Measures the runs through `(x, y)` along one axis and returns the center of the
+    /// middle run and the module size, if they have the finder ratio.
+    fn cross_check(&self, x: usize, y: usize, horizontal: bool) -> Option<(f32, f32)> {
+        let (len, pos) = if horizontal {
+            (self.width, x)
+        } else {
+            (self.height, y)
+        };
+        let dark = |i: usize| {
+            if horizontal {
+                self.at(i, y)
+            } else {
+                self.at(x, i)
+            }
+        };
+        if !dark(pos) {
+            return None;
+        }
+
+        let mut runs = [0usize; 5];
+        let (mut start, mut end) = (pos, pos);
+        while start > 0 && dark(start - 1) {
+            start -= 1;
+        }
+        while end + 1 < len && dark(end + 1) {
+            end += 1;
+        }
+        runs[2] = end - start + 1;
+
+        let mut i = start;
+        for (run, color) in [(1, false), (0, true)] {
+            while i > 0 && dark(i - 1) == color {
+                i -= 1;
+                runs[run] += 1;
+            }
+        }
+        let mut i = end;
+        for (run, color) in [(3, false), (4, true)] {
+            while i + 1 < len && dark(i + 1) == color {
+                i += 1;
+                runs[run] += 1;
+            }
+        }
+
+        let module = finder_module(runs)?;
+        Some(((start + end + 1) as f32 / 2.0, module))
+    }
+
+    /// Samples the grids of all versions that fit the distance of the finder patterns
+    fn grids_for(&self, tl: Finder, tr: Finder, bl: Finder) -> Vec<Grid> {
+        // the runs were measured along the image axes, which are longer than a module when
+        // the code is rotated
+        let leg = tr.center.sub(tl.center);
+        let module =
+            (tl.module + tr.module + bl.module) / 3.0 * leg.x.abs().max(leg.y.abs()) / leg.length();
+        let modules_between =
+            (tl.center.distance(tr.center) + tl.center.distance(bl.center)) / 2.0 / module;
+        let version = ((modules_between + 7.0 - 17.0) / 4.0).round() as i32;
+
+        let mut grids = Vec::new();
+        for version in [version, version - 1, version + 1] {
+            if !(1..=40).contains(&version) {
+                continue;
+            }
+            let size = 17 + 4 * version as usize;
+            let far = size as f64 - 3.5;
+            let br = Point {
+                x: tr.center.x + bl.center.x - tl.center.x,
+                y: tr.center.y + bl.center.y - tl.center.y,
+            };
+            let Some(affine) = Homography::new([
+                ((3.5, 3.5), tl.center),
+                ((far, 3.5), tr.center),
+                ((3.5, far), bl.center),
+                ((far, far), br),
+            ]) else {
+                continue;
+            };
+
+            let perspective = (version >= 2)
+                .then(|| self.find_alignment(&affine, size))
+                .flatten()
+                .and_then(|alignment| {
+                    Homography::new([
+                        ((3.5, 3.5), tl.center),
+                        ((far, 3.5), tr.center),
+                        ((3.5, far), bl.center),
+                        ((far - 3.0, far - 3.0), alignment),
+                    ])
+                });
+            if let Some(perspective) = perspective {
+                grids.push(self.sample_grid(&perspective, size));
+            }
+            grids.push(self.sample_grid(&affine, size));
+        }
+        grids
+    }
+
+    /// Searches the bottom right alignment pattern around where `transform` expects it
+    fn find_alignment(&self, transform: &Homography, size: usize) -> Option<Point> {
+        let center = size as f64 - 6.5;
+        let expected = transform.map(center, center);
+        let step_u = transform.map(center + 1.0, center).sub(expected);
+        let step_v = transform.map(center, center + 1.0).sub(expected);
+        let module = (step_u.length() + step_v.length()) / 2.0;
+        let step = (module / 4.0).max(1.0);
+
+        // perspective moves the pattern away from where the finder patterns expect it, so the
+        // search is widened until it is found
+        for radius in [4.0, 8.0].map(|modules| modules * module) {
+            let mut best: Option<(usize, f32, Point)> = None;
+            let mut dy = -radius;
+            while dy <= radius {
+                let mut dx = -radius;
+                while dx <= radius {
+                    let candidate = Point {
+                        x: expected.x + dx,
+                        y: expected.y + dy,
+                    };
+                    let mut score = 0;
+                    for v in -2i32..=2 {
+                        for u in -2i32..=2 {
+                            let (u, v) = (u as f32, v as f32);
+                            let p = Point {
+                                x: candidate.x + u * step_u.x + v * step_v.x,
+                                y: candidate.y + u * step_u.y + v * step_v.y,
+                            };
+                            if self.sample_module(p, step_u, step_v)
+                                == (u.abs().max(v.abs()) != 1.0)
+                            {
+                                score += 1;
+                            }
+                        }
+                    }
+                    let distance = dx.hypot(dy);
+                    if best.is_none_or(|(best_score, best_distance, _)| {
+                        score > best_score || (score == best_score && distance < best_distance)
+                    }) {
+                        best = Some((score, distance, candidate));
+                    }
+                    dx += step;
+                }
+                dy += step;
+            }
+            if let Some((_, _, point)) = best.filter(|(score, _, _)| *score >= 24) {
+                return Some(point);
+            }
+        }
+        None
+    }
+
+    fn sample_grid(&self, transform: &Homography, size: usize) -> Grid {
+        let mut modules = Vec::with_capacity(size * size);
+        for y in 0..size {
+            for x in 0..size {
+                modules.push(self.sample(transform.map(x as f64 + 0.5, y as f64 + 0.5)));
+            }
+        }
+        Grid { size, modules }
+    }
+}
+
+impl Point {
+    #[inline]
+    fn distance(self, other: Self) -> f32 {
+        self.sub(other).length()
+    }
+
+    #[inline]
+    fn sub(self, other: Self) -> Self {
+        Self {
+            x: self.x - other.x,
+            y: self.y - other.y,
+        }
+    }
+
+    #[inline]
+    fn length(self) -> f32 {
+        self.x.hypot(self.y)
+    }
+}
+
+impl Homography {
+    /// Solves the projective transform that maps four module coordinates to four points
+    fn new(pairs: [((f64, f64), Point); 4]) -> Option<Self> {
+        let mut m = [[0f64; 9]; 8];
+        for (i, ((u, v), p)) in pairs.into_iter().enumerate() {
+            let (x, y) = (p.x as f64, p.y as f64);
+            m[2 * i] = [u, v, 1.0, 0.0, 0.0, 0.0, -u * x, -v * x, x];
+            m[2 * i + 1] = [0.0, 0.0, 0.0, u, v, 1.0, -u * y, -v * y, y];
+        }
+
+        // gaussian elimination with partial pivoting
+        for col in 0..8 {
+            let pivot = (col..8).max_by(|&a, &b| m[a][col].abs().total_cmp(&m[b][col].abs()))?;
+            if m[pivot][col].abs() < 1e-9 {
+                return None;
+            }
+            m.swap(col, pivot);
+            let pivot_row = m[col];
+            for (row, values) in m.iter_mut().enumerate() {
+                if row != col {
+                    let factor = values[col] / pivot_row[col];
+                    for (value, pivot) in values[col..].iter_mut().zip(&pivot_row[col..]) {
+                        *value -= factor * pivot;
+                    }
+                }
+            }
+        }
+
+        let mut h = [0f64; 8];
+        for (i, h) in h.iter_mut().enumerate() {
+            *h = m[i][8] / m[i][i];
+        }
+        Some(Self(h))
+    }
+
+    #[inline]
+    fn map(&self, u: f64, v: f64) -> Point {
+        let h = &self.0;
+        let w = h[6] * u + h[7] * v + 1.0;
+        Point {
+            x: ((h[0] * u + h[1] * v + h[2]) / w) as f32,
+            y: ((h[3] * u + h[4] * v + h[5]) / w) as f32,
+        }
+    }
+}
+
+/// Returns the module size if the run lengths have the ratio of a finder pattern.
+fn finder_module(runs: [usize; 5]) -> Option<f32> {
+    let total: usize = runs.iter().sum();
+    if total < 7 {
+        return None;
+    }
+    let module = total as f32 / 7.0;
+    let tolerance = module / 2.0;
+    runs.iter()
+        .zip(FINDER_RATIO)
+        .all(|(&run, ratio)| (run as f32 - ratio * module).abs() < ratio * tolerance)
+        .then_some(module)
+}
+
+/// Merges `finder` into a known finder at the same place or adds it.
+fn add_finder(finders: &mut Vec<Finder>, finder: Finder) {
+    for known in finders.iter_mut() {
+        if known.center.distance(finder.center) < known.module * 2.0
+            && (known.module - finder.module).abs() < known.module / 2.0
+        {
+            let hits = known.hits as f32;
+            known.center.x = (known.center.x * hits + finder.center.x) / (hits + 1.0);
+            known.center.y = (known.center.y * hits + finder.center.y) / (hits + 1.0);
+            known.module = (known.module * hits + finder.module) / (hits + 1.0);
+            known.hits += 1;
+            return;
+        }
+    }
+    finders.push(finder);
+}
+
+/// Orders triples of finders as top left, top right and bottom left, the ones closest to a
+/// right isosceles triangle first.
+fn finder_triples(finders: &[Finder]) -> Vec<[Finder; 3]> {
+    let mut finders = finders.to_vec();
+    finders.sort_by_key(|finder| std::cmp::Reverse(finder.hits));
+    finders.truncate(8);
+
+    let mut triples = Vec::new();
+    for i in 0..finders.len() {
+        for j in i + 1..finders.len() {
+            for k in j + 1..finders.len() {
+                let [a, b, c] = [finders[i], finders[j], finders[k]];
+                let modules = [a.module, b.module, c.module];
+                let (min, max) = (
+                    modules.iter().copied().fold(f32::MAX, f32::min),
+                    modules.iter().copied().fold(f32::MIN, f32::max),
+                );
+                if max > min * 1.5 {
+                    continue;
+                }
+
+                // the top left finder is opposite of the longest side
+                let (tl, mut tr, mut bl) = {
+                    let (ab, bc, ca) = (
+                        a.center.distance(b.center),
+                        b.center.distance(c.center),
+                        c.center.distance(a.center),
+                    );
+                    if bc >= ab && bc >= ca {
+                        (a, b, c)
+                    } else if ca >= ab {
+                        (b, c, a)
+                    } else {
+                        (c, a, b)
+                    }
+                };
+                let (leg_r, leg_b) = (tr.center.sub(tl.center), bl.center.sub(tl.center));
+                if leg_r.x * leg_b.y - leg_r.y * leg_b.x < 0.0 {
+                    std::mem::swap(&mut tr, &mut bl);
+                }
+
+                let (len_r, len_b) = (leg_r.length(), leg_b.length());
+                if len_r < 7.0 * min || len_b < 7.0 * min {
+                    continue;
+                }
+                let cos = (leg_r.x * leg_b.x + leg_r.y * leg_b.y) / (len_r * len_b);
+                let score = cos.abs() + (1.0 - len_r.min(len_b) / len_r.max(len_b));
+                triples.push((score, [tl, tr, bl]));
+            }
+        }
+    }
+    triples.sort_by(|a, b| a.0.total_cmp(&b.0));
+    triples
+        .into_iter()
+        .take(MAX_TRIPLES)
+        .map(|(_, triple)| triple)
+        .collect()
+}
@@ -0,0 +1,212 @@
+//! QR codes for sharing invitations in person.
+//!
+//! Codes are rendered with the [`qrcode`] crate. Reading them back from images, like a
+//! screenshot or a photo, is done by [`detect`] and [`decode`]. Both work on plain grayscale
+//! images, so a UI only has to convert from and to its own image types.
+
+use qrcode::{Color, QrCode};
+
+mod decode;
+mod detect;
+
+pub use detect::Luma;
+pub use qrcode::types::QrError;
+
+/// Pixels per module of a rendered code
+pub const QR_MODULE_PIXELS: usize = 6;
+/// Light modules around a rendered code, readers need at least 4
+pub const QR_QUIET_ZONE: usize = 4;
+
+/// Renders `text` as QR code, dark modules are black and everything else is white.
+pub fn render(text: &str) -> Result<Luma, QrError> {
+    let code = QrCode::new(text.as_bytes())?;
+    let colors = code.to_colors();
+    let modules = code.width();
+    let size = (modules + 2 * QR_QUIET_ZONE) * QR_MODULE_PIXELS;
+
+    let mut pixels = vec![0xff; size * size];
+    for (i, color) in colors.iter().enumerate() {
+        if *color == Color::Light {
+            continue;
+        }
+        let (x, y) = (i % modules + QR_QUIET_ZONE, i / modules + QR_QUIET_ZONE);
+        for row in y * QR_MODULE_PIXELS..(y + 1) * QR_MODULE_PIXELS {
+            let start = row * size + x * QR_MODULE_PIXELS;
+            pixels[start..start + QR_MODULE_PIXELS].fill(0);
+        }
+    }
+
+    Ok(Luma {
+        width: size,
+        height: size,
+        pixels,
+    })
+}
+
+/// Reads the text of the first QR code found in `image`.
+///
+/// Returns `None` if the image contains no readable QR code, or if its data is not UTF-8.
+pub fn scan(image: &Luma) -> Option<String> {
+    detect::find(image, |grid| {
+        decode::decode(grid).and_then(|data| String::from_utf8(data).ok())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeDelta;
+    use sremp_core::identity::{Extensions, UserIdentity, invitation::Invitation, metadata};
+
+    /// The URI of a real invitation of `user`
+    fn invitation(user: &UserIdentity) -> String {
+        let endpoints = vec![
+            "203.0.113.7:7999".parse().unwrap(),
+            "[2001:db8::7]:7999".parse().unwrap(),
+        ];
+        Invitation::create(user, endpoints, TimeDelta::hours(24))
+            .unwrap()
+            .to_uri()
+            .unwrap()
+    }
+
+    /// A PNG of 512×512 pixels with the size of a typical profile picture, its pixel data is
+    /// not valid but only the chunks are checked
+    fn profile_picture() -> Vec<u8> {
+        let mut data = b"\x89PNG\r\n\x1a\n".to_vec();
+        let mut header = 512u32.to_be_bytes().repeat(2);
+        header.extend([8, 6, 0, 0, 0]);
+        for (kind, chunk) in [
+            (b"IHDR", header),
+            (b"IDAT", vec![0; 60 * 1024]),
+            (b"IEND", Vec::new()),
+        ] {
+            data.extend(u32::try_from(chunk.len()).unwrap().to_be_bytes());
+            data.extend(kind);
+            data.extend(chunk);
+            data.extend([0; 4]); // crc
+        }
+        data
+    }
+
+    /// Flips the module at `(x, y)` of a code rendered by [`render`]
+    fn flip_module(image: &mut Luma, x: usize, y: usize) {
+        let (x, y) = (x + QR_QUIET_ZONE, y + QR_QUIET_ZONE);
+        for row in y * QR_MODULE_PIXELS..(y + 1) * QR_MODULE_PIXELS {
+            let start = row * image.width + x * QR_MODULE_PIXELS;
+            for p in &mut image.pixels[start..start + QR_MODULE_PIXELS] {
+                *p = 0xff - *p;
+            }
+        }
+    }
+
+    fn modules(image: &Luma) -> usize {
+        image.width / QR_MODULE_PIXELS - 2 * QR_QUIET_ZONE
+    }
+
+    #[test]
+    fn rendered_codes_are_read_back() {
+        let uri = invitation(&UserIdentity::create("alice").unwrap());
+        for text in [
+            "a",
+            "HELLO WORLD 42",
+            "0123456789012345",
+            "mixed: UPPER CASE 0123456789 and lower case",
+            "grüße 👋",
+            &uri,
+        ] {
+            assert_eq!(
+                scan(&render(text).unwrap()).as_deref(),
+                Some(text),
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn invitation_of_identity_with_extensions_is_read_back() {
+        let mut user = UserIdentity::create("alice").unwrap();
+        let mut extensions = Extensions::default();
+        extensions
+            .set_profile_picture(Some(profile_picture()))
+            .unwrap();
+        extensions
+            .set_metadata_text(metadata::STATUS, &"a".repeat(256))
+            .unwrap();
+        let mut key = user.identity_private_key().clone();
+        user.identity
+            .set_extensions(Some(extensions), &mut key)
+            .unwrap();
+
+        let uri = invitation(&user);
+        let scanned = scan(&render(&uri).unwrap()).unwrap();
+        assert_eq!(
+            Invitation::from_uri(&scanned).unwrap().contact_id(),
+            user.id()
+        );
+    }
+
+    #[test]
+    fn codes_are_found_in_larger_images() {
+        let uri = invitation(&UserIdentity::create("alice").unwrap());
+        let code = render(&uri).unwrap();
+        let (width, height) = (code.width * 3, code.height * 2);
+        let mut image = Luma {
+            width,
+            height,
+            pixels: vec![0xc0; width * height],
+        };
+        let (left, top) = (code.width + 17, code.height / 3);
+        for y in 0..code.height {
+            let row = (top + y) * width + left;
+            image.pixels[row..row + code.width]
+                .copy_from_slice(&code.pixels[y * code.width..(y + 1) * code.width]);
+        }
+        assert_eq!(scan(&image).as_deref(), Some(uri.as_str()));
+    }
+
+    #[test]
+    fn corrupted_modules_are_corrected() {
+        let uri = invitation(&UserIdentity::create("alice").unwrap());
+        let mut image = render(&uri).unwrap();
+        let size = modules(&image);
+        // the bottom row right of the finder pattern holds only data and error correction
+        for x in (10..size - 10).step_by(5) {
+            flip_module(&mut image, x, size - 1);
+        }
+        assert_eq!(scan(&image).as_deref(), Some(uri.as_str()));
+    }
+
+    #[test]
+    fn too_many_corrupted_modules_are_not_misread() {
+        let uri = invitation(&UserIdentity::create("alice").unwrap());
+        let mut image = render(&uri).unwrap();
+        let size = modules(&image);
+        for y in size / 2..size - 9 {
+            for x in (9..size - 9).filter(|x| (x + y) % 2 == 0) {
+                flip_module(&mut image, x, y);
+            }
+        }
+        assert_eq!(scan(&image), None);
+    }
+
+    #[test]
+    fn images_without_a_code_are_not_read() {
+        let blank = Luma {
+            width: 300,
+            height: 200,
+            pixels: vec![0xff; 300 * 200],
+        };
+        assert_eq!(scan(&blank), None);
+
+        let noise = Luma {
+            width: 256,
+            height: 256,
+            pixels: (0..256 * 256u32)
+                .map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8)
+                .collect(),
+        };
+        assert_eq!(scan(&noise), None);
+    }
+}
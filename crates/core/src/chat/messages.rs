@@ -74,7 +74,7 @@ macro_rules! declare_flags {
 declare_flags!(MessageFlags, received, sent, read);
 
 impl MessageFlags {
-    pub(crate) fn set_sent(&self, value: bool) {
+    pub fn set_sent(&self, value: bool) {
         *self.sent.lock().unwrap() = value;
     }
     pub(crate) fn set_received(&self, value: bool) {
@@ -86,13 +86,16 @@ impl MessageFlags {
 }
 
 impl Message {
+    /// Creates a new [`Message`] written by the local user, which is therefore already read.
     pub fn new(text: impl Display, time_received: DateTime<Utc>, author_id: ContactId) -> Self {
-        Self {
+        let this = Self {
             text: text.to_string(),
             attachments: Default::default(),
             meta: MessageMeta::new(time_received, author_id),
             flags: Default::default(),
-        }
+        };
+        this.flags.set_read(true);
+        this
     }
 
     #[inline]
@@ -100,9 +103,16 @@ impl Message {
         &self.meta
     }
 
+    /// Decodes a [`Message`] that was received from a peer.
+    ///
+    /// The flags are not taken from the peer, the message is marked as received and unread.
     #[inline]
-    pub fn from_wire(&self, raw: &[u8]) -> CoreResult<Self> {
-        Ok(rmp_serde::from_slice(raw)?)
+    pub fn from_wire(raw: &[u8]) -> CoreResult<Self> {
+        let this: Self = rmp_serde::from_slice(raw)?;
+        this.flags.set_sent(false);
+        this.flags.set_received(true);
+        this.flags.set_read(false);
+        Ok(this)
     }
 
     #[inline]
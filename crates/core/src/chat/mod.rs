@@ -1,10 +1,6 @@
 use std::net::SocketAddr;
 
-use crate::{
-    chat::messages::{Message, SharedMessage},
-    domain::NetworkDomain,
-    identity::ContactIdentity,
-};
+use crate::{chat::messages::SharedMessage, domain::NetworkDomain, identity::ContactIdentity};
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -29,7 +25,7 @@ impl Chat {
         &self.messages
     }
 
-    pub fn add_message(&mut self, msg: Message) {
+    pub fn add_message(&mut self, msg: impl Into<SharedMessage>) {
         self.messages.push(msg.into());
         self.sort();
     }
@@ -1,23 +1,31 @@
-use std::{
-    collections::HashMap,
-    net::SocketAddr,
-    ops::{Deref, DerefMut},
-};
+use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
+
+use tokio::task::JoinHandle;
 
 use crate::{
     identity::{ContactId, Identity},
-    net::connection::Connection,
+    net::connection::{multiplex::StreamSenders, stats::ConnectionStats},
 };
 
+/// The connections of the network domain, indexed by the contact they are with.
+///
+/// A contact may be connected more than once at the same time, for example from two devices or
+/// both directly and through a relay.
 #[derive(Debug, Default)]
 pub struct ActiveConnections {
-    inner: HashMap<SocketAddr, ConnectionData>,
+    by_contact: HashMap<ContactId, HashMap<SocketAddr, ConnectionData>>,
+    contact_of: HashMap<SocketAddr, ContactId>,
 }
 
 #[derive(Debug)]
 pub struct ConnectionData {
-    pub conn: Connection,
     pub iden: Identity,
+    /// Queues of the streams, the task that writes to the connection takes from them
+    pub(crate) outgoing: StreamSenders,
+    /// Counters of the connection, updated by its reader and writer
+    pub(crate) stats: Arc<ConnectionStats>,
+    /// Task that reads from the connection
+    pub reader: JoinHandle<()>,
 }
 
 impl ActiveConnections {
@@ -25,29 +33,70 @@ impl ActiveConnections {
         Self::default()
     }
 
-    pub fn find_socket_addr_for_contact(&self, id: &ContactId) -> Option<SocketAddr> {
-        let mut kv: Vec<(&SocketAddr, &ConnectionData)> = self.inner.iter().collect();
-        kv.sort();
-        let correct_idx = match kv.binary_search_by_key(id, |(_, v)| v.iden.id()) {
-            Ok(idx) => idx,
-            Err(_) => return None,
-        };
-        let conn = kv[correct_idx];
-        Some(*conn.0)
+    /// Number of connections, not of contacts
+    pub fn len(&self) -> usize {
+        self.contact_of.len()
     }
-}
 
-impl Deref for ActiveConnections {
-    type Target = HashMap<SocketAddr, ConnectionData>;
+    pub fn contains(&self, remote: &SocketAddr) -> bool {
+        self.contact_of.contains_key(remote)
+    }
 
-    fn deref(&self) -> &Self::Target {
-        &self.inner
+    pub fn get(&self, remote: &SocketAddr) -> Option<&ConnectionData> {
+        self.by_contact
+            .get(self.contact_of.get(remote)?)?
+            .get(remote)
+    }
+
+    /// The identity of the returned connection must not be replaced by one with another
+    /// [`ContactId`], it would not be found by that anymore.
+    pub fn get_mut(&mut self, remote: &SocketAddr) -> Option<&mut ConnectionData> {
+        self.by_contact
+            .get_mut(self.contact_of.get(remote)?)?
+            .get_mut(remote)
+    }
+
+    /// Adds a connection, returns the connection that was on `remote` before, if any.
+    pub fn insert(&mut self, remote: SocketAddr, data: ConnectionData) -> Option<ConnectionData> {
+        let previous = self.remove(&remote);
+        let id = data.iden.id();
+        self.contact_of.insert(remote, id.clone());
+        self.by_contact.entry(id).or_default().insert(remote, data);
+        previous
+    }
+
+    pub fn remove(&mut self, remote: &SocketAddr) -> Option<ConnectionData> {
+        let id = self.contact_of.remove(remote)?;
+        let connections = self.by_contact.get_mut(&id)?;
+        let data = connections.remove(remote);
+        if connections.is_empty() {
+            self.by_contact.remove(&id);
+        }
+        data
     }
-}
 
-impl DerefMut for ActiveConnections {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.inner
+    /// All connections with their remote address
+    pub fn iter(&self) -> impl Iterator<Item = (&SocketAddr, &ConnectionData)> {
+        self.by_contact.values().flatten()
+    }
+
+    /// All connections to the contact with their remote address
+    pub fn of_contact(
+        &self,
+        id: &ContactId,
+    ) -> impl Iterator<Item = (&SocketAddr, &ConnectionData)> {
+        self.by_contact.get(id).into_iter().flatten()
+    }
+
+    /// Picks the best live connection to the contact.
+    ///
+    /// Connections whose writer has stopped are not live. Of the others, the one with the lowest
+    /// round trip time is picked. Connections whose round trip time is not known yet come last.
+    pub fn find_socket_addr_for_contact(&self, id: &ContactId) -> Option<SocketAddr> {
+        self.of_contact(id)
+            .filter(|(_, data)| !data.outgoing.is_closed())
+            .min_by_key(|(_, data)| data.stats.rtt().unwrap_or(Duration::MAX))
+            .map(|(remote, _)| *remote)
     }
 }
 
@@ -57,16 +106,4 @@ impl PartialEq for ConnectionData {
     }
 }
 
-impl PartialOrd for ConnectionData {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
 impl Eq for ConnectionData {}
-
-impl Ord for ConnectionData {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.iden.id().cmp(&other.iden.id())
-    }
-}
@@ -1,17 +1,28 @@
 use std::{fmt::Display, net::SocketAddr, sync::Arc};
 
-use crate::identity::{ContactId, UserIdentity};
+use crate::{
+    identity::{ContactId, UserIdentity},
+    net::envelope::Envelope,
+};
 
 #[derive(Debug, Clone)]
 #[allow(clippy::large_enum_variant)]
 pub enum NetworkCommand {
     Connect(SocketAddr),
+    /// Like [`Connect`](Self::Connect), but the connection is refused unless the peer has the
+    /// identity of the [`ContactId`]
+    ConnectPinned(SocketAddr, ContactId),
     Disconnect(SocketAddr),
-    SendMessage(SocketAddr, ContactId, Arc<Vec<u8>>),
+    SendEnvelope(SocketAddr, ContactId, Arc<Envelope>),
+    /// Sends over the best live connection to the contact, which prefers direct connections
+    /// with a low round trip time
+    SendToContact(ContactId, Arc<Envelope>),
     /// Associated [SocketAddr] is the local addres on which to listen, not a remote address
     StartListener(SocketAddr),
     StopListener,
     SetIdentity(Option<Arc<UserIdentity>>),
+    /// Answered with [`NetworkEvent::ConnectionStats`](crate::domain::NetworkEvent::ConnectionStats)
+    QueryStats,
 }
 
 impl Display for NetworkCommand {
@@ -21,11 +32,15 @@ impl Display for NetworkCommand {
             "{}",
             match self {
                 Self::Connect(addr) => format!("Connect to {addr}"),
+                Self::ConnectPinned(addr, id) => format!("Connect to {addr}, expecting {id}"),
                 Self::Disconnect(addr) => format!("Disconnect from {addr}"),
-                Self::SendMessage(addr, id, _msg) => format!("Send Message to {addr}: {}", id),
+                Self::SendEnvelope(addr, id, envelope) =>
+                    format!("Send {envelope} to {addr} ({id})"),
+                Self::SendToContact(id, envelope) => format!("Send {envelope} to {id}"),
                 Self::StartListener(addr) =>
                     format!("Start listening for incoming connection on {addr}"),
                 Self::StopListener => "Stop listening for incoming connections".to_string(),
+                Self::QueryStats => "Query statistics of the active connections".to_string(),
                 Self::SetIdentity(id) => {
                     if let Some(id) = id {
                         format!(
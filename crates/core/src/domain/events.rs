@@ -1,21 +1,53 @@
 use std::{fmt::Display, net::SocketAddr, sync::Arc};
 
 use crate::{
+    domain::priority::Priority,
     error::CoreError,
     identity::{ContactId, Identity},
+    net::{
+        connection::{HandshakeHash, stats::ConnectionStatsSnapshot},
+        envelope::Envelope,
+    },
 };
 
 #[derive(Debug)]
 pub enum NetworkEvent {
-    ConnectionEstablished(SocketAddr, Arc<Identity>),
+    /// Includes the noise key of the device the peer connected with, and the [`HandshakeHash`]
+    /// of the connection for verifying the peer in band
+    ConnectionEstablished(
+        SocketAddr,
+        Arc<Identity>,
+        x25519_dalek::PublicKey,
+        HandshakeHash,
+    ),
     ConnectionLost(SocketAddr, ContactId),
-    IncomingMessage(SocketAddr, ContactId, Arc<Vec<u8>>),
-    MessageSent(SocketAddr, ContactId, Arc<Vec<u8>>),
+    IncomingEnvelope(SocketAddr, ContactId, Arc<Envelope>),
+    EnvelopeSent(SocketAddr, ContactId, Arc<Envelope>),
+    /// An [`Envelope`] for
+    /// [`NetworkCommand::SendToContact`](crate::domain::NetworkCommand::SendToContact) was queued
+    /// on the connection to this address
+    EnvelopeQueued(SocketAddr, ContactId, Arc<Envelope>),
+    /// The queue of the stream for this [`Envelope`] was full, it was not sent
+    StreamBusy(SocketAddr, ContactId, Arc<Envelope>),
+    /// The peer has pushed a newer version of its [`Identity`], it was already verified
+    IdentityUpdated(SocketAddr, Arc<Identity>),
     ConnectionReset(SocketAddr),
     ConnectionFailed(SocketAddr, String),
     ListenerStarted(SocketAddr),
     ListenerFailed(CoreError),
     ListenerStopped,
+    /// Statistics of all active connections, see [`NetworkCommand::QueryStats`](crate::domain::NetworkCommand::QueryStats)
+    ConnectionStats(Vec<ConnectionStatsSnapshot>),
+}
+
+impl Priority for NetworkEvent {
+    /// Traffic of the connections is bulk, everything else is control.
+    ///
+    /// Answers to commands, like [`NetworkEvent::EnvelopeQueued`], are control too: the client
+    /// domain may wait for the command queue while the network domain emits them.
+    fn is_bulk(&self) -> bool {
+        matches!(self, Self::IncomingEnvelope(..) | Self::EnvelopeSent(..))
+    }
 }
 
 impl Display for NetworkEvent {
@@ -24,13 +56,26 @@ impl Display for NetworkEvent {
             f,
             "{}",
             match self {
-                Self::ConnectionEstablished(addr, iden) =>
+                Self::ConnectionEstablished(addr, iden, ..) =>
                     format!("Connection established with {addr} ({})", iden.id()),
                 Self::ConnectionLost(addr, key) =>
                     format!("Peer {addr} ({}) has disconnected", key),
-                Self::IncomingMessage(addr, key, _msg) =>
-                    format!("Message received from {addr} ({})", key),
-                Self::MessageSent(addr, key, _msg) => format!("Message sent to {addr} ({})", key),
+                Self::IncomingEnvelope(addr, key, envelope) =>
+                    format!("{envelope} received from {addr} ({})", key),
+                Self::EnvelopeSent(addr, key, envelope) =>
+                    format!("{envelope} sent to {addr} ({})", key),
+                Self::EnvelopeQueued(addr, key, envelope) =>
+                    format!("{envelope} queued for {addr} ({})", key),
+                Self::StreamBusy(addr, key, envelope) => format!(
+                    "Stream {} to {addr} ({}) is busy, {envelope} was not sent",
+                    envelope.stream(),
+                    key
+                ),
+                Self::IdentityUpdated(addr, iden) => format!(
+                    "Peer {addr} ({}) has updated their identity to version {}",
+                    iden.id(),
+                    iden.version()
+                ),
                 Self::ConnectionFailed(addr, reason) =>
                     format!("Connection to {addr} attempt was aborted: {reason}"),
                 Self::ListenerStarted(addr) =>
@@ -39,6 +84,8 @@ impl Display for NetworkEvent {
                 Self::ConnectionReset(addr) =>
                     format!("Bad connection awards from {addr} was aborted",),
                 Self::ListenerFailed(err) => format!("Listener failed: {err}"),
+                Self::ConnectionStats(stats) =>
+                    format!("Statistics of {} active connections", stats.len()),
             }
         )
     }
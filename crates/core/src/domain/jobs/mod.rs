@@ -1,16 +1,21 @@
-use std::{collections::hash_map::Entry, net::SocketAddr, sync::Arc};
+use std::{net::SocketAddr, sync::Arc};
 
-use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt},
-    net,
-};
+use async_channel::TrySendError;
 
 use crate::{
     current_function,
     domain::{ConnectionData, NetworkCommand, NetworkDomain, NetworkDomainSync, NetworkEvent},
     error::{CoreError, CoreResult},
-    identity::UserIdentity,
-    net::connection::Connection,
+    identity::{ContactId, Identity, UserIdentity},
+    net::{
+        connection::{
+            Connection, ConnectionReader, ConnectionWriter,
+            multiplex::{self, CHUNK_HEADER_LEN, OutgoingStreams},
+        },
+        envelope::Envelope,
+        stream::CHUNK_SIZE,
+        transport::BoxedStream,
+    },
 };
 
 impl NetworkDomain {
@@ -21,7 +26,10 @@ impl NetworkDomain {
         log::trace!("{}", current_function!());
         log::info!("Processing Network Command: {command}");
         match command {
-            NetworkCommand::Connect(remote) => Self::connect_to(state.clone(), remote).await?,
+            NetworkCommand::Connect(remote) => Self::connect(state.clone(), remote, None).await,
+            NetworkCommand::ConnectPinned(remote, expected) => {
+                Self::connect(state.clone(), remote, Some(expected)).await
+            }
             NetworkCommand::StartListener(listen_addr) => {
                 state.write().await.listen(listen_addr).await?
             }
@@ -38,8 +46,35 @@ impl NetworkDomain {
                     .send_net_evt(NetworkEvent::ListenerStopped)
                     .await
             }
-            NetworkCommand::SetIdentity(iden) => state.write().await.user_identity = iden,
-            _ => todo!(),
+            NetworkCommand::SetIdentity(iden) => Self::set_identity(state.clone(), iden).await,
+            NetworkCommand::Disconnect(remote) => Self::disconnect(state.clone(), remote).await,
+            NetworkCommand::SendEnvelope(remote, _id, envelope) => {
+                state.read().await.send_envelope(remote, envelope).await;
+            }
+            NetworkCommand::SendToContact(id, envelope) => {
+                let state_r = state.read().await;
+                match state_r.active_connections.find_socket_addr_for_contact(&id) {
+                    Some(remote) => {
+                        if state_r.send_envelope(remote, envelope.clone()).await {
+                            state_r
+                                .send_net_evt(NetworkEvent::EnvelopeQueued(remote, id, envelope))
+                                .await
+                        }
+                    }
+                    None => log::warn!("Can't send {envelope} to {id}: not connected"),
+                }
+            }
+            NetworkCommand::QueryStats => {
+                let state_r = state.read().await;
+                let stats = state_r
+                    .active_connections
+                    .iter()
+                    .map(|(remote, data)| data.stats.snapshot(*remote, data.iden.id()))
+                    .collect();
+                state_r
+                    .send_net_evt(NetworkEvent::ConnectionStats(stats))
+                    .await
+            }
         };
         Ok(())
     }
@@ -51,27 +86,53 @@ impl NetworkDomain {
     ) -> CoreResult<()> {
         log::trace!("{}", current_function!());
         let remote_identity = connection.peer_identity().await.clone();
+        let remote_id = remote_identity.id();
 
-        match state.write().await.active_connections.entry(remote) {
-            // we already have a connection with this socket addr???
-            Entry::Occupied(_en) => {
-                log::warn!("Duplicated connection, closing second connection...");
-                connection.disconnect().await?;
-                state
-                    .read()
-                    .await
-                    .send_net_evt(NetworkEvent::ConnectionFailed(
-                        remote,
-                        "already connected to this peer".to_string(),
-                    ))
-                    .await;
-                return Ok(());
-            }
-            Entry::Vacant(en) => en.insert(ConnectionData {
-                conn: connection,
+        let mut state_w = state.write().await;
+        // we already have a connection with this socket addr???
+        if state_w.active_connections.contains(&remote) {
+            drop(state_w);
+            log::warn!("Duplicated connection, closing second connection...");
+            connection.disconnect().await?;
+            state
+                .read()
+                .await
+                .send_net_evt(NetworkEvent::ConnectionFailed(
+                    remote,
+                    "already connected to this peer".to_string(),
+                ))
+                .await;
+            return Ok(());
+        }
+        // NOTE: other connections to the same contact are fine, it may be on several devices
+        let stats = connection.stats();
+        let handshake_hash = connection.handshake_hash();
+        let noise_key = connection.peer_noise_key();
+        let (reader, writer) = connection.into_split();
+        let (outgoing_tx, outgoing_rx) = multiplex::stream_queues();
+        tokio::spawn(Self::connection_writer(
+            state.clone(),
+            remote,
+            remote_id.clone(),
+            writer,
+            outgoing_rx,
+        ));
+        let reader = tokio::spawn(Self::connection_reader(
+            state.clone(),
+            remote,
+            remote_id,
+            reader,
+        ));
+        state_w.active_connections.insert(
+            remote,
+            ConnectionData {
                 iden: remote_identity.clone(),
-            }),
-        };
+                outgoing: outgoing_tx,
+                stats,
+                reader,
+            },
+        );
+        drop(state_w);
 
         state
             .read()
@@ -79,11 +140,228 @@ impl NetworkDomain {
             .send_net_evt(NetworkEvent::ConnectionEstablished(
                 remote,
                 remote_identity.into(),
+                noise_key,
+                handshake_hash,
             ))
             .await;
         Ok(())
     }
 
+    /// Receives [`Envelope`]s from the peer until the connection breaks.
+    ///
+    /// Transport level envelopes are answered here, everything else is passed on to the
+    /// application domain.
+    async fn connection_reader(
+        state: NetworkDomainSync,
+        remote: SocketAddr,
+        remote_id: ContactId,
+        mut reader: ConnectionReader,
+    ) {
+        log::trace!("{}", current_function!());
+        let events = state.read().await.net_event_channel().clone();
+        loop {
+            let envelope = match reader.recv().await {
+                Ok(e) => e,
+                Err(CoreError::IO(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                    log::info!("Peer {remote} has closed the connection");
+                    break;
+                }
+                Err(e) => {
+                    log::warn!("Could not receive from {remote}, dropping connection: {e}");
+                    break;
+                }
+            };
+            log::debug!("Received {envelope} from {remote}");
+
+            match envelope {
+                Envelope::Ping(v) => state.read().await.reply_pong(remote, v),
+                Envelope::Pong(v) => {
+                    log::trace!("Pong ({v}) from {remote}");
+                    reader.stats().record_pong(v)
+                }
+                Envelope::IdentityUpdate(iden) => {
+                    Self::peer_identity_update(state.clone(), remote, iden).await
+                }
+                other => {
+                    // NOTE: this waits while the application domain is behind, so the peer can't
+                    // send faster than we process
+                    Self::emit(
+                        &events,
+                        NetworkEvent::IncomingEnvelope(remote, remote_id.clone(), other.into()),
+                    )
+                    .await
+                }
+            }
+        }
+
+        Self::remove_connection(state, remote).await;
+    }
+
+    /// Replaces the identity of the peer if `iden` is a valid newer version of it.
+    async fn peer_identity_update(
+        state: NetworkDomainSync,
+        remote: SocketAddr,
+        iden: Arc<Identity>,
+    ) {
+        log::trace!("{}", current_function!());
+        let mut state_w = state.write().await;
+        let Some(data) = state_w.active_connections.get_mut(&remote) else {
+            return;
+        };
+        if let Err(e) = data.iden.check_update(&iden) {
+            log::warn!("Rejecting identity update from {remote}: {e}");
+            return;
+        }
+        data.iden = (*iden).clone();
+        state_w
+            .downgrade()
+            .send_net_evt(NetworkEvent::IdentityUpdated(remote, iden))
+            .await;
+    }
+
+    /// Sets the identity of the user.
+    ///
+    /// If this is a newer version of the current identity, it is pushed to all connected peers.
+    async fn set_identity(state: NetworkDomainSync, iden: Option<Arc<UserIdentity>>) {
+        log::trace!("{}", current_function!());
+        let mut state_w = state.write().await;
+        let previous = std::mem::replace(&mut state_w.user_identity, iden.clone());
+        let (Some(previous), Some(iden)) = (previous, iden) else {
+            return;
+        };
+        if iden.identity_key() != previous.identity_key() || iden.version() <= previous.version() {
+            return;
+        }
+
+        let state_r = state_w.downgrade();
+        log::info!(
+            "Identity was updated to version {}, sending it to {} peers",
+            iden.version(),
+            state_r.active_connections.len()
+        );
+        let envelope: Arc<Envelope> =
+            Envelope::IdentityUpdate(Arc::new(iden.identity.clone())).into();
+        for (remote, _) in state_r.active_connections.iter() {
+            state_r.send_envelope(*remote, envelope.clone()).await;
+        }
+    }
+
+    /// Sends the queued [`Envelope`]s of all streams to the peer, chunk by chunk, until the queues
+    /// are closed or the connection breaks.
+    async fn connection_writer(
+        state: NetworkDomainSync,
+        remote: SocketAddr,
+        remote_id: ContactId,
+        mut writer: ConnectionWriter,
+        mut outgoing: OutgoingStreams,
+    ) {
+        log::trace!("{}", current_function!());
+        let events = state.read().await.net_event_channel().clone();
+        let mut buf = Vec::with_capacity(CHUNK_HEADER_LEN + CHUNK_SIZE);
+        while let Some(chunk) = outgoing.next_chunk(&mut buf).await {
+            if let Err(e) = writer.send(&buf).await {
+                log::warn!("Could not send on stream {} to {remote}: {e}", chunk.stream);
+                break;
+            }
+            let Some(envelope) = chunk.finished else {
+                continue;
+            };
+            Self::emit(
+                &events,
+                NetworkEvent::EnvelopeSent(remote, remote_id.clone(), envelope),
+            )
+            .await;
+        }
+
+        if let Err(e) = writer.shutdown().await {
+            log::debug!("Could not shut down the connection to {remote} cleanly: {e}");
+        }
+    }
+
+    /// Queues an [`Envelope`] on its stream for sending to the peer connected on `remote`.
+    ///
+    /// If the queue of the stream is full, the envelope is handed back with
+    /// [`NetworkEvent::StreamBusy`] instead of waiting, so one slow stream can not stall the
+    /// network domain. The stream refuses all other envelopes until the handed back one is sent
+    /// again with [`NetworkCommand::SendEnvelope`].
+    ///
+    /// Returns whether the envelope was queued.
+    pub(crate) async fn send_envelope(&self, remote: SocketAddr, envelope: Arc<Envelope>) -> bool {
+        let Some(data) = self.active_connections.get(&remote) else {
+            log::warn!("Can't send {envelope} to {remote}: not connected");
+            return false;
+        };
+        match data.outgoing.try_send(envelope) {
+            Ok(()) => return true,
+            Err(TrySendError::Full(envelope)) => {
+                log::debug!("Stream {} to {remote} is busy", envelope.stream());
+                self.send_net_evt(NetworkEvent::StreamBusy(remote, data.iden.id(), envelope))
+                    .await
+            }
+            Err(TrySendError::Closed(_)) => {
+                log::warn!("Can't send to {remote}: connection is closing")
+            }
+        }
+        false
+    }
+
+    /// Answers a ping of the peer.
+    ///
+    /// Like pings, the pong is dropped if it does not fit into the queue. A peer that pings faster
+    /// than we can answer gets no answer, instead of filling our queues.
+    fn reply_pong(&self, remote: SocketAddr, value: u64) {
+        let Some(data) = self.active_connections.get(&remote) else {
+            return;
+        };
+        if let Err(e) = data.outgoing.try_send_or_drop(Envelope::Pong(value).into()) {
+            log::debug!("Could not answer the ping of {remote}: {e}");
+        }
+    }
+
+    /// Pings every peer that was not pinged in the last
+    /// [`PING_INTERVAL`](crate::net::connection::stats::PING_INTERVAL), to measure the round trip
+    /// time.
+    ///
+    /// A ping that does not fit into the queue is dropped, the next one will do.
+    pub(super) fn ping_peers(&self) {
+        for (remote, data) in self.active_connections.iter() {
+            let Some(value) = data.stats.next_ping() else {
+                continue;
+            };
+            if let Err(e) = data.outgoing.try_send_or_drop(Envelope::Ping(value).into()) {
+                log::debug!("Could not ping {remote}: {e}");
+            }
+        }
+    }
+
+    async fn disconnect(state: NetworkDomainSync, remote: SocketAddr) {
+        log::trace!("{}", current_function!());
+        match Self::remove_connection(state, remote).await {
+            Some(data) => data.reader.abort(),
+            None => log::warn!("Can't disconnect from {remote}: not connected"),
+        }
+    }
+
+    /// Removes the connection from the active connections and emits
+    /// [`NetworkEvent::ConnectionLost`].
+    ///
+    /// Closing the outgoing queue lets the writer task shut down the stream. The reader task is
+    /// left alone, since this may be called from it.
+    async fn remove_connection(
+        state: NetworkDomainSync,
+        remote: SocketAddr,
+    ) -> Option<ConnectionData> {
+        log::trace!("{}", current_function!());
+        let data = state.write().await.active_connections.remove(&remote)?;
+        data.outgoing.close();
+        state
+            .read()
+            .await
+            .send_net_evt(NetworkEvent::ConnectionLost(remote, data.iden.id()))
+            .await;
+        Some(data)
+    }
+
     fn identity(&self) -> CoreResult<Arc<UserIdentity>> {
         self.user_identity
             .as_ref()
@@ -92,27 +370,55 @@ impl NetworkDomain {
             .cloned()
     }
 
-    async fn connect_to(state: NetworkDomainSync, remote: SocketAddr) -> CoreResult<()> {
+    /// Connects to `remote` and reports a failure as [`NetworkEvent::ConnectionFailed`].
+    async fn connect(state: NetworkDomainSync, remote: SocketAddr, expected: Option<ContactId>) {
+        // NOTE: a peer that can't be reached is no reason to stop the network domain
+        if let Err(e) = Self::connect_to(state.clone(), remote, expected).await {
+            log::warn!("Could not connect to {remote}: {e}");
+            state
+                .read()
+                .await
+                .send_net_evt(NetworkEvent::ConnectionFailed(remote, e.to_string()))
+                .await
+        }
+    }
+
+    async fn connect_to(
+        state: NetworkDomainSync,
+        remote: SocketAddr,
+        expected: Option<ContactId>,
+    ) -> CoreResult<()> {
         log::trace!("{}", current_function!());
-        let connection = {
+        // NOTE: don't hold the lock during the handshake, it may take a while
+        let (transport, user_identity) = {
             let state_b = state.read().await;
-            let user_identity = state_b.identity()?;
-            Connection::connect_to(remote, &user_identity).await?
+            (state_b.transport.clone(), state_b.identity()?)
         };
+        let connection = Connection::connect_to(&*transport, remote, &user_identity).await?;
+        if let Some(expected) = expected {
+            let received = connection.peer_identity().await.id();
+            if received != expected {
+                if let Err(e) = connection.disconnect().await {
+                    log::debug!("Could not close the connection to the unexpected peer: {e}");
+                }
+                return Err(CoreError::UnexpectedPeer {
+                    remote,
+                    expected,
+                    received,
+                });
+            }
+        }
         Self::init_connection(state, remote, connection).await
     }
 
     async fn connect_from(
         state: NetworkDomainSync,
-        stream: net::TcpStream,
+        stream: BoxedStream,
         remote: SocketAddr,
     ) -> CoreResult<()> {
         log::trace!("{}", current_function!());
-        let connection = {
-            let state_b = state.read().await;
-            let user_identity = state_b.identity()?;
-            Connection::connect_from(stream, remote, &user_identity).await?
-        };
+        let user_identity = state.read().await.identity()?;
+        let connection = Connection::connect_from(stream, remote, &user_identity).await?;
         Self::init_connection(state, remote, connection).await
     }
 
@@ -124,7 +430,7 @@ impl NetworkDomain {
             log::debug!("Listener: {:?}", self.listener);
             panic!("{msg}")
         }
-        let listener = net::TcpListener::bind(listen_addr).await?;
+        let listener = self.transport.bind(listen_addr).await?;
         let listen_addr = listener.local_addr()?;
 
         self.listener = Some(listener);
@@ -136,7 +442,7 @@ impl NetworkDomain {
 
     pub(super) async fn handle_incoming_connection(
         state: NetworkDomainSync,
-        stream: net::TcpStream,
+        stream: BoxedStream,
         remote: SocketAddr,
     ) -> CoreResult<()> {
         log::trace!("{}", current_function!());
@@ -1,38 +1,47 @@
 use std::{net::SocketAddr, sync::Arc};
 
-use async_channel::{Receiver, Sender};
-use tokio::{
-    net::{TcpListener, TcpStream},
-    sync::RwLock,
-    task::JoinHandle,
-};
+use async_channel::Receiver;
+use tokio::{sync::RwLock, task::JoinHandle};
 
 mod active_connections;
 mod commands;
 mod events;
 mod jobs;
+pub mod priority;
 
 pub(crate) use active_connections::*;
 pub use commands::NetworkCommand;
 pub use events::NetworkEvent;
+use priority::PrioritySender;
 
-use crate::{current_function, error::CoreResult, identity::UserIdentity};
+use crate::{
+    current_function,
+    error::CoreResult,
+    identity::UserIdentity,
+    net::transport::{BoxedStream, TcpTransport, Transport, TransportListener},
+};
 
 pub type NetworkDomainSync = Arc<tokio::sync::RwLock<NetworkDomain>>;
 
 const JOB_ITERATION_INTERVAL_MS: u64 = 30;
 
+/// Capacity of the channel for [`NetworkCommand`]s
+pub const NET_COMMAND_CAPACITY: usize = 256;
+/// Capacity of the bulk lane of the channel for [`NetworkEvent`]s, see [`priority`]
+pub const NET_EVENT_BULK_CAPACITY: usize = 256;
+
 #[derive(Debug, Clone)]
 pub(crate) struct Channels {
     pub(crate) net_command_channel: Receiver<NetworkCommand>,
-    pub(crate) net_event_channel: Sender<NetworkEvent>,
+    pub(crate) net_event_channel: PrioritySender<NetworkEvent>,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct NetworkDomain {
     pub(crate) active_connections: ActiveConnections,
     pub(crate) user_identity: Option<Arc<UserIdentity>>,
-    pub(crate) listener: Option<TcpListener>,
+    pub(crate) listener: Option<Box<dyn TransportListener>>,
+    pub(crate) transport: Arc<dyn Transport>,
     channels: Option<Channels>,
 }
 
@@ -41,11 +50,20 @@ impl NetworkDomain {
         Self::default()
     }
 
+    /// Creates a new [`NetworkDomain`] that opens its connections with `transport` instead of
+    /// TCP.
+    pub fn with_transport(transport: Arc<dyn Transport>) -> Self {
+        Self {
+            transport,
+            ..Default::default()
+        }
+    }
+
     fn into_sync(self) -> NetworkDomainSync {
         Arc::new(RwLock::new(self))
     }
 
-    async fn listener_accept_or_wait(&self) -> CoreResult<(TcpStream, SocketAddr)> {
+    async fn listener_accept_or_wait(&self) -> CoreResult<(BoxedStream, SocketAddr)> {
         let incoming = match &self.listener {
             Some(l) => l.accept().await?,
             None => std::future::pending().await,
@@ -66,17 +84,20 @@ impl NetworkDomain {
     }
 
     #[inline]
-    pub(crate) fn net_event_channel(&self) -> &Sender<NetworkEvent> {
+    pub(crate) fn net_event_channel(&self) -> &PrioritySender<NetworkEvent> {
         &self.channels_ref().net_event_channel
     }
 
     #[inline]
     pub(crate) async fn send_net_evt(&self, evt: NetworkEvent) {
+        Self::emit(self.net_event_channel(), evt).await
+    }
+
+    /// Like [`send_net_evt`](Self::send_net_evt), for tasks that must not hold the lock on the
+    /// domain while waiting for the bulk lane of the channel.
+    pub(crate) async fn emit(channel: &PrioritySender<NetworkEvent>, evt: NetworkEvent) {
         log::info!("Emitting net event: {evt}");
-        self.net_event_channel()
-            .send(evt)
-            .await
-            .expect("could not send net event");
+        channel.send(evt).await.expect("could not send net event");
     }
 
     async fn run(self) -> CoreResult<()> {
@@ -98,7 +119,7 @@ impl NetworkDomain {
                     });
                 }
                 _ = tokio::time::sleep(tokio::time::Duration::from_millis(JOB_ITERATION_INTERVAL_MS)) => {
-                    // nothing
+                    this.ping_peers();
                 }
             };
         }
@@ -107,7 +128,7 @@ impl NetworkDomain {
     pub fn start(
         mut self,
         net_command_channel: Receiver<NetworkCommand>,
-        net_event_channel: Sender<NetworkEvent>,
+        net_event_channel: PrioritySender<NetworkEvent>,
         rt: &mut tokio::runtime::Runtime,
     ) -> CoreResult<JoinHandle<CoreResult<()>>> {
         self.channels = Some(Channels {
@@ -120,3 +141,15 @@ impl NetworkDomain {
         Ok(handle)
     }
 }
+
+impl Default for NetworkDomain {
+    fn default() -> Self {
+        Self {
+            active_connections: ActiveConnections::new(),
+            user_identity: Default::default(),
+            listener: Default::default(),
+            transport: Arc::new(TcpTransport),
+            channels: Default::default(),
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! Channels between the domains that let control messages overtake bulk traffic.
+//!
+//! A priority channel has two lanes. Bulk messages, which are driven by peers and may arrive
+//! faster than they can be processed, travel on a bounded lane: when it is full, the sender waits.
+//! That back-pressure travels back through the domains until the network domain stops reading
+//! from the socket of the peer.
+//!
+//! Control messages travel on a separate lane and are always received first. This lane is not
+//! bounded, because control messages are caused by commands or by connections being opened and
+//! closed, and a domain must be able to emit them while it is itself waiting for another domain.
+//! Bounding it too could deadlock two domains that wait for each other.
+
+use async_channel::{Receiver, RecvError, SendError, Sender, TryRecvError, TrySendError};
+
+/// Messages that can be sent over a priority channel
+pub trait Priority {
+    /// Whether this message is bulk traffic that may wait behind control messages
+    fn is_bulk(&self) -> bool;
+}
+
+/// Sending side of a priority channel, see [`priority_channel`]
+#[derive(Debug)]
+pub struct PrioritySender<T> {
+    control: Sender<T>,
+    bulk: Sender<T>,
+}
+
+/// Receiving side of a priority channel, see [`priority_channel`]
+#[derive(Debug)]
+pub struct PriorityReceiver<T> {
+    control: Receiver<T>,
+    bulk: Receiver<T>,
+}
+
+/// Creates a priority channel whose bulk lane holds at most `bulk_capacity` messages.
+pub fn priority_channel<T>(bulk_capacity: usize) -> (PrioritySender<T>, PriorityReceiver<T>) {
+    let (control_tx, control_rx) = async_channel::unbounded();
+    let (bulk_tx, bulk_rx) = async_channel::bounded(bulk_capacity);
+    (
+        PrioritySender {
+            control: control_tx,
+            bulk: bulk_tx,
+        },
+        PriorityReceiver {
+            control: control_rx,
+            bulk: bulk_rx,
+        },
+    )
+}
+
+impl<T: Priority> PrioritySender<T> {
+    /// Sends a message on its lane, waits while a bulk message does not fit.
+    pub async fn send(&self, msg: T) -> Result<(), SendError<T>> {
+        self.lane(&msg).send(msg).await
+    }
+
+    /// Sends a message on its lane without waiting.
+    pub fn try_send(&self, msg: T) -> Result<(), TrySendError<T>> {
+        self.lane(&msg).try_send(msg)
+    }
+
+    #[inline]
+    fn lane(&self, msg: &T) -> &Sender<T> {
+        if msg.is_bulk() {
+            &self.bulk
+        } else {
+            &self.control
+        }
+    }
+}
+
+impl<T> PrioritySender<T> {
+    /// Closes both lanes, messages that were already sent can still be received.
+    pub fn close(&self) {
+        self.control.close();
+        self.bulk.close();
+    }
+}
+
+impl<T> PriorityReceiver<T> {
+    /// Waits for the next message, control messages are received before bulk messages.
+    ///
+    /// Returns an error once both lanes are closed and empty.
+    pub async fn recv(&self) -> Result<T, RecvError> {
+        if let Ok(msg) = self.control.try_recv() {
+            return Ok(msg);
+        }
+        tokio::select! {
+            biased;
+            Ok(msg) = self.control.recv() => Ok(msg),
+            Ok(msg) = self.bulk.recv() => Ok(msg),
+            else => Err(RecvError),
+        }
+    }
+
+    /// Receives the next message without waiting, control messages are received before bulk
+    /// messages.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        match self.control.try_recv() {
+            Ok(msg) => Ok(msg),
+            // NOTE: the channel is only closed once both lanes are
+            Err(TryRecvError::Empty) => match self.bulk.try_recv() {
+                Err(TryRecvError::Closed) => Err(TryRecvError::Empty),
+                other => other,
+            },
+            Err(TryRecvError::Closed) => self.bulk.try_recv(),
+        }
+    }
+
+    /// Number of messages waiting on both lanes
+    pub fn len(&self) -> usize {
+        self.control.len() + self.bulk.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.control.is_empty() && self.bulk.is_empty()
+    }
+}
+
+impl<T> Clone for PrioritySender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            control: self.control.clone(),
+            bulk: self.bulk.clone(),
+        }
+    }
+}
+
+impl<T> Clone for PriorityReceiver<T> {
+    fn clone(&self) -> Self {
+        Self {
+            control: self.control.clone(),
+            bulk: self.bulk.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Eq)]
+    enum Msg {
+        Control(u32),
+        Bulk(u32),
+    }
+
+    impl Priority for Msg {
+        fn is_bulk(&self) -> bool {
+            matches!(self, Self::Bulk(_))
+        }
+    }
+
+    #[tokio::test]
+    async fn control_messages_overtake_bulk_messages() {
+        let (tx, rx) = priority_channel(4);
+        tx.send(Msg::Bulk(1)).await.unwrap();
+        tx.send(Msg::Bulk(2)).await.unwrap();
+        tx.send(Msg::Control(1)).await.unwrap();
+        tx.send(Msg::Control(2)).await.unwrap();
+        assert_eq!(rx.len(), 4);
+
+        let mut received = Vec::new();
+        while let Ok(msg) = rx.try_recv() {
+            received.push(msg);
+        }
+        assert_eq!(
+            received,
+            [Msg::Control(1), Msg::Control(2), Msg::Bulk(1), Msg::Bulk(2)]
+        );
+        assert!(rx.is_empty());
+    }
+
+    #[tokio::test]
+    async fn only_the_bulk_lane_is_bounded() {
+        let (tx, rx) = priority_channel(1);
+        tx.try_send(Msg::Bulk(1)).unwrap();
+        assert!(tx.try_send(Msg::Bulk(2)).unwrap_err().is_full());
+        for i in 0..100 {
+            tx.try_send(Msg::Control(i)).unwrap();
+        }
+        assert_eq!(rx.recv().await.unwrap(), Msg::Control(0));
+    }
+
+    #[tokio::test]
+    async fn closing_keeps_what_was_sent() {
+        let (tx, rx) = priority_channel(4);
+        tx.send(Msg::Bulk(1)).await.unwrap();
+        tx.send(Msg::Control(1)).await.unwrap();
+        tx.close();
+        assert!(tx.send(Msg::Control(2)).await.is_err());
+
+        assert_eq!(rx.recv().await.unwrap(), Msg::Control(1));
+        assert_eq!(rx.recv().await.unwrap(), Msg::Bulk(1));
+        assert!(rx.recv().await.is_err());
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Closed));
+    }
+}
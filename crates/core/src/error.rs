@@ -1,9 +1,13 @@
 use std::net::SocketAddr;
 
 use async_channel::SendError;
+use chrono::{DateTime, Utc};
 use thiserror::Error;
 
-use crate::domain::{NetworkCommand, NetworkEvent};
+use crate::{
+    domain::{NetworkCommand, NetworkEvent},
+    identity::ContactId,
+};
 
 pub type CoreResult<T> = std::result::Result<T, CoreError>;
 
@@ -32,6 +36,8 @@ pub enum CoreError {
         "Tried to create a frame for the transport layer that is too large ({0} >= MAX_FRAME_SIZE)"
     )]
     FrameTooLarge(usize),
+    #[error("Received a frame that is too short to contain a version header ({0} bytes)")]
+    FrameTooShort(usize),
     #[error("Frame length is over 2 byte long: {0}")]
     FrameLengthOverU16(usize),
     #[error("Could not get the public key of peer ({0}) during the connection initialization")]
@@ -43,10 +49,90 @@ pub enum CoreError {
         remote: SocketAddr,
         source: ed25519_dalek::SignatureError,
     },
-    #[error("The given username does not conform to the constraints of the specification")]
-    InvalidUsername,
+    #[error("The username is invalid, {0}")]
+    InvalidUsername(&'static str),
     #[error("Frame with a bad protocol name was received")]
     BadProtocolName([u8; 12]),
+    #[error("Envelope with an invalid version was received: {0}")]
+    BadEnvelopeVersion(u8),
+    #[error("Received a chunk that is too short to contain a chunk header")]
+    MalformedChunk,
+    #[error("Received a chunk for an unknown stream: {0}")]
+    UnknownStream(u8),
+    #[error("Received a chunk with unknown flags: {0:#010b}")]
+    BadChunkFlags(u8),
+    #[error("Reassembled envelope is larger than MAX_ENVELOPE_SIZE ({0} bytes)")]
+    EnvelopeTooLarge(usize),
+    #[error("Unfinished envelopes of all connections take up too much memory ({0} bytes)")]
+    TooMuchBuffered(usize),
+    #[error("Identity update for {expected} has a different identity key ({received})")]
+    IdentityKeyMismatch {
+        expected: ContactId,
+        received: ContactId,
+    },
+    #[error(
+        "Identity update for {id} is not newer than the known one (version {received} <= {known})"
+    )]
+    StaleIdentity {
+        id: ContactId,
+        known: u64,
+        received: u64,
+    },
+    #[error("Device certificate for {expected} was issued by another identity key ({issuer})")]
+    ForeignDeviceCertificate {
+        expected: ContactId,
+        issuer: ContactId,
+    },
+    #[error("The given device name does not conform to the constraints of the specification")]
+    InvalidDeviceName,
+    #[error("Succession of {0} names the same identity as its successor")]
+    SelfSuccession(ContactId),
+    #[error("{0} can not endorse itself")]
+    SelfEndorsement(ContactId),
+    #[error("Key derivation failed: {0}")]
+    KeyDerivation(argon2::Error),
+    #[error("The data is not an exported identity")]
+    NotAnIdentityExport,
+    #[error("Exported identity has an unsupported format version: {0}")]
+    UnsupportedExportVersion(u8),
+    #[error("Key derivation parameters of the exported identity are too expensive")]
+    ExportKdfTooExpensive,
+    #[error("Could not decrypt the exported identity, the passphrase is wrong or it was modified")]
+    ExportDecryption,
+    #[error("The private {0} key does not belong to the identity")]
+    KeyPairMismatch(&'static str),
+    #[error("Invalid recovery phrase: {0}")]
+    InvalidRecoveryPhrase(bip39::Error),
+    #[error("A recovery phrase must have {expected} words, but it has {received}")]
+    RecoveryPhraseLength { expected: usize, received: usize },
+    #[error("The recovery phrase leads to a weak identity key")]
+    WeakRecoveryKey,
+    #[error("The text is not a valid invitation")]
+    NotAnInvitation,
+    #[error("Invitation has an unsupported format version: {0}")]
+    UnsupportedInvitationVersion(u8),
+    #[error("The invitation has expired at {0}")]
+    InvitationExpired(DateTime<Utc>),
+    #[error("The invitation does not contain any endpoint")]
+    InvitationWithoutEndpoints,
+    #[error("Peer {remote} presented the identity {received}, but {expected} was expected")]
+    UnexpectedPeer {
+        remote: SocketAddr,
+        expected: ContactId,
+        received: ContactId,
+    },
+    #[error(
+        "A pairing code must be a number below 100 and two words of the BIP-39 english word list"
+    )]
+    InvalidPairingCode,
+    #[error("The peer sent an invalid pairing share")]
+    InvalidPairingShare,
+    #[error("Invalid profile picture: {0}")]
+    InvalidProfilePicture(&'static str),
+    #[error("Invalid metadata entry {0:?}: {1}")]
+    InvalidMetadata(String, &'static str),
+    #[error("An identity is written as 64 hexadecimal characters")]
+    InvalidContactId,
 }
 impl From<SendError<NetworkCommand>> for CoreError {
     fn from(value: SendError<NetworkCommand>) -> Self {
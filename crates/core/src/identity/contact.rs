@@ -5,15 +5,29 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     error::CoreResult,
-    identity::{Identity, Trust, UserIdentity},
+    identity::{ContactId, Endorsement, Identity, Trust, UserIdentity},
 };
 
+/// Maximum number of endorsers recorded for a [`ContactIdentity`]
+pub const MAX_ENDORSEMENTS: usize = 64;
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ContactIdentity {
     pub identity: Identity,
     pub trust: Trust,
     pub first_seen: DateTime<Utc>,
     pub last_seen: DateTime<Utc>,
+    /// The identity that replaced this one, see [`Succession`](super::Succession)
+    #[serde(default)]
+    pub successor: Option<ContactId>,
+    /// Whether [`trust`](Self::trust) was handed over by the identity this one succeeded, rather
+    /// than decided by the user
+    #[serde(default)]
+    pub trust_inherited: bool,
+    /// The newest [`Endorsement`] of each endorser of this identity, including withdrawn ones,
+    /// so an older endorsement can not be replayed
+    #[serde(default)]
+    pub endorsements: Vec<Endorsement>,
 }
 
 impl ContactIdentity {
@@ -24,6 +38,9 @@ impl ContactIdentity {
             trust,
             first_seen: Utc::now(),
             last_seen: Utc::now(),
+            successor: None,
+            trust_inherited: false,
+            endorsements: Vec::new(),
         })
     }
 
@@ -34,7 +51,40 @@ impl ContactIdentity {
             trust: Trust::Trusted,
             first_seen: Utc::now(),
             last_seen: Utc::now(),
+            successor: None,
+            trust_inherited: false,
+            endorsements: Vec::new(),
+        }
+    }
+
+    /// Records a verified [`Endorsement`] of this identity, unless a newer one of the same
+    /// endorser is already known. Endorsers beyond [`MAX_ENDORSEMENTS`] are not recorded.
+    ///
+    /// Returns whether anything has changed.
+    pub fn add_endorsement(&mut self, endorsement: Endorsement) -> bool {
+        if endorsement.endorsed() != &self.id() {
+            return false;
         }
+        let full = self.endorsements.len() >= MAX_ENDORSEMENTS;
+        match self
+            .endorsements
+            .iter_mut()
+            .find(|known| known.endorser() == endorsement.endorser())
+        {
+            Some(known) if endorsement.supersedes(known) => *known = endorsement,
+            Some(_) => return false,
+            None if full => return false,
+            None => self.endorsements.push(endorsement),
+        }
+        true
+    }
+
+    /// The identities that currently vouch for this one
+    pub fn endorsers(&self) -> impl Iterator<Item = ContactId> + '_ {
+        self.endorsements
+            .iter()
+            .filter(|e| !e.is_revoked())
+            .map(Endorsement::endorser)
     }
 
     /// Sets the last-seen timestamp of this [`ContactIdentity`] to now.
@@ -1,7 +1,7 @@
 use ed25519_dalek::ed25519::signature::SignerMut;
 
 use crate::{
-    error::CoreResult,
+    error::{CoreError, CoreResult},
     identity::{Identity, IdentityVerifiedData},
 };
 
@@ -47,8 +47,39 @@ impl Identity {
         log::debug!("Signature is valid");
         Self::validate_username(&self.verified.username)?;
         log::debug!("Username is valid");
+        if let Some(extensions) = &self.verified.extensions {
+            extensions.validate()?;
+        }
+        for device in &self.verified.devices {
+            device.verify(&self.verified.identity_key)?;
+        }
+        log::debug!(
+            "{} device certificates are valid",
+            self.verified.devices.len()
+        );
         Ok(())
     }
+
+    /// Checks that `newer` may replace this [`Identity`].
+    ///
+    /// It must be validly signed with the same identity key and have a strictly higher version,
+    /// so that an old version can not be replayed to undo a change.
+    pub fn check_update(&self, newer: &Identity) -> CoreResult<()> {
+        if newer.identity_key() != self.identity_key() {
+            return Err(CoreError::IdentityKeyMismatch {
+                expected: self.id(),
+                received: newer.id(),
+            });
+        }
+        if newer.version() <= self.version() {
+            return Err(CoreError::StaleIdentity {
+                id: self.id(),
+                known: self.version(),
+                received: newer.version(),
+            });
+        }
+        newer.verify()
+    }
 }
 
 pub(super) fn generate_good_key_x25519() -> x25519_dalek::StaticSecret {
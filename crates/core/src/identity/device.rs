@@ -0,0 +1,82 @@
+use chrono::{DateTime, Utc};
+use ed25519_dalek::ed25519::signature::SignerMut;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{CoreError, CoreResult},
+    identity::{ContactId, Identity},
+};
+
+/// The part of a [`DeviceCertificate`] that is signed by the identity key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceCertificateData {
+    /// Name of the device as chosen by the user, like "laptop"
+    name: String,
+    /// Identity key that issued the certificate
+    identity_key: ed25519_dalek::VerifyingKey,
+    /// Noise static key of the device
+    noise_key: x25519_dalek::PublicKey,
+    created: DateTime<Utc>,
+}
+
+/// Binds the noise key of an additional device to an identity key.
+///
+/// Every device of an identity has its own noise key, so the private identity key is the only
+/// secret the devices have in common. The devices that are currently active are listed in the
+/// [`Identity`], see [`Identity::devices`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceCertificate {
+    data: DeviceCertificateData,
+    signature: ed25519_dalek::Signature,
+}
+
+impl DeviceCertificate {
+    /// Creates a [`DeviceCertificate`] for `noise_key`, signed by `identity_private_key`.
+    pub fn issue(
+        name: &str,
+        noise_key: x25519_dalek::PublicKey,
+        identity_private_key: &mut ed25519_dalek::SigningKey,
+    ) -> CoreResult<Self> {
+        Identity::validate_username(name).map_err(|_| CoreError::InvalidDeviceName)?;
+        let data = DeviceCertificateData {
+            name: name.to_string(),
+            identity_key: identity_private_key.verifying_key(),
+            noise_key,
+            created: Utc::now(),
+        };
+        let signature = identity_private_key.try_sign(&rmp_serde::to_vec(&data)?)?;
+        Ok(Self { data, signature })
+    }
+
+    /// Checks that the certificate was issued by `identity_key`.
+    pub fn verify(&self, identity_key: &ed25519_dalek::VerifyingKey) -> CoreResult<()> {
+        if self.data.identity_key != *identity_key {
+            return Err(CoreError::ForeignDeviceCertificate {
+                expected: (*identity_key).into(),
+                issuer: self.data.identity_key.into(),
+            });
+        }
+        identity_key.verify_strict(&rmp_serde::to_vec(&self.data)?, &self.signature)?;
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub fn name(&self) -> &str {
+        &self.data.name
+    }
+
+    #[inline(always)]
+    pub fn issuer(&self) -> ContactId {
+        self.data.identity_key.into()
+    }
+
+    #[inline(always)]
+    pub fn noise_key(&self) -> x25519_dalek::PublicKey {
+        self.data.noise_key
+    }
+
+    #[inline(always)]
+    pub fn created(&self) -> DateTime<Utc> {
+        self.data.created
+    }
+}
@@ -0,0 +1,177 @@
+//! Passphrase protected files for backing up a [`UserIdentity`] or moving it to another device.
+//!
+//! An export consists of a header and the encrypted identity:
+//!
+//! ```text
+//! magic       8 bytes   "SREMPID\0"
+//! version     u8        IDENTITY_EXPORT_VERSION
+//! m_cost      u32 (BE)  Argon2id memory in KiB
+//! t_cost      u32 (BE)  Argon2id iterations
+//! p_cost      u32 (BE)  Argon2id parallelism
+//! salt        16 bytes
+//! nonce       12 bytes
+//! ciphertext  the UserIdentity as MessagePack, encrypted with ChaCha20Poly1305
+//! ```
+//!
+//! The key is derived from the passphrase with Argon2id. The header is authenticated as
+//! associated data, so the parameters can not be changed without the decryption failing.
+
+use chacha20poly1305::{
+    ChaCha20Poly1305, KeyInit,
+    aead::{Aead, Payload},
+};
+use rand::RngCore;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::{
+    error::{CoreError, CoreResult},
+    identity::UserIdentity,
+};
+
+/// Version of the export format that this implementation produces
+pub const IDENTITY_EXPORT_VERSION: u8 = 1;
+
+const MAGIC: &[u8; 8] = b"SREMPID\0";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = MAGIC.len() + 1 + 3 * 4 + SALT_LEN + NONCE_LEN;
+
+/// Parameters of the Argon2id key derivation of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExportKdfParams {
+    /// Memory in KiB
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+impl ExportKdfParams {
+    /// Used by [`UserIdentity::export`], 64 MiB and three passes
+    pub const DEFAULT: Self = Self {
+        m_cost: 64 * 1024,
+        t_cost: 3,
+        p_cost: 1,
+    };
+
+    /// Imports with more expensive parameters are refused, so a crafted file can not make us
+    /// allocate arbitrary amounts of memory
+    pub const MAX: Self = Self {
+        m_cost: 1024 * 1024,
+        t_cost: 64,
+        p_cost: 16,
+    };
+
+    fn derive_key(&self, passphrase: &str, salt: &[u8]) -> CoreResult<Zeroizing<[u8; 32]>> {
+        let params = argon2::Params::new(self.m_cost, self.t_cost, self.p_cost, Some(32))
+            .map_err(CoreError::KeyDerivation)?;
+        let argon =
+            argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+        let mut key = Zeroizing::new([0; 32]);
+        argon
+            .hash_password_into(passphrase.as_bytes(), salt, &mut *key)
+            .map_err(CoreError::KeyDerivation)?;
+        Ok(key)
+    }
+
+    fn exceeds(&self, max: &Self) -> bool {
+        self.m_cost > max.m_cost || self.t_cost > max.t_cost || self.p_cost > max.p_cost
+    }
+}
+
+impl UserIdentity {
+    /// Encrypts this identity with a passphrase, see the [module documentation](self).
+    pub fn export(&self, passphrase: &str) -> CoreResult<Vec<u8>> {
+        self.export_with(passphrase, ExportKdfParams::DEFAULT)
+    }
+
+    /// Like [`export`](Self::export), but with other parameters for the key derivation.
+    pub fn export_with(&self, passphrase: &str, params: ExportKdfParams) -> CoreResult<Vec<u8>> {
+        let mut salt = [0; SALT_LEN];
+        let mut nonce = [0; NONCE_LEN];
+        rand::rngs::OsRng.fill_bytes(&mut salt);
+        rand::rngs::OsRng.fill_bytes(&mut nonce);
+
+        let mut out = Vec::with_capacity(HEADER_LEN + 512);
+        out.extend_from_slice(MAGIC);
+        out.push(IDENTITY_EXPORT_VERSION);
+        out.extend_from_slice(&params.m_cost.to_be_bytes());
+        out.extend_from_slice(&params.t_cost.to_be_bytes());
+        out.extend_from_slice(&params.p_cost.to_be_bytes());
+        out.extend_from_slice(&salt);
+        out.extend_from_slice(&nonce);
+
+        let key = params.derive_key(passphrase, &salt)?;
+        let plaintext = Zeroizing::new(rmp_serde::to_vec(self)?);
+        let ciphertext = ChaCha20Poly1305::new((&*key).into())
+            .encrypt(
+                (&nonce).into(),
+                Payload {
+                    msg: &plaintext,
+                    aad: &out,
+                },
+            )
+            .expect("a serialized identity is far below the size limit of the cipher");
+        out.extend_from_slice(&ciphertext);
+        Ok(out)
+    }
+
+    /// Decrypts an identity that was exported with [`export`](Self::export).
+    ///
+    /// The identity is only returned if its signature is valid and the private keys belong to
+    /// the public keys in it.
+    pub fn import(data: &[u8], passphrase: &str) -> CoreResult<Self> {
+        if data.len() < HEADER_LEN || !data.starts_with(MAGIC) {
+            return Err(CoreError::NotAnIdentityExport);
+        }
+        let (header, ciphertext) = data.split_at(HEADER_LEN);
+        let version = header[MAGIC.len()];
+        if version != IDENTITY_EXPORT_VERSION {
+            return Err(CoreError::UnsupportedExportVersion(version));
+        }
+        let rest = &header[MAGIC.len() + 1..];
+        let u32_at = |i: usize| {
+            u32::from_be_bytes(rest[i * 4..i * 4 + 4].try_into().expect("slice of 4 bytes"))
+        };
+        let params = ExportKdfParams {
+            m_cost: u32_at(0),
+            t_cost: u32_at(1),
+            p_cost: u32_at(2),
+        };
+        if params.exceeds(&ExportKdfParams::MAX) {
+            return Err(CoreError::ExportKdfTooExpensive);
+        }
+        let (salt, nonce) = rest[12..].split_at(SALT_LEN);
+
+        let key = params.derive_key(passphrase, salt)?;
+        let mut plaintext = ChaCha20Poly1305::new((&*key).into())
+            .decrypt(
+                nonce.into(),
+                Payload {
+                    msg: ciphertext,
+                    aad: header,
+                },
+            )
+            .map_err(|_| CoreError::ExportDecryption)?;
+        let user: CoreResult<Self> = rmp_serde::from_slice(&plaintext).map_err(Into::into);
+        plaintext.zeroize();
+        let user = user?;
+
+        user.identity.verify()?;
+        user.check_key_pairs()?;
+        Ok(user)
+    }
+
+    /// Checks that the private keys belong to the public keys of the identity.
+    pub fn check_key_pairs(&self) -> CoreResult<()> {
+        if self.identity_key.verifying_key() != self.identity.identity_key() {
+            return Err(CoreError::KeyPairMismatch("identity"));
+        }
+        if !self
+            .identity
+            .is_device_key(&x25519_dalek::PublicKey::from(&self.noise_key))
+        {
+            return Err(CoreError::KeyPairMismatch("noise"));
+        }
+        Ok(())
+    }
+}
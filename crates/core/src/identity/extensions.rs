@@ -0,0 +1,322 @@
+//! Optional profile data of an [`Identity`](super::Identity): a picture and a few metadata
+//! entries.
+//!
+//! Extensions are signed with the rest of the identity and sent to every peer, so they are kept
+//! small. The picture is checked by reading the header of the image, which is enough to know its
+//! format and dimensions. The pixels are not decoded here, so a picture with a valid header may
+//! still fail to decode, clients show no picture then.
+
+use std::{collections::BTreeMap, fmt::Display};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{CoreError, CoreResult};
+
+/// Maximum size of an encoded profile picture
+pub const MAX_PROFILE_PICTURE_BYTES: usize = 64 * 1024;
+/// Maximum width and height of a profile picture in pixels
+pub const MAX_PROFILE_PICTURE_SIDE: u32 = 512;
+/// Maximum number of metadata entries
+pub const MAX_METADATA_ENTRIES: usize = 16;
+/// Maximum length of a metadata key in bytes
+pub const MAX_METADATA_KEY_BYTES: usize = 32;
+/// Maximum length of a metadata value in bytes
+pub const MAX_METADATA_VALUE_BYTES: usize = 256;
+
+/// Metadata keys with a defined meaning, their values are UTF-8 text.
+///
+/// Other keys may be used, clients ignore the ones they do not know.
+pub mod metadata {
+    /// A short status, like "on vacation until monday"
+    pub const STATUS: &str = "status";
+    /// The pronouns of the user, like "they/them"
+    pub const PRONOUNS: &str = "pronouns";
+    /// A few sentences about the user
+    pub const ABOUT: &str = "about";
+
+    /// All keys with a defined meaning
+    pub const KNOWN_KEYS: [&str; 3] = [STATUS, PRONOUNS, ABOUT];
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct Extensions {
+    profile_picture: Option<Vec<u8>>,
+    /// Sorted, so that the extensions serialize to the same bytes for the signature everywhere
+    additional_metadata: BTreeMap<String, Vec<u8>>,
+}
+
+/// Encodings that a profile picture may use
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    Webp,
+}
+
+/// Format and dimensions of an encoded image, read from its header
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ImageInfo {
+    pub format: ImageFormat,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Extensions {
+    /// Whether there is neither a picture nor metadata
+    pub fn is_empty(&self) -> bool {
+        self.profile_picture.is_none() && self.additional_metadata.is_empty()
+    }
+
+    /// The encoded profile picture, see [`ImageInfo::read`] for its format
+    #[inline]
+    pub fn profile_picture(&self) -> Option<&[u8]> {
+        self.profile_picture.as_deref()
+    }
+
+    /// Sets the encoded profile picture after checking it with [`validate_profile_picture`].
+    pub fn set_profile_picture(&mut self, picture: Option<Vec<u8>>) -> CoreResult<()> {
+        if let Some(picture) = &picture {
+            validate_profile_picture(picture)?;
+        }
+        self.profile_picture = picture;
+        Ok(())
+    }
+
+    /// The raw value of a metadata entry
+    #[inline]
+    pub fn metadata(&self, key: &str) -> Option<&[u8]> {
+        self.additional_metadata.get(key).map(Vec::as_slice)
+    }
+
+    /// The value of a metadata entry as text, [`None`] if it is missing or not UTF-8
+    #[inline]
+    pub fn metadata_text(&self, key: &str) -> Option<&str> {
+        std::str::from_utf8(self.metadata(key)?).ok()
+    }
+
+    /// All metadata entries
+    #[inline]
+    pub fn metadata_entries(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        self.additional_metadata
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_slice()))
+    }
+
+    /// Sets or removes a metadata entry.
+    pub fn set_metadata(&mut self, key: &str, value: Option<Vec<u8>>) -> CoreResult<()> {
+        let Some(value) = value else {
+            self.additional_metadata.remove(key);
+            return Ok(());
+        };
+        validate_metadata_entry(key, &value)?;
+        if !self.additional_metadata.contains_key(key)
+            && self.additional_metadata.len() >= MAX_METADATA_ENTRIES
+        {
+            return Err(CoreError::InvalidMetadata(
+                key.to_string(),
+                "too many metadata entries",
+            ));
+        }
+        self.additional_metadata.insert(key.to_string(), value);
+        Ok(())
+    }
+
+    /// Sets a metadata entry to a text, or removes it if the text is empty.
+    #[inline]
+    pub fn set_metadata_text(&mut self, key: &str, text: &str) -> CoreResult<()> {
+        let text = text.trim();
+        self.set_metadata(key, (!text.is_empty()).then(|| text.as_bytes().to_vec()))
+    }
+
+    /// Checks the picture and all metadata entries against the limits of the specification.
+    pub fn validate(&self) -> CoreResult<()> {
+        if let Some(picture) = &self.profile_picture {
+            validate_profile_picture(picture)?;
+        }
+        if self.additional_metadata.len() > MAX_METADATA_ENTRIES {
+            return Err(CoreError::InvalidMetadata(
+                String::new(),
+                "too many metadata entries",
+            ));
+        }
+        for (key, value) in &self.additional_metadata {
+            validate_metadata_entry(key, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks that `picture` has the header of a PNG, JPEG or WebP image within the size limits.
+pub fn validate_profile_picture(picture: &[u8]) -> CoreResult<ImageInfo> {
+    if picture.len() > MAX_PROFILE_PICTURE_BYTES {
+        return Err(CoreError::InvalidProfilePicture(
+            "the image is larger than 64 KiB",
+        ));
+    }
+    let info = ImageInfo::read(picture).ok_or(CoreError::InvalidProfilePicture(
+        "the image is not a PNG, JPEG or WebP file, or its header is broken",
+    ))?;
+    if info.width == 0
+        || info.height == 0
+        || info.width > MAX_PROFILE_PICTURE_SIDE
+        || info.height > MAX_PROFILE_PICTURE_SIDE
+    {
+        return Err(CoreError::InvalidProfilePicture(
+            "the image must be between 1 and 512 pixels wide and high",
+        ));
+    }
+    Ok(info)
+}
+
+fn validate_metadata_entry(key: &str, value: &[u8]) -> CoreResult<()> {
+    let invalid = |reason| Err(CoreError::InvalidMetadata(key.to_string(), reason));
+    if key.is_empty() || key.len() > MAX_METADATA_KEY_BYTES {
+        return invalid("the key must be between 1 and 32 bytes long");
+    }
+    if value.len() > MAX_METADATA_VALUE_BYTES {
+        return invalid("the value is longer than 256 bytes");
+    }
+    if metadata::KNOWN_KEYS.contains(&key) && std::str::from_utf8(value).is_err() {
+        return invalid("the value is not UTF-8 text");
+    }
+    Ok(())
+}
+
+impl ImageInfo {
+    /// Reads the format and dimensions from the header of an encoded image.
+    ///
+    /// Only the structure of the file up to the dimensions is checked, the pixels are not decoded.
+    /// Returns [`None`] if the format is not supported or that structure is broken.
+    pub fn read(data: &[u8]) -> Option<Self> {
+        if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+            read_png(data)
+        } else if data.starts_with(&[0xff, 0xd8]) {
+            read_jpeg(data)
+        } else if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP") {
+            read_webp(data)
+        } else {
+            None
+        }
+    }
+}
+
+impl Display for ImageFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Png => "PNG",
+                Self::Jpeg => "JPEG",
+                Self::Webp => "WebP",
+            }
+        )
+    }
+}
+
+fn be_u16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn be_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn le_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn le_u24(data: &[u8], at: usize) -> Option<u32> {
+    let b = data.get(at..at + 3)?;
+    Some(u32::from(b[0]) | u32::from(b[1]) << 8 | u32::from(b[2]) << 16)
+}
+
+/// The chunks must start with `IHDR` and follow each other up to `IEND`
+fn read_png(data: &[u8]) -> Option<ImageInfo> {
+    let mut at = 8;
+    let mut info = None;
+    loop {
+        let len = usize::try_from(be_u32(data, at)?).ok()?;
+        let kind = data.get(at + 4..at + 8)?;
+        let end = at.checked_add(12)?.checked_add(len)?;
+        if end > data.len() {
+            return None;
+        }
+        match kind {
+            b"IHDR" if at == 8 && len == 13 => {
+                info = Some(ImageInfo {
+                    format: ImageFormat::Png,
+                    width: be_u32(data, at + 8)?,
+                    height: be_u32(data, at + 12)?,
+                });
+            }
+            b"IEND" => return info,
+            _ if info.is_none() => return None,
+            _ => (),
+        }
+        at = end;
+    }
+}
+
+/// The markers must follow each other up to the start of the frame
+fn read_jpeg(data: &[u8]) -> Option<ImageInfo> {
+    let mut at = 2;
+    loop {
+        if *data.get(at)? != 0xff {
+            return None;
+        }
+        let marker = *data.get(at + 1)?;
+        match marker {
+            // fill bytes before a marker
+            0xff => at += 1,
+            // markers without a length
+            0x01 | 0xd0..=0xd7 => at += 2,
+            // start of frame, except for the huffman, arithmetic coding and restart markers
+            0xc0..=0xcf if !matches!(marker, 0xc4 | 0xc8 | 0xcc) => {
+                return Some(ImageInfo {
+                    format: ImageFormat::Jpeg,
+                    height: u32::from(be_u16(data, at + 5)?),
+                    width: u32::from(be_u16(data, at + 7)?),
+                });
+            }
+            // start of scan or end of image before a frame
+            0xda | 0xd9 => return None,
+            _ => at += 2 + usize::from(be_u16(data, at + 2)?),
+        }
+    }
+}
+
+/// The RIFF size must match, the first chunk has the dimensions
+fn read_webp(data: &[u8]) -> Option<ImageInfo> {
+    let riff_len = usize::try_from(le_u32(data, 4)?).ok()?;
+    if riff_len.checked_add(8)? != data.len() {
+        return None;
+    }
+    let chunk = data.get(12..16)?;
+    let body = 20;
+    let (width, height) = match chunk {
+        b"VP8 " => {
+            // frame tag, then the start code of a key frame
+            if data.get(body + 3..body + 6)? != [0x9d, 0x01, 0x2a] {
+                return None;
+            }
+            let w = u16::from_le_bytes(data.get(body + 6..body + 8)?.try_into().ok()?);
+            let h = u16::from_le_bytes(data.get(body + 8..body + 10)?.try_into().ok()?);
+            (u32::from(w & 0x3fff), u32::from(h & 0x3fff))
+        }
+        b"VP8L" => {
+            if *data.get(body)? != 0x2f {
+                return None;
+            }
+            let bits = le_u32(data, body + 1)?;
+            ((bits & 0x3fff) + 1, ((bits >> 14) & 0x3fff) + 1)
+        }
+        b"VP8X" => (le_u24(data, body + 4)? + 1, le_u24(data, body + 7)? + 1),
+        _ => return None,
+    };
+    Some(ImageInfo {
+        format: ImageFormat::Webp,
+        width,
+        height,
+    })
+}
@@ -0,0 +1,107 @@
+//! Short representations of identity keys that users can compare out of band.
+//!
+//! A [`SafetyNumber`] is shared by two contacts and read aloud or compared side by side,
+//! [`ContactId::fingerprint_words`] describes a single key.
+
+use std::fmt::Display;
+
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::identity::ContactId;
+
+/// Number of groups of five digits in a [`SafetyNumber`]
+pub const SAFETY_NUMBER_GROUPS: usize = 12;
+/// Number of words returned by [`ContactId::fingerprint_words`]
+pub const FINGERPRINT_WORDS: usize = 8;
+
+const SAFETY_NUMBER_CONTEXT: &[u8] = b"SREMP safety number v1";
+const FINGERPRINT_CONTEXT: &[u8] = b"SREMP fingerprint v1";
+/// Makes finding a key with a colliding half of a safety number more expensive
+const SAFETY_NUMBER_ITERATIONS: usize = 5200;
+
+/// Numeric code that two contacts compare to verify each others identity keys.
+///
+/// Each half belongs to one of the keys, the half of the lower [`ContactId`] comes first, so
+/// both sides see the same number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SafetyNumber([u32; SAFETY_NUMBER_GROUPS]);
+
+impl SafetyNumber {
+    /// Computes the [`SafetyNumber`] of `a` and `b`, the order does not matter.
+    pub fn new(a: &ContactId, b: &ContactId) -> Self {
+        let (low, high) = if a <= b { (a, b) } else { (b, a) };
+        let half = SAFETY_NUMBER_GROUPS / 2;
+        let mut groups = [0; SAFETY_NUMBER_GROUPS];
+        groups[..half].copy_from_slice(&Self::half(low));
+        groups[half..].copy_from_slice(&Self::half(high));
+        Self(groups)
+    }
+
+    fn half(id: &ContactId) -> [u32; SAFETY_NUMBER_GROUPS / 2] {
+        let mut digest = Sha512::new()
+            .chain_update(SAFETY_NUMBER_CONTEXT)
+            .chain_update(id.as_bytes())
+            .finalize();
+        for _ in 1..SAFETY_NUMBER_ITERATIONS {
+            digest = Sha512::new()
+                .chain_update(digest)
+                .chain_update(id.as_bytes())
+                .finalize();
+        }
+        let mut half = [0; SAFETY_NUMBER_GROUPS / 2];
+        for (group, chunk) in half.iter_mut().zip(digest.chunks_exact(5)) {
+            let mut buf = [0; 8];
+            buf[3..].copy_from_slice(chunk);
+            *group = (u64::from_be_bytes(buf) % 100_000) as u32;
+        }
+        half
+    }
+
+    /// The groups of five digits, each below 100000
+    #[inline(always)]
+    pub fn groups(&self) -> &[u32; SAFETY_NUMBER_GROUPS] {
+        &self.0
+    }
+}
+
+impl Display for SafetyNumber {
+    /// Groups of five digits separated by spaces
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.0
+                .iter()
+                .map(|group| format!("{group:05}"))
+                .collect::<Vec<_>>()
+                .join(" ")
+        )
+    }
+}
+
+impl ContactId {
+    /// Describes the identity key with words of the BIP-39 english word list.
+    ///
+    /// Each word stands for 11 bits of a hash of the key.
+    pub fn fingerprint_words(&self) -> [&'static str; FINGERPRINT_WORDS] {
+        let digest = Sha256::new()
+            .chain_update(FINGERPRINT_CONTEXT)
+            .chain_update(self.as_bytes())
+            .finalize();
+        words_from_digest(&digest)
+    }
+}
+
+/// Picks `N` words of the BIP-39 english word list, 11 bits of `digest` per word.
+pub(super) fn words_from_digest<const N: usize>(digest: &[u8]) -> [&'static str; N] {
+    let list = bip39::Language::English.word_list();
+    let mut bits = digest
+        .iter()
+        .flat_map(|b| (0..8).rev().map(move |i| (b >> i) & 1));
+    std::array::from_fn(|_| {
+        let index = (0..11).fold(0usize, |acc, _| {
+            (acc << 1) | bits.next().expect("enough bits in the digest") as usize
+        });
+        list[index]
+    })
+}
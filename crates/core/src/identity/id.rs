@@ -1,12 +1,12 @@
-use std::{fmt::Display, sync::Arc};
+use std::{fmt::Display, str::FromStr, sync::Arc};
 
 use serde::{Deserialize, Serialize};
 
-use crate::ser_helper::*;
+use crate::{error::CoreError, ser_helper::*};
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct ContactId {
-    #[serde(flatten)]
     #[serde(serialize_with = "ser_arc", deserialize_with = "deser_arc")]
     key: Arc<ed25519_dalek::VerifyingKey>,
 }
@@ -18,6 +18,14 @@ impl From<ed25519_dalek::VerifyingKey> for ContactId {
     }
 }
 
+impl ContactId {
+    /// The public identity key as bytes
+    #[inline(always)]
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        self.key.as_bytes()
+    }
+}
+
 impl PartialOrd for ContactId {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         Some(self.cmp(other))
@@ -37,6 +45,24 @@ impl Display for ContactId {
     }
 }
 
+impl FromStr for ContactId {
+    type Err = CoreError;
+
+    /// Parses the hexadecimal form that [`Display`] writes, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.len() != 64 || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(CoreError::InvalidContactId);
+        }
+        let mut bytes = [0; 32];
+        for (byte, hex) in bytes.iter_mut().zip(s.as_bytes().chunks(2)) {
+            let hex = std::str::from_utf8(hex).map_err(|_| CoreError::InvalidContactId)?;
+            *byte = u8::from_str_radix(hex, 16).map_err(|_| CoreError::InvalidContactId)?;
+        }
+        Ok(ed25519_dalek::VerifyingKey::from_bytes(&bytes)?.into())
+    }
+}
+
 #[inline]
 pub fn format_key(key: &ed25519_dalek::VerifyingKey) -> String {
     let mut buf = String::new();
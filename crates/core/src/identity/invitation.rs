@@ -0,0 +1,134 @@
+//! Invitations to connect to a user, shared out of band as a `sremp:` URI or QR code.
+//!
+//! An [`Invitation`] contains the identity key and username of the user, the endpoints they can
+//! be reached on and an expiry, signed by the identity key. Whoever connects with it knows the
+//! [`ContactId`] to expect and refuses a peer with another identity.
+//!
+//! The rest of the [`Identity`], like its devices and extensions, is exchanged when connecting.
+//! Leaving it out keeps invitations small enough for a QR code.
+//!
+//! The URI is `sremp:invite/` followed by the MessagePack encoded invitation in unpadded
+//! base64url.
+
+use std::net::SocketAddr;
+
+use base64ct::{Base64UrlUnpadded, Encoding};
+use chrono::{DateTime, TimeDelta, Utc};
+use ed25519_dalek::ed25519::signature::SignerMut;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{CoreError, CoreResult},
+    identity::{ContactId, Identity, UserIdentity},
+};
+
+/// Version of the invitation format that this implementation produces
+pub const INVITATION_VERSION: u8 = 1;
+/// Prefix of an [`Invitation`] encoded as URI
+pub const INVITATION_URI_PREFIX: &str = "sremp:invite/";
+
+/// The part of an [`Invitation`] that is signed by the identity key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct InvitationData {
+    version: u8,
+    identity_key: ed25519_dalek::VerifyingKey,
+    username: String,
+    endpoints: Vec<SocketAddr>,
+    expires: DateTime<Utc>,
+}
+
+/// Signed invitation to connect to the user of [`Invitation::contact_id`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Invitation {
+    data: InvitationData,
+    signature: ed25519_dalek::Signature,
+}
+
+impl Invitation {
+    /// Creates an [`Invitation`] to connect to `user` on `endpoints`, which expires after
+    /// `valid_for`.
+    pub fn create(
+        user: &UserIdentity,
+        endpoints: Vec<SocketAddr>,
+        valid_for: TimeDelta,
+    ) -> CoreResult<Self> {
+        if endpoints.is_empty() {
+            return Err(CoreError::InvitationWithoutEndpoints);
+        }
+        let data = InvitationData {
+            version: INVITATION_VERSION,
+            identity_key: user.identity.identity_key(),
+            username: user.identity.username().to_string(),
+            endpoints,
+            expires: Utc::now() + valid_for,
+        };
+        let signature = user
+            .identity_private_key()
+            .clone()
+            .try_sign(&rmp_serde::to_vec(&data)?)?;
+        Ok(Self { data, signature })
+    }
+
+    /// Checks the signatures, that there is an endpoint and that it has not expired.
+    pub fn verify(&self) -> CoreResult<()> {
+        if self.data.version != INVITATION_VERSION {
+            return Err(CoreError::UnsupportedInvitationVersion(self.data.version));
+        }
+        Identity::validate_username(&self.data.username)?;
+        self.data
+            .identity_key
+            .verify_strict(&rmp_serde::to_vec(&self.data)?, &self.signature)?;
+        if self.data.endpoints.is_empty() {
+            return Err(CoreError::InvitationWithoutEndpoints);
+        }
+        if self.data.expires < Utc::now() {
+            return Err(CoreError::InvitationExpired(self.data.expires));
+        }
+        Ok(())
+    }
+
+    /// Encodes this [`Invitation`] as `sremp:` URI.
+    pub fn to_uri(&self) -> CoreResult<String> {
+        Ok(format!(
+            "{INVITATION_URI_PREFIX}{}",
+            Base64UrlUnpadded::encode_string(&rmp_serde::to_vec(self)?)
+        ))
+    }
+
+    /// Decodes and verifies an [`Invitation`] from its URI, surrounding whitespace is ignored.
+    pub fn from_uri(uri: &str) -> CoreResult<Self> {
+        let payload = uri
+            .trim()
+            .strip_prefix(INVITATION_URI_PREFIX)
+            .ok_or(CoreError::NotAnInvitation)?;
+        let bytes =
+            Base64UrlUnpadded::decode_vec(payload).map_err(|_| CoreError::NotAnInvitation)?;
+        let invitation: Self =
+            rmp_serde::from_slice(&bytes).map_err(|_| CoreError::NotAnInvitation)?;
+        invitation.verify()?;
+        Ok(invitation)
+    }
+
+    /// The contact that must be on the other end of a connection made with this invitation
+    #[inline(always)]
+    pub fn contact_id(&self) -> ContactId {
+        self.data.identity_key.into()
+    }
+
+    /// Username of the contact when the invitation was created
+    #[inline(always)]
+    pub fn username(&self) -> &str {
+        &self.data.username
+    }
+
+    /// Addresses to try in order
+    #[inline(always)]
+    pub fn endpoints(&self) -> &[SocketAddr] {
+        &self.data.endpoints
+    }
+
+    #[inline(always)]
+    pub fn expires(&self) -> DateTime<Utc> {
+        self.data.expires
+    }
+}
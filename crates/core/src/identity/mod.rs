@@ -1,4 +1,4 @@
-use std::{collections::HashMap, fmt::Debug};
+use std::fmt::Debug;
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -17,6 +17,34 @@ pub use user::*;
 mod contact;
 pub use contact::*;
 
+mod device;
+pub use device::*;
+
+mod extensions;
+pub use extensions::*;
+
+mod username;
+pub use username::*;
+
+mod statement;
+pub use statement::*;
+
+pub mod export;
+
+mod recovery;
+pub use recovery::*;
+
+mod fingerprint;
+pub use fingerprint::*;
+
+mod sas;
+pub use sas::*;
+
+mod pairing;
+pub use pairing::*;
+
+pub mod invitation;
+
 mod crypto;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
@@ -29,13 +57,17 @@ pub struct Flags {
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct IdentityVerifiedData {
-    username: String, // TODO: 1 to 40 characters according to spec
+    username: String,
     identity_key: ed25519_dalek::VerifyingKey,
     noise_key: x25519_dalek::PublicKey,
     flags: Flags,
     extensions: Option<Extensions>,
     version: u64,
     created: DateTime<Utc>,
+    /// Certificates of the devices besides the one with `noise_key`
+    // NOTE: left out when empty, so identities without devices keep their signature
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    devices: Vec<DeviceCertificate>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -44,12 +76,6 @@ pub struct Identity {
     signature: ed25519_dalek::Signature,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
-pub struct Extensions {
-    profile_picture: Option<Vec<u8>>,
-    additional_metadata: HashMap<String, Vec<u8>>,
-}
-
 impl Identity {
     /// Creates a new [`Identity`].
     pub fn create(
@@ -57,16 +83,18 @@ impl Identity {
         identity_private_key: &mut ed25519_dalek::SigningKey,
         noise_public_key: x25519_dalek::PublicKey,
     ) -> CoreResult<Self> {
-        Self::validate_username(username)?;
+        let username = Self::normalize_username(username);
+        Self::validate_username(&username)?;
 
         let vd = IdentityVerifiedData {
-            username: username.to_string(),
+            username,
             identity_key: identity_private_key.verifying_key(),
             noise_key: noise_public_key,
             flags: Default::default(),
             extensions: Default::default(),
             version: 0,
             created: Utc::now(),
+            devices: Vec::new(),
         };
 
         let sig = vd.sign(identity_private_key)?;
@@ -82,23 +110,15 @@ impl Identity {
         &self.verified.username
     }
 
-    pub fn validate_username(username: &str) -> CoreResult<()> {
-        let chars_len = username.chars().count();
-        if !(1..=40).contains(&chars_len) {
-            Err(crate::error::CoreError::InvalidUsername)
-        } else {
-            Ok(())
-        }
-    }
-
     #[inline]
     pub fn set_username(
         &mut self,
         username: &str,
         private_key: &mut ed25519_dalek::SigningKey,
     ) -> CoreResult<()> {
-        Self::validate_username(username)?;
-        self.verified.username = username.to_string();
+        let username = Self::normalize_username(username);
+        Self::validate_username(&username)?;
+        self.verified.username = username;
         self.post_update(private_key)
     }
 
@@ -127,6 +147,52 @@ impl Identity {
         self.post_update(private_key)
     }
 
+    /// Certificates of the additional devices of this identity
+    #[inline(always)]
+    pub fn devices(&self) -> &[DeviceCertificate] {
+        &self.verified.devices
+    }
+
+    /// Whether a device of this identity uses `noise_key`, either the main device or one with a
+    /// [`DeviceCertificate`].
+    ///
+    /// The certificates are only checked by [`verify`](Self::verify).
+    pub fn is_device_key(&self, noise_key: &x25519_dalek::PublicKey) -> bool {
+        self.noise_key() == *noise_key || self.devices().iter().any(|d| d.noise_key() == *noise_key)
+    }
+
+    /// Adds a device, or replaces the certificate of a device with the same noise key.
+    pub fn add_device(
+        &mut self,
+        certificate: DeviceCertificate,
+        private_key: &mut ed25519_dalek::SigningKey,
+    ) -> CoreResult<()> {
+        certificate.verify(&self.identity_key())?;
+        self.verified
+            .devices
+            .retain(|d| d.noise_key() != certificate.noise_key());
+        self.verified.devices.push(certificate);
+        self.post_update(private_key)
+    }
+
+    /// Removes the device with `noise_key`, so peers stop accepting it once they have the new
+    /// version of this identity. Returns false if there was no such device.
+    pub fn remove_device(
+        &mut self,
+        noise_key: &x25519_dalek::PublicKey,
+        private_key: &mut ed25519_dalek::SigningKey,
+    ) -> CoreResult<bool> {
+        let before = self.verified.devices.len();
+        self.verified
+            .devices
+            .retain(|d| d.noise_key() != *noise_key);
+        if self.verified.devices.len() == before {
+            return Ok(false);
+        }
+        self.post_update(private_key)?;
+        Ok(true)
+    }
+
     #[inline(always)]
     pub fn flags(&self) -> Flags {
         self.verified.flags
@@ -147,12 +213,16 @@ impl Identity {
         self.verified.extensions.as_ref()
     }
 
-    #[inline]
+    /// Replaces the [`Extensions`], empty ones are left out.
     pub fn set_extensions(
         &mut self,
         extensions: Option<Extensions>,
         private_key: &mut ed25519_dalek::SigningKey,
     ) -> CoreResult<()> {
+        let extensions = extensions.filter(|e| !e.is_empty());
+        if let Some(extensions) = &extensions {
+            extensions.validate()?;
+        }
         self.verified.extensions = extensions;
         self.post_update(private_key)
     }
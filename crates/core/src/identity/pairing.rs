@@ -0,0 +1,291 @@
+//! Pairing with a short code that both users enter, like `7-guitar-ocean`.
+//!
+//! The code is the password of SPAKE2 over ristretto255 (RFC 9382), run over an established
+//! connection. The transcript includes the handshake hash of the connection and both identity
+//! keys, so the confirmations only match if both users entered the same code and nobody is in
+//! the middle of the connection. A wrong guess of an attacker makes one pairing fail, the code
+//! can not be tested offline.
+
+use std::{fmt::Display, str::FromStr};
+
+use curve25519_dalek::{
+    RistrettoPoint, Scalar, constants::RISTRETTO_BASEPOINT_TABLE, ristretto::CompressedRistretto,
+    traits::IsIdentity,
+};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha512};
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::{
+    error::{CoreError, CoreResult},
+    identity::ContactId,
+    net::connection::HandshakeHash,
+};
+
+/// Number of words in a [`PairingCode`], after the number
+pub const PAIRING_CODE_WORDS: usize = 2;
+/// Upper bound of the number at the start of a [`PairingCode`]
+const PAIRING_CODE_NUMBERS: u32 = 100;
+
+const PAIRING_CONTEXT: &[u8] = b"SREMP pairing v1";
+
+/// Public share of one side of a [`Pairing`]
+pub type PairingShare = [u8; 32];
+/// Proof that a side of a [`Pairing`] knows the code
+pub type PairingConfirmation = [u8; 32];
+
+/// A number and words of the BIP-39 english word list, that one user reads out to the other
+#[derive(Clone, PartialEq, Eq, Zeroize, ZeroizeOnDrop)]
+pub struct PairingCode(String);
+
+/// One side of a pairing on a connection
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct Pairing {
+    password: Scalar,
+    secret: Scalar,
+    share: PairingShare,
+    transcript_prefix: Vec<u8>,
+    #[zeroize(skip)]
+    first: bool,
+}
+
+/// The confirmations of both sides, once the [`PairingShare`] of the contact is known
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct PairingKeys {
+    ours: PairingConfirmation,
+    theirs: PairingConfirmation,
+}
+
+/// Steps of a [`Pairing`], sent in an [`Envelope::Pairing`](crate::net::envelope::Envelope::Pairing)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PairingMessage {
+    /// The [`PairingShare`] of the peer, who has entered a code
+    Start(PairingShare),
+    /// The [`PairingConfirmation`] of the peer
+    Confirm(PairingConfirmation),
+    /// The peer has aborted the pairing
+    Cancel,
+}
+
+impl PairingCode {
+    /// Creates a random [`PairingCode`].
+    pub fn generate() -> Self {
+        let mut rng = rand::rngs::OsRng;
+        let list = bip39::Language::English.word_list();
+        let mut parts = vec![(rng.next_u32() % PAIRING_CODE_NUMBERS).to_string()];
+        parts.extend(
+            (0..PAIRING_CODE_WORDS).map(|_| list[rng.next_u32() as usize % list.len()].to_string()),
+        );
+        Self(parts.join("-"))
+    }
+}
+
+impl FromStr for PairingCode {
+    type Err = CoreError;
+
+    /// Parses a code with its parts separated by dashes or whitespace, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<String> = s
+            .split(|c: char| c == '-' || c.is_whitespace())
+            .filter(|part| !part.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        let valid = parts.len() == PAIRING_CODE_WORDS + 1
+            && parts[0]
+                .parse::<u32>()
+                .is_ok_and(|n| n < PAIRING_CODE_NUMBERS)
+            && parts[1..]
+                .iter()
+                .all(|word| bip39::Language::English.find_word(word).is_some());
+        if !valid {
+            return Err(CoreError::InvalidPairingCode);
+        }
+        Ok(Self(parts.join("-")))
+    }
+}
+
+impl Display for PairingCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::fmt::Debug for PairingCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PairingCode {{redacted}}")
+    }
+}
+
+impl Pairing {
+    /// Starts pairing `user` with `contact` over the connection with `handshake_hash`.
+    pub fn new(
+        code: &PairingCode,
+        handshake_hash: &HandshakeHash,
+        user: &ContactId,
+        contact: &ContactId,
+    ) -> Self {
+        // the contact with the lower id takes the role of A
+        let first = user < contact;
+        let (a, b) = if first {
+            (user, contact)
+        } else {
+            (contact, user)
+        };
+
+        let mut wide = [0; 64];
+        wide.copy_from_slice(
+            &Sha512::new()
+                .chain_update(PAIRING_CONTEXT)
+                .chain_update(code.0.as_bytes())
+                .finalize(),
+        );
+        let password = Scalar::from_bytes_mod_order_wide(&wide);
+        rand::rngs::OsRng.fill_bytes(&mut wide);
+        let secret = Scalar::from_bytes_mod_order_wide(&wide);
+        wide.zeroize();
+
+        let blind = if first { point_m() } else { point_n() };
+        let share = (RISTRETTO_BASEPOINT_TABLE * &secret + blind * password)
+            .compress()
+            .to_bytes();
+
+        let mut transcript_prefix = Vec::new();
+        for part in [PAIRING_CONTEXT, handshake_hash, a.as_bytes(), b.as_bytes()] {
+            push_prefixed(&mut transcript_prefix, part);
+        }
+
+        Self {
+            password,
+            secret,
+            share,
+            transcript_prefix,
+            first,
+        }
+    }
+
+    /// The share to send to the contact
+    #[inline(always)]
+    pub fn share(&self) -> PairingShare {
+        self.share
+    }
+
+    /// Computes the confirmations of both sides from the share of the contact.
+    pub fn finish(&self, theirs: &PairingShare) -> CoreResult<PairingKeys> {
+        let point = CompressedRistretto(*theirs)
+            .decompress()
+            .filter(|point| !point.is_identity())
+            .ok_or(CoreError::InvalidPairingShare)?;
+        let blind = if self.first { point_n() } else { point_m() };
+        let shared = (point - blind * self.password) * self.secret;
+        if shared.is_identity() {
+            return Err(CoreError::InvalidPairingShare);
+        }
+
+        let (share_a, share_b) = if self.first {
+            (&self.share, theirs)
+        } else {
+            (theirs, &self.share)
+        };
+        let mut transcript = self.transcript_prefix.clone();
+        for part in [
+            share_a.as_slice(),
+            share_b,
+            shared.compress().as_bytes(),
+            self.password.as_bytes(),
+        ] {
+            push_prefixed(&mut transcript, part);
+        }
+        let confirm = |role: &[u8]| -> PairingConfirmation {
+            Sha256::new()
+                .chain_update(&transcript)
+                .chain_update(role)
+                .finalize()
+                .into()
+        };
+        let (confirm_a, confirm_b) = (confirm(b"A"), confirm(b"B"));
+        transcript.zeroize();
+
+        Ok(if self.first {
+            PairingKeys {
+                ours: confirm_a,
+                theirs: confirm_b,
+            }
+        } else {
+            PairingKeys {
+                ours: confirm_b,
+                theirs: confirm_a,
+            }
+        })
+    }
+}
+
+impl std::fmt::Debug for Pairing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Pairing")
+            .field("share", &self.share)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PairingKeys {
+    /// The confirmation to send to the contact
+    #[inline(always)]
+    pub fn ours(&self) -> PairingConfirmation {
+        self.ours
+    }
+
+    /// Checks the confirmation of the contact, `true` if both entered the same code.
+    pub fn verify(&self, theirs: &PairingConfirmation) -> bool {
+        self.theirs.ct_eq(theirs).into()
+    }
+}
+
+impl std::fmt::Debug for PairingKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PairingKeys {{redacted}}")
+    }
+}
+
+impl Display for PairingMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Start(_) => "start",
+                Self::Confirm(_) => "confirm",
+                Self::Cancel => "cancel",
+            }
+        )
+    }
+}
+
+/// Appends `part` with its length, so that parts can not be shifted into each other
+fn push_prefixed(transcript: &mut Vec<u8>, part: &[u8]) {
+    transcript.extend((part.len() as u64).to_le_bytes());
+    transcript.extend(part);
+}
+
+/// A point whose discrete logarithm nobody knows
+fn unknown_point(label: &[u8]) -> RistrettoPoint {
+    let mut wide = [0; 64];
+    wide.copy_from_slice(
+        &Sha512::new()
+            .chain_update(PAIRING_CONTEXT)
+            .chain_update(label)
+            .finalize(),
+    );
+    RistrettoPoint::from_uniform_bytes(&wide)
+}
+
+#[inline]
+fn point_m() -> RistrettoPoint {
+    unknown_point(b"M")
+}
+
+#[inline]
+fn point_n() -> RistrettoPoint {
+    unknown_point(b"N")
+}
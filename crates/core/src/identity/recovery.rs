@@ -0,0 +1,158 @@
+//! Paper backups of the identity key as a list of words.
+//!
+//! A [`RecoveryPhrase`] holds 256 bits of entropy as 24 words of the BIP-39 english word list,
+//! the last word includes a checksum. The identity key is derived from the entropy with
+//! SHA-256 and a domain separation prefix, so the same phrase always leads to the same
+//! identity key.
+//!
+//! Only identities created with [`UserIdentity::create_recoverable`] have a phrase. The noise
+//! key is not part of it, [`UserIdentity::recover`] generates a fresh one.
+
+use std::{fmt::Display, str::FromStr};
+
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::{
+    error::{CoreError, CoreResult},
+    identity::{Identity, UserIdentity, crypto::generate_good_key_x25519},
+};
+
+/// Number of words in a [`RecoveryPhrase`]
+pub const RECOVERY_PHRASE_WORDS: usize = 24;
+
+const ENTROPY_LEN: usize = 32;
+const DERIVATION_CONTEXT: &[u8] = b"SREMP identity key v1";
+
+/// Words from which the identity key of a [`UserIdentity`] can be derived again
+#[derive(Clone, PartialEq, Eq)]
+pub struct RecoveryPhrase(bip39::Mnemonic);
+
+impl RecoveryPhrase {
+    /// Creates a new [`RecoveryPhrase`] that leads to a good identity key.
+    #[cold]
+    fn generate() -> Self {
+        let mut entropy = Zeroizing::new([0; ENTROPY_LEN]);
+        for _ in 0..10 {
+            rand::rngs::OsRng.fill_bytes(&mut *entropy);
+            let phrase = Self(
+                bip39::Mnemonic::from_entropy(&*entropy)
+                    .expect("32 bytes are a valid entropy length"),
+            );
+            if phrase.identity_key().is_ok() {
+                return phrase;
+            }
+        }
+        panic!(
+            "10 fails in a row to creating a good key. This is almost impossible! Something is wrong with your system!"
+        )
+    }
+
+    /// Derives the identity key from this phrase.
+    pub fn identity_key(&self) -> CoreResult<ed25519_dalek::SigningKey> {
+        let (entropy, len) = self.0.to_entropy_array();
+        let entropy = Zeroizing::new(entropy);
+        let mut hasher = Sha256::new();
+        hasher.update(DERIVATION_CONTEXT);
+        hasher.update(&entropy[..len]);
+        let seed = Zeroizing::new(<[u8; 32]>::from(hasher.finalize()));
+
+        let key = ed25519_dalek::SigningKey::from_bytes(&seed);
+        if key.verifying_key().is_weak() {
+            return Err(CoreError::WeakRecoveryKey);
+        }
+        Ok(key)
+    }
+
+    /// The words of this phrase in order
+    pub fn words(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.0.words()
+    }
+}
+
+impl FromStr for RecoveryPhrase {
+    type Err = CoreError;
+
+    /// Parses the words separated by whitespace, ignoring case. The checksum is verified.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words = Zeroizing::new(
+            s.split_whitespace()
+                .map(str::to_lowercase)
+                .collect::<Vec<_>>(),
+        );
+        if words.len() != RECOVERY_PHRASE_WORDS {
+            return Err(CoreError::RecoveryPhraseLength {
+                expected: RECOVERY_PHRASE_WORDS,
+                received: words.len(),
+            });
+        }
+        let normalized = Zeroizing::new(words.join(" "));
+        bip39::Mnemonic::parse_in_normalized(bip39::Language::English, &normalized)
+            .map(Self)
+            .map_err(CoreError::InvalidRecoveryPhrase)
+    }
+}
+
+impl Display for RecoveryPhrase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::fmt::Debug for RecoveryPhrase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "RecoveryPhrase {{redacted}}")
+    }
+}
+
+impl UserIdentity {
+    /// Creates a new [`UserIdentity`] whose identity key is derived from a [`RecoveryPhrase`].
+    ///
+    /// The phrase is not stored, it must be shown to the user right away.
+    #[cold]
+    pub fn create_recoverable(username: &str) -> CoreResult<(Self, RecoveryPhrase)> {
+        let phrase = RecoveryPhrase::generate();
+        let mut identity_key = phrase.identity_key()?;
+        let noise_key = generate_good_key_x25519();
+        let identity = Identity::create(
+            username,
+            &mut identity_key,
+            x25519_dalek::PublicKey::from(&noise_key),
+        )?;
+
+        Ok((
+            Self {
+                identity,
+                identity_key,
+                noise_key,
+            },
+            phrase,
+        ))
+    }
+
+    /// Restores a [`UserIdentity`] from its [`RecoveryPhrase`].
+    ///
+    /// The identity key is the same as before, the noise key is new. Peers only accept an
+    /// identity with a higher version than the one they know, which is lost with the device.
+    /// The restored identity therefore uses the current unix time as version, which is far
+    /// above what a version reaches by single updates. Certified devices are not restored.
+    #[cold]
+    pub fn recover(phrase: &RecoveryPhrase, username: &str) -> CoreResult<Self> {
+        let mut identity_key = phrase.identity_key()?;
+        let noise_key = generate_good_key_x25519();
+        let mut identity = Identity::create(
+            username,
+            &mut identity_key,
+            x25519_dalek::PublicKey::from(&noise_key),
+        )?;
+        identity.verified.version = identity.created().timestamp().max(0) as u64;
+        identity.signature = identity.verified.sign(&mut identity_key)?;
+
+        Ok(Self {
+            identity,
+            identity_key,
+            noise_key,
+        })
+    }
+}
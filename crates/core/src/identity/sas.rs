@@ -0,0 +1,78 @@
+//! Short authentication strings for verifying a contact in band.
+//!
+//! Both peers of a connection derive the same [`ShortAuthString`] from the hash of its noise
+//! handshake and the two identity keys. A man in the middle has a separate handshake with each
+//! side, so the users see different words. The users compare the words over a channel they
+//! trust, like a call, and each side tells the other with a [`SasMessage`] whether they matched.
+
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    identity::{ContactId, fingerprint::words_from_digest},
+    net::connection::HandshakeHash,
+};
+
+/// Number of words in a [`ShortAuthString`]
+pub const SAS_WORDS: usize = 5;
+
+const SAS_CONTEXT: &[u8] = b"SREMP SAS v1";
+
+/// Words of the BIP-39 english word list that both users of a connection see
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ShortAuthString([&'static str; SAS_WORDS]);
+
+/// Steps of the verification with a [`ShortAuthString`], sent in an
+/// [`Envelope::Verification`](crate::net::envelope::Envelope::Verification)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SasMessage {
+    /// The peer shows the [`ShortAuthString`] of this connection to its user
+    Start,
+    /// The user of the peer has compared the words, `true` if they matched
+    Confirm(bool),
+    /// The peer has aborted the verification without a result
+    Cancel,
+}
+
+impl ShortAuthString {
+    /// Derives the [`ShortAuthString`] of a connection between `a` and `b`, the order of the
+    /// contacts does not matter.
+    pub fn new(handshake_hash: &HandshakeHash, a: &ContactId, b: &ContactId) -> Self {
+        let (low, high) = if a <= b { (a, b) } else { (b, a) };
+        let digest = Sha256::new()
+            .chain_update(SAS_CONTEXT)
+            .chain_update(handshake_hash)
+            .chain_update(low.as_bytes())
+            .chain_update(high.as_bytes())
+            .finalize();
+        Self(words_from_digest(&digest))
+    }
+
+    #[inline(always)]
+    pub fn words(&self) -> &[&'static str; SAS_WORDS] {
+        &self.0
+    }
+}
+
+impl Display for ShortAuthString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.join(" "))
+    }
+}
+
+impl Display for SasMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Start => "start",
+                Self::Confirm(true) => "match",
+                Self::Confirm(false) => "mismatch",
+                Self::Cancel => "cancel",
+            }
+        )
+    }
+}
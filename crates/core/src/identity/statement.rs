@@ -0,0 +1,266 @@
+use std::fmt::Display;
+
+use chrono::{DateTime, Utc};
+use ed25519_dalek::ed25519::signature::SignerMut;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{CoreError, CoreResult},
+    identity::{ContactId, Identity},
+};
+
+/// The part of a [`Succession`] that is signed by both identity keys
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct SuccessionData {
+    predecessor: ed25519_dalek::VerifyingKey,
+    successor: Identity,
+    created: DateTime<Utc>,
+}
+
+/// The identity key of `predecessor` endorses the identity `successor` as its replacement.
+///
+/// Both keys sign it, so nobody can name an identity as successor without owning it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Succession {
+    data: SuccessionData,
+    predecessor_signature: ed25519_dalek::Signature,
+    successor_signature: ed25519_dalek::Signature,
+}
+
+/// The part of a [`Revocation`] that is signed by the identity key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct RevocationData {
+    identity_key: ed25519_dalek::VerifyingKey,
+    reason: String,
+    created: DateTime<Utc>,
+}
+
+/// The identity key declares itself as no longer valid, usually because it was compromised.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Revocation {
+    data: RevocationData,
+    signature: ed25519_dalek::Signature,
+}
+
+/// The part of an [`Endorsement`] that is signed by the identity key of the endorser
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct EndorsementData {
+    endorser: ed25519_dalek::VerifyingKey,
+    endorsed: ContactId,
+    created: DateTime<Utc>,
+    revoked: bool,
+}
+
+/// The identity key of `endorser` vouches for the identity `endorsed`, or withdraws an earlier
+/// endorsement of it if `revoked` is set.
+///
+/// Only the newest endorsement of an endorser for the same identity counts, see
+/// [`supersedes`](Self::supersedes).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Endorsement {
+    data: EndorsementData,
+    signature: ed25519_dalek::Signature,
+}
+
+/// A signed statement about an identity that concerns all of its contacts.
+///
+/// Statements are self-authenticating, so they can be passed on by anyone.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[allow(clippy::large_enum_variant)]
+pub enum IdentityStatement {
+    Succession(Succession),
+    Revocation(Revocation),
+    Endorsement(Endorsement),
+}
+
+impl Succession {
+    /// Creates a [`Succession`] from the identity of `predecessor_key` to `successor`.
+    pub fn create(
+        predecessor_key: &mut ed25519_dalek::SigningKey,
+        successor: Identity,
+        successor_key: &mut ed25519_dalek::SigningKey,
+    ) -> CoreResult<Self> {
+        let data = SuccessionData {
+            predecessor: predecessor_key.verifying_key(),
+            successor,
+            created: Utc::now(),
+        };
+        let bytes = rmp_serde::to_vec(&data)?;
+        Ok(Self {
+            predecessor_signature: predecessor_key.try_sign(&bytes)?,
+            successor_signature: successor_key.try_sign(&bytes)?,
+            data,
+        })
+    }
+
+    pub fn verify(&self) -> CoreResult<()> {
+        self.data.successor.verify()?;
+        if self.data.successor.identity_key() == self.data.predecessor {
+            return Err(CoreError::SelfSuccession(self.predecessor()));
+        }
+        let bytes = rmp_serde::to_vec(&self.data)?;
+        self.data
+            .predecessor
+            .verify_strict(&bytes, &self.predecessor_signature)?;
+        self.data
+            .successor
+            .identity_key()
+            .verify_strict(&bytes, &self.successor_signature)?;
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub fn predecessor(&self) -> ContactId {
+        self.data.predecessor.into()
+    }
+
+    #[inline(always)]
+    pub fn successor(&self) -> &Identity {
+        &self.data.successor
+    }
+
+    #[inline(always)]
+    pub fn created(&self) -> DateTime<Utc> {
+        self.data.created
+    }
+}
+
+impl Revocation {
+    /// Creates a [`Revocation`] of the identity of `identity_key`.
+    pub fn create(identity_key: &mut ed25519_dalek::SigningKey, reason: &str) -> CoreResult<Self> {
+        let data = RevocationData {
+            identity_key: identity_key.verifying_key(),
+            reason: reason.to_string(),
+            created: Utc::now(),
+        };
+        Ok(Self {
+            signature: identity_key.try_sign(&rmp_serde::to_vec(&data)?)?,
+            data,
+        })
+    }
+
+    pub fn verify(&self) -> CoreResult<()> {
+        self.data
+            .identity_key
+            .verify_strict(&rmp_serde::to_vec(&self.data)?, &self.signature)?;
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub fn revoked(&self) -> ContactId {
+        self.data.identity_key.into()
+    }
+
+    #[inline(always)]
+    pub fn reason(&self) -> &str {
+        &self.data.reason
+    }
+
+    #[inline(always)]
+    pub fn created(&self) -> DateTime<Utc> {
+        self.data.created
+    }
+}
+
+impl Endorsement {
+    /// Creates an [`Endorsement`] of `endorsed` by the identity of `endorser_key`, or withdraws
+    /// it if `revoked` is set.
+    pub fn create(
+        endorser_key: &mut ed25519_dalek::SigningKey,
+        endorsed: ContactId,
+        revoked: bool,
+    ) -> CoreResult<Self> {
+        let data = EndorsementData {
+            endorser: endorser_key.verifying_key(),
+            endorsed,
+            created: Utc::now(),
+            revoked,
+        };
+        Ok(Self {
+            signature: endorser_key.try_sign(&rmp_serde::to_vec(&data)?)?,
+            data,
+        })
+    }
+
+    pub fn verify(&self) -> CoreResult<()> {
+        if self.endorser() == self.data.endorsed {
+            return Err(CoreError::SelfEndorsement(self.endorser()));
+        }
+        self.data
+            .endorser
+            .verify_strict(&rmp_serde::to_vec(&self.data)?, &self.signature)?;
+        Ok(())
+    }
+
+    /// Whether this endorsement replaces `other`, because it is a newer one of the same endorser
+    /// for the same identity.
+    pub fn supersedes(&self, other: &Self) -> bool {
+        self.data.endorser == other.data.endorser
+            && self.data.endorsed == other.data.endorsed
+            && self.data.created > other.data.created
+    }
+
+    #[inline(always)]
+    pub fn endorser(&self) -> ContactId {
+        self.data.endorser.into()
+    }
+
+    #[inline(always)]
+    pub fn endorsed(&self) -> &ContactId {
+        &self.data.endorsed
+    }
+
+    #[inline(always)]
+    pub fn created(&self) -> DateTime<Utc> {
+        self.data.created
+    }
+
+    /// Whether the endorser has withdrawn the endorsement
+    #[inline(always)]
+    pub fn is_revoked(&self) -> bool {
+        self.data.revoked
+    }
+}
+
+impl IdentityStatement {
+    pub fn verify(&self) -> CoreResult<()> {
+        match self {
+            Self::Succession(s) => s.verify(),
+            Self::Revocation(r) => r.verify(),
+            Self::Endorsement(e) => e.verify(),
+        }
+    }
+
+    /// The identity that is replaced, revoked or endorsed
+    pub fn subject(&self) -> ContactId {
+        match self {
+            Self::Succession(s) => s.predecessor(),
+            Self::Revocation(r) => r.revoked(),
+            Self::Endorsement(e) => e.endorsed().clone(),
+        }
+    }
+}
+
+impl Display for IdentityStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Succession(s) => format!(
+                    "Succession of {} by {}",
+                    s.predecessor(),
+                    s.successor().id()
+                ),
+                Self::Revocation(r) => format!("Revocation of {}", r.revoked()),
+                Self::Endorsement(e) if e.is_revoked() => format!(
+                    "Withdrawn endorsement of {} by {}",
+                    e.endorsed(),
+                    e.endorser()
+                ),
+                Self::Endorsement(e) =>
+                    format!("Endorsement of {} by {}", e.endorsed(), e.endorser()),
+            }
+        )
+    }
+}
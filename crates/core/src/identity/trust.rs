@@ -7,6 +7,14 @@ pub enum Trust {
     Unknown,
     Trusted,
     Rejected,
+    /// The identity key was revoked or replaced by its owner
+    Revoked,
+    /// Both users have compared a [`ShortAuthString`](super::ShortAuthString) of a connection
+    /// and it matched, which is stronger than [`Trust::Trusted`]
+    Verified,
+    /// The comparison of a [`ShortAuthString`](super::ShortAuthString) failed, there may be a
+    /// man in the middle
+    Suspicious,
 }
 
 impl Display for Trust {
@@ -18,6 +26,9 @@ impl Display for Trust {
                 Self::Unknown => "Unknown",
                 Self::Trusted => "Trusted",
                 Self::Rejected => "Rejected",
+                Self::Revoked => "Revoked",
+                Self::Verified => "Verified",
+                Self::Suspicious => "Suspicious",
             }
         )
     }
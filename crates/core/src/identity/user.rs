@@ -5,7 +5,7 @@ use serde::{Deserialize, Serialize};
 use crate::{
     error::CoreResult,
     identity::{
-        Identity,
+        ContactId, DeviceCertificate, Endorsement, Identity, Revocation, Succession,
         crypto::{generate_good_key_ed25519, generate_good_key_x25519},
     },
 };
@@ -53,6 +53,54 @@ impl UserIdentity {
         let mut id_priv = self.identity_private_key().clone();
         self.identity.set_noise_key(noise_pub, &mut id_priv)
     }
+
+    /// Creates a new identity with the same username that replaces this one, and the
+    /// [`Succession`] that tells the contacts about it.
+    #[cold]
+    pub fn create_successor(&self) -> CoreResult<(Self, Succession)> {
+        let mut successor = Self::create(self.username())?;
+        let mut successor_key = successor.identity_key.clone();
+        let succession = Succession::create(
+            &mut self.identity_key.clone(),
+            successor.identity.clone(),
+            &mut successor_key,
+        )?;
+        successor.identity_key = successor_key;
+        Ok((successor, succession))
+    }
+
+    /// Creates a [`Revocation`] of this identity.
+    #[cold]
+    pub fn revoke(&self, reason: &str) -> CoreResult<Revocation> {
+        Revocation::create(&mut self.identity_key.clone(), reason)
+    }
+
+    /// Creates an [`Endorsement`] of the contact `endorsed` by this identity, or withdraws it if
+    /// `revoked` is set.
+    pub fn endorse(&self, endorsed: ContactId, revoked: bool) -> CoreResult<Endorsement> {
+        Endorsement::create(&mut self.identity_key.clone(), endorsed, revoked)
+    }
+
+    /// Certifies a new device for this identity and returns the [`UserIdentity`] to use on it.
+    ///
+    /// The device gets its own noise key, the identity of this device is updated too.
+    #[cold]
+    pub fn new_device(&mut self, name: &str) -> CoreResult<Self> {
+        let noise_key = generate_good_key_x25519();
+        let mut identity_key = self.identity_key.clone();
+        let certificate = DeviceCertificate::issue(
+            name,
+            x25519_dalek::PublicKey::from(&noise_key),
+            &mut identity_key,
+        )?;
+        self.identity.add_device(certificate, &mut identity_key)?;
+
+        Ok(Self {
+            identity: self.identity.clone(),
+            identity_key,
+            noise_key,
+        })
+    }
 }
 
 impl std::fmt::Debug for UserIdentity {
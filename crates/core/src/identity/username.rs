@@ -0,0 +1,150 @@
+//! Rules for usernames and a skeleton to detect names that look alike.
+//!
+//! Usernames are shown to other users, who decide whom they trust based on them. So a username
+//! must be in Unicode normal form C and must not contain control, bidirectional or invisible
+//! characters, which could make a name look like something it is not.
+//!
+//! The [`username_skeleton`] follows the idea of the skeletons of Unicode Technical Standard #39
+//! with a small table of the most common confusables, like cyrillic `а` for latin `a`. It is a
+//! hint for the user, not a guarantee that different skeletons look different.
+
+use unicode_normalization::{UnicodeNormalization, char::is_combining_mark};
+
+use crate::{
+    error::{CoreError, CoreResult},
+    identity::Identity,
+};
+
+/// Maximum length of a username in characters
+pub const MAX_USERNAME_CHARS: usize = 40;
+/// Maximum number of combining marks on one character
+const MAX_COMBINING_MARKS: usize = 3;
+
+impl Identity {
+    /// Brings a username into the form that [`validate_username`](Self::validate_username)
+    /// expects: normal form C, without whitespace at the ends and with single spaces between
+    /// words.
+    pub fn normalize_username(username: &str) -> String {
+        username
+            .nfc()
+            .collect::<String>()
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Checks a username against the rules of the specification.
+    ///
+    /// The username must already be normalized with
+    /// [`normalize_username`](Self::normalize_username).
+    pub fn validate_username(username: &str) -> CoreResult<()> {
+        let invalid = |reason| Err(CoreError::InvalidUsername(reason));
+        let chars_len = username.chars().count();
+        if !(1..=MAX_USERNAME_CHARS).contains(&chars_len) {
+            return invalid("it must have between 1 and 40 characters");
+        }
+        if Self::normalize_username(username) != username {
+            return invalid("it is not normalized");
+        }
+        let mut marks = 0;
+        for c in username.chars() {
+            if c.is_control() {
+                return invalid("it contains a control character");
+            }
+            if is_invisible(c) {
+                return invalid("it contains an invisible or bidirectional formatting character");
+            }
+            if c.is_whitespace() && c != ' ' {
+                return invalid("it contains whitespace other than spaces");
+            }
+            if is_combining_mark(c) {
+                marks += 1;
+                if marks > MAX_COMBINING_MARKS {
+                    return invalid("it stacks too many combining marks");
+                }
+            } else {
+                marks = 0;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Format characters that are not visible themselves or change the direction of the text
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{00AD}' // soft hyphen
+        | '\u{034F}' // combining grapheme joiner
+        | '\u{061C}' // arabic letter mark
+        | '\u{115F}' | '\u{1160}' | '\u{3164}' | '\u{FFA0}' // hangul fillers
+        | '\u{17B4}' | '\u{17B5}' // khmer inherent vowels
+        | '\u{180B}'..='\u{180F}' // mongolian variation selectors and vowel separator
+        | '\u{200B}'..='\u{200F}' // zero width characters and direction marks
+        | '\u{202A}'..='\u{202E}' // bidirectional embeddings and overrides
+        | '\u{2060}'..='\u{206F}' // word joiner, invisible operators and bidirectional isolates
+        | '\u{FE00}'..='\u{FE0F}' // variation selectors
+        | '\u{FEFF}' // zero width no-break space
+        | '\u{FFF9}'..='\u{FFFB}' // interlinear annotations
+        | '\u{1D173}'..='\u{1D17A}' // musical formatting
+        | '\u{E0000}'..='\u{E0FFF}' // tags and variation selectors supplement
+    )
+}
+
+/// Reduces a username to a form in which names that look alike are equal.
+///
+/// Compatibility characters, like fullwidth or mathematical letters, are decomposed, combining
+/// marks, separators and case are removed, and common confusables are replaced by the latin
+/// letter they look like.
+pub fn username_skeleton(username: &str) -> String {
+    let skeleton: String = username
+        .nfkd()
+        .filter(|c| {
+            !is_combining_mark(*c)
+                && !is_invisible(*c)
+                && !c.is_whitespace()
+                && !matches!(c, '-' | '_' | '.' | '·')
+        })
+        // NOTE: before lowercasing, since uppercase I looks like lowercase l
+        .map(|c| {
+            if matches!(c, 'I' | '|' | 'Ӏ' | 'Ι') {
+                'l'
+            } else {
+                c
+            }
+        })
+        .flat_map(char::to_lowercase)
+        .map(prototype)
+        .collect();
+    skeleton.replace("rn", "m").replace("vv", "w")
+}
+
+/// The latin letter a lowercase character is most often confused with
+fn prototype(c: char) -> char {
+    match c {
+        'а' | 'α' | 'ɑ' => 'a',
+        'в' | 'β' | 'ь' => 'b',
+        'с' | 'ϲ' => 'c',
+        'ԁ' => 'd',
+        'е' | 'ε' => 'e',
+        'һ' => 'h',
+        'і' | 'ι' | 'ı' => 'i',
+        'ј' | 'ϳ' => 'j',
+        'к' | 'κ' => 'k',
+        '1' | 'ӏ' => 'l',
+        'м' => 'm',
+        'п' | 'η' => 'n',
+        '0' | 'о' | 'ο' | 'σ' | 'ө' => 'o',
+        'р' | 'ρ' => 'p',
+        'ԛ' => 'q',
+        'ѕ' => 's',
+        'т' | 'τ' => 't',
+        'υ' | 'ս' => 'u',
+        'ν' | 'ѵ' => 'v',
+        'ԝ' | 'ω' => 'w',
+        'х' | 'χ' => 'x',
+        'у' | 'γ' | 'ү' => 'y',
+        'ᴢ' => 'z',
+        other => other,
+    }
+}
@@ -1,9 +1,6 @@
-use std::ops::Deref;
+use std::io::IoSlice;
 
-use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt},
-    net,
-};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 
 use crate::error::{CoreError, CoreResult};
 
@@ -13,102 +10,129 @@ pub use version_header::*;
 pub const MAX_FRAME_SIZE: usize = 65535;
 pub const MAX_FRAME_PAYLOAD_SIZE: usize = MAX_FRAME_SIZE - VersionHeader::BYTE_LENGTH;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Length of everything in front of the payload: the frame length and the version header
+const FRAME_PREFIX_LEN: usize = 2 + VersionHeader::BYTE_LENGTH;
+
+/// A received frame, borrowed from the buffer of the [`FrameReader`]
+#[derive(Debug, PartialEq, Eq)]
 #[must_use]
-pub struct Frame {
+pub struct Frame<'a> {
     version: VersionHeader,
-    data: Vec<u8>,
+    data: &'a [u8],
 }
 
-impl Frame {
-    #[inline]
-    fn from_raw(full_frame_raw: &[u8]) -> CoreResult<Self> {
-        if full_frame_raw.len() > MAX_FRAME_SIZE {
-            return Err(CoreError::FrameTooLarge(full_frame_raw.len()));
-        }
-        Ok(Self {
-            version: check_version(
-                full_frame_raw[0..VersionHeader::BYTE_LENGTH]
-                    .try_into()
-                    .unwrap(),
-            )?,
-            data: full_frame_raw[VersionHeader::BYTE_LENGTH + 1..].to_vec(),
-        })
-    }
+/// Reads frames from a stream into one buffer that is reused for every frame.
+#[derive(Debug)]
+pub(crate) struct FrameReader {
+    buf: Box<[u8; MAX_FRAME_SIZE]>,
+}
 
-    #[inline]
-    pub fn from_payload(payload: &[u8]) -> CoreResult<Self> {
-        check_payload_length(payload.len())?;
-        Ok(Self {
-            version: PROTOCOL_DIRECT_VERSION_HEADER,
-            data: payload.to_vec(),
-        })
-    }
+/// Writes frames to a stream, the payload is placed directly into its buffer.
+#[derive(Debug)]
+pub(crate) struct FrameWriter {
+    buf: Box<[u8; MAX_FRAME_PAYLOAD_SIZE]>,
+}
 
-    pub async fn send(self, stream: &mut net::TcpStream) -> CoreResult<()> {
-        log::debug!("Sending Frame");
-        log::trace!("Sending Length");
-        stream.write_u16(self.len()).await?;
+impl Frame<'_> {
+    /// Length of the frame as it is announced on the wire
+    #[inline(always)]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn len(&self) -> u16 {
+        (VersionHeader::BYTE_LENGTH + self.data.len()) as u16 // cannot receive a frame that is too big
+    }
 
-        log::trace!("Sending version");
-        stream.write_all(self.version.as_bytes()).await?;
-        stream.flush().await?;
-        log::trace!("Sending Data");
-        stream.write_all(&self.data).await?;
-        stream.flush().await?;
+    #[inline(always)]
+    pub fn version(&self) -> &VersionHeader {
+        &self.version
+    }
 
-        log::trace!("Sending Finished");
-        Ok(())
+    #[inline(always)]
+    pub(super) fn data(&self) -> &[u8] {
+        self.data
     }
+}
 
-    pub async fn recv(stream: &mut net::TcpStream) -> CoreResult<Self> {
-        log::debug!("Receiving Frame");
-        log::trace!("Reading Length");
-        let len = stream.read_u16().await? as usize;
-        if len > MAX_FRAME_SIZE {
-            return Err(CoreError::FrameTooLarge(len));
+impl FrameReader {
+    pub(crate) fn new() -> Self {
+        Self {
+            buf: vec![0; MAX_FRAME_SIZE]
+                .into_boxed_slice()
+                .try_into()
+                .expect("buffer has the size of a frame"),
         }
-        log::trace!("Length: {len}");
-
-        log::trace!("Reading version");
-        let mut buf = [0; VersionHeader::BYTE_LENGTH];
-        stream.read_exact(&mut buf).await?;
-        let version = check_version(&buf)?;
-
-        log::trace!("Reading Data");
-        let mut buf = vec![0; len - VersionHeader::BYTE_LENGTH];
-        buf.reserve_exact(len);
-        stream.read_exact(&mut buf).await?;
-        log::trace!("Data: {buf:x?}");
-
-        check_payload_length(buf.len())?;
-
-        Ok(Self { version, data: buf })
     }
 
-    #[inline(always)]
-    #[allow(clippy::cast_possible_truncation)]
-    pub fn len(&self) -> u16 {
-        VersionHeader::BYTE_LENGTH as u16 + self.data.len() as u16 // cannot construct a frame that is too big
+    /// Reads the next [`Frame`], which stays valid until the next call.
+    pub(crate) async fn recv(
+        &mut self,
+        stream: &mut (impl AsyncRead + Unpin),
+    ) -> CoreResult<Frame<'_>> {
+        let len = stream.read_u16().await? as usize;
+        if len < VersionHeader::BYTE_LENGTH {
+            return Err(CoreError::FrameTooShort(len));
+        }
+        log::trace!("Receiving frame of {len} bytes");
+
+        let buf = &mut self.buf[..len];
+        stream.read_exact(buf).await?;
+        let (version, data) = buf.split_at(VersionHeader::BYTE_LENGTH);
+        let version = check_version(
+            version
+                .try_into()
+                .expect("split at the length of the version header"),
+        )?;
+
+        Ok(Frame { version, data })
     }
+}
 
-    #[inline(always)]
-    pub(super) fn data(&self) -> &[u8] {
-        &self.data
+impl FrameWriter {
+    pub(crate) fn new() -> Self {
+        Self {
+            buf: vec![0; MAX_FRAME_PAYLOAD_SIZE]
+                .into_boxed_slice()
+                .try_into()
+                .expect("buffer has the size of a frame payload"),
+        }
     }
 
-    #[inline(always)]
-    pub fn version(&self) -> &VersionHeader {
-        &self.version
+    /// Buffer for the payload of the next frame, pass the length of the payload to
+    /// [`send`](Self::send).
+    #[inline]
+    pub(crate) fn payload_buf(&mut self) -> &mut [u8] {
+        &mut self.buf[..]
     }
-}
 
-impl Deref for Frame {
-    type Target = Vec<u8>;
+    /// Sends the first `len` bytes of the [payload buffer](Self::payload_buf) as a frame.
+    ///
+    /// The frame is written with one vectored write, unless the stream does not take all of it
+    /// at once. Returns the length of the frame as it is announced on the wire.
+    pub(crate) async fn send(
+        &mut self,
+        stream: &mut (impl AsyncWrite + Unpin),
+        len: usize,
+    ) -> CoreResult<u16> {
+        check_payload_length(len)?;
+        #[allow(clippy::cast_possible_truncation)] // checked above
+        let frame_len = (VersionHeader::BYTE_LENGTH + len) as u16;
+        log::trace!("Sending frame of {frame_len} bytes");
+
+        let mut prefix = [0; FRAME_PREFIX_LEN];
+        prefix[..2].copy_from_slice(&frame_len.to_be_bytes());
+        prefix[2..].copy_from_slice(PROTOCOL_DIRECT_VERSION_HEADER.as_bytes());
+
+        let mut slices = [IoSlice::new(&prefix), IoSlice::new(&self.buf[..len])];
+        let mut slices = &mut slices[..];
+        while !slices.is_empty() {
+            let written = stream.write_vectored(slices).await?;
+            if written == 0 {
+                return Err(std::io::Error::from(std::io::ErrorKind::WriteZero).into());
+            }
+            IoSlice::advance_slices(&mut slices, written);
+        }
+        stream.flush().await?;
 
-    #[inline(always)]
-    fn deref(&self) -> &Self::Target {
-        &self.data
+        Ok(frame_len)
     }
 }
 
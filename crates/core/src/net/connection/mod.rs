@@ -1,16 +1,31 @@
-use std::sync::LazyLock;
+use std::sync::{Arc, LazyLock};
 
-use snow::{TransportState, params::NoiseParams};
-use tokio::{io::AsyncWriteExt, net};
+use snow::{StatelessTransportState, params::NoiseParams};
+use tokio::{
+    io::{AsyncWriteExt, ReadHalf, WriteHalf},
+    time::Instant,
+};
 
 use crate::{
     current_function,
     error::{CoreError, CoreResult},
     identity::{Identity, UserIdentity},
+    net::{
+        envelope::Envelope,
+        transport::{BoxedStream, Transport},
+    },
 };
 
 mod frame;
+pub use frame::VersionHeader;
 use frame::*;
+pub(crate) mod multiplex;
+use multiplex::Reassembler;
+pub mod stats;
+use stats::{ConnectionPath, ConnectionStats};
+
+/// Hash of the noise handshake, both peers of a connection have the same one
+pub type HandshakeHash = [u8; 32];
 
 pub static NOISE_PARAMS: LazyLock<NoiseParams> = LazyLock::new(|| {
     "Noise_XX_25519_ChaChaPoly_BLAKE2s"
@@ -35,22 +50,58 @@ macro_rules! delegate {
 #[derive(Debug)]
 #[must_use]
 pub struct P2PConnection {
-    stream: net::TcpStream,
+    stream: BoxedStream,
+    frames: FrameCodec,
     peer_identity: Identity,
-    transport: snow::TransportState,
+    transport: StatelessTransportState,
+    handshake_hash: HandshakeHash,
+    stats: Arc<ConnectionStats>,
+}
+
+/// Buffers for framing a connection, allocated once for the handshake and kept for its lifetime
+#[derive(Debug)]
+struct FrameCodec {
+    reader: FrameReader,
+    writer: FrameWriter,
+}
+
+/// Receiving half of an established [`Connection`], see [`Connection::into_split`]
+#[derive(Debug)]
+pub(crate) struct ConnectionReader {
+    stream: ReadHalf<BoxedStream>,
+    frames: FrameReader,
+    transport: Arc<StatelessTransportState>,
+    nonce: u64,
+    /// Decrypted payload of the last frame
+    buf: Box<[u8]>,
+    reassembler: Reassembler,
+    stats: Arc<ConnectionStats>,
+}
+
+/// Sending half of an established [`Connection`], see [`Connection::into_split`]
+#[derive(Debug)]
+pub(crate) struct ConnectionWriter {
+    stream: WriteHalf<BoxedStream>,
+    frames: FrameWriter,
+    transport: Arc<StatelessTransportState>,
+    nonce: u64,
+    stats: Arc<ConnectionStats>,
 }
 
 impl Connection {
     pub(crate) async fn connect_to(
+        transport: &dyn Transport,
         remote: std::net::SocketAddr,
         user: &UserIdentity,
     ) -> CoreResult<Self> {
         log::trace!("{}", current_function!());
-        Ok(Self::P2P(P2PConnection::connect_to(remote, user).await?))
+        Ok(Self::P2P(
+            P2PConnection::connect_to(transport, remote, user).await?,
+        ))
     }
 
     pub(crate) async fn connect_from(
-        stream: net::TcpStream,
+        stream: BoxedStream,
         remote: std::net::SocketAddr,
         user: &UserIdentity,
     ) -> CoreResult<Self> {
@@ -66,86 +117,154 @@ impl Connection {
     pub(crate) async fn peer_identity(&self) -> &Identity {
         delegate!(self, peer_identity().await)
     }
-}
-
-impl P2PConnection {
-    async fn connect_to(remote: std::net::SocketAddr, user: &UserIdentity) -> CoreResult<Self> {
-        log::trace!("{}", current_function!());
-        let mut tcp_stream = net::TcpStream::connect(remote).await?;
-        log::debug!("Tcp Connection Established");
-        let (peer_identity, transport) = Self::dead_switch(&mut tcp_stream, async |tcp_stream| {
-            let mut noise = Self::noise_initiator(user)?;
-            let mut buf = [0u8; MAX_FRAME_SIZE];
-            let mut len;
 
-            log::debug!("Beginning noise handshake as initiator");
+    /// Noise key of the peer, one of the [devices](Identity::is_device_key) of its identity
+    pub(crate) fn peer_noise_key(&self) -> x25519_dalek::PublicKey {
+        delegate!(self, peer_noise_key())
+    }
 
-            log::debug!("Sending Noise: `XX: --> e`");
-            len = noise.write_message(&[], &mut buf)?;
-            Frame::from_payload(&buf[..len])?.send(tcp_stream).await?;
+    /// Hash of the noise handshake, a man in the middle can not make it equal on both sides
+    pub(crate) fn handshake_hash(&self) -> HandshakeHash {
+        delegate!(self, handshake_hash)
+    }
 
-            log::debug!("Receiving: `XX: <-- e, ee, s, es`");
-            let frame = Frame::recv(tcp_stream).await?;
-            _ = noise.read_message(frame.data(), &mut buf)?;
+    /// Counters of this [`Connection`], shared with its halves
+    pub(crate) fn stats(&self) -> Arc<ConnectionStats> {
+        delegate!(self, stats.clone())
+    }
 
-            log::debug!("Sending Noise: `XX: --> s, se`");
-            len = noise.write_message(&[], &mut buf)?;
-            Frame::from_payload(&buf[..len])?.send(tcp_stream).await?;
+    /// Splits the [`Connection`] into halves that can send and receive independently of each
+    /// other.
+    pub(crate) fn into_split(self) -> (ConnectionReader, ConnectionWriter) {
+        delegate!(self, into_split())
+    }
+}
 
-            Self::post_handshake(&mut buf, tcp_stream, user, noise, remote).await
-        })
-        .await?;
+impl P2PConnection {
+    async fn connect_to(
+        transport: &dyn Transport,
+        remote: std::net::SocketAddr,
+        user: &UserIdentity,
+    ) -> CoreResult<Self> {
+        log::trace!("{}", current_function!());
+        let mut stream = transport.connect(remote).await?;
+        log::debug!("Tcp Connection Established");
+        let start = Instant::now();
+        let mut frames = FrameCodec::new();
+        let result = Self::handshake_initiator(&mut stream, &mut frames, remote, user).await;
+        let (peer_identity, transport, handshake_hash, version) =
+            Self::dead_switch(&mut stream, result).await?;
 
         Ok(Self {
-            stream: tcp_stream,
+            stream,
+            frames,
             peer_identity,
             transport,
+            handshake_hash,
+            stats: ConnectionStats::new(ConnectionPath::Direct, version, start.elapsed()).into(),
         })
     }
 
     async fn connect_from(
-        mut tcp_stream: net::TcpStream,
+        mut stream: BoxedStream,
         remote: std::net::SocketAddr,
         user: &UserIdentity,
     ) -> CoreResult<Self> {
         log::trace!("{}", current_function!());
-        let (peer_identity, transport) = Self::dead_switch(&mut tcp_stream, async |tcp_stream| {
-            let mut noise = Self::noise_responder(user)?;
-            let mut buf = [0u8; MAX_FRAME_SIZE];
-            let mut frame;
-
-            log::debug!("Beginning noise handshake as responder");
-
-            log::debug!("Receiving: `XX: --> e`");
-            frame = Frame::recv(tcp_stream).await?;
-            _ = noise.read_message(frame.data(), &mut buf)?;
-
-            log::debug!("Sending Noise: `XX: <-- e, ee, s, es`");
-            let len = noise.write_message(&[], &mut buf)?;
-            Frame::from_payload(&buf[..len])?.send(tcp_stream).await?;
-
-            log::debug!("Receiving: `XX: --> s, se`");
-            frame = Frame::recv(tcp_stream).await?;
-            _ = noise.read_message(frame.data(), &mut buf)?;
-
-            Self::post_handshake(&mut buf, tcp_stream, user, noise, remote).await
-        })
-        .await?;
+        let start = Instant::now();
+        let mut frames = FrameCodec::new();
+        let result = Self::handshake_responder(&mut stream, &mut frames, remote, user).await;
+        let (peer_identity, transport, handshake_hash, version) =
+            Self::dead_switch(&mut stream, result).await?;
 
         Ok(Self {
-            stream: tcp_stream,
+            stream,
+            frames,
             peer_identity,
             transport,
+            handshake_hash,
+            stats: ConnectionStats::new(ConnectionPath::Direct, version, start.elapsed()).into(),
         })
     }
 
+    async fn handshake_initiator(
+        stream: &mut BoxedStream,
+        frames: &mut FrameCodec,
+        remote: std::net::SocketAddr,
+        user: &UserIdentity,
+    ) -> CoreResult<(
+        Identity,
+        StatelessTransportState,
+        HandshakeHash,
+        VersionHeader,
+    )> {
+        let mut noise = Self::noise_initiator(user)?;
+        let mut len;
+
+        log::debug!("Beginning noise handshake as initiator");
+
+        log::debug!("Sending Noise: `XX: --> e`");
+        len = noise.write_message(&[], frames.writer.payload_buf())?;
+        frames.writer.send(stream, len).await?;
+
+        log::debug!("Receiving: `XX: <-- e, ee, s, es`");
+        let frame = frames.reader.recv(stream).await?;
+        _ = noise.read_message(frame.data(), frames.writer.payload_buf())?;
+
+        log::debug!("Sending Noise: `XX: --> s, se`");
+        len = noise.write_message(&[], frames.writer.payload_buf())?;
+        frames.writer.send(stream, len).await?;
+
+        Self::post_handshake(stream, frames, user, noise, remote).await
+    }
+
+    async fn handshake_responder(
+        stream: &mut BoxedStream,
+        frames: &mut FrameCodec,
+        remote: std::net::SocketAddr,
+        user: &UserIdentity,
+    ) -> CoreResult<(
+        Identity,
+        StatelessTransportState,
+        HandshakeHash,
+        VersionHeader,
+    )> {
+        let mut noise = Self::noise_responder(user)?;
+        let mut frame;
+
+        log::debug!("Beginning noise handshake as responder");
+
+        log::debug!("Receiving: `XX: --> e`");
+        frame = frames.reader.recv(stream).await?;
+        _ = noise.read_message(frame.data(), frames.writer.payload_buf())?;
+
+        log::debug!("Sending Noise: `XX: <-- e, ee, s, es`");
+        let len = noise.write_message(&[], frames.writer.payload_buf())?;
+        frames.writer.send(stream, len).await?;
+
+        log::debug!("Receiving: `XX: --> s, se`");
+        frame = frames.reader.recv(stream).await?;
+        _ = noise.read_message(frame.data(), frames.writer.payload_buf())?;
+
+        Self::post_handshake(stream, frames, user, noise, remote).await
+    }
+
+    /// Finishes the handshake and exchanges the identities.
+    ///
+    /// The payload buffer of the frame writer doubles as scratch space for decrypting, it is not
+    /// in use while receiving.
     async fn post_handshake(
-        buf: &mut [u8; MAX_FRAME_SIZE],
-        stream: &mut net::TcpStream,
+        stream: &mut BoxedStream,
+        frames: &mut FrameCodec,
         user: &UserIdentity,
         noise: snow::HandshakeState,
         remote: std::net::SocketAddr,
-    ) -> CoreResult<(Identity, TransportState)> {
+    ) -> CoreResult<(
+        Identity,
+        StatelessTransportState,
+        HandshakeHash,
+        VersionHeader,
+    )> {
         // SREMP uses the identity keys as the noise static key.
         let remote_static_key = noise
             .get_remote_static()
@@ -158,30 +277,44 @@ impl P2PConnection {
 
         let peer_public_key = x25519_dalek::PublicKey::from(*peer_key_bytes);
 
-        let mut transport = noise.into_transport_mode()?;
+        let handshake_hash: HandshakeHash = noise
+            .get_handshake_hash()
+            .try_into()
+            .expect("the handshake hash of BLAKE2s has 32 bytes");
+        let transport = noise.into_stateless_transport_mode()?;
         log::debug!("Finished noise handshake");
 
         // NOTE: both send before receiving, then listen for the incoming identity response
         // That way, the identity exchange is simultaneous and we dont need to program an order of
         // who sends first
 
+        // NOTE: the identity exchange uses the first nonce in each direction, the halves of the
+        // connection continue after it
         log::debug!("Sending identity to peer");
-        let mut len = transport.write_message(&rmp_serde::to_vec(&user.identity)?, buf)?;
-        Frame::from_payload(&buf[..len])?.send(stream).await?;
+        let mut len = transport.write_message(
+            0,
+            &rmp_serde::to_vec(&user.identity)?,
+            frames.writer.payload_buf(),
+        )?;
+        frames.writer.send(stream, len).await?;
 
         log::debug!("Receiving identity from peer");
-        let frame = Frame::recv(stream).await?;
-        len = transport.read_message(frame.data(), buf)?;
+        let frame = frames.reader.recv(stream).await?;
+        let buf = frames.writer.payload_buf();
+        len = transport.read_message(0, frame.data(), buf)?;
+        let peer_version = frame.version().clone();
         let peer_identity: Identity = rmp_serde::from_slice(&buf[..len])?;
-        log::debug!("Received (unverified) Identity: {peer_identity:#?}");
+        log::debug!(
+            "Received (unverified) identity {} of {:?}",
+            peer_identity.id(),
+            peer_identity.username()
+        );
 
         peer_identity.verify()?;
 
-        // FIXME: username might be a super long string, we should add some validator for the
-        // username.
-
-        if peer_identity.noise_key() != peer_public_key {
-            log::error!("identity key does not match noise static public key:");
+        // NOTE: the peer may be any of the devices of the identity
+        if !peer_identity.is_device_key(&peer_public_key) {
+            log::error!("noise static public key is not one of the devices of the identity");
             return Err(CoreError::PeerKeyIsInvalid {
                 remote,
                 source: ed25519_dalek::SignatureError::new(),
@@ -190,7 +323,7 @@ impl P2PConnection {
 
         log::debug!("Noise Handshake and identity exchange with peer {remote} successful");
 
-        Ok((peer_identity, transport))
+        Ok((peer_identity, transport, handshake_hash, peer_version))
     }
 
     async fn disconnect(mut self) -> CoreResult<()> {
@@ -202,22 +335,47 @@ impl P2PConnection {
         &self.peer_identity
     }
 
-    /// Closes the [`net::TcpStream`] on error
-    async fn dead_switch<T, F>(stream: &mut net::TcpStream, f: F) -> CoreResult<T>
-    where
-        F: AsyncFnOnce(&mut net::TcpStream) -> CoreResult<T>,
-    {
-        match f(stream).await {
-            Ok(t) => Ok(t),
-            Err(e) => {
-                log::warn!("Error while handling a Connection, cutting the TcpStream: {e}");
-                stream
-                    .shutdown()
-                    .await
-                    .expect("could not shutdown the stream on error in dead switch");
-                Err(e)
+    fn peer_noise_key(&self) -> x25519_dalek::PublicKey {
+        let key: [u8; 32] = self
+            .transport
+            .get_remote_static()
+            .and_then(|key| key.try_into().ok())
+            .expect("the handshake has checked the noise key of the peer");
+        key.into()
+    }
+
+    fn into_split(self) -> (ConnectionReader, ConnectionWriter) {
+        let (read_half, write_half) = tokio::io::split(self.stream);
+        let transport = Arc::new(self.transport);
+        (
+            ConnectionReader {
+                stream: read_half,
+                frames: self.frames.reader,
+                transport: transport.clone(),
+                nonce: 1,
+                buf: vec![0; MAX_FRAME_PAYLOAD_SIZE].into_boxed_slice(),
+                reassembler: Reassembler::default(),
+                stats: self.stats.clone(),
+            },
+            ConnectionWriter {
+                stream: write_half,
+                frames: self.frames.writer,
+                transport,
+                nonce: 1,
+                stats: self.stats,
+            },
+        )
+    }
+
+    /// Closes the stream if the handshake has failed
+    async fn dead_switch<T>(stream: &mut BoxedStream, result: CoreResult<T>) -> CoreResult<T> {
+        if let Err(e) = &result {
+            log::warn!("Error while handling a Connection, cutting the TcpStream: {e}");
+            if let Err(e) = stream.shutdown().await {
+                log::debug!("Could not shut down the stream in the dead switch: {e}");
             }
         }
+        result
     }
 
     fn noise_builder<'a>(user: &'a UserIdentity) -> CoreResult<snow::Builder<'a>> {
@@ -233,3 +391,55 @@ impl P2PConnection {
         Ok(Self::noise_builder(user)?.build_responder()?)
     }
 }
+
+impl FrameCodec {
+    fn new() -> Self {
+        Self {
+            reader: FrameReader::new(),
+            writer: FrameWriter::new(),
+        }
+    }
+}
+
+impl ConnectionReader {
+    #[inline]
+    pub(crate) fn stats(&self) -> &ConnectionStats {
+        &self.stats
+    }
+
+    /// Waits for the next complete [`Envelope`] from the peer, on any stream.
+    pub(crate) async fn recv(&mut self) -> CoreResult<Envelope> {
+        loop {
+            let frame = self.frames.recv(&mut self.stream).await?;
+            self.stats.record_in(frame.len() as usize);
+            let len = self
+                .transport
+                .read_message(self.nonce, frame.data(), &mut self.buf)?;
+            self.nonce += 1;
+            if let Some(envelope) = self.reassembler.push(&self.buf[..len])? {
+                return Ok(envelope);
+            }
+        }
+    }
+}
+
+impl ConnectionWriter {
+    /// Encrypts a chunk, as written by [`OutgoingStreams::next_chunk`], and sends it to the
+    /// peer.
+    ///
+    /// [`OutgoingStreams::next_chunk`]: multiplex::OutgoingStreams::next_chunk
+    pub(crate) async fn send(&mut self, chunk: &[u8]) -> CoreResult<()> {
+        let len = self
+            .transport
+            .write_message(self.nonce, chunk, self.frames.payload_buf())?;
+        self.nonce += 1;
+        let frame_len = self.frames.send(&mut self.stream, len).await?;
+        self.stats.record_out(frame_len as usize);
+        Ok(())
+    }
+
+    pub(crate) async fn shutdown(mut self) -> CoreResult<()> {
+        self.stream.shutdown().await?;
+        Ok(())
+    }
+}
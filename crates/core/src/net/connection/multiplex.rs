@@ -0,0 +1,448 @@
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use async_channel::{Receiver, Sender, TrySendError};
+use bytes::Bytes;
+
+use crate::{
+    error::{CoreError, CoreResult},
+    net::{
+        envelope::Envelope,
+        stream::{CHUNK_SIZE, MAX_ENVELOPE_SIZE, StreamId},
+    },
+};
+
+/// Length of the header in front of each chunk: the stream id and the flags
+pub(crate) const CHUNK_HEADER_LEN: usize = 2;
+/// Flag that marks the last chunk of an envelope
+const FLAG_FIN: u8 = 0b0000_0001;
+/// Largest encoding buffer that is kept for the next envelope of a stream, larger ones are freed
+const MAX_KEPT_WIRE_SIZE: usize = 64 * CHUNK_SIZE;
+/// Most bytes of unfinished envelopes kept over all connections: room for one envelope of
+/// [`MAX_ENVELOPE_SIZE`] and whatever the other streams and connections send meanwhile
+const MAX_BUFFERED_SIZE: usize = 2 * MAX_ENVELOPE_SIZE;
+
+/// Memory for unfinished envelopes, shared by the [`Reassembler`]s of all connections
+static RECEIVE_BUDGET: BufferBudget = BufferBudget::new(MAX_BUFFERED_SIZE);
+
+/// Sending side of the stream queues of a connection.
+#[derive(Debug)]
+pub(crate) struct StreamSenders {
+    senders: [Sender<Arc<Envelope>>; StreamId::ALL.len()],
+    /// The first envelope given back since the queue of a stream was full, until it is retried
+    refused: [Mutex<Option<Arc<Envelope>>>; StreamId::ALL.len()],
+}
+
+/// Receiving side of the stream queues of a connection, which decides which stream may send the
+/// next chunk.
+#[derive(Debug)]
+pub(crate) struct OutgoingStreams {
+    queues: [Receiver<Arc<Envelope>>; StreamId::ALL.len()],
+    current: [Option<PendingEnvelope>; StreamId::ALL.len()],
+    /// Encoded form of the current envelope of each stream, reused for the next one
+    wire: [Vec<u8>; StreamId::ALL.len()],
+    credits: [u32; StreamId::ALL.len()],
+}
+
+#[derive(Debug)]
+struct PendingEnvelope {
+    envelope: Arc<Envelope>,
+    offset: usize,
+}
+
+/// Describes the chunk of an envelope that [`OutgoingStreams::next_chunk`] has written
+#[derive(Debug)]
+pub(crate) struct Chunk {
+    pub(crate) stream: StreamId,
+    /// Set on the last chunk of an envelope
+    pub(crate) finished: Option<Arc<Envelope>>,
+}
+
+/// Reassembles the chunks received for each stream into [`Envelope`]s.
+#[derive(Debug)]
+pub(crate) struct Reassembler {
+    partial: [Vec<u8>; StreamId::ALL.len()],
+    max_envelope_size: usize,
+    budget: &'static BufferBudget,
+}
+
+/// Limits the bytes buffered by several [`Reassembler`]s together.
+#[derive(Debug)]
+pub(crate) struct BufferBudget {
+    limit: usize,
+    used: AtomicUsize,
+}
+
+/// Creates the queues for the streams of a new connection.
+pub(crate) fn stream_queues() -> (StreamSenders, OutgoingStreams) {
+    let channels = StreamId::ALL.map(|s| async_channel::bounded(s.queue_capacity()));
+    let senders = channels.clone().map(|(tx, _)| tx);
+    let queues = channels.map(|(_, rx)| rx);
+    (
+        StreamSenders {
+            senders,
+            refused: Default::default(),
+        },
+        OutgoingStreams {
+            queues,
+            current: Default::default(),
+            wire: Default::default(),
+            credits: StreamId::ALL.map(StreamId::weight),
+        },
+    )
+}
+
+impl StreamSenders {
+    /// Queues the [`Envelope`] on its stream.
+    ///
+    /// Does not wait if the queue of that stream is full, instead the envelope is given back and
+    /// the caller has to retry it later.
+    ///
+    /// Once an envelope was given back, the stream refuses every other envelope until that one
+    /// is retried and fits. Envelopes that are given back can so be retried in the order they were
+    /// refused in, without being overtaken by envelopes that were sent after them.
+    pub(crate) fn try_send(
+        &self,
+        envelope: Arc<Envelope>,
+    ) -> Result<(), TrySendError<Arc<Envelope>>> {
+        let i = envelope.stream().index();
+        let mut refused = self.refused[i].lock().expect("lock was poisoned");
+        if refused
+            .as_ref()
+            .is_some_and(|first| !Arc::ptr_eq(first, &envelope))
+        {
+            return Err(TrySendError::Full(envelope));
+        }
+        match self.senders[i].try_send(envelope) {
+            Ok(()) => {
+                *refused = None;
+                Ok(())
+            }
+            Err(TrySendError::Full(envelope)) => {
+                refused.get_or_insert_with(|| envelope.clone());
+                Err(TrySendError::Full(envelope))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Like [`try_send`](Self::try_send), for envelopes that are dropped instead of retried when
+    /// the queue is full, like pings.
+    pub(crate) fn try_send_or_drop(
+        &self,
+        envelope: Arc<Envelope>,
+    ) -> Result<(), TrySendError<Arc<Envelope>>> {
+        let i = envelope.stream().index();
+        if self.refused[i].lock().expect("lock was poisoned").is_some() {
+            return Err(TrySendError::Full(envelope));
+        }
+        self.senders[i].try_send(envelope)
+    }
+
+    /// Whether the queues were closed, or the writer has stopped taking from them
+    pub(crate) fn is_closed(&self) -> bool {
+        self.senders.iter().any(Sender::is_closed)
+    }
+
+    /// Closes all queues, the writer finishes what was queued and stops after that.
+    pub(crate) fn close(&self) {
+        for sender in &self.senders {
+            sender.close();
+        }
+    }
+}
+
+impl OutgoingStreams {
+    /// Waits until a stream has something to send and writes its next chunk into `buf`: the chunk
+    /// header followed by the chunk data. The buffer is cleared first, so the same one can be
+    /// used for every chunk.
+    ///
+    /// Streams are served in order of priority, each stream may send as many chunks as its
+    /// [weight](StreamId::weight) in a round. That way, a large transfer on a stream with low
+    /// priority delays other streams by at most one chunk.
+    ///
+    /// Returns [`None`] once all queues are closed and empty.
+    pub(crate) async fn next_chunk(&mut self, buf: &mut Vec<u8>) -> Option<Chunk> {
+        loop {
+            for stream in StreamId::ALL {
+                let i = stream.index();
+                while self.current[i].is_none() {
+                    let Ok(envelope) = self.queues[i].try_recv() else {
+                        break;
+                    };
+                    self.current[i] = PendingEnvelope::new(envelope, &mut self.wire[i]);
+                }
+            }
+
+            if self.current.iter().all(Option::is_none) {
+                let (i, envelope) = tokio::select! {
+                    biased;
+                    Ok(e) = self.queues[0].recv() => (0, e),
+                    Ok(e) = self.queues[1].recv() => (1, e),
+                    Ok(e) = self.queues[2].recv() => (2, e),
+                    else => return None,
+                };
+                self.current[i] = PendingEnvelope::new(envelope, &mut self.wire[i]);
+                continue;
+            }
+
+            let next = StreamId::ALL
+                .into_iter()
+                .find(|s| self.current[s.index()].is_some() && self.credits[s.index()] > 0);
+            let Some(stream) = next else {
+                // every stream with pending data has used up its share, start a new round
+                self.credits = StreamId::ALL.map(StreamId::weight);
+                continue;
+            };
+
+            let i = stream.index();
+            self.credits[i] -= 1;
+            let pending = self.current[i]
+                .as_mut()
+                .expect("stream was checked to have a pending envelope");
+            let wire = &mut self.wire[i];
+            let end = usize::min(pending.offset + CHUNK_SIZE, wire.len());
+            let fin = end == wire.len();
+
+            buf.clear();
+            buf.push(stream as u8);
+            buf.push(if fin { FLAG_FIN } else { 0 });
+            buf.extend_from_slice(&wire[pending.offset..end]);
+            pending.offset = end;
+
+            let finished = if fin {
+                if wire.capacity() > MAX_KEPT_WIRE_SIZE {
+                    *wire = Vec::new();
+                }
+                self.current[i].take().map(|p| p.envelope)
+            } else {
+                None
+            };
+            return Some(Chunk { stream, finished });
+        }
+    }
+}
+
+impl PendingEnvelope {
+    /// Encodes the envelope into `wire`.
+    fn new(envelope: Arc<Envelope>, wire: &mut Vec<u8>) -> Option<Self> {
+        match envelope.write_wire(wire) {
+            Ok(()) => Some(Self {
+                envelope,
+                offset: 0,
+            }),
+            Err(e) => {
+                log::error!("Could not encode {envelope}, it will not be sent: {e}");
+                None
+            }
+        }
+    }
+}
+
+impl BufferBudget {
+    pub(crate) const fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            used: AtomicUsize::new(0),
+        }
+    }
+
+    /// Takes `len` bytes from the budget, fails if that would exceed its limit.
+    fn reserve(&self, len: usize) -> CoreResult<()> {
+        self.used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                used.checked_add(len).filter(|&n| n <= self.limit)
+            })
+            .map(|_| ())
+            .map_err(|used| CoreError::TooMuchBuffered(used.saturating_add(len)))
+    }
+
+    /// Gives `len` bytes back to the budget.
+    fn release(&self, len: usize) {
+        self.used.fetch_sub(len, Ordering::Relaxed);
+    }
+}
+
+impl Default for Reassembler {
+    fn default() -> Self {
+        Self::with_limits(MAX_ENVELOPE_SIZE, &RECEIVE_BUDGET)
+    }
+}
+
+impl Drop for Reassembler {
+    fn drop(&mut self) {
+        self.budget.release(self.partial.iter().map(Vec::len).sum());
+    }
+}
+
+impl Reassembler {
+    /// Creates a reassembler that refuses envelopes larger than `max_envelope_size` and takes
+    /// the memory for unfinished envelopes from `budget`.
+    pub(crate) fn with_limits(max_envelope_size: usize, budget: &'static BufferBudget) -> Self {
+        Self {
+            partial: Default::default(),
+            max_envelope_size,
+            budget,
+        }
+    }
+
+    /// Takes a received chunk, returns the [`Envelope`] if this was its last chunk.
+    pub(crate) fn push(&mut self, chunk: &[u8]) -> CoreResult<Option<Envelope>> {
+        if chunk.len() < CHUNK_HEADER_LEN {
+            return Err(CoreError::MalformedChunk);
+        }
+        let stream = StreamId::from_u8(chunk[0]).ok_or(CoreError::UnknownStream(chunk[0]))?;
+        let flags = chunk[1];
+        if flags & !FLAG_FIN != 0 {
+            return Err(CoreError::BadChunkFlags(flags));
+        }
+        let data = &chunk[CHUNK_HEADER_LEN..];
+        let started = self.partial[stream.index()].len();
+
+        if flags & FLAG_FIN != 0 && started == 0 {
+            return Ok(Some(Envelope::from_wire(data)?));
+        }
+        if started + data.len() > self.max_envelope_size {
+            return Err(CoreError::EnvelopeTooLarge(started + data.len()));
+        }
+        self.budget.reserve(data.len())?;
+        let buf = &mut self.partial[stream.index()];
+        buf.extend_from_slice(data);
+        if flags & FLAG_FIN != 0 {
+            let buf = Bytes::from(std::mem::take(buf));
+            self.budget.release(buf.len());
+            Ok(Some(Envelope::from_wire_bytes(buf)?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chat(text: &str) -> Arc<Envelope> {
+        Arc::new(Envelope::ChatMessage(Bytes::copy_from_slice(
+            text.as_bytes(),
+        )))
+    }
+
+    #[tokio::test]
+    async fn refused_envelopes_are_not_overtaken() {
+        let (senders, mut outgoing) = stream_queues();
+        for i in 0..StreamId::Chat.queue_capacity() {
+            senders.try_send(chat(&i.to_string())).unwrap();
+        }
+        let first = chat("first");
+        let second = chat("second");
+        assert!(senders.try_send(first.clone()).unwrap_err().is_full());
+        assert!(senders.try_send(second.clone()).unwrap_err().is_full());
+
+        // drain the queue completely, the refused envelopes still come first
+        let mut buf = Vec::new();
+        while !senders.senders[StreamId::Chat.index()].is_empty() {
+            outgoing.next_chunk(&mut buf).await;
+        }
+        assert!(senders.try_send(chat("newer")).unwrap_err().is_full());
+        assert!(senders.try_send(second.clone()).unwrap_err().is_full());
+        senders.try_send(first).unwrap();
+        senders.try_send(second).unwrap();
+        senders.try_send(chat("newer")).unwrap();
+    }
+
+    #[test]
+    fn dropped_envelopes_do_not_block_the_stream() {
+        let (senders, _outgoing) = stream_queues();
+        for i in 0..StreamId::Control.queue_capacity() {
+            senders
+                .try_send_or_drop(Envelope::Ping(i as u64).into())
+                .unwrap();
+        }
+        assert!(
+            senders
+                .try_send_or_drop(Envelope::Ping(0).into())
+                .unwrap_err()
+                .is_full()
+        );
+        assert!(
+            senders.refused[StreamId::Control.index()]
+                .lock()
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn chunks_are_reassembled() {
+        let (senders, mut outgoing) = stream_queues();
+        let large = Arc::new(Envelope::ChatMessage(vec![7; 3 * CHUNK_SIZE].into()));
+        senders.try_send(large.clone()).unwrap();
+        senders.try_send(Envelope::Ping(1).into()).unwrap();
+        senders.close();
+
+        let mut reassembler = Reassembler::default();
+        let mut received = Vec::new();
+        let mut buf = Vec::new();
+        while outgoing.next_chunk(&mut buf).await.is_some() {
+            if let Some(envelope) = reassembler.push(&buf).unwrap() {
+                received.push(envelope);
+            }
+        }
+        // the ping overtakes the large message, which arrives intact
+        assert_eq!(received, [Envelope::Ping(1), (*large).clone()]);
+    }
+
+    #[test]
+    fn bad_chunks_are_rejected() {
+        let ping = Envelope::Ping(1).to_wire().unwrap();
+        let mut reassembler = Reassembler::default();
+        assert!(matches!(
+            reassembler.push(&[0]),
+            Err(CoreError::MalformedChunk)
+        ));
+        assert!(matches!(
+            reassembler.push(&[[3, FLAG_FIN].as_slice(), &ping].concat()),
+            Err(CoreError::UnknownStream(3))
+        ));
+        assert!(matches!(
+            reassembler.push(&[[0, FLAG_FIN | 0b10].as_slice(), &ping].concat()),
+            Err(CoreError::BadChunkFlags(0b11))
+        ));
+        assert!(
+            reassembler
+                .push(&[[0, FLAG_FIN].as_slice(), &ping].concat())
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn buffered_chunks_are_limited() {
+        static BUDGET: BufferBudget = BufferBudget::new(6 * CHUNK_SIZE);
+        let chunk = |stream: u8| [[stream, 0].as_slice(), &vec![0; CHUNK_SIZE]].concat();
+
+        let mut reassembler = Reassembler::with_limits(4 * CHUNK_SIZE, &BUDGET);
+        let result = (0..5).try_for_each(|_| reassembler.push(&chunk(2)).map(|_| ()));
+        assert!(matches!(result, Err(CoreError::EnvelopeTooLarge(_))));
+        assert_eq!(BUDGET.used.load(Ordering::Relaxed), 4 * CHUNK_SIZE);
+
+        // the budget is shared with the other connections
+        let mut other = Reassembler::with_limits(4 * CHUNK_SIZE, &BUDGET);
+        other.push(&chunk(1)).unwrap();
+        other.push(&chunk(2)).unwrap();
+        assert!(matches!(
+            other.push(&chunk(1)),
+            Err(CoreError::TooMuchBuffered(_))
+        ));
+
+        // and given back once a connection is dropped
+        drop(reassembler);
+        other.push(&chunk(1)).unwrap();
+        let fin = [[1, FLAG_FIN].as_slice(), &[0xc1]].concat();
+        assert!(other.push(&fin).is_err());
+        assert_eq!(BUDGET.used.load(Ordering::Relaxed), CHUNK_SIZE);
+    }
+}
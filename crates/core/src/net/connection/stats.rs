@@ -0,0 +1,259 @@
+use std::{
+    fmt::Display,
+    net::SocketAddr,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+use crate::{identity::ContactId, net::connection::VersionHeader};
+
+/// How often the peers of the active connections are pinged to measure the round trip time
+pub const PING_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Marks counters that have no value yet
+const UNSET: u64 = u64::MAX;
+
+/// How a connection reaches the peer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ConnectionPath {
+    /// Directly connected to the peer
+    Direct,
+}
+
+/// Live counters of one connection, shared by its reader and writer.
+///
+/// Times are kept as microseconds since the connection was established, so they fit into atomics.
+#[derive(Debug)]
+pub(crate) struct ConnectionStats {
+    path: ConnectionPath,
+    protocol_version: VersionHeader,
+    handshake_duration: Duration,
+    established: Instant,
+    established_at: DateTime<Utc>,
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+    frames_in: AtomicU64,
+    frames_out: AtomicU64,
+    last_activity: AtomicU64,
+    last_ping: AtomicU64,
+    /// Value of the last ping, until the peer has answered it
+    ping_nonce: AtomicU64,
+    rtt: AtomicU64,
+}
+
+/// What a connection has been doing, see
+/// [`NetworkCommand::QueryStats`](crate::domain::NetworkCommand::QueryStats)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConnectionStatsSnapshot {
+    pub remote: SocketAddr,
+    pub contact: ContactId,
+    pub path: ConnectionPath,
+    /// Protocol version the peer announced in its frames
+    pub protocol_version: VersionHeader,
+    pub established: DateTime<Utc>,
+    pub handshake_duration: Duration,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub frames_in: u64,
+    pub frames_out: u64,
+    /// Last measured round trip time, if the peer has answered a ping yet
+    pub rtt: Option<Duration>,
+    /// Time since the last frame was sent or received
+    pub idle: Duration,
+}
+
+impl ConnectionStats {
+    pub(crate) fn new(
+        path: ConnectionPath,
+        protocol_version: VersionHeader,
+        handshake_duration: Duration,
+    ) -> Self {
+        Self {
+            path,
+            protocol_version,
+            handshake_duration,
+            established: Instant::now(),
+            established_at: Utc::now(),
+            bytes_in: AtomicU64::new(0),
+            bytes_out: AtomicU64::new(0),
+            frames_in: AtomicU64::new(0),
+            frames_out: AtomicU64::new(0),
+            last_activity: AtomicU64::new(0),
+            last_ping: AtomicU64::new(UNSET),
+            ping_nonce: AtomicU64::new(UNSET),
+            rtt: AtomicU64::new(UNSET),
+        }
+    }
+
+    /// Microseconds since the connection was established
+    #[allow(clippy::cast_possible_truncation)] // more than 500000 years
+    fn now(&self) -> u64 {
+        self.established.elapsed().as_micros() as u64
+    }
+
+    pub(crate) fn record_in(&self, bytes: usize) {
+        self.frames_in.fetch_add(1, Ordering::Relaxed);
+        self.bytes_in.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.last_activity.store(self.now(), Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_out(&self, bytes: usize) {
+        self.frames_out.fetch_add(1, Ordering::Relaxed);
+        self.bytes_out.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.last_activity.store(self.now(), Ordering::Relaxed);
+    }
+
+    /// Returns the value for a new ping if the last one is older than [`PING_INTERVAL`].
+    ///
+    /// The value is random, so the peer can not answer a ping before it has received it. An
+    /// earlier ping that was not answered yet is forgotten.
+    pub(crate) fn next_ping(&self) -> Option<u64> {
+        let now = self.now();
+        let last = self.last_ping.load(Ordering::Relaxed);
+        if last != UNSET && Duration::from_micros(now.saturating_sub(last)) < PING_INTERVAL {
+            return None;
+        }
+        let nonce = loop {
+            let nonce = rand::rngs::OsRng.next_u64();
+            if nonce != UNSET {
+                break nonce;
+            }
+        };
+        self.last_ping.store(now, Ordering::Relaxed);
+        self.ping_nonce.store(nonce, Ordering::Relaxed);
+        Some(nonce)
+    }
+
+    /// Takes the value of a pong. Only the answer to the last ping is used to measure the round
+    /// trip time, and only once.
+    pub(crate) fn record_pong(&self, value: u64) {
+        let now = self.now();
+        if value == UNSET
+            || self
+                .ping_nonce
+                .compare_exchange(value, UNSET, Ordering::Relaxed, Ordering::Relaxed)
+                .is_err()
+        {
+            log::debug!("Ignoring pong that does not answer the last ping");
+            return;
+        }
+        let sent = self.last_ping.load(Ordering::Relaxed);
+        self.rtt.store(now.saturating_sub(sent), Ordering::Relaxed);
+    }
+
+    /// Last measured round trip time, if the peer has answered a ping yet
+    pub(crate) fn rtt(&self) -> Option<Duration> {
+        let rtt = self.rtt.load(Ordering::Relaxed);
+        (rtt != UNSET).then(|| Duration::from_micros(rtt))
+    }
+
+    pub(crate) fn snapshot(
+        &self,
+        remote: SocketAddr,
+        contact: ContactId,
+    ) -> ConnectionStatsSnapshot {
+        ConnectionStatsSnapshot {
+            remote,
+            contact,
+            path: self.path,
+            protocol_version: self.protocol_version.clone(),
+            established: self.established_at,
+            handshake_duration: self.handshake_duration,
+            bytes_in: self.bytes_in.load(Ordering::Relaxed),
+            bytes_out: self.bytes_out.load(Ordering::Relaxed),
+            frames_in: self.frames_in.load(Ordering::Relaxed),
+            frames_out: self.frames_out.load(Ordering::Relaxed),
+            rtt: self.rtt(),
+            idle: Duration::from_micros(
+                self.now()
+                    .saturating_sub(self.last_activity.load(Ordering::Relaxed)),
+            ),
+        }
+    }
+}
+
+impl Display for ConnectionPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Direct => "Direct",
+            }
+        )
+    }
+}
+
+impl Display for ConnectionStatsSnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Connection to {} ({})", self.remote, self.contact)?;
+        writeln!(
+            f,
+            "Path: {}, protocol: {}",
+            self.path, self.protocol_version
+        )?;
+        writeln!(
+            f,
+            "Established: {} (handshake took {:?})",
+            self.established, self.handshake_duration
+        )?;
+        writeln!(
+            f,
+            "In: {} bytes in {} frames",
+            self.bytes_in, self.frames_in
+        )?;
+        writeln!(
+            f,
+            "Out: {} bytes in {} frames",
+            self.bytes_out, self.frames_out
+        )?;
+        match self.rtt {
+            Some(rtt) => writeln!(f, "RTT: {rtt:?}")?,
+            None => writeln!(f, "RTT: unknown")?,
+        }
+        write!(f, "Idle for {:?}", self.idle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats() -> ConnectionStats {
+        ConnectionStats::new(
+            ConnectionPath::Direct,
+            VersionHeader::default(),
+            Duration::ZERO,
+        )
+    }
+
+    #[test]
+    fn only_the_answer_to_the_last_ping_counts() {
+        let stats = stats();
+        let ping = stats.next_ping().unwrap();
+        assert_eq!(stats.next_ping(), None, "pinged within the interval");
+
+        stats.record_pong(ping.wrapping_add(1));
+        stats.record_pong(UNSET);
+        assert_eq!(stats.rtt(), None);
+
+        stats.record_pong(ping);
+        let rtt = stats.rtt().unwrap();
+        assert!(rtt < PING_INTERVAL);
+    }
+
+    #[test]
+    fn a_pong_is_only_counted_once() {
+        let stats = stats();
+        let ping = stats.next_ping().unwrap();
+        stats.record_pong(ping);
+        stats.rtt.store(UNSET, Ordering::Relaxed);
+        stats.record_pong(ping);
+        assert_eq!(stats.rtt(), None);
+    }
+}
@@ -0,0 +1,202 @@
+use std::{fmt::Display, sync::Arc};
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{CoreError, CoreResult},
+    identity::{Identity, IdentityStatement, PairingMessage, SasMessage},
+};
+
+/// Version of the envelope wire format that this implementation produces.
+///
+/// Receivers accept envelopes of any version. Kinds they do not know are decoded as
+/// [`Envelope::Unknown`] instead of failing, so that newer peers can introduce new kinds without
+/// breaking older ones.
+pub const ENVELOPE_VERSION: u8 = 1;
+
+/// Type tags of the [`Envelope`] variants as they are transmitted.
+///
+/// WARN: A tag must never be reused or changed once released, peers rely on them
+pub mod kind {
+    pub const PING: u16 = 0x0001;
+    pub const PONG: u16 = 0x0002;
+    pub const IDENTITY_UPDATE: u16 = 0x0003;
+    pub const IDENTITY_STATEMENT: u16 = 0x0004;
+    pub const VERIFICATION: u16 = 0x0005;
+    pub const PAIRING: u16 = 0x0006;
+    pub const CHAT_MESSAGE: u16 = 0x0100;
+    pub const TYPING: u16 = 0x0101;
+}
+
+/// Everything that is sent over a connection after the handshake and identity exchange is wrapped
+/// in an [`Envelope`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Envelope {
+    /// Liveness check, the peer answers with a [`Envelope::Pong`] carrying the same value
+    Ping(u64),
+    Pong(u64),
+    /// The peer has changed its [`Identity`], this is the new signed version of it
+    IdentityUpdate(Arc<Identity>),
+    /// A succession or revocation of an identity, usually the one of the peer
+    IdentityStatement(Arc<IdentityStatement>),
+    /// A step of the verification of this connection with a short authentication string
+    Verification(SasMessage),
+    /// A step of pairing over this connection with a code that both users entered
+    Pairing(PairingMessage),
+    /// Opaque chat message payload, the network domain does not interpret it
+    ChatMessage(Bytes),
+    /// The peer started (`true`) or stopped (`false`) typing
+    Typing(bool),
+    /// An envelope with a type tag this implementation does not know
+    Unknown {
+        kind: u16,
+        body: Vec<u8>,
+    },
+}
+
+/// The wire format of an [`Envelope`], with the body borrowed so it is not copied around
+#[derive(Debug, Serialize, Deserialize)]
+struct RawEnvelope<'a> {
+    version: u8,
+    kind: u16,
+    #[serde(with = "serde_bytes", borrow)]
+    body: &'a [u8],
+}
+
+impl Envelope {
+    /// Returns the type tag of this [`Envelope`].
+    pub fn kind(&self) -> u16 {
+        match self {
+            Self::Ping(_) => kind::PING,
+            Self::Pong(_) => kind::PONG,
+            Self::IdentityUpdate(_) => kind::IDENTITY_UPDATE,
+            Self::IdentityStatement(_) => kind::IDENTITY_STATEMENT,
+            Self::Verification(_) => kind::VERIFICATION,
+            Self::Pairing(_) => kind::PAIRING,
+            Self::ChatMessage(_) => kind::CHAT_MESSAGE,
+            Self::Typing(_) => kind::TYPING,
+            Self::Unknown { kind, .. } => *kind,
+        }
+    }
+
+    /// Encodes this [`Envelope`] with MessagePack.
+    pub fn to_wire(&self) -> CoreResult<Vec<u8>> {
+        let mut buf = Vec::new();
+        self.write_wire(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Like [`to_wire`](Self::to_wire), but writes into `buf` after clearing it, so that the same
+    /// buffer can be used for many envelopes.
+    pub fn write_wire(&self, buf: &mut Vec<u8>) -> CoreResult<()> {
+        let encoded;
+        let body: &[u8] = match self {
+            Self::ChatMessage(data) => data,
+            Self::Unknown { body, .. } => body,
+            Self::Ping(v) | Self::Pong(v) => {
+                encoded = rmp_serde::to_vec(v)?;
+                &encoded
+            }
+            Self::IdentityUpdate(iden) => {
+                encoded = rmp_serde::to_vec(&**iden)?;
+                &encoded
+            }
+            Self::IdentityStatement(statement) => {
+                encoded = rmp_serde::to_vec(&**statement)?;
+                &encoded
+            }
+            Self::Verification(step) => {
+                encoded = rmp_serde::to_vec(step)?;
+                &encoded
+            }
+            Self::Pairing(step) => {
+                encoded = rmp_serde::to_vec(step)?;
+                &encoded
+            }
+            Self::Typing(typing) => {
+                encoded = rmp_serde::to_vec(typing)?;
+                &encoded
+            }
+        };
+        buf.clear();
+        rmp_serde::encode::write(
+            buf,
+            &RawEnvelope {
+                version: ENVELOPE_VERSION,
+                kind: self.kind(),
+                body,
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Decodes an [`Envelope`] from its MessagePack representation.
+    pub fn from_wire(raw: &[u8]) -> CoreResult<Self> {
+        Self::decode(raw, Bytes::copy_from_slice)
+    }
+
+    /// Like [`from_wire`](Self::from_wire), but the body of a chat message is not copied, it
+    /// refers to `raw` instead.
+    pub fn from_wire_bytes(raw: Bytes) -> CoreResult<Self> {
+        Self::decode(&raw, |body| raw.slice_ref(body))
+    }
+
+    fn decode(raw: &[u8], chat_body: impl FnOnce(&[u8]) -> Bytes) -> CoreResult<Self> {
+        let raw: RawEnvelope = rmp_serde::from_slice(raw)?;
+        if raw.version == 0 {
+            return Err(CoreError::BadEnvelopeVersion(raw.version));
+        }
+        if raw.version > ENVELOPE_VERSION {
+            log::debug!(
+                "Received envelope of newer version {} (ours is {ENVELOPE_VERSION})",
+                raw.version
+            );
+        }
+        Ok(match raw.kind {
+            kind::PING => Self::Ping(rmp_serde::from_slice(raw.body)?),
+            kind::PONG => Self::Pong(rmp_serde::from_slice(raw.body)?),
+            kind::IDENTITY_UPDATE => {
+                Self::IdentityUpdate(Arc::new(rmp_serde::from_slice(raw.body)?))
+            }
+            kind::IDENTITY_STATEMENT => {
+                Self::IdentityStatement(Arc::new(rmp_serde::from_slice(raw.body)?))
+            }
+            kind::VERIFICATION => Self::Verification(rmp_serde::from_slice(raw.body)?),
+            kind::PAIRING => Self::Pairing(rmp_serde::from_slice(raw.body)?),
+            kind::CHAT_MESSAGE => Self::ChatMessage(chat_body(raw.body)),
+            kind::TYPING => Self::Typing(rmp_serde::from_slice(raw.body)?),
+            other => Self::Unknown {
+                kind: other,
+                body: raw.body.to_vec(),
+            },
+        })
+    }
+}
+
+impl Display for Envelope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ping(v) => write!(f, "Ping ({v})"),
+            Self::Pong(v) => write!(f, "Pong ({v})"),
+            Self::IdentityUpdate(iden) => write!(
+                f,
+                "Identity update of {} to version {}",
+                iden.id(),
+                iden.version()
+            ),
+            Self::IdentityStatement(statement) => write!(f, "{statement}"),
+            Self::Verification(step) => write!(f, "Verification ({step})"),
+            Self::Pairing(step) => write!(f, "Pairing ({step})"),
+            Self::ChatMessage(data) => write!(f, "Chat message ({} bytes)", data.len()),
+            Self::Typing(typing) => write!(f, "Typing ({typing})"),
+            Self::Unknown { kind, body } => {
+                write!(
+                    f,
+                    "Unknown envelope kind {kind:#06x} ({} bytes)",
+                    body.len()
+                )
+            }
+        }
+    }
+}
@@ -1 +1,4 @@
 pub mod connection;
+pub mod envelope;
+pub mod stream;
+pub mod transport;
@@ -0,0 +1,95 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::net::envelope::Envelope;
+
+/// Maximum amount of envelope bytes carried by one transport message.
+///
+/// Larger envelopes are split into chunks, so that the streams of a connection can be interleaved
+/// at this granularity.
+pub const CHUNK_SIZE: usize = 16 * 1024;
+
+/// Maximum size of an [`Envelope`] after reassembling its chunks.
+pub const MAX_ENVELOPE_SIZE: usize = 64 * 1024 * 1024;
+
+/// Logical streams that are multiplexed over one connection.
+///
+/// Each stream has its own send queue and a weight that decides how many chunks it may send
+/// while the other streams are busy too.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum StreamId {
+    /// Small, latency sensitive traffic of the protocol itself
+    Control = 0,
+    /// Regular chat messages
+    Chat = 1,
+    /// Large transfers like attachments, which must not hold up chat messages. Nothing is sent
+    /// on it yet, chat messages of any size stay on [`Chat`](Self::Chat) to keep their order.
+    Bulk = 2,
+}
+
+impl StreamId {
+    /// All streams, ordered by priority
+    pub const ALL: [Self; 3] = [Self::Control, Self::Chat, Self::Bulk];
+
+    #[inline]
+    pub fn from_u8(raw: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| *s as u8 == raw)
+    }
+
+    #[inline]
+    pub(crate) fn index(self) -> usize {
+        self as usize
+    }
+
+    /// How many chunks the stream may send in one scheduling round
+    #[inline]
+    pub const fn weight(self) -> u32 {
+        match self {
+            Self::Control => 8,
+            Self::Chat => 4,
+            Self::Bulk => 1,
+        }
+    }
+
+    /// How many envelopes may wait in the send queue of the stream
+    #[inline]
+    pub const fn queue_capacity(self) -> usize {
+        match self {
+            Self::Control => 64,
+            Self::Chat => 64,
+            Self::Bulk => 4,
+        }
+    }
+}
+
+impl Display for StreamId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Control => "Control",
+                Self::Chat => "Chat",
+                Self::Bulk => "Bulk",
+            }
+        )
+    }
+}
+
+impl Envelope {
+    /// Returns the [`StreamId`] this [`Envelope`] is sent on.
+    pub fn stream(&self) -> StreamId {
+        match self {
+            Self::Ping(_)
+            | Self::Pong(_)
+            | Self::IdentityUpdate(_)
+            | Self::IdentityStatement(_)
+            | Self::Verification(_)
+            | Self::Pairing(_)
+            | Self::Typing(_) => StreamId::Control,
+            Self::ChatMessage(_) | Self::Unknown { .. } => StreamId::Chat,
+        }
+    }
+}
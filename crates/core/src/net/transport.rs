@@ -0,0 +1,67 @@
+use std::{fmt::Debug, future::Future, io, net::SocketAddr, pin::Pin};
+
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// A boxed future, as returned by the methods of [`Transport`] and [`TransportListener`]
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// A reliable, ordered byte stream to a peer, like a TCP stream.
+pub trait TransportStream: AsyncRead + AsyncWrite + Send + Sync + Unpin + Debug {}
+
+impl<T> TransportStream for T where T: AsyncRead + AsyncWrite + Send + Sync + Unpin + Debug {}
+
+pub type BoxedStream = Box<dyn TransportStream>;
+
+/// Opens the byte streams that connections run over.
+///
+/// The network domain uses [`TcpTransport`] unless something else is given with
+/// [`NetworkDomain::with_transport`](crate::domain::NetworkDomain::with_transport), which allows
+/// running it over virtual links in tests.
+pub trait Transport: Send + Sync + Debug {
+    /// Opens a stream to `remote`.
+    fn connect(&self, remote: SocketAddr) -> BoxFuture<'_, io::Result<BoxedStream>>;
+
+    /// Starts listening for incoming streams on `local`.
+    fn bind(&self, local: SocketAddr) -> BoxFuture<'_, io::Result<Box<dyn TransportListener>>>;
+}
+
+/// Accepts incoming streams, see [`Transport::bind`]
+pub trait TransportListener: Send + Sync + Debug {
+    /// Waits for the next incoming stream and returns it together with the address of the peer.
+    fn accept(&self) -> BoxFuture<'_, io::Result<(BoxedStream, SocketAddr)>>;
+
+    fn local_addr(&self) -> io::Result<SocketAddr>;
+}
+
+/// [`Transport`] over plain TCP
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TcpTransport;
+
+impl Transport for TcpTransport {
+    fn connect(&self, remote: SocketAddr) -> BoxFuture<'_, io::Result<BoxedStream>> {
+        Box::pin(async move {
+            let stream = tokio::net::TcpStream::connect(remote).await?;
+            Ok(Box::new(stream) as BoxedStream)
+        })
+    }
+
+    fn bind(&self, local: SocketAddr) -> BoxFuture<'_, io::Result<Box<dyn TransportListener>>> {
+        Box::pin(async move {
+            let listener = tokio::net::TcpListener::bind(local).await?;
+            Ok(Box::new(listener) as Box<dyn TransportListener>)
+        })
+    }
+}
+
+impl TransportListener for tokio::net::TcpListener {
+    fn accept(&self) -> BoxFuture<'_, io::Result<(BoxedStream, SocketAddr)>> {
+        Box::pin(async move {
+            let (stream, remote) = tokio::net::TcpListener::accept(self).await?;
+            Ok((Box::new(stream) as BoxedStream, remote))
+        })
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        tokio::net::TcpListener::local_addr(self)
+    }
+}
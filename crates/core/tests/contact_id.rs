@@ -0,0 +1,11 @@
+use sremp_core::identity::{ContactId, UserIdentity};
+
+#[test]
+fn contact_id_parses_its_display_form() {
+    let alice = UserIdentity::create("alice").unwrap().id();
+    let text = alice.to_string();
+    assert_eq!(text.parse::<ContactId>().unwrap(), alice);
+    assert_eq!(text.to_lowercase().parse::<ContactId>().unwrap(), alice);
+    assert!(text[..62].parse::<ContactId>().is_err());
+    assert!(format!("{}zz", &text[..62]).parse::<ContactId>().is_err());
+}
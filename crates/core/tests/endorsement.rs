@@ -0,0 +1,57 @@
+use sremp_core::identity::{
+    ContactIdentity, IdentityStatement, MAX_ENDORSEMENTS, Trust, UserIdentity,
+};
+
+#[test]
+fn newest_endorsement_of_an_endorser_counts() {
+    let alice = UserIdentity::create("alice").unwrap();
+    let bob = UserIdentity::create("bob").unwrap();
+    let mut contact =
+        ContactIdentity::from_peer_identity(bob.identity.clone(), Trust::Unknown).unwrap();
+
+    let endorsement = alice.endorse(bob.id(), false).unwrap();
+    endorsement.verify().unwrap();
+    let withdrawal = alice.endorse(bob.id(), true).unwrap();
+    assert!(withdrawal.supersedes(&endorsement));
+
+    assert!(contact.add_endorsement(endorsement.clone()));
+    assert_eq!(contact.endorsers().collect::<Vec<_>>(), [alice.id()]);
+    assert!(contact.add_endorsement(withdrawal));
+    assert_eq!(contact.endorsers().count(), 0);
+    // the old endorsement can not be replayed after it was withdrawn
+    assert!(!contact.add_endorsement(endorsement));
+    assert_eq!(contact.endorsers().count(), 0);
+
+    // an endorsement of someone else is not recorded
+    let carol = UserIdentity::create("carol").unwrap();
+    assert!(!contact.add_endorsement(alice.endorse(carol.id(), false).unwrap()));
+
+    let own = IdentityStatement::Endorsement(alice.endorse(alice.id(), false).unwrap());
+    assert!(own.verify().is_err());
+}
+
+#[test]
+fn endorsers_are_capped() {
+    let bob = UserIdentity::create("bob").unwrap();
+    let mut contact =
+        ContactIdentity::from_peer_identity(bob.identity.clone(), Trust::Unknown).unwrap();
+
+    let endorsers: Vec<_> = (0..=MAX_ENDORSEMENTS)
+        .map(|_| UserIdentity::create("endorser").unwrap())
+        .collect();
+    for endorser in &endorsers[..MAX_ENDORSEMENTS] {
+        assert!(contact.add_endorsement(endorser.endorse(bob.id(), false).unwrap()));
+    }
+    assert!(
+        !contact.add_endorsement(
+            endorsers[MAX_ENDORSEMENTS]
+                .endorse(bob.id(), false)
+                .unwrap()
+        )
+    );
+    assert_eq!(contact.endorsements.len(), MAX_ENDORSEMENTS);
+
+    // endorsers that are already recorded can still withdraw
+    assert!(contact.add_endorsement(endorsers[0].endorse(bob.id(), true).unwrap()));
+    assert_eq!(contact.endorsers().count(), MAX_ENDORSEMENTS - 1);
+}
@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use sremp_core::{
+    identity::UserIdentity,
+    net::envelope::{ENVELOPE_VERSION, Envelope, kind},
+};
+
+#[derive(serde::Serialize)]
+struct RawEnvelope {
+    version: u8,
+    kind: u16,
+    #[serde(with = "serde_bytes")]
+    body: Vec<u8>,
+}
+
+fn raw(version: u8, kind: u16, body: &[u8]) -> Vec<u8> {
+    rmp_serde::to_vec(&RawEnvelope {
+        version,
+        kind,
+        body: body.to_vec(),
+    })
+    .unwrap()
+}
+
+#[test]
+fn envelopes_survive_the_wire() {
+    let user = UserIdentity::create("alice").unwrap();
+    let envelopes = [
+        Envelope::Ping(7),
+        Envelope::Pong(u64::MAX),
+        Envelope::IdentityUpdate(Arc::new(user.identity.clone())),
+        Envelope::ChatMessage(Bytes::from_static(b"opaque chat body")),
+        Envelope::Typing(true),
+        Envelope::Unknown {
+            kind: 0x7fff,
+            body: vec![1, 2, 3],
+        },
+    ];
+    let mut buf = Vec::new();
+    for envelope in envelopes {
+        let wire = envelope.to_wire().unwrap();
+        assert_eq!(Envelope::from_wire(&wire).unwrap(), envelope);
+        envelope.write_wire(&mut buf).unwrap();
+        assert_eq!(buf, wire);
+    }
+}
+
+#[test]
+fn chat_bodies_are_not_copied() {
+    let body = vec![42; 1024];
+    let wire = Bytes::from(
+        Envelope::ChatMessage(body.clone().into())
+            .to_wire()
+            .unwrap(),
+    );
+    let Envelope::ChatMessage(decoded) = Envelope::from_wire_bytes(wire.clone()).unwrap() else {
+        panic!("not decoded as a chat message");
+    };
+    assert_eq!(decoded, body);
+    assert!(wire.as_ptr_range().contains(&decoded.as_ptr()));
+}
+
+#[test]
+fn unknown_kinds_are_kept_and_bad_versions_rejected() {
+    let decoded = Envelope::from_wire(&raw(ENVELOPE_VERSION + 1, 0x4242, b"future")).unwrap();
+    assert_eq!(
+        decoded,
+        Envelope::Unknown {
+            kind: 0x4242,
+            body: b"future".to_vec()
+        }
+    );
+    assert_eq!(decoded.kind(), 0x4242);
+
+    assert!(Envelope::from_wire(&raw(0, kind::PING, &rmp_serde::to_vec(&1u64).unwrap())).is_err());
+    assert!(
+        Envelope::from_wire(&raw(
+            ENVELOPE_VERSION,
+            kind::PING,
+            &rmp_serde::to_vec("not a number").unwrap()
+        ))
+        .is_err()
+    );
+    assert!(Envelope::from_wire(b"garbage").is_err());
+}
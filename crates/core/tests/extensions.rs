@@ -0,0 +1,137 @@
+use sremp_core::identity::{Extensions, ImageFormat, ImageInfo, UserIdentity, metadata};
+
+fn png(width: u32, height: u32) -> Vec<u8> {
+    let mut data = b"\x89PNG\r\n\x1a\n".to_vec();
+    data.extend(13u32.to_be_bytes());
+    data.extend(b"IHDR");
+    data.extend(width.to_be_bytes());
+    data.extend(height.to_be_bytes());
+    data.extend([8, 6, 0, 0, 0]);
+    data.extend([0; 4]); // crc
+    data.extend(0u32.to_be_bytes());
+    data.extend(b"IEND");
+    data.extend([0xae, 0x42, 0x60, 0x82]);
+    data
+}
+
+fn jpeg(width: u16, height: u16) -> Vec<u8> {
+    let mut data = vec![0xff, 0xd8];
+    data.extend([0xff, 0xe0, 0, 16]);
+    data.extend(b"JFIF\0");
+    data.extend([1, 1, 0, 0, 1, 0, 1, 0, 0]);
+    data.extend([0xff, 0xc0, 0, 11, 8]);
+    data.extend(height.to_be_bytes());
+    data.extend(width.to_be_bytes());
+    data.extend([1, 1, 0x11, 0]);
+    data
+}
+
+fn webp_lossless(width: u32, height: u32) -> Vec<u8> {
+    let bits = (width - 1) | (height - 1) << 14;
+    let mut chunk = b"VP8L".to_vec();
+    chunk.extend(5u32.to_le_bytes());
+    chunk.push(0x2f);
+    chunk.extend(bits.to_le_bytes());
+    chunk.push(0); // padding to an even length
+    let mut data = b"RIFF".to_vec();
+    data.extend(u32::try_from(chunk.len() + 4).unwrap().to_le_bytes());
+    data.extend(b"WEBP");
+    data.extend(chunk);
+    data
+}
+
+#[test]
+fn image_header_gives_format_and_dimensions() {
+    for (data, format) in [
+        (png(64, 48), ImageFormat::Png),
+        (jpeg(64, 48), ImageFormat::Jpeg),
+        (webp_lossless(64, 48), ImageFormat::Webp),
+    ] {
+        assert_eq!(
+            ImageInfo::read(&data),
+            Some(ImageInfo {
+                format,
+                width: 64,
+                height: 48
+            })
+        );
+        assert_eq!(ImageInfo::read(&data[..data.len() - 6]), None);
+    }
+    assert_eq!(ImageInfo::read(b"GIF89a\x40\x00\x30\x00"), None);
+}
+
+#[test]
+fn profile_picture_and_metadata_are_validated() {
+    let mut extensions = Extensions::default();
+    extensions.set_profile_picture(Some(png(512, 512))).unwrap();
+    assert!(extensions.set_profile_picture(Some(png(513, 16))).is_err());
+    assert!(extensions.set_profile_picture(Some(vec![0; 16])).is_err());
+    let mut huge = png(16, 16);
+    huge.resize(70 * 1024, 0);
+    assert!(extensions.set_profile_picture(Some(huge)).is_err());
+
+    extensions
+        .set_metadata_text(metadata::PRONOUNS, " they/them ")
+        .unwrap();
+    assert_eq!(
+        extensions.metadata_text(metadata::PRONOUNS),
+        Some("they/them")
+    );
+    assert!(
+        extensions
+            .set_metadata(metadata::STATUS, Some(vec![0xff, 0xfe]))
+            .is_err()
+    );
+    assert!(
+        extensions
+            .set_metadata_text(metadata::STATUS, &"a".repeat(257))
+            .is_err()
+    );
+
+    let mut user = UserIdentity::create("alice").unwrap();
+    let mut key = user.identity_private_key().clone();
+    user.identity
+        .set_extensions(Some(extensions.clone()), &mut key)
+        .unwrap();
+    user.identity.verify().unwrap();
+    assert_eq!(user.identity.extensions(), Some(&extensions));
+
+    extensions
+        .set_metadata_text(metadata::PRONOUNS, "")
+        .unwrap();
+    extensions.set_profile_picture(None).unwrap();
+    user.identity
+        .set_extensions(Some(extensions), &mut key)
+        .unwrap();
+    assert_eq!(user.identity.extensions(), None);
+}
+
+#[test]
+fn metadata_survives_a_wire_round_trip() {
+    let mut extensions = Extensions::default();
+    for (key, text) in [
+        (metadata::STATUS, "on vacation"),
+        (metadata::PRONOUNS, "they/them"),
+        (metadata::ABOUT, "likes trains"),
+        ("x-custom", "something else"),
+    ] {
+        extensions.set_metadata_text(key, text).unwrap();
+    }
+
+    for _ in 0..20 {
+        let mut user = UserIdentity::create("alice").unwrap();
+        let mut key = user.identity_private_key().clone();
+        user.identity
+            .set_extensions(Some(extensions.clone()), &mut key)
+            .unwrap();
+
+        for data in [
+            rmp_serde::to_vec(&user.identity).unwrap(),
+            rmp_serde::to_vec_named(&user.identity).unwrap(),
+        ] {
+            let received: sremp_core::identity::Identity = rmp_serde::from_slice(&data).unwrap();
+            received.verify().unwrap();
+            assert_eq!(received.extensions(), Some(&extensions));
+        }
+    }
+}
@@ -0,0 +1,17 @@
+use sremp_core::identity::{SafetyNumber, UserIdentity};
+
+#[test]
+fn safety_number_is_symmetric_and_pairwise() {
+    let alice = UserIdentity::create("alice").unwrap().id();
+    let bob = UserIdentity::create("bob").unwrap().id();
+    let carol = UserIdentity::create("carol").unwrap().id();
+
+    let ab = SafetyNumber::new(&alice, &bob);
+    assert_eq!(ab, SafetyNumber::new(&bob, &alice));
+    assert_ne!(ab, SafetyNumber::new(&alice, &carol));
+    assert!(ab.groups().iter().all(|g| *g < 100_000));
+    assert_eq!(ab.to_string().len(), 12 * 5 + 11);
+
+    assert_eq!(alice.fingerprint_words(), alice.clone().fingerprint_words());
+    assert_ne!(alice.fingerprint_words(), bob.fingerprint_words());
+}
@@ -0,0 +1,74 @@
+use sremp_core::{
+    error::CoreError,
+    identity::{
+        UserIdentity,
+        export::{ExportKdfParams, IDENTITY_EXPORT_VERSION},
+    },
+};
+
+/// Keeps the tests fast, real exports use [`ExportKdfParams::DEFAULT`]
+const CHEAP: ExportKdfParams = ExportKdfParams {
+    m_cost: 64,
+    t_cost: 1,
+    p_cost: 1,
+};
+
+#[test]
+fn export_roundtrip() {
+    let user = UserIdentity::create("alice").unwrap();
+    let data = user.export_with("correct horse", CHEAP).unwrap();
+    let imported = UserIdentity::import(&data, "correct horse").unwrap();
+    assert_eq!(imported.identity, user.identity);
+    assert_eq!(imported.identity_key, user.identity_key);
+    assert_eq!(imported.noise_key.to_bytes(), user.noise_key.to_bytes());
+}
+
+#[test]
+fn import_rejects_wrong_passphrase_and_tampering() {
+    let user = UserIdentity::create("alice").unwrap();
+    let data = user.export_with("correct horse", CHEAP).unwrap();
+
+    assert!(matches!(
+        UserIdentity::import(&data, "battery staple"),
+        Err(CoreError::ExportDecryption)
+    ));
+
+    // the parameters are authenticated too
+    let mut tampered = data.clone();
+    tampered[12] ^= 1;
+    assert!(matches!(
+        UserIdentity::import(&tampered, "correct horse"),
+        Err(CoreError::ExportDecryption)
+    ));
+
+    let mut tampered = data.clone();
+    *tampered.last_mut().unwrap() ^= 1;
+    assert!(matches!(
+        UserIdentity::import(&tampered, "correct horse"),
+        Err(CoreError::ExportDecryption)
+    ));
+
+    let mut newer = data;
+    newer[8] = IDENTITY_EXPORT_VERSION + 1;
+    assert!(matches!(
+        UserIdentity::import(&newer, "correct horse"),
+        Err(CoreError::UnsupportedExportVersion(_))
+    ));
+    assert!(matches!(
+        UserIdentity::import(b"not an identity", "correct horse"),
+        Err(CoreError::NotAnIdentityExport)
+    ));
+}
+
+#[test]
+fn import_rejects_foreign_keys() {
+    let user = UserIdentity {
+        noise_key: UserIdentity::create("mallory").unwrap().noise_key,
+        ..UserIdentity::create("alice").unwrap()
+    };
+    let data = user.export_with("correct horse", CHEAP).unwrap();
+    assert!(matches!(
+        UserIdentity::import(&data, "correct horse"),
+        Err(CoreError::KeyPairMismatch("noise"))
+    ));
+}
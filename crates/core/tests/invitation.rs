@@ -0,0 +1,77 @@
+use chrono::TimeDelta;
+use sremp_core::{
+    error::CoreError,
+    identity::{
+        Extensions, UserIdentity,
+        invitation::{INVITATION_URI_PREFIX, Invitation},
+        metadata,
+    },
+};
+
+#[test]
+fn invitation_uri_roundtrip() {
+    let user = UserIdentity::create("alice").unwrap();
+    let endpoints = vec![
+        "10.0.0.1:4000".parse().unwrap(),
+        "[::1]:4000".parse().unwrap(),
+    ];
+    let invitation = Invitation::create(&user, endpoints.clone(), TimeDelta::hours(1)).unwrap();
+
+    let uri = invitation.to_uri().unwrap();
+    assert!(uri.starts_with(INVITATION_URI_PREFIX));
+    let parsed = Invitation::from_uri(&format!(" {uri}\n")).unwrap();
+    assert_eq!(parsed, invitation);
+    assert_eq!(parsed.contact_id(), user.id());
+    assert_eq!(parsed.username(), "alice");
+    assert_eq!(parsed.endpoints(), endpoints);
+}
+
+#[test]
+fn invitation_leaves_out_extensions() {
+    let mut user = UserIdentity::create("alice").unwrap();
+    let endpoints = vec!["10.0.0.1:4000".parse().unwrap()];
+    let plain = Invitation::create(&user, endpoints.clone(), TimeDelta::hours(1))
+        .unwrap()
+        .to_uri()
+        .unwrap();
+
+    let mut extensions = Extensions::default();
+    extensions
+        .set_metadata_text(metadata::STATUS, &"a".repeat(256))
+        .unwrap();
+    let mut key = user.identity_private_key().clone();
+    user.identity
+        .set_extensions(Some(extensions), &mut key)
+        .unwrap();
+    let uri = Invitation::create(&user, endpoints, TimeDelta::hours(1))
+        .unwrap()
+        .to_uri()
+        .unwrap();
+    // the expiry is encoded with a varying number of digits, the status would add 256 bytes
+    assert!(uri.len() < plain.len() + 32, "{uri}");
+    assert_eq!(Invitation::from_uri(&uri).unwrap().contact_id(), user.id());
+}
+
+#[test]
+fn invitation_rejects_expiry_and_garbage() {
+    let user = UserIdentity::create("alice").unwrap();
+    let endpoints = vec!["10.0.0.1:4000".parse().unwrap()];
+
+    let expired = Invitation::create(&user, endpoints.clone(), TimeDelta::seconds(-1)).unwrap();
+    assert!(matches!(
+        Invitation::from_uri(&expired.to_uri().unwrap()),
+        Err(CoreError::InvitationExpired(_))
+    ));
+    assert!(matches!(
+        Invitation::create(&user, Vec::new(), TimeDelta::hours(1)),
+        Err(CoreError::InvitationWithoutEndpoints)
+    ));
+    assert!(matches!(
+        Invitation::from_uri("sremp:invite/AAAA"),
+        Err(CoreError::NotAnInvitation)
+    ));
+    assert!(matches!(
+        Invitation::from_uri("https://example.com"),
+        Err(CoreError::NotAnInvitation)
+    ));
+}
@@ -0,0 +1,136 @@
+use sremp_core::{
+    identity::{ContactId, Pairing, PairingCode, UserIdentity},
+    net::connection::HandshakeHash,
+};
+
+const HANDSHAKE_HASH: HandshakeHash = [7; 32];
+
+/// Runs a pairing between `user` and `contact`, returns whether each side accepted the other.
+fn pair(
+    (user, user_code, user_hash): (&ContactId, &str, &HandshakeHash),
+    (contact, contact_code, contact_hash): (&ContactId, &str, &HandshakeHash),
+) -> (bool, bool) {
+    let ours = Pairing::new(&user_code.parse().unwrap(), user_hash, user, contact);
+    let theirs = Pairing::new(&contact_code.parse().unwrap(), contact_hash, contact, user);
+
+    let our_keys = ours.finish(&theirs.share()).unwrap();
+    let their_keys = theirs.finish(&ours.share()).unwrap();
+    (
+        our_keys.verify(&their_keys.ours()),
+        their_keys.verify(&our_keys.ours()),
+    )
+}
+
+#[test]
+fn same_code_pairs() {
+    let alice = UserIdentity::create("alice").unwrap().id();
+    let bob = UserIdentity::create("bob").unwrap().id();
+    let code = PairingCode::generate().to_string();
+
+    assert_eq!(
+        pair(
+            (&alice, &code, &HANDSHAKE_HASH),
+            (&bob, &code, &HANDSHAKE_HASH)
+        ),
+        (true, true)
+    );
+    // either side may start, the roles follow from the ids
+    assert_eq!(
+        pair(
+            (&bob, &code, &HANDSHAKE_HASH),
+            (&alice, &code, &HANDSHAKE_HASH)
+        ),
+        (true, true)
+    );
+}
+
+#[test]
+fn different_code_does_not_pair() {
+    let alice = UserIdentity::create("alice").unwrap().id();
+    let bob = UserIdentity::create("bob").unwrap().id();
+
+    assert_eq!(
+        pair(
+            (&alice, "7-guitar-ocean", &HANDSHAKE_HASH),
+            (&bob, "7-guitar-oak", &HANDSHAKE_HASH)
+        ),
+        (false, false)
+    );
+    assert_eq!(
+        pair(
+            (&alice, "7-guitar-ocean", &HANDSHAKE_HASH),
+            (&bob, "8-guitar-ocean", &HANDSHAKE_HASH)
+        ),
+        (false, false)
+    );
+}
+
+#[test]
+fn different_connection_does_not_pair() {
+    let alice = UserIdentity::create("alice").unwrap().id();
+    let bob = UserIdentity::create("bob").unwrap().id();
+
+    // someone in the middle has a connection to each of them
+    assert_eq!(
+        pair(
+            (&alice, "7-guitar-ocean", &HANDSHAKE_HASH),
+            (&bob, "7-guitar-ocean", &[8; 32])
+        ),
+        (false, false)
+    );
+}
+
+#[test]
+fn confirmations_can_not_be_reflected() {
+    let alice = UserIdentity::create("alice").unwrap().id();
+    let bob = UserIdentity::create("bob").unwrap().id();
+    let code: PairingCode = "7-guitar-ocean".parse().unwrap();
+
+    let ours = Pairing::new(&code, &HANDSHAKE_HASH, &alice, &bob);
+    let theirs = Pairing::new(&code, &HANDSHAKE_HASH, &bob, &alice);
+    let our_keys = ours.finish(&theirs.share()).unwrap();
+    assert!(!our_keys.verify(&our_keys.ours()));
+
+    // both sides taking the same role do not pair either
+    let confused = Pairing::new(&code, &HANDSHAKE_HASH, &alice, &bob);
+    let our_keys = ours.finish(&confused.share()).unwrap();
+    let confused_keys = confused.finish(&ours.share()).unwrap();
+    assert!(!our_keys.verify(&confused_keys.ours()));
+    assert!(!confused_keys.verify(&our_keys.ours()));
+}
+
+#[test]
+fn invalid_shares_are_refused() {
+    let alice = UserIdentity::create("alice").unwrap().id();
+    let bob = UserIdentity::create("bob").unwrap().id();
+    let pairing = Pairing::new(
+        &"7-guitar-ocean".parse().unwrap(),
+        &HANDSHAKE_HASH,
+        &alice,
+        &bob,
+    );
+
+    // the identity point and bytes that are no point at all
+    assert!(pairing.finish(&[0; 32]).is_err());
+    assert!(pairing.finish(&[0xff; 32]).is_err());
+}
+
+#[test]
+fn codes_are_parsed_leniently() {
+    let code: PairingCode = "7 Guitar  OCEAN".parse().unwrap();
+    assert_eq!(code.to_string(), "7-guitar-ocean");
+    assert_eq!(code, "7-guitar-ocean".parse().unwrap());
+
+    for invalid in [
+        "",
+        "7-guitar",
+        "100-guitar-ocean",
+        "7-guitar-ocean-oak",
+        "7-guitar-xyzzy",
+    ] {
+        assert!(invalid.parse::<PairingCode>().is_err(), "{invalid}");
+    }
+
+    let generated = PairingCode::generate();
+    assert_eq!(generated, generated.to_string().parse().unwrap());
+}
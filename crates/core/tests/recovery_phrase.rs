@@ -0,0 +1,45 @@
+use std::str::FromStr;
+
+use sremp_core::{
+    error::CoreError,
+    identity::{RECOVERY_PHRASE_WORDS, RecoveryPhrase, UserIdentity},
+};
+
+#[test]
+fn recover_recreates_identity_key() {
+    let (user, phrase) = UserIdentity::create_recoverable("alice").unwrap();
+    assert_eq!(phrase.words().count(), RECOVERY_PHRASE_WORDS);
+
+    // as typed in by a user from paper
+    let typed = phrase
+        .words()
+        .map(str::to_uppercase)
+        .collect::<Vec<_>>()
+        .join("  \n");
+    let parsed = RecoveryPhrase::from_str(&typed).unwrap();
+    assert_eq!(parsed, phrase);
+
+    let recovered = UserIdentity::recover(&parsed, "alice").unwrap();
+    assert_eq!(recovered.identity_key, user.identity_key);
+    assert_eq!(recovered.id(), user.id());
+    assert_ne!(recovered.noise_key(), user.noise_key());
+    recovered.check_key_pairs().unwrap();
+    user.identity.check_update(&recovered.identity).unwrap();
+}
+
+#[test]
+fn phrase_with_wrong_checksum_or_length_is_rejected() {
+    // all zero entropy, the valid phrase ends with "art" instead
+    let mut words = vec!["abandon"; RECOVERY_PHRASE_WORDS];
+    assert!(matches!(
+        RecoveryPhrase::from_str(&words.join(" ")),
+        Err(CoreError::InvalidRecoveryPhrase(_))
+    ));
+    *words.last_mut().unwrap() = "art";
+    RecoveryPhrase::from_str(&words.join(" ")).unwrap();
+
+    assert!(matches!(
+        RecoveryPhrase::from_str(&words[1..].join(" ")),
+        Err(CoreError::RecoveryPhraseLength { received: 23, .. })
+    ));
+}
@@ -0,0 +1,46 @@
+use sremp_core::identity::{Identity, UserIdentity, username_skeleton};
+
+#[test]
+fn usernames_are_normalized_and_invisible_characters_rejected() {
+    // e with a combining acute accent is composed to é
+    let user = UserIdentity::create("  Ren\u{0065}\u{0301}e   Durand ").unwrap();
+    assert_eq!(user.identity.username(), "Ren\u{00e9}e Durand");
+    user.identity.verify().unwrap();
+
+    for bad in [
+        "",
+        "ali\u{200B}ce",
+        "\u{202E}ecila",
+        "alice\u{2066}",
+        "a\u{0301}\u{0301}\u{0301}\u{0301}\u{0301}",
+        &"a".repeat(41),
+    ] {
+        assert!(UserIdentity::create(bad).is_err(), "{bad:?} was accepted");
+    }
+    // whitespace is collapsed when creating, but not accepted as it is
+    assert_eq!(
+        UserIdentity::create("alice\n\tbob")
+            .unwrap()
+            .identity
+            .username(),
+        "alice bob"
+    );
+    assert!(Identity::validate_username("alice\nbob").is_err());
+    assert!(Identity::validate_username(" alice").is_err());
+    assert!(Identity::validate_username("Ren\u{0065}\u{0301}e").is_err());
+}
+
+#[test]
+fn skeleton_matches_lookalike_names() {
+    let alice = username_skeleton("alice");
+    // cyrillic а, uppercase I for l, fullwidth letters
+    assert_eq!(username_skeleton("\u{0430}lice"), alice);
+    assert_eq!(username_skeleton("AIice"), alice);
+    assert_eq!(
+        username_skeleton("\u{FF41}\u{FF4C}\u{FF49}\u{FF43}\u{FF45}"),
+        alice
+    );
+    assert_eq!(username_skeleton("ali-ce"), alice);
+    assert_eq!(username_skeleton("modern"), username_skeleton("rnodern"));
+    assert_ne!(username_skeleton("alice"), username_skeleton("alina"));
+}
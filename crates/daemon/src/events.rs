@@ -0,0 +1,195 @@
+//! How [`UiEvent`]s are written as notifications.
+
+use serde_json::{Map, Value, json};
+use sremp_client::domain::{
+    UiEvent, chats::Chats, known_identities::KnownIdentities, outbox::DeliveryState,
+    tofu::PeerStatus,
+};
+use sremp_core::{
+    chat::messages::Message,
+    identity::{ContactId, ContactIdentity, Identity, Trust},
+    net::connection::stats::ConnectionStatsSnapshot,
+};
+
+use crate::rpc::notification;
+
+pub(crate) fn event_notification(event: &UiEvent) -> Value {
+    let (method, params) = match event {
+        UiEvent::ConnectionEstablished(remote, cid, status) => {
+            let mut params = json!({"address": remote.to_string(), "contact": cid.to_string()});
+            params["status"] = peer_status(status);
+            ("connection_established", params)
+        }
+        UiEvent::ConnectionLost(remote, cid) => (
+            "connection_lost",
+            json!({"address": remote.to_string(), "contact": cid.to_string()}),
+        ),
+        UiEvent::IncomingMessage(remote, cid, msg) => (
+            "incoming_message",
+            json!({
+                "address": remote.to_string(),
+                "contact": cid.to_string(),
+                "message": message(msg),
+            }),
+        ),
+        UiEvent::DeliveryStateChanged(cid, msg, state) => (
+            "delivery_state_changed",
+            json!({
+                "contact": cid.to_string(),
+                "message": message(msg),
+                "state": delivery_state(*state),
+            }),
+        ),
+        UiEvent::ContactTyping(cid, typing) => (
+            "contact_typing",
+            json!({"contact": cid.to_string(), "typing": typing}),
+        ),
+        UiEvent::VerificationStarted(cid, sas) => (
+            "verification_started",
+            json!({"contact": cid.to_string(), "words": sas.words()}),
+        ),
+        UiEvent::VerificationFinished(cid, trust) => (
+            "verification_finished",
+            json!({"contact": cid.to_string(), "trust": trust_name(*trust)}),
+        ),
+        UiEvent::VerificationCancelled(cid) => (
+            "verification_cancelled",
+            json!({"contact": cid.to_string()}),
+        ),
+        UiEvent::PairingRequested(cid) => {
+            ("pairing_requested", json!({"contact": cid.to_string()}))
+        }
+        UiEvent::PairingFinished(cid, success) => (
+            "pairing_finished",
+            json!({"contact": cid.to_string(), "success": success}),
+        ),
+        UiEvent::PairingCancelled(cid) => {
+            ("pairing_cancelled", json!({"contact": cid.to_string()}))
+        }
+        UiEvent::ConnectionReset(remote) => {
+            ("connection_reset", json!({"address": remote.to_string()}))
+        }
+        UiEvent::ConnectionFailed(remote, reason) => (
+            "connection_failed",
+            json!({"address": remote.to_string(), "reason": reason}),
+        ),
+        UiEvent::ListenerStarted(addr) => {
+            ("listener_started", json!({"address": addr.to_string()}))
+        }
+        UiEvent::ListenerStopped => ("listener_stopped", json!({})),
+        UiEvent::IdentitySet(user) => (
+            "identity_set",
+            json!({"identity": user.as_ref().map(|user| identity(&user.identity))}),
+        ),
+        UiEvent::LoadedChats(chats) => ("loaded_chats", json!({"chats": chat_map(chats)})),
+        UiEvent::SetKnownIdentities(contacts) => (
+            "known_identities",
+            json!({"contacts": contact_list(contacts)}),
+        ),
+        UiEvent::ConnectionStats(stats) => (
+            "connection_stats",
+            json!({"connections": stats.iter().map(connection_stats).collect::<Vec<_>>()}),
+        ),
+        UiEvent::StatementRejected(statement, reason) => (
+            "statement_rejected",
+            json!({"subject": statement.subject().to_string(), "reason": reason}),
+        ),
+    };
+    notification(method, params)
+}
+
+/// The public part of an identity, the private keys never leave the daemon
+fn identity(identity: &Identity) -> Value {
+    json!({
+        "id": identity.id().to_string(),
+        "username": identity.username(),
+        "machine_account": identity.flags().is_machine_account,
+        "devices": identity.devices().iter().map(|d| d.name()).collect::<Vec<_>>(),
+    })
+}
+
+fn contact(contact: &ContactIdentity) -> Value {
+    let mut value = identity(&contact.identity);
+    value["trust"] = trust_name(contact.trust).into();
+    value["first_seen"] = contact.first_seen.to_rfc3339().into();
+    value["last_seen"] = contact.last_seen.to_rfc3339().into();
+    value["successor"] = contact.successor.as_ref().map(ContactId::to_string).into();
+    value
+}
+
+fn contact_list(contacts: &KnownIdentities) -> Vec<Value> {
+    let mut list: Vec<_> = contacts.values().collect();
+    list.sort_by_key(|c| c.id());
+    list.into_iter().map(|c| contact(c)).collect()
+}
+
+pub(crate) fn message(msg: &Message) -> Value {
+    json!({
+        "author": msg.meta.author_id.to_string(),
+        "time": msg.meta.time_received.to_rfc3339(),
+        "text": msg.text,
+    })
+}
+
+fn chat_map(chats: &Chats) -> Map<String, Value> {
+    chats
+        .iter()
+        .map(|(cid, chat)| {
+            (
+                cid.to_string(),
+                chat.messages().iter().map(|m| message(m)).collect(),
+            )
+        })
+        .collect()
+}
+
+fn peer_status(status: &PeerStatus) -> Value {
+    match status {
+        PeerStatus::Trusted => "trusted".into(),
+        PeerStatus::Rejected => "rejected".into(),
+        PeerStatus::New => "new".into(),
+        PeerStatus::KeyChanged {
+            address_of,
+            name_of,
+        } => json!({
+            "key_changed": {
+                "address_of": address_of.as_ref().map(ContactId::to_string),
+                "name_of": name_of.iter().map(ContactId::to_string).collect::<Vec<_>>(),
+            }
+        }),
+    }
+}
+
+fn delivery_state(state: DeliveryState) -> &'static str {
+    match state {
+        DeliveryState::Pending => "pending",
+        DeliveryState::Sent => "sent",
+        DeliveryState::Failed => "failed",
+    }
+}
+
+pub(crate) fn trust_name(trust: Trust) -> &'static str {
+    match trust {
+        Trust::Unknown => "unknown",
+        Trust::Trusted => "trusted",
+        Trust::Rejected => "rejected",
+        Trust::Revoked => "revoked",
+        Trust::Verified => "verified",
+        Trust::Suspicious => "suspicious",
+    }
+}
+
+fn connection_stats(stats: &ConnectionStatsSnapshot) -> Value {
+    json!({
+        "address": stats.remote.to_string(),
+        "contact": stats.contact.to_string(),
+        "established": stats.established.to_rfc3339(),
+        "handshake_ms": stats.handshake_duration.as_millis(),
+        "bytes_in": stats.bytes_in,
+        "bytes_out": stats.bytes_out,
+        "frames_in": stats.frames_in,
+        "frames_out": stats.frames_out,
+        "rtt_ms": stats.rtt.map(|rtt| rtt.as_millis()),
+        "idle_ms": stats.idle.as_millis(),
+    })
+}
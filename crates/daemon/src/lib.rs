@@ -0,0 +1,80 @@
+//! Runs the network and client domains of SREMP as a background service, so that scripts and
+//! several frontends can share one identity and one set of connections.
+//!
+//! # Protocol
+//!
+//! Frontends connect to a Unix socket that only the user running the daemon can use. Both sides
+//! send [JSON-RPC 2.0](https://www.jsonrpc.org/specification) objects, one per line.
+//!
+//! Each [`UiCommand`](sremp_client::domain::UiCommand) is a method with named parameters, for
+//! example:
+//!
+//! ```text
+//! {"jsonrpc":"2.0","id":1,"method":"connect","params":{"address":"10.0.0.2:4000"}}
+//! {"jsonrpc":"2.0","id":1,"result":null}
+//! ```
+//!
+//! A result only means that the command was accepted. What comes of it is sent as a notification
+//! for the matching [`UiEvent`](sremp_client::domain::UiEvent), to every attached frontend:
+//!
+//! ```text
+//! {"jsonrpc":"2.0","method":"connection_failed","params":{"address":"10.0.0.2:4000","reason":"..."}}
+//! ```
+//!
+//! When a frontend attaches, it first receives notifications for the current state: the
+//! identity, the known identities, the chats, the listener and the open connections.
+//!
+//! Identities are written as the 64 hexadecimal characters of their key, times as RFC 3339.
+//!
+//! # Methods
+//!
+//! | method                        | parameters                   |
+//! | ----------------------------- | ---------------------------- |
+//! | `create_identity`             | `username`                   |
+//! | `create_recoverable_identity` | `username`                   |
+//! | `recover_identity`            | `username`, `phrase`         |
+//! | `import_identity`             | `path`, `passphrase`         |
+//! | `clear_identity`              |                              |
+//! | `create_successor`            |                              |
+//! | `revoke_identity`             | `reason`                     |
+//! | `add_device`                  | `name`, `path`, `passphrase` |
+//! | `remove_device`               | `name`                       |
+//! | `send_message`                | `contact`, `text`            |
+//! | `start_chat`                  | `contact`                    |
+//! | `trust_contact`               | `contact`, `trust`           |
+//! | `start_verification`          | `contact`                    |
+//! | `confirm_verification`        | `contact`, `matches`         |
+//! | `cancel_verification`         | `contact`                    |
+//! | `start_pairing`               | `contact`, `code`            |
+//! | `cancel_pairing`              | `contact`                    |
+//! | `retry_messages`              | `contact`                    |
+//! | `publish_statement`           | `statement`                  |
+//! | `endorse`                     | `contact`, `endorse`         |
+//! | `start_listener`              | `address`                    |
+//! | `stop_listener`               |                              |
+//! | `connect`                     | `address`                    |
+//! | `accept_invitation`           | `invitation`                 |
+//! | `disconnect`                  | `address`                    |
+//! | `query_connection_stats`      |                              |
+//!
+//! `trust` is `trusted`, `rejected` or `unknown`, the other levels are the outcome of a
+//! verification. `invitation` is the URI of an
+//! [`Invitation`](sremp_core::identity::invitation::Invitation). `statement` is an
+//! [`IdentityStatement`](sremp_core::identity::IdentityStatement), one that does not verify is
+//! refused as invalid parameter. `create_successor` publishes a
+//! [`Succession`](sremp_core::identity::Succession) to the contacts and then switches to the new
+//! identity, `revoke_identity` publishes a [`Revocation`](sremp_core::identity::Revocation) of
+//! the identity. `add_device` saves the identity of the new device to `path`, encrypted like an
+//! exported identity, so it can be imported there.
+//!
+//! `send_message` answers with the message it has sent, `create_recoverable_identity` with the
+//! `phrase` that restores the identity, all others with `null`.
+#![cfg(unix)]
+
+mod events;
+mod methods;
+mod rpc;
+mod server;
+mod state;
+
+pub use server::{Daemon, bind, default_socket_path};
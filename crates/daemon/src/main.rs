@@ -0,0 +1,149 @@
+//! Runs SREMP in the background, see the `sremp_daemon` library for the protocol.
+
+#[cfg(unix)]
+use std::path::PathBuf;
+
+const USAGE: &str = "Usage: sremp-daemon [--socket <path>] [--state <path>]
+
+  --socket <path>   where frontends connect, by default in $XDG_RUNTIME_DIR/sremp
+  --state <path>    where contacts, chats and unsent messages are kept across restarts
+  --help            show this help";
+
+#[cfg(unix)]
+fn main() -> std::process::ExitCode {
+    use sremp_client::domain::{ClientDomain, UI_COMMAND_CAPACITY, UI_EVENT_BULK_CAPACITY};
+    use sremp_core::domain::{
+        NET_COMMAND_CAPACITY, NET_EVENT_BULK_CAPACITY, NetworkDomain, priority::priority_channel,
+    };
+    use sremp_daemon::Daemon;
+
+    let mut socket: Option<PathBuf> = None;
+    let mut state: Option<PathBuf> = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--socket" => match args.next() {
+                Some(path) => socket = Some(path.into()),
+                None => {
+                    eprintln!("--socket needs a path\n\n{USAGE}");
+                    return std::process::ExitCode::FAILURE;
+                }
+            },
+            "--state" => match args.next() {
+                Some(path) => state = Some(path.into()),
+                None => {
+                    eprintln!("--state needs a path\n\n{USAGE}");
+                    return std::process::ExitCode::FAILURE;
+                }
+            },
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                return std::process::ExitCode::SUCCESS;
+            }
+            other => {
+                eprintln!("Unknown argument: {other}\n\n{USAGE}");
+                return std::process::ExitCode::FAILURE;
+            }
+        }
+    }
+    let socket = socket.unwrap_or_else(sremp_daemon::default_socket_path);
+    setup_logging();
+
+    let mut rt = tokio::runtime::Runtime::new().expect("could not create tokio runtime");
+
+    let (net_command_tx, net_command_rx) = async_channel::bounded(NET_COMMAND_CAPACITY);
+    let (net_event_tx, net_event_rx) = priority_channel(NET_EVENT_BULK_CAPACITY);
+
+    let (ui_command_tx, ui_command_rx) = async_channel::bounded(UI_COMMAND_CAPACITY);
+    let (ui_event_tx, ui_event_rx) = priority_channel(UI_EVENT_BULK_CAPACITY);
+
+    NetworkDomain::new()
+        .start(net_command_rx, net_event_tx, &mut rt)
+        .expect("could not start network domain");
+    let client = match state {
+        Some(path) => match ClientDomain::load(&path) {
+            Ok(client) => client,
+            Err(e) => {
+                log::error!("Could not load the state from {}: {e}", path.display());
+                return std::process::ExitCode::FAILURE;
+            }
+        },
+        None => ClientDomain::new(),
+    };
+    let client = client
+        .start(
+            net_command_tx,
+            net_event_rx,
+            ui_command_rx,
+            ui_event_tx,
+            &mut rt,
+        )
+        .expect("could not start application domain");
+
+    let events = ui_event_rx.clone();
+    let daemon = Daemon::new(ui_command_tx.clone(), ui_event_rx);
+    let result = rt.block_on(async {
+        let listener = sremp_daemon::bind(&socket)?;
+        log::info!("Frontends can connect to {}", socket.display());
+        let result = tokio::select! {
+            result = daemon.serve(listener) => result,
+            result = shutdown_signal() => result,
+        };
+        std::fs::remove_file(&socket)?;
+        // the client domain saves its state once it sees that no more commands come
+        ui_command_tx.close();
+        wait_for_client(client, events).await;
+        result
+    });
+    match result {
+        Ok(()) => std::process::ExitCode::SUCCESS,
+        Err(e) => {
+            log::error!("{e}");
+            std::process::ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn main() -> std::process::ExitCode {
+    eprintln!("sremp-daemon needs Unix sockets, which this platform does not have\n\n{USAGE}");
+    std::process::ExitCode::FAILURE
+}
+
+/// Waits for Ctrl-C or SIGTERM
+#[cfg(unix)]
+async fn shutdown_signal() -> std::io::Result<()> {
+    let mut terminate = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => result?,
+        _ = terminate.recv() => (),
+    }
+    log::info!("Shutting down");
+    Ok(())
+}
+
+/// Waits until the client domain has stopped. Its last events are taken meanwhile, so it does not
+/// wait for frontends that are gone.
+#[cfg(unix)]
+async fn wait_for_client(
+    client: tokio::task::JoinHandle<sremp_client::error::ClientResult<()>>,
+    events: sremp_core::domain::priority::PriorityReceiver<sremp_client::domain::UiEvent>,
+) {
+    let drain = async { while events.recv().await.is_ok() {} };
+    tokio::select! {
+        biased;
+        stopped = client => {
+            if let Ok(Err(e)) = stopped {
+                log::error!("The client domain has failed: {e}");
+            }
+        }
+        () = drain => (),
+    }
+}
+
+#[cfg(unix)]
+fn setup_logging() {
+    let mut l = env_logger::builder();
+    l.filter_level(log::LevelFilter::Info);
+    l.parse_default_env().init();
+}
@@ -0,0 +1,194 @@
+//! The methods frontends can call, one for each [`UiCommand`], see the [crate] documentation.
+
+use std::sync::{Arc, Mutex};
+
+use serde_json::{Value, json};
+use sremp_client::domain::UiCommand;
+use sremp_core::{
+    chat::messages::{Message, SharedMessage},
+    identity::{IdentityStatement, RecoveryPhrase, Trust, UserIdentity, invitation::Invitation},
+};
+
+use crate::{
+    events::message,
+    rpc::{INTERNAL_ERROR, METHOD_NOT_FOUND, Params, RpcError},
+    state::State,
+};
+
+/// The [`UiCommand`]s a request asks for, in the order to send them, and the result to answer it
+/// with
+pub(crate) async fn commands(
+    method: &str,
+    params: &Params,
+    state: &Mutex<State>,
+) -> Result<(Vec<UiCommand>, Value), RpcError> {
+    let command = match method {
+        "create_identity" => {
+            let user = UserIdentity::create(params.string("username")?)
+                .map_err(RpcError::invalid_params)?;
+            UiCommand::SetIdentity(Some(Arc::new(user)))
+        }
+        "create_recoverable_identity" => {
+            let (user, phrase) = UserIdentity::create_recoverable(params.string("username")?)
+                .map_err(RpcError::invalid_params)?;
+            return Ok((
+                vec![UiCommand::SetIdentity(Some(Arc::new(user)))],
+                json!({"phrase": phrase.to_string()}),
+            ));
+        }
+        "recover_identity" => {
+            let phrase: RecoveryPhrase = params
+                .string("phrase")?
+                .parse()
+                .map_err(RpcError::invalid_params)?;
+            let user = UserIdentity::recover(&phrase, params.string("username")?)
+                .map_err(RpcError::invalid_params)?;
+            UiCommand::SetIdentity(Some(Arc::new(user)))
+        }
+        "import_identity" => {
+            let path = params.string("path")?.to_owned();
+            let passphrase = params.string("passphrase")?.to_owned();
+            // reading the file and deriving the key with Argon2id would block the other requests
+            let user = tokio::task::spawn_blocking(move || {
+                let data = std::fs::read(path).map_err(RpcError::invalid_params)?;
+                UserIdentity::import(&data, &passphrase).map_err(RpcError::invalid_params)
+            })
+            .await
+            .map_err(|e| RpcError::new(INTERNAL_ERROR, e))??;
+            UiCommand::SetIdentity(Some(Arc::new(user)))
+        }
+        "clear_identity" => UiCommand::SetIdentity(None),
+        "create_successor" => {
+            let (successor, succession) = current_user(state)?
+                .create_successor()
+                .map_err(RpcError::invalid_params)?;
+            // contacts must learn of the succession before the successor connects
+            return Ok((
+                vec![
+                    UiCommand::PublishStatement(Arc::new(IdentityStatement::Succession(
+                        succession,
+                    ))),
+                    UiCommand::SetIdentity(Some(Arc::new(successor))),
+                ],
+                Value::Null,
+            ));
+        }
+        "revoke_identity" => {
+            let revocation = current_user(state)?
+                .revoke(params.string("reason")?)
+                .map_err(RpcError::invalid_params)?;
+            UiCommand::PublishStatement(Arc::new(IdentityStatement::Revocation(revocation)))
+        }
+        "add_device" => {
+            let mut user = current_user(state)?;
+            let name = params.string("name")?.to_owned();
+            if user.identity.devices().iter().any(|d| d.name() == name) {
+                return Err(RpcError::invalid_params(format!(
+                    "there is a device named {name} already"
+                )));
+            }
+            let path = params.string("path")?.to_owned();
+            let passphrase = params.string("passphrase")?.to_owned();
+            // encrypting the identity of the device with Argon2id would block the other requests
+            let user = tokio::task::spawn_blocking(move || {
+                let device = user.new_device(&name).map_err(RpcError::invalid_params)?;
+                let data = device
+                    .export(&passphrase)
+                    .map_err(RpcError::invalid_params)?;
+                std::fs::write(path, data).map_err(RpcError::invalid_params)?;
+                Ok::<_, RpcError>(user)
+            })
+            .await
+            .map_err(|e| RpcError::new(INTERNAL_ERROR, e))??;
+            UiCommand::SetIdentity(Some(Arc::new(user)))
+        }
+        "remove_device" => {
+            let mut user = current_user(state)?;
+            let name = params.string("name")?;
+            let Some(noise_key) = user
+                .identity
+                .devices()
+                .iter()
+                .find(|d| d.name() == name)
+                .map(|d| d.noise_key())
+            else {
+                return Err(RpcError::invalid_params(format!(
+                    "there is no device named {name}"
+                )));
+            };
+            let mut key = user.identity_key.clone();
+            user.identity
+                .remove_device(&noise_key, &mut key)
+                .map_err(RpcError::invalid_params)?;
+            UiCommand::SetIdentity(Some(Arc::new(user)))
+        }
+        "send_message" => {
+            let cid = params.parse("contact")?;
+            let text = params.string("text")?;
+            let mut state = state.lock().expect("state lock is poisoned");
+            let Some(user) = &state.user else {
+                return Err(RpcError::invalid_params("there is no identity yet"));
+            };
+            let msg: SharedMessage =
+                Message::new(text, chrono::Utc::now(), user.identity.id()).into();
+            let result = message(&msg);
+            state.add_message(&cid, msg.clone());
+            return Ok((vec![UiCommand::SendMessage(cid, msg)], result));
+        }
+        "start_chat" => UiCommand::StartChat(params.parse("contact")?),
+        "trust_contact" => {
+            let trust = match params.string("trust")? {
+                "trusted" => Trust::Trusted,
+                "rejected" => Trust::Rejected,
+                "unknown" => Trust::Unknown,
+                other => {
+                    return Err(RpcError::invalid_params(format!(
+                        "\"{other}\" is not one of trusted, rejected or unknown"
+                    )));
+                }
+            };
+            UiCommand::TrustContact(params.parse("contact")?, trust)
+        }
+        "start_verification" => UiCommand::StartVerification(params.parse("contact")?),
+        "confirm_verification" => {
+            UiCommand::ConfirmVerification(params.parse("contact")?, params.bool("matches")?)
+        }
+        "cancel_verification" => UiCommand::CancelVerification(params.parse("contact")?),
+        "start_pairing" => UiCommand::StartPairing(params.parse("contact")?, params.parse("code")?),
+        "cancel_pairing" => UiCommand::CancelPairing(params.parse("contact")?),
+        "retry_messages" => UiCommand::RetryMessages(params.parse("contact")?),
+        "publish_statement" => {
+            let statement: IdentityStatement =
+                serde_json::from_value(params.value("statement")?.clone())
+                    .map_err(RpcError::invalid_params)?;
+            statement.verify().map_err(RpcError::invalid_params)?;
+            UiCommand::PublishStatement(Arc::new(statement))
+        }
+        "endorse" => UiCommand::Endorse(params.parse("contact")?, params.bool("endorse")?),
+        "start_listener" => UiCommand::StartListener(params.parse("address")?),
+        "stop_listener" => UiCommand::StopListener,
+        "connect" => UiCommand::Connect(params.parse("address")?),
+        "accept_invitation" => {
+            let invitation = Invitation::from_uri(params.string("invitation")?)
+                .map_err(RpcError::invalid_params)?;
+            UiCommand::AcceptInvitation(Arc::new(invitation))
+        }
+        "disconnect" => UiCommand::Disconnect(params.parse("address")?),
+        "query_connection_stats" => UiCommand::QueryConnectionStats,
+        other => {
+            return Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("there is no method \"{other}\""),
+            ));
+        }
+    };
+    Ok((vec![command], Value::Null))
+}
+
+/// A copy of the identity of the user, to change it and set it again
+fn current_user(state: &Mutex<State>) -> Result<UserIdentity, RpcError> {
+    match &state.lock().expect("state lock is poisoned").user {
+        Some(user) => Ok((**user).clone()),
+        None => Err(RpcError::invalid_params("there is no identity yet")),
+    }
+}
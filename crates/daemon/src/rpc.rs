@@ -0,0 +1,139 @@
+//! The parts of JSON-RPC 2.0 the daemon needs.
+
+use std::{fmt::Display, str::FromStr};
+
+use serde_json::{Map, Value, json};
+
+pub(crate) const PARSE_ERROR: i64 = -32700;
+pub(crate) const INVALID_REQUEST: i64 = -32600;
+pub(crate) const METHOD_NOT_FOUND: i64 = -32601;
+pub(crate) const INVALID_PARAMS: i64 = -32602;
+pub(crate) const INTERNAL_ERROR: i64 = -32603;
+/// The client domain has stopped and can not take commands anymore
+pub(crate) const DOMAIN_STOPPED: i64 = -32000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RpcError {
+    pub(crate) code: i64,
+    pub(crate) message: String,
+}
+
+impl RpcError {
+    pub(crate) fn new(code: i64, message: impl Display) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+        }
+    }
+
+    pub(crate) fn invalid_params(message: impl Display) -> Self {
+        Self::new(INVALID_PARAMS, message)
+    }
+}
+
+/// A request, or a notification if it has no `id`
+#[derive(Debug, Clone)]
+pub(crate) struct Request {
+    pub(crate) id: Option<Value>,
+    pub(crate) method: String,
+    pub(crate) params: Params,
+}
+
+impl Request {
+    /// Parses one line. On error, also returns the `id` of the request if it could be read, so
+    /// the error can still be answered.
+    pub(crate) fn parse(line: &str) -> Result<Self, (Value, RpcError)> {
+        let value: Value =
+            serde_json::from_str(line).map_err(|e| (Value::Null, RpcError::new(PARSE_ERROR, e)))?;
+        let Value::Object(mut object) = value else {
+            return Err((
+                Value::Null,
+                RpcError::new(INVALID_REQUEST, "a request must be an object"),
+            ));
+        };
+        let id = object.remove("id");
+        let error_id = id.clone().unwrap_or(Value::Null);
+        if object.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+            return Err((
+                error_id,
+                RpcError::new(INVALID_REQUEST, "only JSON-RPC 2.0 is supported"),
+            ));
+        }
+        let Some(Value::String(method)) = object.remove("method") else {
+            return Err((
+                error_id,
+                RpcError::new(INVALID_REQUEST, "the method must be a string"),
+            ));
+        };
+        let params = match object.remove("params") {
+            None | Some(Value::Null) => Map::new(),
+            Some(Value::Object(params)) => params,
+            Some(_) => {
+                return Err((
+                    error_id,
+                    RpcError::invalid_params("the parameters must be named"),
+                ));
+            }
+        };
+        Ok(Self {
+            id,
+            method,
+            params: Params(params),
+        })
+    }
+}
+
+/// Named parameters of a [`Request`]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Params(Map<String, Value>);
+
+impl Params {
+    pub(crate) fn string(&self, name: &str) -> Result<&str, RpcError> {
+        match self.0.get(name) {
+            Some(Value::String(s)) => Ok(s),
+            Some(_) => Err(RpcError::invalid_params(format!(
+                "\"{name}\" must be a string"
+            ))),
+            None => Err(RpcError::invalid_params(format!("\"{name}\" is missing"))),
+        }
+    }
+
+    pub(crate) fn bool(&self, name: &str) -> Result<bool, RpcError> {
+        self.0
+            .get(name)
+            .and_then(Value::as_bool)
+            .ok_or_else(|| RpcError::invalid_params(format!("\"{name}\" must be true or false")))
+    }
+
+    /// A string parameter parsed into `T`, like an address or an identity
+    pub(crate) fn parse<T>(&self, name: &str) -> Result<T, RpcError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.string(name)?
+            .parse()
+            .map_err(|e| RpcError::invalid_params(format!("\"{name}\" is invalid: {e}")))
+    }
+
+    pub(crate) fn value(&self, name: &str) -> Result<&Value, RpcError> {
+        self.0
+            .get(name)
+            .ok_or_else(|| RpcError::invalid_params(format!("\"{name}\" is missing")))
+    }
+}
+
+pub(crate) fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        Err(error) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": {"code": error.code, "message": error.message},
+        }),
+    }
+}
+
+pub(crate) fn notification(method: &str, params: Value) -> Value {
+    json!({"jsonrpc": "2.0", "method": method, "params": params})
+}
@@ -0,0 +1,276 @@
+use std::{
+    io,
+    os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use async_channel::Sender;
+use sremp_client::domain::{UiCommand, UiEvent};
+use sremp_core::domain::priority::PriorityReceiver;
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+    sync::{broadcast, mpsc},
+};
+
+use crate::{
+    events::event_notification,
+    methods,
+    rpc::{self, DOMAIN_STOPPED, Request, RpcError},
+    state::State,
+};
+
+/// How many notifications may wait for a slow frontend before it misses some
+const NOTIFICATION_BUFFER: usize = 1024;
+/// Longest request a frontend may send, a frontend that sends a longer one is detached
+const MAX_LINE_BYTES: usize = 1024 * 1024;
+
+/// Serves frontends on a Unix socket, see the [crate] documentation for the protocol.
+#[derive(Debug)]
+pub struct Daemon {
+    commands: Sender<UiCommand>,
+    events: PriorityReceiver<UiEvent>,
+}
+
+/// What the connections to the frontends share
+#[derive(Debug)]
+struct Shared {
+    commands: Sender<UiCommand>,
+    state: Mutex<State>,
+    notifications: broadcast::Sender<Arc<str>>,
+}
+
+impl Daemon {
+    /// Creates a daemon for a [`ClientDomain`](sremp_client::domain::ClientDomain) that was
+    /// started with the other ends of these channels.
+    pub fn new(commands: Sender<UiCommand>, events: PriorityReceiver<UiEvent>) -> Self {
+        Self { commands, events }
+    }
+
+    /// Accepts frontends until the client domain stops.
+    pub async fn serve(self, listener: UnixListener) -> io::Result<()> {
+        let shared = Arc::new(Shared {
+            commands: self.commands,
+            state: Mutex::new(State::default()),
+            notifications: broadcast::channel(NOTIFICATION_BUFFER).0,
+        });
+        let mut pump = tokio::spawn(pump_events(self.events, shared.clone()));
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => {
+                        tokio::spawn(attach(stream, shared.clone()));
+                    }
+                    Err(e) => log::warn!("Could not accept a frontend: {e}"),
+                },
+                _ = &mut pump => {
+                    log::info!("The client domain has stopped");
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// The default place of the socket, in `$XDG_RUNTIME_DIR` or else in a directory of the user
+/// in the temporary directory
+pub fn default_socket_path() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join("sremp"),
+        None => std::env::temp_dir().join(format!("sremp-{}", euid())),
+    }
+    .join("daemon.sock")
+}
+
+/// Binds the socket at `path`, so that only the user can reach it.
+///
+/// The directory of the socket is created if needed, and must not be accessible by others. A
+/// socket that is left over from a daemon that did not exit cleanly is replaced.
+pub fn bind(path: &Path) -> io::Result<UnixListener> {
+    if let Some(dir) = path.parent() {
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)?;
+        let meta = std::fs::metadata(dir)?;
+        if meta.uid() != euid() || meta.mode() & 0o077 != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!(
+                    "{} must belong to the user and not be accessible by others",
+                    dir.display()
+                ),
+            ));
+        }
+    }
+    if path.exists() {
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("a daemon is already running on {}", path.display()),
+            ));
+        }
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+fn euid() -> u32 {
+    // SAFETY: geteuid has no preconditions and can not fail
+    unsafe { libc::geteuid() }
+}
+
+fn to_line(value: &serde_json::Value) -> Arc<str> {
+    value.to_string().into()
+}
+
+/// Remembers each event and passes it on to all frontends.
+async fn pump_events(events: PriorityReceiver<UiEvent>, shared: Arc<Shared>) {
+    while let Ok(event) = events.recv().await {
+        let line = to_line(&event_notification(&event));
+        let mut state = shared.state.lock().expect("state lock is poisoned");
+        state.apply(&event);
+        // holding the lock, so that a frontend that attaches now gets either the event or the
+        // state after it, never neither or both
+        let _ = shared.notifications.send(line);
+    }
+}
+
+async fn attach(stream: UnixStream, shared: Arc<Shared>) {
+    match stream.peer_cred() {
+        Ok(cred) if cred.uid() == euid() => (),
+        Ok(cred) => {
+            log::warn!("Refusing a frontend of user {}", cred.uid());
+            return;
+        }
+        Err(e) => {
+            log::warn!("Could not check who a frontend belongs to: {e}");
+            return;
+        }
+    }
+    log::info!("A frontend has attached");
+
+    let (read, mut write) = stream.into_split();
+    let (replay, mut subscription) = {
+        let state = shared.state.lock().expect("state lock is poisoned");
+        let replay: Vec<_> = state
+            .replay()
+            .iter()
+            .map(|event| to_line(&event_notification(event)))
+            .collect();
+        (replay, shared.notifications.subscribe())
+    };
+    let (responses_tx, mut responses) = mpsc::unbounded_channel::<Arc<str>>();
+
+    let writer = tokio::spawn(async move {
+        for line in replay {
+            write_line(&mut write, &line).await?;
+        }
+        loop {
+            let line = tokio::select! {
+                response = responses.recv() => match response {
+                    Some(line) => line,
+                    None => break,
+                },
+                notification = subscription.recv() => match notification {
+                    Ok(line) => line,
+                    Err(broadcast::error::RecvError::Lagged(count)) => to_line(&rpc::notification(
+                        "events_missed",
+                        serde_json::json!({"count": count}),
+                    )),
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+            };
+            write_line(&mut write, &line).await?;
+        }
+        io::Result::Ok(())
+    });
+
+    let mut read = BufReader::new(read);
+    loop {
+        match next_line(&mut read).await {
+            Ok(Some(line)) if line.trim().is_empty() => (),
+            Ok(Some(line)) => {
+                let Some(response) = handle_line(&shared, &line).await else {
+                    continue;
+                };
+                if responses_tx.send(response).is_err() {
+                    break;
+                }
+            }
+            Ok(None) => break,
+            Err(e) => {
+                log::warn!("Could not read from a frontend: {e}");
+                break;
+            }
+        }
+    }
+    drop(responses_tx);
+    if let Ok(Err(e)) = writer.await {
+        log::debug!("Could not write to a frontend: {e}");
+    }
+    log::info!("A frontend has detached");
+}
+
+/// Reads the next line without its line break, or [`None`] at the end of the stream.
+///
+/// Fails if the line is longer than [`MAX_LINE_BYTES`] or not UTF-8.
+async fn next_line(read: &mut (impl AsyncBufRead + Unpin)) -> io::Result<Option<String>> {
+    let mut line = Vec::new();
+    let limit = u64::try_from(MAX_LINE_BYTES + 1).expect("the limit fits into u64");
+    if read.take(limit).read_until(b'\n', &mut line).await? == 0 {
+        return Ok(None);
+    }
+    if line.last() == Some(&b'\n') {
+        line.pop();
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+    } else if line.len() > MAX_LINE_BYTES {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("a request is longer than {MAX_LINE_BYTES} bytes"),
+        ));
+    }
+    String::from_utf8(line)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+async fn write_line(write: &mut (impl AsyncWriteExt + Unpin), line: &str) -> io::Result<()> {
+    write.write_all(line.as_bytes()).await?;
+    write.write_all(b"\n").await
+}
+
+/// Executes a request, and returns the response unless it was a notification
+async fn handle_line(shared: &Shared, line: &str) -> Option<Arc<str>> {
+    let request = match Request::parse(line) {
+        Ok(request) => request,
+        Err((id, error)) => return Some(to_line(&rpc::response(id, Err(error)))),
+    };
+    let result = match methods::commands(&request.method, &request.params, &shared.state).await {
+        Ok((commands, result)) => send_all(&shared.commands, commands).await.map(|()| result),
+        Err(error) => Err(error),
+    };
+    match (request.id, result) {
+        (Some(id), result) => Some(to_line(&rpc::response(id, result))),
+        (None, Err(error)) => {
+            log::debug!("Notification {} failed: {}", request.method, error.message);
+            None
+        }
+        (None, Ok(_)) => None,
+    }
+}
+
+async fn send_all(sender: &Sender<UiCommand>, commands: Vec<UiCommand>) -> Result<(), RpcError> {
+    for command in commands {
+        sender
+            .send(command)
+            .await
+            .map_err(|_| RpcError::new(DOMAIN_STOPPED, "the client domain has stopped"))?;
+    }
+    Ok(())
+}
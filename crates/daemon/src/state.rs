@@ -0,0 +1,62 @@
+use std::{collections::BTreeMap, net::SocketAddr, sync::Arc};
+
+use sremp_client::domain::{
+    UiEvent, chats::Chats, known_identities::KnownIdentities, tofu::PeerStatus,
+};
+use sremp_core::{
+    chat::messages::SharedMessage,
+    identity::{ContactId, UserIdentity},
+};
+
+/// What the daemon remembers from the [`UiEvent`]s, so it can tell a frontend that attaches
+/// later what it has missed
+#[derive(Debug, Default)]
+pub(crate) struct State {
+    pub(crate) user: Option<Arc<UserIdentity>>,
+    contacts: KnownIdentities,
+    chats: Chats,
+    listener: Option<SocketAddr>,
+    connections: BTreeMap<SocketAddr, (ContactId, PeerStatus)>,
+}
+
+impl State {
+    pub(crate) fn apply(&mut self, event: &UiEvent) {
+        match event {
+            UiEvent::IdentitySet(user) => self.user = user.clone(),
+            UiEvent::SetKnownIdentities(contacts) => self.contacts = contacts.clone(),
+            UiEvent::LoadedChats(chats) => self.chats = chats.clone(),
+            UiEvent::ListenerStarted(addr) => self.listener = Some(*addr),
+            UiEvent::ListenerStopped => self.listener = None,
+            UiEvent::ConnectionEstablished(remote, cid, status) => {
+                self.connections
+                    .insert(*remote, (cid.clone(), status.clone()));
+            }
+            UiEvent::ConnectionLost(remote, _) | UiEvent::ConnectionReset(remote) => {
+                self.connections.remove(remote);
+            }
+            UiEvent::IncomingMessage(_, cid, msg) => self.add_message(cid, msg.clone()),
+            _ => (),
+        }
+    }
+
+    /// Adds a message to a chat, the client domain only tells about incoming messages
+    pub(crate) fn add_message(&mut self, cid: &ContactId, msg: SharedMessage) {
+        self.chats.entry(cid.clone()).or_default().add_message(msg);
+    }
+
+    /// The events that bring a new frontend up to date
+    pub(crate) fn replay(&self) -> Vec<UiEvent> {
+        let mut events = vec![
+            UiEvent::IdentitySet(self.user.clone()),
+            UiEvent::SetKnownIdentities(self.contacts.clone()),
+            UiEvent::LoadedChats(self.chats.clone()),
+        ];
+        if let Some(addr) = self.listener {
+            events.push(UiEvent::ListenerStarted(addr));
+        }
+        events.extend(self.connections.iter().map(|(remote, (cid, status))| {
+            UiEvent::ConnectionEstablished(*remote, cid.clone(), status.clone())
+        }));
+        events
+    }
+}
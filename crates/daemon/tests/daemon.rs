@@ -0,0 +1,354 @@
+#![cfg(unix)]
+
+use std::{
+    net::SocketAddr,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use async_channel::Receiver;
+use serde_json::{Value, json};
+use sremp_client::domain::{UiCommand, UiEvent};
+use sremp_core::{
+    domain::priority::{PrioritySender, priority_channel},
+    identity::{IdentityStatement, UserIdentity},
+};
+use sremp_daemon::Daemon;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines},
+    net::{
+        UnixStream,
+        unix::{OwnedReadHalf, OwnedWriteHalf},
+    },
+};
+
+/// A frontend on the socket of the daemon
+struct Frontend {
+    lines: Lines<BufReader<OwnedReadHalf>>,
+    write: OwnedWriteHalf,
+}
+
+impl Frontend {
+    async fn attach(socket: &Path) -> Self {
+        let (read, write) = UnixStream::connect(socket).await.unwrap().into_split();
+        Self {
+            lines: BufReader::new(read).lines(),
+            write,
+        }
+    }
+
+    async fn call(&mut self, id: u64, method: &str, params: Value) -> Value {
+        let request = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+        self.write
+            .write_all(format!("{request}\n").as_bytes())
+            .await
+            .unwrap();
+        self.wait_for(|msg| msg["id"] == id).await
+    }
+
+    /// Skips notifications until one for `method` arrives, and returns its parameters.
+    async fn notification(&mut self, method: &str) -> Value {
+        self.wait_for(|msg| msg["method"] == method).await["params"].take()
+    }
+
+    async fn wait_for(&mut self, f: impl Fn(&Value) -> bool) -> Value {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let line = self
+                    .lines
+                    .next_line()
+                    .await
+                    .unwrap()
+                    .expect("daemon hung up");
+                let msg: Value = serde_json::from_str(&line).unwrap();
+                if f(&msg) {
+                    return msg;
+                }
+            }
+        })
+        .await
+        .expect("daemon did not answer")
+    }
+}
+
+fn socket_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("sremp-daemon-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir.join("daemon.sock")
+}
+
+fn start_daemon(socket: &Path) -> (Receiver<UiCommand>, PrioritySender<UiEvent>) {
+    let (command_tx, command_rx) = async_channel::bounded(16);
+    let (event_tx, event_rx) = priority_channel(16);
+    let listener = sremp_daemon::bind(socket).unwrap();
+    tokio::spawn(Daemon::new(command_tx, event_rx).serve(listener));
+    (command_rx, event_tx)
+}
+
+#[tokio::test]
+async fn frontends_send_commands_and_share_events() {
+    let socket = socket_path("rpc");
+    let (commands, events) = start_daemon(&socket);
+
+    let mut first = Frontend::attach(&socket).await;
+    assert_eq!(
+        first.notification("identity_set").await["identity"],
+        Value::Null
+    );
+
+    let addr: SocketAddr = "10.0.0.2:4000".parse().unwrap();
+    let response = first.call(1, "connect", json!({"address": addr})).await;
+    assert_eq!(response["result"], Value::Null);
+    assert!(matches!(commands.recv().await.unwrap(), UiCommand::Connect(a) if a == addr));
+
+    let response = first.call(2, "fly", json!({})).await;
+    assert_eq!(response["error"]["code"], -32601);
+    let response = first
+        .call(3, "start_chat", json!({"contact": "not an identity"}))
+        .await;
+    assert_eq!(response["error"]["code"], -32602);
+    let response = first
+        .call(4, "send_message", json!({"contact": "00", "text": "hi"}))
+        .await;
+    assert_eq!(response["error"]["code"], -32602);
+
+    first
+        .call(5, "create_identity", json!({"username": "alice"}))
+        .await;
+    let UiCommand::SetIdentity(Some(user)) = commands.recv().await.unwrap() else {
+        panic!("expected a new identity");
+    };
+    assert_eq!(user.username(), "alice");
+    events
+        .send(UiEvent::IdentitySet(Some(user.clone())))
+        .await
+        .unwrap();
+    events.send(UiEvent::ListenerStarted(addr)).await.unwrap();
+
+    let identity = first.notification("identity_set").await["identity"].take();
+    assert_eq!(identity["username"], "alice");
+    assert_eq!(identity["id"], user.id().to_string());
+    assert!(identity.get("identity_key").is_none());
+    first.notification("listener_started").await;
+
+    // a frontend that attaches later is told what it has missed
+    let mut second = Frontend::attach(&socket).await;
+    let identity = second.notification("identity_set").await["identity"].take();
+    assert_eq!(identity["username"], "alice");
+    let listener = second.notification("listener_started").await;
+    assert_eq!(listener["address"], addr.to_string());
+
+    let _ = std::fs::remove_dir_all(socket.parent().unwrap());
+}
+
+#[tokio::test]
+async fn identity_is_recovered_from_its_phrase() {
+    let socket = socket_path("recovery");
+    let (commands, _events) = start_daemon(&socket);
+    let mut frontend = Frontend::attach(&socket).await;
+
+    let response = frontend
+        .call(
+            1,
+            "create_recoverable_identity",
+            json!({"username": "alice"}),
+        )
+        .await;
+    let phrase = response["result"]["phrase"].as_str().unwrap().to_string();
+    let UiCommand::SetIdentity(Some(created)) = commands.recv().await.unwrap() else {
+        panic!("expected a new identity");
+    };
+
+    let response = frontend
+        .call(
+            2,
+            "recover_identity",
+            json!({"username": "alice", "phrase": "not a phrase"}),
+        )
+        .await;
+    assert_eq!(response["error"]["code"], -32602);
+    frontend
+        .call(
+            3,
+            "recover_identity",
+            json!({"username": "alice", "phrase": phrase}),
+        )
+        .await;
+    let UiCommand::SetIdentity(Some(recovered)) = commands.recv().await.unwrap() else {
+        panic!("expected the recovered identity");
+    };
+    assert_eq!(recovered.id(), created.id());
+    assert!(recovered.version() > created.version());
+
+    let _ = std::fs::remove_dir_all(socket.parent().unwrap());
+}
+
+#[tokio::test]
+async fn devices_are_added_and_removed() {
+    let socket = socket_path("devices");
+    let (commands, events) = start_daemon(&socket);
+    let mut frontend = Frontend::attach(&socket).await;
+    let file = socket.parent().unwrap().join("phone.sremp");
+
+    frontend
+        .call(1, "create_identity", json!({"username": "alice"}))
+        .await;
+    let UiCommand::SetIdentity(Some(user)) = commands.recv().await.unwrap() else {
+        panic!("expected a new identity");
+    };
+    events.send(UiEvent::IdentitySet(Some(user))).await.unwrap();
+    frontend.notification("identity_set").await;
+
+    let response = frontend
+        .call(
+            2,
+            "add_device",
+            json!({"name": "phone", "path": file, "passphrase": "correct horse"}),
+        )
+        .await;
+    assert_eq!(response["result"], Value::Null);
+    let UiCommand::SetIdentity(Some(user)) = commands.recv().await.unwrap() else {
+        panic!("expected the identity with the new device");
+    };
+    let phone = UserIdentity::import(&std::fs::read(&file).unwrap(), "correct horse").unwrap();
+    assert_eq!(phone.id(), user.id());
+    assert_eq!(phone.version(), user.version());
+    assert_eq!(user.devices()[0].name(), "phone");
+    events.send(UiEvent::IdentitySet(Some(user))).await.unwrap();
+    let identity = frontend.notification("identity_set").await["identity"].take();
+    assert_eq!(identity["devices"], json!(["phone"]));
+
+    let response = frontend
+        .call(3, "remove_device", json!({"name": "tablet"}))
+        .await;
+    assert_eq!(response["error"]["code"], -32602);
+    frontend
+        .call(4, "remove_device", json!({"name": "phone"}))
+        .await;
+    let UiCommand::SetIdentity(Some(user)) = commands.recv().await.unwrap() else {
+        panic!("expected the identity without the device");
+    };
+    assert!(user.devices().is_empty());
+
+    let _ = std::fs::remove_dir_all(socket.parent().unwrap());
+}
+
+#[tokio::test]
+async fn identity_is_succeeded_and_revoked() {
+    let socket = socket_path("succession");
+    let (commands, events) = start_daemon(&socket);
+    let mut frontend = Frontend::attach(&socket).await;
+
+    let response = frontend.call(1, "create_successor", json!({})).await;
+    assert_eq!(response["error"]["code"], -32602);
+    let alice = Arc::new(UserIdentity::create("alice").unwrap());
+    events
+        .send(UiEvent::IdentitySet(Some(alice.clone())))
+        .await
+        .unwrap();
+    frontend.notification("identity_set").await;
+
+    frontend.call(2, "create_successor", json!({})).await;
+    let UiCommand::PublishStatement(statement) = commands.recv().await.unwrap() else {
+        panic!("expected the succession to be published");
+    };
+    let IdentityStatement::Succession(succession) = &*statement else {
+        panic!("expected a succession, got {statement}");
+    };
+    succession.verify().unwrap();
+    assert_eq!(succession.predecessor(), alice.id());
+    let UiCommand::SetIdentity(Some(successor)) = commands.recv().await.unwrap() else {
+        panic!("expected the successor to be set");
+    };
+    assert_eq!(succession.successor().id(), successor.id());
+
+    let response = frontend.call(3, "revoke_identity", json!({})).await;
+    assert_eq!(response["error"]["code"], -32602);
+    frontend
+        .call(4, "revoke_identity", json!({"reason": "lost my phone"}))
+        .await;
+    assert!(matches!(
+        commands.recv().await.unwrap(),
+        UiCommand::PublishStatement(s) if matches!(
+            &*s,
+            IdentityStatement::Revocation(r) if r.revoked() == alice.id()
+                && r.reason() == "lost my phone"
+        )
+    ));
+
+    let _ = std::fs::remove_dir_all(socket.parent().unwrap());
+}
+
+#[tokio::test]
+async fn statements_that_do_not_verify_are_refused() {
+    let socket = socket_path("statement");
+    let (commands, _events) = start_daemon(&socket);
+    let mut frontend = Frontend::attach(&socket).await;
+
+    let alice = UserIdentity::create("alice").unwrap();
+    let revocation = IdentityStatement::Revocation(alice.revoke("lost my phone").unwrap());
+    let mut statement = serde_json::to_value(&revocation).unwrap();
+    statement["Revocation"]["data"]["reason"] = "changed my mind".into();
+
+    let response = frontend
+        .call(1, "publish_statement", json!({"statement": statement}))
+        .await;
+    assert_eq!(response["error"]["code"], -32602);
+
+    let statement = serde_json::to_value(&revocation).unwrap();
+    let response = frontend
+        .call(2, "publish_statement", json!({"statement": statement}))
+        .await;
+    assert_eq!(response["result"], Value::Null);
+    assert!(matches!(
+        commands.recv().await.unwrap(),
+        UiCommand::PublishStatement(s) if *s == revocation
+    ));
+
+    let _ = std::fs::remove_dir_all(socket.parent().unwrap());
+}
+
+#[tokio::test]
+async fn frontends_that_send_overlong_lines_are_detached() {
+    let socket = socket_path("overlong");
+    let (_commands, _events) = start_daemon(&socket);
+
+    let mut frontend = Frontend::attach(&socket).await;
+    frontend.notification("identity_set").await;
+    // the daemon may hang up before it has read everything
+    let _ = frontend.write.write_all(&vec![b'x'; 2 * 1024 * 1024]).await;
+
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while let Ok(Some(_)) = frontend.lines.next_line().await {}
+    })
+    .await
+    .expect("daemon did not hang up");
+
+    let _ = std::fs::remove_dir_all(socket.parent().unwrap());
+}
+
+#[tokio::test]
+async fn socket_is_private_to_the_user() {
+    let socket = socket_path("private");
+    let (_commands, _events) = start_daemon(&socket);
+    let dir = socket.parent().unwrap();
+    assert_eq!(
+        std::fs::metadata(dir).unwrap().permissions().mode() & 0o777,
+        0o700
+    );
+    assert_eq!(
+        std::fs::metadata(&socket).unwrap().permissions().mode() & 0o777,
+        0o600
+    );
+
+    let err = sremp_daemon::bind(&socket).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AddrInUse);
+
+    std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let err = sremp_daemon::bind(&dir.join("other.sock")).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+
+    let _ = std::fs::remove_dir_all(dir);
+}
@@ -29,4 +29,7 @@ pub(super) fn register_actions(app: &Application, state: UiDomainSync) {
             state_c.borrow().send_cmd(UiCommand::StopListener);
         }
     );
+    simple_action!(app, state, _app_c, state_c, A_ID_CONNECTION_STATS!(), {
+        state_c.borrow().send_cmd(UiCommand::QueryConnectionStats);
+    });
 }
@@ -1,6 +1,14 @@
 use super::ids::*;
 use super::macros::simple_action;
-use crate::{domain::UiDomainSync, gui::identity::dialog_create_identity};
+use crate::{
+    domain::UiDomainSync,
+    gui::{
+        identity::{dialog_create_identity, show_contact_identity, show_user_identity},
+        pairing::show_pairing_dialog,
+        profile::dialog_edit_profile,
+    },
+};
+use sremp_client::domain::UiCommand;
 
 use gtk::{Application, prelude::*};
 
@@ -8,4 +16,81 @@ pub(super) fn register_actions(app: &Application, state: UiDomainSync) {
     simple_action!(app, state, app_c, state_c, A_ID_IDENTITY_CREATE!(), {
         dialog_create_identity(&app_c, state_c.clone());
     });
+    simple_action!(app, state, app_c, state_c, A_ID_IDENTITY_SHOW_USER!(), {
+        let state_b = state_c.borrow();
+        let Some(user) = state_b.user_identity() else {
+            log::warn!("There is no user identity to show");
+            return;
+        };
+        show_user_identity(&app_c, &user, state_b.listen_endpoints());
+    });
+    simple_action!(app, state, app_c, state_c, A_ID_IDENTITY_EDIT_PROFILE!(), {
+        dialog_edit_profile(&app_c, state_c.clone());
+    });
+    simple_action!(app, state, app_c, state_c, A_ID_IDENTITY_SHOW_CONTACT!(), {
+        let state_b = state_c.borrow();
+        let Some(cid) = state_b.selected_chat() else {
+            log::warn!("No chat is selected, can't show its contact");
+            return;
+        };
+        let user = state_b.user_identity().map(|u| u.id());
+        show_contact_identity(&app_c, &state_b.contacts()[&cid], user.as_ref());
+    });
+    simple_action!(
+        app,
+        state,
+        _app_c,
+        state_c,
+        A_ID_IDENTITY_VERIFY_CONTACT!(),
+        {
+            let Some(cid) = state_c.borrow().selected_chat() else {
+                log::warn!("No chat is selected, can't verify its contact");
+                return;
+            };
+            state_c.borrow().send_cmd(UiCommand::StartVerification(cid));
+        }
+    );
+    simple_action!(
+        app,
+        state,
+        _app_c,
+        state_c,
+        A_ID_IDENTITY_PAIR_CONTACT!(),
+        {
+            let Some(cid) = state_c.borrow().selected_chat() else {
+                log::warn!("No chat is selected, can't pair with its contact");
+                return;
+            };
+            let contact = state_c.borrow().contacts()[&cid].clone();
+            show_pairing_dialog(state_c.clone(), contact, false);
+        }
+    );
+    simple_action!(
+        app,
+        state,
+        _app_c,
+        state_c,
+        A_ID_IDENTITY_ENDORSE_CONTACT!(),
+        {
+            let Some(cid) = state_c.borrow().selected_chat() else {
+                log::warn!("No chat is selected, can't vouch for its contact");
+                return;
+            };
+            state_c.borrow().send_cmd(UiCommand::Endorse(cid, true));
+        }
+    );
+    simple_action!(
+        app,
+        state,
+        _app_c,
+        state_c,
+        A_ID_IDENTITY_WITHDRAW_ENDORSEMENT!(),
+        {
+            let Some(cid) = state_c.borrow().selected_chat() else {
+                log::warn!("No chat is selected, can't stop vouching for its contact");
+                return;
+            };
+            state_c.borrow().send_cmd(UiCommand::Endorse(cid, false));
+        }
+    );
 }
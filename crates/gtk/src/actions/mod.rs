@@ -53,12 +53,21 @@ pub(crate) mod ids {
     aid!(A_ID_CONNECTION_LISTEN, "connection.listen");
     aid!(A_ID_CONNECTION_CONNECT, "connection.connect");
     aid!(A_ID_CONNECTION_DISCONNECT, "connection.disconnect");
+    aid!(A_ID_CONNECTION_STATS, "connection.stats");
 
     aid!(A_ID_INFO, "info");
 
     aid!(A_ID_IDENTITY_CREATE, "identity.create");
     aid!(A_ID_IDENTITY_SHOW_USER, "identity.show_user");
-    aid!(A_ID_IDENTITY_SHOW_CONTACT, "identity.show_user");
+    aid!(A_ID_IDENTITY_EDIT_PROFILE, "identity.edit_profile");
+    aid!(A_ID_IDENTITY_SHOW_CONTACT, "identity.show_contact");
+    aid!(A_ID_IDENTITY_VERIFY_CONTACT, "identity.verify_contact");
+    aid!(A_ID_IDENTITY_PAIR_CONTACT, "identity.pair_contact");
+    aid!(A_ID_IDENTITY_ENDORSE_CONTACT, "identity.endorse_contact");
+    aid!(
+        A_ID_IDENTITY_WITHDRAW_ENDORSEMENT,
+        "identity.withdraw_endorsement"
+    );
 }
 
 pub(super) fn register_actions(app: &Application, state: UiDomainSync) {
@@ -1,6 +1,7 @@
-use std::net::SocketAddr;
+use std::{net::SocketAddr, sync::Arc};
 
 use sremp_client::domain::UiCommand;
+use sremp_core::identity::invitation::Invitation;
 
 use crate::domain::UiDomain;
 
@@ -8,4 +9,8 @@ impl UiDomain {
     pub(crate) fn initiate_connection(&mut self, remote_address: SocketAddr) {
         self.send_cmd(UiCommand::Connect(remote_address));
     }
+
+    pub(crate) fn accept_invitation(&mut self, invitation: Invitation) {
+        self.send_cmd(UiCommand::AcceptInvitation(Arc::new(invitation)));
+    }
 }
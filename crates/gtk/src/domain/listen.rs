@@ -1,4 +1,7 @@
-use std::{fmt::Display, net::SocketAddr};
+use std::{
+    fmt::Display,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket},
+};
 
 use sremp_client::{domain::UiCommand, error::ClientError};
 use sremp_core::current_function;
@@ -20,6 +23,25 @@ impl UiDomain {
         self.send_cmd(UiCommand::StartListener(local_address));
         self.listen_status = ListenerStatus::Starting;
     }
+    /// Addresses others can reach the listener on, empty if it is not active.
+    ///
+    /// A listener on all interfaces is announced with the address of the interface that
+    /// has the default route.
+    pub(crate) fn listen_endpoints(&self) -> Vec<SocketAddr> {
+        let ListenerStatus::Active(addr) = self.listen_status else {
+            return Vec::new();
+        };
+        if !addr.ip().is_unspecified() {
+            return vec![addr];
+        }
+        match primary_ip(addr.is_ipv6()) {
+            Some(ip) => vec![SocketAddr::new(ip, addr.port())],
+            None => {
+                log::warn!("Could not find the local address the listener is reachable on");
+                Vec::new()
+            }
+        }
+    }
     pub(crate) fn fmt_listen_status(&self) -> String {
         let s = self.listen_status.to_string();
         log::trace!("{} -> {s:?}", current_function!());
@@ -27,6 +49,27 @@ impl UiDomain {
     }
 }
 
+/// Finds the local address of the interface with the default route.
+///
+/// Connecting an udp socket only selects the route, nothing is sent to the documentation
+/// address.
+fn primary_ip(ipv6: bool) -> Option<IpAddr> {
+    let (local, remote): (IpAddr, IpAddr) = if ipv6 {
+        (
+            Ipv6Addr::UNSPECIFIED.into(),
+            Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1).into(),
+        )
+    } else {
+        (
+            Ipv4Addr::UNSPECIFIED.into(),
+            Ipv4Addr::new(192, 0, 2, 1).into(),
+        )
+    };
+    let socket = UdpSocket::bind(SocketAddr::new(local, 0)).ok()?;
+    socket.connect(SocketAddr::new(remote, 9)).ok()?;
+    Some(socket.local_addr().ok()?.ip()).filter(|ip| !ip.is_unspecified())
+}
+
 impl Display for ListenerStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -1,11 +1,13 @@
 use std::{collections::HashMap, ops::Deref, rc::Rc, sync::Arc};
 
 use async_channel::{Receiver, Sender};
+use gtk::glib;
 use tokio::sync::RwLock;
 
 use sremp_client::domain::{UiCommand, UiEvent, chats::Chats, known_identities::KnownIdentities};
 use sremp_core::{
     chat::Chat,
+    domain::priority::PriorityReceiver,
     identity::{ContactId, UserIdentity, format_key},
 };
 
@@ -17,13 +19,17 @@ use tracked_widgets::TrackedWidgets;
 use crate::{
     RUNTIME,
     domain::listen::ListenerStatus,
-    gui::{chat::ChatView, chats::ChatList, identity::show_identity_created_success},
+    gui::{
+        chat::ChatView, chats::ChatList, identity::show_identity_created_success,
+        tofu::update_endorsements,
+    },
 };
 
 #[derive(Debug)]
 pub(crate) struct UiDomain {
+    /// Commands waiting to be handed to the client domain, see [`UiDomain::send_cmd`]
     pub(crate) command_channel: Sender<UiCommand>,
-    pub(crate) event_channel: Receiver<UiEvent>,
+    pub(crate) event_channel: PriorityReceiver<UiEvent>,
     pub(crate) listen_status: ListenerStatus,
     // actual UI stuff
     pub(crate) tracked_widgets: TrackedWidgets,
@@ -40,10 +46,12 @@ impl UiDomain {
     #[cold]
     pub(crate) fn new(
         command_channel: Sender<UiCommand>,
-        event_channel: Receiver<UiEvent>,
+        event_channel: PriorityReceiver<UiEvent>,
     ) -> Self {
+        let (pending_tx, pending_rx) = async_channel::unbounded();
+        glib::spawn_future_local(forward_commands(pending_rx, command_channel));
         Self {
-            command_channel,
+            command_channel: pending_tx,
             event_channel,
             tracked_widgets: Default::default(),
             user_identity: Default::default(),
@@ -56,13 +64,16 @@ impl UiDomain {
         UiDomainSync::new(self)
     }
 
+    /// Sends a command to the client domain.
+    ///
+    /// This never blocks the main loop. If the client domain is so far behind that its queue is
+    /// full, the command waits in a local queue and is handed over in order once there is room.
     #[inline]
     pub(crate) fn send_cmd(&self, cmd: UiCommand) {
         log::info!("Sending ui command: {cmd}");
-        self.command_channel
-            .send_blocking(cmd)
-            .expect("could not send Ui Command");
-        log::trace!("ui command sent");
+        if self.command_channel.try_send(cmd).is_err() {
+            panic!("could not send Ui Command");
+        }
     }
 
     #[inline]
@@ -79,9 +90,12 @@ impl UiDomain {
 
     #[inline]
     pub(crate) fn apply_user_identity(&mut self, iden: Option<Arc<UserIdentity>>) {
-        self.user_identity = iden.clone();
+        let previous = std::mem::replace(&mut self.user_identity, iden.clone());
 
-        if let Some(iden) = iden {
+        // NOTE: a new version of the same identity, like after editing the profile, is not new
+        if let Some(iden) =
+            iden.filter(|iden| previous.is_none_or(|previous| previous.id() != iden.id()))
+        {
             log::info!(
                 "Created new user identity for username '{}': {}",
                 iden.identity.username(),
@@ -142,6 +156,7 @@ impl UiDomain {
     pub(crate) fn set_contacts(&mut self, contacts: KnownIdentities) {
         self.chat_list_mut().set_contacts(contacts.clone());
         self.chat_view_mut().set_contacts(contacts);
+        update_endorsements(self);
     }
 
     #[inline]
@@ -155,6 +170,20 @@ impl UiDomain {
     }
 }
 
+/// Hands the commands of the user to the client domain in the order they were given, waiting
+/// while its queue is full.
+async fn forward_commands(pending: Receiver<UiCommand>, command_channel: Sender<UiCommand>) {
+    while let Ok(cmd) = pending.recv().await {
+        if command_channel.is_full() {
+            log::warn!("Client domain is behind, waiting to send ui command: {cmd}");
+        }
+        if command_channel.send(cmd).await.is_err() {
+            panic!("could not send Ui Command");
+        }
+        log::trace!("ui command sent");
+    }
+}
+
 impl UiDomainSync {
     #[must_use]
     #[inline]
@@ -1,3 +1,7 @@
+use std::collections::HashMap;
+
+use sremp_core::identity::ContactId;
+
 use crate::gui::{chat::ChatView, chats::ChatList};
 
 #[derive(Debug, Default)]
@@ -5,6 +9,13 @@ pub(crate) struct TrackedWidgets {
     lbl_listener_status: Option<gtk::Label>,
     chat_list: Option<ChatList>,
     chat_view: Option<ChatView>,
+    /// Open dialogs that show a short authentication string, closed when the verification ends
+    verification_dialogs: HashMap<ContactId, gtk::Dialog>,
+    /// Open dialogs that ask for a pairing code, closed when the pairing ends
+    pairing_dialogs: HashMap<ContactId, gtk::Dialog>,
+    /// Labels of open TOFU dialogs that show who vouches for the peer, updated when endorsements
+    /// arrive after the connection
+    tofu_endorsement_labels: HashMap<ContactId, gtk::Label>,
 }
 
 impl TrackedWidgets {
@@ -39,4 +50,32 @@ impl TrackedWidgets {
     pub(crate) fn set_chat_view(&mut self, chat_view: Option<ChatView>) {
         self.chat_view = chat_view;
     }
+
+    pub(crate) fn add_verification_dialog(&mut self, cid: ContactId, dialog: gtk::Dialog) {
+        self.verification_dialogs.insert(cid, dialog);
+    }
+
+    pub(crate) fn take_verification_dialog(&mut self, cid: &ContactId) -> Option<gtk::Dialog> {
+        self.verification_dialogs.remove(cid)
+    }
+
+    pub(crate) fn add_pairing_dialog(&mut self, cid: ContactId, dialog: gtk::Dialog) {
+        self.pairing_dialogs.insert(cid, dialog);
+    }
+
+    pub(crate) fn take_pairing_dialog(&mut self, cid: &ContactId) -> Option<gtk::Dialog> {
+        self.pairing_dialogs.remove(cid)
+    }
+
+    pub(crate) fn add_tofu_endorsement_label(&mut self, cid: ContactId, label: gtk::Label) {
+        self.tofu_endorsement_labels.insert(cid, label);
+    }
+
+    pub(crate) fn take_tofu_endorsement_label(&mut self, cid: &ContactId) -> Option<gtk::Label> {
+        self.tofu_endorsement_labels.remove(cid)
+    }
+
+    pub(crate) fn tofu_endorsement_labels(&self) -> &HashMap<ContactId, gtk::Label> {
+        &self.tofu_endorsement_labels
+    }
 }
@@ -8,7 +8,7 @@ use crate::GUI_SPACING_LARGE;
 use crate::GUI_SPACING_MID;
 use crate::GUI_SPACING_XLARGE;
 use crate::GUI_SPACING_XXXLARGE;
-use crate::gui::label;
+use crate::gui::{label, profile::widget_avatar};
 
 #[derive(Debug, Clone)]
 pub(super) struct MessageBubble {
@@ -30,6 +30,9 @@ impl MessageBubble {
         w_lbl_author.set_halign(gtk::Align::Start);
         w_lbl_author.set_margin_end(GUI_SPACING_XLARGE);
 
+        let w_avatar = widget_avatar(author, 24);
+        w_avatar.set_margin_end(GUI_SPACING_MID);
+        w_meta_box.append(&w_avatar);
         w_meta_box.append(&w_lbl_author);
         w_meta_box.append(&w_lbl_time);
 
@@ -2,10 +2,13 @@ use gtk::prelude::*;
 use sremp_client::domain::chats::Chats;
 use sremp_client::domain::known_identities::{KnownIdentities, SharedContact};
 use sremp_core::chat::Chat;
-use sremp_core::identity::ContactId;
+use sremp_core::identity::{ContactId, metadata};
 
 use crate::domain::UiDomainSync;
-use crate::gui::label;
+use crate::gui::{
+    label,
+    profile::{widget_avatar, widget_username},
+};
 use crate::{GUI_SPACING_LARGE, GUI_SPACING_MID};
 
 #[derive(Debug)]
@@ -128,14 +131,29 @@ fn widget_chat_card(
     chat: &Chat,
 ) -> impl IsA<gtk::Widget> {
     let w_box = gtk::Box::builder()
-        .orientation(gtk::Orientation::Vertical)
+        .orientation(gtk::Orientation::Horizontal)
+        .spacing(GUI_SPACING_MID)
         .margin_top(GUI_SPACING_LARGE)
         .margin_bottom(GUI_SPACING_LARGE)
         .margin_start(GUI_SPACING_LARGE)
         .margin_end(GUI_SPACING_LARGE)
         .build();
 
-    w_box.append(&label(contact.username()));
+    w_box.append(&widget_avatar(&contact, 32));
+    let w_names = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .build();
+    w_names.append(&widget_username(&contact));
+    if let Some(status) = contact
+        .extensions()
+        .and_then(|e| e.metadata_text(metadata::STATUS))
+    {
+        let w_status = label(status);
+        w_status.add_css_class("dim-label");
+        w_status.set_ellipsize(gtk::pango::EllipsizeMode::End);
+        w_names.append(&w_status);
+    }
+    w_box.append(&w_names);
 
     gtk::Frame::builder()
         .margin_top(GUI_SPACING_MID)
@@ -1,6 +1,7 @@
-use crate::{GUI_SPACING_MID, domain::UiDomainSync, gui::label, jobs::update_listener_label};
+use crate::{GUI_SPACING_MID, domain::UiDomainSync, gui::label, jobs::update_listener_label, qr};
 
-use gtk::prelude::*;
+use gtk::{gdk, gio, prelude::*};
+use sremp_core::identity::invitation::Invitation;
 
 pub(crate) fn dialog_connect(app: &gtk::Application, state: UiDomainSync) {
     let win_dialog = gtk::Window::builder()
@@ -40,6 +41,13 @@ pub(crate) fn dialog_connect(app: &gtk::Application, state: UiDomainSync) {
         .text("33399")
         .build();
 
+    let w_invitation_entry = gtk::Entry::builder()
+        .placeholder_text("sremp:invite/... (optional)")
+        .hexpand(true)
+        .build();
+
+    let w_btn_scan = gtk::Button::builder().label("Open QR Image…").build();
+
     let w_box_btn = gtk::Box::builder()
         .orientation(gtk::Orientation::Horizontal)
         .spacing(6)
@@ -58,9 +66,13 @@ pub(crate) fn dialog_connect(app: &gtk::Application, state: UiDomainSync) {
     w_grid.attach(&label("Port"), 0, 1, 1, 1);
     w_grid.attach(&w_port_entry, 1, 1, 1, 1);
 
+    w_grid.attach(&label("Invitation"), 0, 2, 1, 1);
+    w_grid.attach(&w_invitation_entry, 1, 2, 1, 1);
+    w_grid.attach(&w_btn_scan, 1, 3, 1, 1);
+
     let w_error = label("undefined error");
     w_error.set_visible(false);
-    w_grid.attach(&w_error, 0, 2, 2, 1);
+    w_grid.attach(&w_error, 0, 4, 2, 1);
 
     w_box.append(&w_grid);
     w_box.append(&w_box_btn);
@@ -72,10 +84,68 @@ pub(crate) fn dialog_connect(app: &gtk::Application, state: UiDomainSync) {
         win_dialog_clone.close();
     });
 
+    let w_invitation_entry_c = w_invitation_entry.clone();
+    let w_error_c = w_error.clone();
+    let fill_invitation = move |file: &gio::File| match qr::scan_file(file) {
+        Ok(Some(text)) => {
+            w_invitation_entry_c.set_text(&text);
+            w_error_c.set_visible(false);
+        }
+        Ok(None) => {
+            w_error_c.set_text("No QR code found in the image");
+            w_error_c.set_visible(true);
+        }
+        Err(e) => {
+            w_error_c.set_text(&format!("Could not open the image: {e}"));
+            w_error_c.set_visible(true);
+        }
+    };
+
+    let win_dialog_clone = win_dialog.clone();
+    let fill_invitation_c = fill_invitation.clone();
+    w_btn_scan.connect_clicked(move |_| {
+        let filter = gtk::FileFilter::new();
+        filter.set_name(Some("Images"));
+        filter.add_pixbuf_formats();
+
+        let chooser = gtk::FileChooserNative::new(
+            Some("Open QR Image"),
+            Some(&win_dialog_clone),
+            gtk::FileChooserAction::Open,
+            Some("Open"),
+            Some("Cancel"),
+        );
+        chooser.add_filter(&filter);
+        let fill_invitation = fill_invitation_c.clone();
+        // the native dialog is not kept alive by gtk, the handler holds it until the response
+        let keep_alive = std::cell::RefCell::new(Some(chooser.clone()));
+        chooser.connect_response(move |chooser, response| {
+            if response == gtk::ResponseType::Accept {
+                if let Some(file) = chooser.file() {
+                    fill_invitation(&file);
+                }
+            }
+            keep_alive.take();
+        });
+        chooser.show();
+    });
+
+    // NOTE: images can also be dropped anywhere on the dialog
+    let drop_target = gtk::DropTarget::new(gio::File::static_type(), gdk::DragAction::COPY);
+    drop_target.connect_drop(move |_, value, _, _| match value.get::<gio::File>() {
+        Ok(file) => {
+            fill_invitation(&file);
+            true
+        }
+        Err(_) => false,
+    });
+    w_box.add_controller(drop_target);
+
     let win_dialog_clone = win_dialog.clone();
     let w_error_clone = w_error.clone();
 
     w_btn_accept.connect_clicked(move |_| {
+        let raw_invitation = w_invitation_entry.text().to_string();
         let raw_host = w_host_entry.text().to_string();
         let raw_port = w_port_entry.text().to_string();
 
@@ -84,6 +154,18 @@ pub(crate) fn dialog_connect(app: &gtk::Application, state: UiDomainSync) {
             w_error_clone.set_visible(true);
         };
 
+        // NOTE: an invitation contains its own endpoints, host and port are not needed then
+        if !raw_invitation.trim().is_empty() {
+            match Invitation::from_uri(&raw_invitation) {
+                Ok(invitation) => {
+                    state.borrow_mut().accept_invitation(invitation);
+                    win_dialog_clone.close();
+                }
+                Err(e) => handle_error(format!("Could not use the invitation: {e}")),
+            }
+            return;
+        }
+
         match format!("{raw_host}:{raw_port}").parse::<std::net::SocketAddr>() {
             Ok(remote) => {
                 state.borrow_mut().initiate_connection(remote);
@@ -0,0 +1,40 @@
+use gtk::prelude::*;
+use sremp_core::net::connection::stats::ConnectionStatsSnapshot;
+
+use crate::{GUI_SPACING_MID, gui::label};
+
+/// Shows a window with the statistics of all active connections
+pub(crate) fn show_connection_stats(stats: &[ConnectionStatsSnapshot]) {
+    let win = gtk::Window::builder()
+        .default_width(400)
+        .default_height(300)
+        .title("Connection Diagnostics")
+        .build();
+
+    let w_box = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(GUI_SPACING_MID)
+        .margin_top(GUI_SPACING_MID)
+        .margin_bottom(GUI_SPACING_MID)
+        .margin_start(GUI_SPACING_MID)
+        .margin_end(GUI_SPACING_MID)
+        .build();
+
+    if stats.is_empty() {
+        w_box.append(&label("There are no active connections"));
+    }
+    for snapshot in stats {
+        let w_stats = label(snapshot);
+        w_stats.set_halign(gtk::Align::Start);
+        w_stats.set_selectable(true);
+        w_box.append(&gtk::Frame::builder().child(&w_stats).build());
+    }
+
+    let w_scroll = gtk::ScrolledWindow::builder()
+        .hscrollbar_policy(gtk::PolicyType::Never)
+        .child(&w_box)
+        .build();
+
+    win.set_child(Some(&w_scroll));
+    win.present();
+}
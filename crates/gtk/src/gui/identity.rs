@@ -1,9 +1,20 @@
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
 
+use chrono::TimeDelta;
 use gtk::prelude::*;
-use sremp_core::identity::{ContactIdentity, UserIdentity, format_key};
+use sremp_core::identity::{
+    ContactId, ContactIdentity, SafetyNumber, UserIdentity, format_key, invitation::Invitation,
+};
 
-use crate::{GUI_SPACING_MID, domain::UiDomainSync, gui::label};
+use crate::{
+    GUI_SPACING_MID,
+    domain::UiDomainSync,
+    gui::{label, profile::widget_profile},
+    qr,
+};
+
+/// How long an invitation shown to the user can be used
+const INVITATION_VALIDITY: TimeDelta = TimeDelta::hours(24);
 
 /// Creates and shows a dialog for creating a new user identity
 pub(crate) fn dialog_create_identity(app: &gtk::Application, state: UiDomainSync) {
@@ -189,7 +200,13 @@ pub(crate) fn show_identity_created_success(user_identity: Arc<UserIdentity>) {
     log::debug!("Showing identity created success window");
 }
 
-pub(crate) fn show_user_identity(app: &gtk::Application, user: UserIdentity) {
+/// Shows the identity of the user and an invitation to connect to them on `endpoints`, as
+/// QR code and as text
+pub(crate) fn show_user_identity(
+    app: &gtk::Application,
+    user: &UserIdentity,
+    endpoints: Vec<SocketAddr>,
+) {
     let win_dialog = gtk::Window::builder()
         .modal(true)
         .default_width(500)
@@ -211,19 +228,116 @@ pub(crate) fn show_user_identity(app: &gtk::Application, user: UserIdentity) {
         .margin_end(GUI_SPACING_MID)
         .build();
 
+    w_box.append(&widget_profile(&user.identity));
     w_box.append(&label(format!("Username: {}", user.identity.username())));
     w_box.append(&label(format!(
         "Public Key: {}",
         format_key(&user.identity.identity_key())
     )));
     w_box.append(&label(format!("Created: {}", user.identity.created())));
+    w_box.append(&label(format!(
+        "Fingerprint: {}",
+        user.id().fingerprint_words().join(" ")
+    )));
+    w_box.append(&widget_invitation(user, endpoints));
 
     win_dialog.set_child(Some(&w_box));
 
     win_dialog.present();
 }
 
-pub(crate) fn show_contact_identity(app: &gtk::Application, contact: &ContactIdentity) {
+/// Shows an invitation to connect to `user` as QR code and as text
+fn widget_invitation(user: &UserIdentity, endpoints: Vec<SocketAddr>) -> gtk::Box {
+    let w_box = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(6)
+        .build();
+
+    if endpoints.is_empty() {
+        w_box.append(&label(
+            "Start listening to get an invitation that others can scan to connect to you.",
+        ));
+        return w_box;
+    }
+
+    let uri = match Invitation::create(user, endpoints, INVITATION_VALIDITY)
+        .and_then(|invitation| invitation.to_uri())
+    {
+        Ok(uri) => uri,
+        Err(e) => {
+            log::error!("Could not create an invitation: {e}");
+            w_box.append(&label(format!("Could not create an invitation: {e}")));
+            return w_box;
+        }
+    };
+
+    w_box.append(&label(format!(
+        "Let others scan this invitation to connect to you, it is valid for {} hours:",
+        INVITATION_VALIDITY.num_hours()
+    )));
+    match qr::render(&uri) {
+        Ok(texture) => {
+            let w_qr = gtk::Picture::for_paintable(&texture);
+            w_qr.set_can_shrink(false);
+            w_qr.set_halign(gtk::Align::Center);
+            w_box.append(&w_qr);
+        }
+        Err(e) => {
+            log::error!("Could not render the invitation as QR code: {e}");
+            w_box.append(&label(format!(
+                "Could not show the invitation as QR code ({e}), share the text below instead."
+            )));
+        }
+    }
+
+    let w_uri = label(&uri);
+    w_uri.set_wrap(true);
+    w_uri.set_wrap_mode(gtk::pango::WrapMode::Char);
+    w_uri.set_selectable(true);
+    w_box.append(&w_uri);
+    w_box
+}
+
+/// Shows the safety number of `user` and `contact` and the fingerprint of `contact`, to compare
+/// them out of band
+pub(crate) fn widget_verification(user: &ContactId, contact: &ContactId) -> gtk::Box {
+    let w_box = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(6)
+        .build();
+
+    let groups: Vec<String> = SafetyNumber::new(user, contact)
+        .groups()
+        .iter()
+        .map(|group| format!("{group:05}"))
+        .collect();
+    let w_safety_number = label(format!(
+        "{}\n{}\n{}",
+        groups[..4].join(" "),
+        groups[4..8].join(" "),
+        groups[8..].join(" ")
+    ));
+    w_safety_number.add_css_class("monospace");
+    w_safety_number.set_selectable(true);
+
+    let w_fingerprint = label(contact.fingerprint_words().join(" "));
+    w_fingerprint.set_wrap(true);
+    w_fingerprint.set_selectable(true);
+
+    w_box.append(&label(
+        "Compare this safety number with your contact, in person or over a call:",
+    ));
+    w_box.append(&w_safety_number);
+    w_box.append(&label("Fingerprint of the contact:"));
+    w_box.append(&w_fingerprint);
+    w_box
+}
+
+pub(crate) fn show_contact_identity(
+    app: &gtk::Application,
+    contact: &ContactIdentity,
+    user: Option<&ContactId>,
+) {
     let win_dialog = gtk::Window::builder()
         .modal(true)
         .default_width(500)
@@ -245,6 +359,7 @@ pub(crate) fn show_contact_identity(app: &gtk::Application, contact: &ContactIde
         .margin_end(GUI_SPACING_MID)
         .build();
 
+    w_box.append(&widget_profile(&contact.identity));
     w_box.append(&label(format!("Username: {}", contact.identity.username())));
     w_box.append(&label(format!(
         "Public Key: {}",
@@ -253,6 +368,9 @@ pub(crate) fn show_contact_identity(app: &gtk::Application, contact: &ContactIde
     w_box.append(&label(format!("Trust: {}", contact.trust)));
     w_box.append(&label(format!("First Seen: {}", contact.first_seen)));
     w_box.append(&label(format!("Last Seen: {}", contact.last_seen)));
+    if let Some(user) = user {
+        w_box.append(&widget_verification(user, &contact.id()));
+    }
 
     win_dialog.set_child(Some(&w_box));
 
@@ -9,9 +9,13 @@ use crate::domain::UiDomainSync;
 pub(crate) mod chat;
 pub(crate) mod chats;
 pub(crate) mod connect;
+pub(crate) mod diagnostics;
 pub(crate) mod identity;
+pub(crate) mod pairing;
+pub(crate) mod profile;
 pub(crate) mod tofu;
 pub(crate) mod topbar;
+pub(crate) mod verification;
 
 use chat::*;
 use chats::*;
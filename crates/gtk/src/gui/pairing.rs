@@ -0,0 +1,128 @@
+use gtk::prelude::{BoxExt, DialogExt, EditableExt, GtkWindowExt, WidgetExt};
+use sremp_client::domain::{UiCommand, known_identities::SharedContact};
+use sremp_core::identity::PairingCode;
+
+use crate::{domain::UiDomainSync, gui::label};
+
+/// Asks the user for the code to pair with the contact.
+///
+/// If the user starts the pairing, a new code is filled in that they tell the contact. If the
+/// contact has started it (`requested`), the user enters the code the contact tells them.
+pub(crate) fn show_pairing_dialog(state: UiDomainSync, contact: SharedContact, requested: bool) {
+    let dialog = gtk::Dialog::builder()
+        .title("Pair with contact")
+        .modal(true)
+        .build();
+
+    let content_area = dialog.content_area();
+    content_area.set_spacing(12);
+    content_area.set_margin_top(12);
+    content_area.set_margin_bottom(12);
+    content_area.set_margin_start(12);
+    content_area.set_margin_end(12);
+
+    let w_code_entry = gtk::Entry::builder()
+        .placeholder_text("7-guitar-ocean")
+        .build();
+    if requested {
+        content_area.append(&label(format!(
+            "{} wants to pair with you.\nEnter the code they tell you on a call or in person:",
+            contact.username()
+        )));
+    } else {
+        content_area.append(&label(format!(
+            "Tell {} this code on a call or in person, and let them enter it:",
+            contact.username()
+        )));
+        w_code_entry.set_text(&PairingCode::generate().to_string());
+    }
+    content_area.append(&w_code_entry);
+    let w_error = label("");
+    w_error.add_css_class("error");
+    w_error.set_visible(false);
+    content_area.append(&w_error);
+
+    dialog.add_button("Cancel", gtk::ResponseType::Cancel);
+    dialog.add_button("Pair", gtk::ResponseType::Accept);
+
+    let contact_id = contact.id();
+    state
+        .borrow_mut()
+        .tracked_widgets
+        .add_pairing_dialog(contact_id.clone(), dialog.clone());
+
+    dialog.connect_response(move |dialog, response| {
+        if response == gtk::ResponseType::Accept {
+            let code: PairingCode = match w_code_entry.text().parse() {
+                Ok(code) => code,
+                Err(e) => {
+                    w_error.set_text(&e.to_string());
+                    w_error.set_visible(true);
+                    return;
+                }
+            };
+            state
+                .borrow_mut()
+                .tracked_widgets
+                .take_pairing_dialog(&contact_id);
+            state
+                .borrow()
+                .send_cmd(UiCommand::StartPairing(contact_id.clone(), code));
+            dialog.close();
+            return;
+        }
+
+        // NOTE: the dialog is not tracked anymore if the pairing was ended by the contact
+        let tracked = state
+            .borrow_mut()
+            .tracked_widgets
+            .take_pairing_dialog(&contact_id)
+            .is_some();
+        match response {
+            gtk::ResponseType::Cancel | gtk::ResponseType::DeleteEvent if tracked => state
+                .borrow()
+                .send_cmd(UiCommand::CancelPairing(contact_id.clone())),
+            gtk::ResponseType::Cancel | gtk::ResponseType::DeleteEvent => (),
+            other => log::warn!("Undefined dialog action: {other:?}"),
+        }
+        dialog.close();
+    });
+
+    dialog.present();
+}
+
+/// Closes the dialog of the pairing and tells the user how it ended, [`None`] if it was
+/// cancelled.
+pub(crate) fn show_pairing_result(
+    state: UiDomainSync,
+    contact: SharedContact,
+    paired: Option<bool>,
+) {
+    let open = state
+        .borrow_mut()
+        .tracked_widgets
+        .take_pairing_dialog(&contact.id());
+    if let Some(dialog) = open {
+        dialog.close();
+    }
+
+    let text = match paired {
+        Some(true) => format!("Paired with {}, they are verified.", contact.username()),
+        Some(false) => format!(
+            "The codes did not match, {} is not verified.\nCheck the code and try again.",
+            contact.username()
+        ),
+        None => format!("The pairing with {} was cancelled.", contact.username()),
+    };
+
+    let dialog = gtk::Dialog::builder().title("Pairing").modal(true).build();
+    let content_area = dialog.content_area();
+    content_area.set_margin_top(12);
+    content_area.set_margin_bottom(12);
+    content_area.set_margin_start(12);
+    content_area.set_margin_end(12);
+    content_area.append(&label(text));
+    dialog.add_button("OK", gtk::ResponseType::Ok);
+    dialog.connect_response(|dialog, _| dialog.close());
+    dialog.present();
+}
@@ -0,0 +1,345 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc, sync::Arc};
+
+use gtk::{gdk, gdk_pixbuf, gio, glib, prelude::*};
+use sremp_core::identity::{
+    ContactId, Identity, MAX_PROFILE_PICTURE_BYTES, metadata, validate_profile_picture,
+};
+
+use crate::{GUI_SPACING_MID, domain::UiDomainSync, gui::label};
+
+/// Side of the pictures that the user picks, in pixels
+const PROFILE_PICTURE_SIDE: i32 = 256;
+
+thread_local! {
+    /// Decoded profile pictures by identity and version, [`None`] if the picture could not be
+    /// decoded
+    static TEXTURES: RefCell<HashMap<(ContactId, u64), Option<gdk::Texture>>> =
+        RefCell::new(HashMap::new());
+}
+
+/// Shows the profile picture of the identity, or a placeholder if it has none.
+pub(crate) fn widget_avatar(identity: &Identity, size: i32) -> gtk::Image {
+    let w_image = match texture(identity) {
+        Some(texture) => gtk::Image::from_paintable(Some(&texture)),
+        None => gtk::Image::from_icon_name("avatar-default-symbolic"),
+    };
+    w_image.set_pixel_size(size);
+    w_image.set_valign(gtk::Align::Start);
+    w_image
+}
+
+/// Shows the profile picture and the metadata with a known meaning, if the identity has them
+pub(crate) fn widget_profile(identity: &Identity) -> gtk::Box {
+    let w_box = gtk::Box::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .spacing(GUI_SPACING_MID)
+        .build();
+    w_box.append(&widget_avatar(identity, 64));
+
+    let w_details = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(4)
+        .build();
+    let w_username = widget_username(identity);
+    w_username.add_css_class("title-3");
+    w_details.append(&w_username);
+    if let Some(extensions) = identity.extensions() {
+        for (key, name) in [
+            (metadata::PRONOUNS, "Pronouns"),
+            (metadata::STATUS, "Status"),
+            (metadata::ABOUT, "About"),
+        ] {
+            if let Some(text) = extensions.metadata_text(key) {
+                let w_text = label(format!("{name}: {text}"));
+                w_text.set_wrap(true);
+                w_details.append(&w_text);
+            }
+        }
+    }
+    w_box.append(&w_details);
+    w_box
+}
+
+/// The username, followed by a badge if the identity is a machine account
+pub(crate) fn widget_username(identity: &Identity) -> gtk::Box {
+    let w_box = gtk::Box::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .spacing(GUI_SPACING_MID)
+        .build();
+    w_box.append(&label(identity.username()));
+    if identity.flags().is_machine_account {
+        let w_badge = label("Bot");
+        w_badge.add_css_class("caption-heading");
+        w_badge.add_css_class("accent");
+        w_badge.set_tooltip_text(Some(
+            "This is a machine account, its messages are written by a program",
+        ));
+        w_box.append(&w_badge);
+    }
+    w_box
+}
+
+fn texture(identity: &Identity) -> Option<gdk::Texture> {
+    let picture = identity.extensions()?.profile_picture()?;
+    TEXTURES.with_borrow_mut(|textures| {
+        textures
+            .entry((identity.id(), identity.version()))
+            .or_insert_with(|| match decode(picture) {
+                Ok(texture) => Some(texture),
+                Err(e) => {
+                    log::warn!(
+                        "Could not decode the profile picture of {}: {e}",
+                        identity.id()
+                    );
+                    None
+                }
+            })
+            .clone()
+    })
+}
+
+/// Decodes an encoded profile picture
+fn decode(data: &[u8]) -> Result<gdk::Texture, glib::Error> {
+    let stream = gio::MemoryInputStream::from_bytes(&glib::Bytes::from(data));
+    let pixbuf = gdk_pixbuf::Pixbuf::from_stream(&stream, None::<&gio::Cancellable>)?;
+    Ok(gdk::Texture::for_pixbuf(&pixbuf))
+}
+
+/// Loads an image file as profile picture, scaled down and encoded so that it fits the limits.
+pub(crate) fn load_profile_picture(file: &gio::File) -> Result<Vec<u8>, glib::Error> {
+    let Some(path) = file.path() else {
+        return Err(glib::Error::new(
+            gio::IOErrorEnum::NotSupported,
+            "only local files can be used as profile picture",
+        ));
+    };
+    let pixbuf = gdk_pixbuf::Pixbuf::from_file_at_scale(
+        path,
+        PROFILE_PICTURE_SIDE,
+        PROFILE_PICTURE_SIDE,
+        true,
+    )?;
+
+    // photos are usually too large as PNG
+    let mut encoded = pixbuf.save_to_bufferv("png", &[])?;
+    if encoded.len() > MAX_PROFILE_PICTURE_BYTES {
+        // JPEG has no alpha channel, transparent parts become white
+        let opaque = gdk_pixbuf::Pixbuf::new(
+            gdk_pixbuf::Colorspace::Rgb,
+            false,
+            8,
+            pixbuf.width(),
+            pixbuf.height(),
+        )
+        .expect("a pixbuf of at most 256x256 pixels can be allocated");
+        opaque.fill(0xffffffff);
+        pixbuf.composite(
+            &opaque,
+            0,
+            0,
+            pixbuf.width(),
+            pixbuf.height(),
+            0.0,
+            0.0,
+            1.0,
+            1.0,
+            gdk_pixbuf::InterpType::Nearest,
+            255,
+        );
+        for quality in ["90", "75", "50"] {
+            encoded = opaque.save_to_bufferv("jpeg", &[("quality", quality)])?;
+            if encoded.len() <= MAX_PROFILE_PICTURE_BYTES {
+                break;
+            }
+        }
+    }
+
+    validate_profile_picture(&encoded)
+        .map_err(|e| glib::Error::new(gio::IOErrorEnum::InvalidData, &e.to_string()))?;
+    Ok(encoded)
+}
+
+/// Creates and shows a dialog for changing the profile picture and metadata of the user
+pub(crate) fn dialog_edit_profile(app: &gtk::Application, state: UiDomainSync) {
+    let Some(user) = state.borrow().user_identity() else {
+        log::warn!("There is no user identity to edit");
+        return;
+    };
+    let extensions = user.identity.extensions().cloned().unwrap_or_default();
+
+    let win_dialog = gtk::Window::builder()
+        .modal(true)
+        .default_width(400)
+        .resizable(false)
+        .title("Edit Profile")
+        .build();
+    if let Some(window) = app.active_window() {
+        win_dialog.set_transient_for(Some(&window));
+    }
+
+    let w_box = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(GUI_SPACING_MID)
+        .margin_top(GUI_SPACING_MID)
+        .margin_bottom(GUI_SPACING_MID)
+        .margin_start(GUI_SPACING_MID)
+        .margin_end(GUI_SPACING_MID)
+        .build();
+
+    // the picture is only replaced in the identity when the user saves
+    let picture = Rc::new(RefCell::new(
+        extensions.profile_picture().map(<[u8]>::to_vec),
+    ));
+    let w_preview = widget_avatar(&user.identity, 96);
+    w_preview.set_halign(gtk::Align::Center);
+    let set_preview = {
+        let w_preview = w_preview.clone();
+        move |data: Option<&[u8]>| {
+            match data.map(decode) {
+                Some(Ok(texture)) => w_preview.set_paintable(Some(&texture)),
+                _ => w_preview.set_icon_name(Some("avatar-default-symbolic")),
+            }
+            w_preview.set_pixel_size(96);
+        }
+    };
+
+    let w_box_picture = gtk::Box::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .spacing(6)
+        .halign(gtk::Align::Center)
+        .build();
+    let w_btn_choose = gtk::Button::builder().label("Choose Picture…").build();
+    let w_btn_remove = gtk::Button::builder().label("Remove Picture").build();
+    w_box_picture.append(&w_btn_choose);
+    w_box_picture.append(&w_btn_remove);
+
+    let w_grid = gtk::Grid::builder()
+        .row_spacing(6)
+        .column_spacing(12)
+        .build();
+    let entries: Vec<(&str, gtk::Entry)> = [
+        (metadata::STATUS, "Status", "What are you up to?"),
+        (metadata::PRONOUNS, "Pronouns", "they/them"),
+        (metadata::ABOUT, "About", "A few words about you"),
+    ]
+    .into_iter()
+    .enumerate()
+    .map(|(row, (key, name, placeholder))| {
+        let w_entry = gtk::Entry::builder()
+            .placeholder_text(placeholder)
+            .text(extensions.metadata_text(key).unwrap_or_default())
+            .hexpand(true)
+            .build();
+        let row = i32::try_from(row).expect("there are only a few metadata keys");
+        w_grid.attach(&label(name), 0, row, 1, 1);
+        w_grid.attach(&w_entry, 1, row, 1, 1);
+        (key, w_entry)
+    })
+    .collect();
+
+    let w_error = label("");
+    w_error.set_visible(false);
+    w_error.add_css_class("error");
+
+    let w_box_btn = gtk::Box::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .spacing(6)
+        .halign(gtk::Align::End)
+        .build();
+    let w_btn_cancel = gtk::Button::builder().label("Cancel").build();
+    let w_btn_save = gtk::Button::builder().label("Save").build();
+    w_btn_save.add_css_class("suggested-action");
+    w_box_btn.append(&w_btn_cancel);
+    w_box_btn.append(&w_btn_save);
+
+    w_box.append(&w_preview);
+    w_box.append(&w_box_picture);
+    w_box.append(&w_grid);
+    w_box.append(&w_error);
+    w_box.append(&w_box_btn);
+    win_dialog.set_child(Some(&w_box));
+
+    let win_dialog_c = win_dialog.clone();
+    let picture_c = picture.clone();
+    let w_error_c = w_error.clone();
+    let set_preview_c = set_preview.clone();
+    w_btn_choose.connect_clicked(move |_| {
+        let filter = gtk::FileFilter::new();
+        filter.set_name(Some("Images"));
+        filter.add_pixbuf_formats();
+
+        let chooser = gtk::FileChooserNative::new(
+            Some("Choose Profile Picture"),
+            Some(&win_dialog_c),
+            gtk::FileChooserAction::Open,
+            Some("Open"),
+            Some("Cancel"),
+        );
+        chooser.add_filter(&filter);
+        let picture = picture_c.clone();
+        let w_error = w_error_c.clone();
+        let set_preview = set_preview_c.clone();
+        // the native dialog is not kept alive by gtk, the handler holds it until the response
+        let keep_alive = RefCell::new(Some(chooser.clone()));
+        chooser.connect_response(move |chooser, response| {
+            if let Some(file) = chooser
+                .file()
+                .filter(|_| response == gtk::ResponseType::Accept)
+            {
+                match load_profile_picture(&file) {
+                    Ok(data) => {
+                        set_preview(Some(&data));
+                        picture.replace(Some(data));
+                        w_error.set_visible(false);
+                    }
+                    Err(e) => {
+                        w_error.set_text(&format!("Could not use the image: {e}"));
+                        w_error.set_visible(true);
+                    }
+                }
+            }
+            keep_alive.take();
+        });
+        chooser.show();
+    });
+
+    let picture_c = picture.clone();
+    w_btn_remove.connect_clicked(move |_| {
+        picture_c.replace(None);
+        set_preview(None);
+    });
+
+    let win_dialog_c = win_dialog.clone();
+    w_btn_cancel.connect_clicked(move |_| {
+        win_dialog_c.close();
+    });
+
+    let win_dialog_c = win_dialog.clone();
+    w_btn_save.connect_clicked(move |_| {
+        let mut extensions = extensions.clone();
+        let mut updated = (*user).clone();
+        let mut key = updated.identity_key.clone();
+        let result = extensions
+            .set_profile_picture(picture.borrow().clone())
+            .and_then(|()| {
+                entries.iter().try_for_each(|(key, w_entry)| {
+                    extensions.set_metadata_text(key, &w_entry.text())
+                })
+            })
+            .and_then(|()| updated.identity.set_extensions(Some(extensions), &mut key));
+        match result {
+            Ok(()) => {
+                state
+                    .borrow_mut()
+                    .set_user_identity(Some(Arc::new(updated)));
+                win_dialog_c.close();
+            }
+            Err(e) => {
+                w_error.set_text(&e.to_string());
+                w_error.set_visible(true);
+            }
+        }
+    });
+
+    win_dialog.present();
+}
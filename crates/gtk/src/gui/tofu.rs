@@ -1,16 +1,25 @@
 use gtk::prelude::{BoxExt, DialogExt, GtkWindowExt, WidgetExt};
-use sremp_client::domain::{UiCommand, known_identities::SharedContact};
-use sremp_core::identity::Trust;
+use sremp_client::domain::{UiCommand, known_identities::SharedContact, tofu::PeerStatus};
+use sremp_core::identity::{ContactId, Trust};
 
-use crate::{domain::UiDomainSync, gui::label};
+use crate::{
+    domain::{UiDomain, UiDomainSync},
+    gui::{identity::widget_verification, label, profile::widget_profile},
+};
 
 pub(crate) fn show_tofu_dialog(
     state: UiDomainSync,
     contact: SharedContact,
     socket: std::net::SocketAddr,
+    status: PeerStatus,
 ) {
+    let key_changed = matches!(status, PeerStatus::KeyChanged { .. });
     let dialog = gtk::Dialog::builder()
-        .title("Trust this identity?")
+        .title(if key_changed {
+            "Warning: the identity of this peer may have changed!"
+        } else {
+            "Trust this identity?"
+        })
         .modal(true)
         .build();
 
@@ -26,11 +35,31 @@ pub(crate) fn show_tofu_dialog(
         .spacing(8)
         .build();
 
+    if let PeerStatus::KeyChanged {
+        address_of,
+        name_of,
+    } = &status
+    {
+        info_box.append(&widget_key_changed(&state, address_of.as_ref(), name_of));
+    }
     info_box.append(&gtk::Label::new(Some("A peer has connected to you:")));
+    info_box.append(&widget_profile(&contact));
     info_box.append(&label(format!("Identity: {}", contact.id())));
     info_box.append(&label(format!("Username: {}", contact.username())));
     info_box.append(&label(format!("Created: {}", contact.created())));
     info_box.append(&label(format!("Network Address: {socket}")));
+    if let Some(user) = state.borrow().user_identity() {
+        info_box.append(&widget_verification(&user.id(), &contact.id()));
+    }
+    let w_endorsements = label("");
+    w_endorsements.add_css_class("success");
+    w_endorsements.set_wrap(true);
+    info_box.append(&w_endorsements);
+    state
+        .borrow_mut()
+        .tracked_widgets
+        .add_tofu_endorsement_label(contact.id(), w_endorsements);
+    update_endorsements(&state.borrow());
 
     let question = gtk::Label::new(Some("\nDo you trust this identity?"));
     question.add_css_class("bold");
@@ -39,11 +68,21 @@ pub(crate) fn show_tofu_dialog(
     content_area.append(&info_box);
 
     dialog.add_button("Reject", gtk::ResponseType::Reject);
-    dialog.add_button("Trust", gtk::ResponseType::Accept);
+    dialog.add_button(
+        if key_changed { "Trust anyway" } else { "Trust" },
+        gtk::ResponseType::Accept,
+    );
+    if key_changed {
+        dialog.set_default_response(gtk::ResponseType::Reject);
+    }
 
     let contact_id = contact.id();
 
     dialog.connect_response(move |dialog, response| {
+        state
+            .borrow_mut()
+            .tracked_widgets
+            .take_tofu_endorsement_label(&contact_id);
         match response {
             gtk::ResponseType::Accept => {
                 state
@@ -77,3 +116,65 @@ pub(crate) fn show_tofu_dialog(
     // present() returns immediately and doesn't block
     dialog.present();
 }
+
+/// Shows the contacts the user trusts that vouch for the peer in the open TOFU dialogs
+pub(crate) fn update_endorsements(state: &UiDomain) {
+    for (cid, w_endorsements) in state.tracked_widgets.tofu_endorsement_labels() {
+        let names: Vec<String> = state
+            .contacts()
+            .vouched_for_by(cid)
+            .iter()
+            .map(|endorser| endorser.username().to_string())
+            .collect();
+        w_endorsements.set_visible(!names.is_empty());
+        w_endorsements.set_text(&format!("Vouched for by {}", names.join(", ")));
+    }
+}
+
+/// A prominent warning that the peer is not who the user may expect, like the message of SSH when
+/// a host key has changed
+fn widget_key_changed(
+    state: &UiDomainSync,
+    address_of: Option<&ContactId>,
+    name_of: &[ContactId],
+) -> gtk::Box {
+    let describe = |cid: &ContactId| match state.borrow().contacts().get(cid) {
+        Some(other) => format!("{} ({cid})", other.username()),
+        None => cid.to_string(),
+    };
+    let w_box = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(4)
+        .build();
+    w_box.add_css_class("error");
+
+    let w_title = label("POSSIBLE IMPERSONATION");
+    w_title.add_css_class("title-2");
+    w_box.append(&w_title);
+    if let Some(cid) = address_of {
+        let w_address = label(format!(
+            "A different identity answered on this address before: {}\nThe contact may have \
+            created a new identity, or someone may be intercepting the connection.",
+            describe(cid)
+        ));
+        w_address.set_wrap(true);
+        w_box.append(&w_address);
+    }
+    if !name_of.is_empty() {
+        let names: Vec<String> = name_of.iter().map(describe).collect();
+        let w_names = label(format!(
+            "This name looks like that of a contact you already know, but it is a different \
+            identity. It may be someone pretending to be them:\n{}",
+            names.join("\n")
+        ));
+        w_names.set_wrap(true);
+        w_box.append(&w_names);
+    }
+    let w_advice = label(
+        "Do not trust this identity unless you have confirmed it with your contact in another \
+        way, for example with a pairing code.",
+    );
+    w_advice.set_wrap(true);
+    w_box.append(&w_advice);
+    w_box
+}
@@ -22,6 +22,10 @@ pub(crate) fn widget_topbar(_app: &gtk::Application, state: UiDomainSync) -> imp
         Some("Disconnect"),
         Some(actions::ids::A_ID_CONNECTION_DISCONNECT!(app)),
     );
+    menu_connection.append(
+        Some("Diagnostics"),
+        Some(actions::ids::A_ID_CONNECTION_STATS!(app)),
+    );
 
     menu_settings.append(
         Some("Delete everything"),
@@ -56,7 +60,31 @@ pub(crate) fn widget_topbar(_app: &gtk::Application, state: UiDomainSync) -> imp
     );
     menu_identity.append(
         Some("Show my Identity"),
-        Some(actions::ids::A_ID_IDENTITY_SHOW_USER!(app)), // TODO: open a show window on clicked
+        Some(actions::ids::A_ID_IDENTITY_SHOW_USER!(app)),
+    );
+    menu_identity.append(
+        Some("Edit my Profile"),
+        Some(actions::ids::A_ID_IDENTITY_EDIT_PROFILE!(app)),
+    );
+    menu_identity.append(
+        Some("Show current Contact"),
+        Some(actions::ids::A_ID_IDENTITY_SHOW_CONTACT!(app)),
+    );
+    menu_identity.append(
+        Some("Verify current Contact"),
+        Some(actions::ids::A_ID_IDENTITY_VERIFY_CONTACT!(app)),
+    );
+    menu_identity.append(
+        Some("Pair with current Contact"),
+        Some(actions::ids::A_ID_IDENTITY_PAIR_CONTACT!(app)),
+    );
+    menu_identity.append(
+        Some("Vouch for current Contact"),
+        Some(actions::ids::A_ID_IDENTITY_ENDORSE_CONTACT!(app)),
+    );
+    menu_identity.append(
+        Some("Stop vouching for current Contact"),
+        Some(actions::ids::A_ID_IDENTITY_WITHDRAW_ENDORSEMENT!(app)),
     );
 
     menu.append_submenu(Some("Connection"), &menu_connection);
@@ -0,0 +1,103 @@
+use gtk::prelude::{BoxExt, DialogExt, GtkWindowExt, WidgetExt};
+use sremp_client::domain::{UiCommand, known_identities::SharedContact};
+use sremp_core::identity::{ShortAuthString, Trust};
+
+use crate::{domain::UiDomainSync, gui::label};
+
+/// Shows the words of a verification, the user tells whether the contact sees the same ones.
+pub(crate) fn show_sas_dialog(state: UiDomainSync, contact: SharedContact, sas: ShortAuthString) {
+    let dialog = gtk::Dialog::builder()
+        .title("Verify contact")
+        .modal(true)
+        .build();
+
+    let content_area = dialog.content_area();
+    content_area.set_spacing(12);
+    content_area.set_margin_top(12);
+    content_area.set_margin_bottom(12);
+    content_area.set_margin_start(12);
+    content_area.set_margin_end(12);
+
+    content_area.append(&label(format!(
+        "Call {} or meet them, and compare these words.\nThey see the same words only if nobody is listening in:",
+        contact.username()
+    )));
+    let w_words = label(sas.words().join("  "));
+    w_words.add_css_class("title-2");
+    w_words.set_selectable(true);
+    content_area.append(&w_words);
+
+    dialog.add_button("They don't match", gtk::ResponseType::Reject);
+    dialog.add_button("They match", gtk::ResponseType::Accept);
+
+    let contact_id = contact.id();
+    state
+        .borrow_mut()
+        .tracked_widgets
+        .add_verification_dialog(contact_id.clone(), dialog.clone());
+
+    dialog.connect_response(move |dialog, response| {
+        // NOTE: the dialog is not tracked anymore if the verification was ended by the contact
+        let tracked = state
+            .borrow_mut()
+            .tracked_widgets
+            .take_verification_dialog(&contact_id)
+            .is_some();
+        match response {
+            gtk::ResponseType::Accept => state
+                .borrow()
+                .send_cmd(UiCommand::ConfirmVerification(contact_id.clone(), true)),
+            gtk::ResponseType::Reject => state
+                .borrow()
+                .send_cmd(UiCommand::ConfirmVerification(contact_id.clone(), false)),
+            gtk::ResponseType::DeleteEvent if tracked => state
+                .borrow()
+                .send_cmd(UiCommand::CancelVerification(contact_id.clone())),
+            gtk::ResponseType::DeleteEvent => (),
+            other => log::warn!("Undefined dialog action: {other:?}"),
+        }
+        dialog.close();
+    });
+
+    dialog.present();
+}
+
+/// Closes the dialog of the verification and tells the user how it ended, [`None`] if it was
+/// cancelled.
+pub(crate) fn show_verification_result(
+    state: UiDomainSync,
+    contact: SharedContact,
+    trust: Option<Trust>,
+) {
+    let open = state
+        .borrow_mut()
+        .tracked_widgets
+        .take_verification_dialog(&contact.id());
+    if let Some(dialog) = open {
+        dialog.close();
+    }
+
+    let text = match trust {
+        Some(Trust::Verified) => format!("{} is verified.", contact.username()),
+        Some(Trust::Suspicious) => format!(
+            "The words did not match for one of you.\n{} is marked as suspicious, someone may be listening in.",
+            contact.username()
+        ),
+        Some(other) => format!("{} is now {other}.", contact.username()),
+        None => format!("The verification of {} was cancelled.", contact.username()),
+    };
+
+    let dialog = gtk::Dialog::builder()
+        .title("Verification")
+        .modal(true)
+        .build();
+    let content_area = dialog.content_area();
+    content_area.set_margin_top(12);
+    content_area.set_margin_bottom(12);
+    content_area.set_margin_start(12);
+    content_area.set_margin_end(12);
+    content_area.append(&label(text));
+    dialog.add_button("OK", gtk::ResponseType::Ok);
+    dialog.connect_response(|dialog, _| dialog.close());
+    dialog.present();
+}
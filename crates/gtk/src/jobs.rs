@@ -4,68 +4,114 @@
 #![deny(clippy::await_holding_lock)]
 
 use log::trace;
-use sremp_client::domain::UiEvent;
+use sremp_client::domain::{UiCommand, UiEvent, tofu::PeerStatus};
 use sremp_core::current_function;
 
 use crate::{
     domain::{UiDomain, UiDomainSync, listen::ListenerStatus},
-    gui::{identity::show_identity_created_success, tofu::show_tofu_dialog},
+    gui::{
+        diagnostics::show_connection_stats,
+        identity::show_identity_created_success,
+        pairing::{show_pairing_dialog, show_pairing_result},
+        tofu::show_tofu_dialog,
+        verification::{show_sas_dialog, show_verification_result},
+    },
 };
 
 use gtk::glib;
 
+/// Number of events that are processed before the main loop gets control back
+const EVENT_BATCH_SIZE: usize = 64;
+
 pub(super) fn start_jobs(state: UiDomainSync) {
     glib::spawn_future_local(event_processor(state));
 }
 
 async fn event_processor(state: UiDomainSync) {
     loop {
-        {
+        // NOTE: events are processed in batches, so a burst is handled quickly without starving
+        // the main loop
+        for _ in 0..EVENT_BATCH_SIZE {
             // WARN: explcit binding and dropping the binding is required here, otherwise the
             // binding is held during the processing of the received event, even though the event
             // is owned and not bound to the held lock on the ui domain.
             // Holding the lock while processing the event may lead to deadlocks.
             let state_b = state.borrow();
-            if let Ok(event) = state_b.event_channel.try_recv() {
-                drop(state_b);
-                log::info!("Processing network event: {event}");
+            let Ok(event) = state_b.event_channel.try_recv() else {
+                break;
+            };
+            drop(state_b);
+            log::info!("Processing network event: {event}");
 
-                match event {
-                    UiEvent::ListenerStarted(addr) => {
-                        state.borrow_mut().listen_status = ListenerStatus::Active(addr);
-                        log::trace!(
-                            "Listener was started, text should show that is is running on {addr}"
-                        );
-                        update_listener_label(&state.borrow());
-                    }
-                    UiEvent::ListenerStopped => {
-                        update_listener_label(&state.borrow());
-                    }
-                    UiEvent::IdentitySet(iden) => {
-                        log::trace!("borrowing mutable ui domain state");
-                        // NOTE: Deadlock if the lock is still held above
-                        state.borrow_mut().apply_user_identity(iden.clone());
+            match event {
+                UiEvent::ListenerStarted(addr) => {
+                    state.borrow_mut().listen_status = ListenerStatus::Active(addr);
+                    log::trace!(
+                        "Listener was started, text should show that is is running on {addr}"
+                    );
+                    update_listener_label(&state.borrow());
+                }
+                UiEvent::ListenerStopped => {
+                    update_listener_label(&state.borrow());
+                }
+                UiEvent::IdentitySet(iden) => {
+                    log::trace!("borrowing mutable ui domain state");
+                    // NOTE: Deadlock if the lock is still held above
+                    state.borrow_mut().apply_user_identity(iden.clone());
+                }
+                UiEvent::LoadedChats(chats) => {
+                    if let Some(cl) = state.borrow_mut().tracked_widgets.chat_list_mut() {
+                        cl.replace_chats(chats);
                     }
-                    UiEvent::LoadedChats(chats) => {
-                        if let Some(cl) = state.borrow_mut().tracked_widgets.chat_list_mut() {
-                            cl.replace_chats(chats);
-                        }
+                }
+                UiEvent::SetKnownIdentities(contacts) => {
+                    state.borrow_mut().set_contacts(contacts);
+                }
+                UiEvent::ConnectionEstablished(socket, cid, status) => match status {
+                    PeerStatus::Trusted => {
+                        state.borrow().send_cmd(UiCommand::StartChat(cid));
                     }
-                    UiEvent::SetKnownIdentities(contacts) => {
-                        state.borrow_mut().set_contacts(contacts);
+                    PeerStatus::Rejected => {
+                        log::info!("Closing the connection to rejected contact {cid}");
+                        state.borrow().send_cmd(UiCommand::Disconnect(socket));
                     }
-                    UiEvent::ConnectionEstablished(socket, cid) => {
+                    status => {
                         let contact = state.borrow().contacts()[&cid].clone();
                         // open TOFU window and let the user choose if they trust the
                         // identity.
                         // If so, create a new chat with the peer.
                         // If not, disconnect.
                         // This should not block processing of UiEvents, i think?
-                        show_tofu_dialog(state.clone(), contact, socket);
-                    }
-                    other => {
-                        log::warn!("Received unimplemented Ui event: {other}")
+                        show_tofu_dialog(state.clone(), contact, socket, status);
                     }
+                },
+                UiEvent::ConnectionStats(stats) => show_connection_stats(&stats),
+                UiEvent::VerificationStarted(cid, sas) => {
+                    let contact = state.borrow().contacts()[&cid].clone();
+                    show_sas_dialog(state.clone(), contact, sas);
+                }
+                UiEvent::VerificationFinished(cid, trust) => {
+                    let contact = state.borrow().contacts()[&cid].clone();
+                    show_verification_result(state.clone(), contact, Some(trust));
+                }
+                UiEvent::VerificationCancelled(cid) => {
+                    let contact = state.borrow().contacts()[&cid].clone();
+                    show_verification_result(state.clone(), contact, None);
+                }
+                UiEvent::PairingRequested(cid) => {
+                    let contact = state.borrow().contacts()[&cid].clone();
+                    show_pairing_dialog(state.clone(), contact, true);
+                }
+                UiEvent::PairingFinished(cid, paired) => {
+                    let contact = state.borrow().contacts()[&cid].clone();
+                    show_pairing_result(state.clone(), contact, Some(paired));
+                }
+                UiEvent::PairingCancelled(cid) => {
+                    let contact = state.borrow().contacts()[&cid].clone();
+                    show_pairing_result(state.clone(), contact, None);
+                }
+                other => {
+                    log::warn!("Received unimplemented Ui event: {other}")
                 }
             }
         }
@@ -1,11 +1,15 @@
 use std::sync::OnceLock;
 
-use async_channel::{Receiver, Sender};
+use async_channel::Sender;
 use gtk::gio::ApplicationFlags;
 use gtk::prelude::*;
 use gtk::{Application, glib};
 
-use sremp_client::domain::{UiCommand, UiEvent};
+use sremp_client::domain::{UI_COMMAND_CAPACITY, UI_EVENT_BULK_CAPACITY, UiCommand, UiEvent};
+use sremp_core::domain::{
+    NET_COMMAND_CAPACITY, NET_EVENT_BULK_CAPACITY,
+    priority::{PriorityReceiver, priority_channel},
+};
 
 use crate::actions::register_actions;
 use crate::domain::UiDomain;
@@ -15,6 +19,7 @@ mod actions;
 mod domain;
 mod gui;
 mod jobs;
+mod qr;
 
 pub(crate) const GUI_SPACING_MID: i32 = 8;
 pub(crate) const GUI_SPACING_LARGE: i32 = 12;
@@ -29,11 +34,11 @@ fn main() -> glib::ExitCode {
     setup_logging();
     let mut rt = tokio::runtime::Runtime::new().expect("could not create tokio runtime");
 
-    let (net_command_tx, net_command_rx) = async_channel::unbounded();
-    let (net_event_tx, net_event_rx) = async_channel::unbounded();
+    let (net_command_tx, net_command_rx) = async_channel::bounded(NET_COMMAND_CAPACITY);
+    let (net_event_tx, net_event_rx) = priority_channel(NET_EVENT_BULK_CAPACITY);
 
-    let (ui_command_tx, ui_command_rx) = async_channel::unbounded();
-    let (ui_event_tx, ui_event_rx) = async_channel::unbounded();
+    let (ui_command_tx, ui_command_rx) = async_channel::bounded(UI_COMMAND_CAPACITY);
+    let (ui_event_tx, ui_event_rx) = priority_channel(UI_EVENT_BULK_CAPACITY);
 
     let net_domain = sremp_core::domain::NetworkDomain::new();
     net_domain
@@ -56,7 +61,7 @@ fn main() -> glib::ExitCode {
 
 fn start_gui(
     command_tx: Sender<UiCommand>,
-    event_rx: Receiver<UiEvent>,
+    event_rx: PriorityReceiver<UiEvent>,
     rt: tokio::runtime::Runtime,
 ) -> glib::ExitCode {
     let app = Application::builder().application_id(APP_ID).build();
@@ -0,0 +1,52 @@
+//! QR codes for sharing invitations in person.
+//!
+//! Rendering and reading the codes is done by [`sremp_client::qr`], this only converts between
+//! its grayscale images and textures.
+
+use gtk::{gdk, gio, glib, prelude::*};
+use sremp_client::qr::{self, Luma, QrError};
+
+/// Renders `text` as QR code into a texture.
+pub(crate) fn render(text: &str) -> Result<gdk::MemoryTexture, QrError> {
+    let image = qr::render(text)?;
+    let pixels: Vec<u8> = image.pixels.iter().flat_map(|&p| [p, p, p]).collect();
+
+    let side = i32::try_from(image.width).expect("a QR code of version 40 is 1068 pixels wide");
+    Ok(gdk::MemoryTexture::new(
+        side,
+        side,
+        gdk::MemoryFormat::R8g8b8,
+        &glib::Bytes::from_owned(pixels),
+        image.width * 3,
+    ))
+}
+
+/// Loads an image file and reads the text of the first QR code found in it.
+///
+/// Returns `Ok(None)` if the image contains no readable QR code.
+pub(crate) fn scan_file(file: &gio::File) -> Result<Option<String>, glib::Error> {
+    let texture = gdk::Texture::from_file(file)?;
+    let (width, height) = (texture.width() as usize, texture.height() as usize);
+    let mut argb = vec![0; width * height * 4];
+    texture.download(&mut argb, width * 4);
+
+    // premultiplied BGRA, transparent pixels are taken as white background
+    let pixels = argb
+        .chunks_exact(4)
+        .map(|px| {
+            let light = 255 - px[3] as u32;
+            let (b, g, r) = (
+                px[0] as u32 + light,
+                px[1] as u32 + light,
+                px[2] as u32 + light,
+            );
+            ((r * 299 + g * 587 + b * 114) / 1000).min(255) as u8
+        })
+        .collect();
+
+    Ok(qr::scan(&Luma {
+        width,
+        height,
+        pixels,
+    }))
+}
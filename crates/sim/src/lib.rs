@@ -0,0 +1,136 @@
+//! Deterministic in-memory network simulator for testing SREMP.
+//!
+//! A [`Simulation`] runs any number of [`SimNode`]s in one process. Each node is a complete
+//! client (a [`NetworkDomain`](sremp_core::domain::NetworkDomain) and a
+//! [`ClientDomain`](sremp_client::domain::ClientDomain)), whose connections go over the virtual
+//! links of a [`SimNetwork`] instead of TCP. Links can be given latency, loss and reordering, and
+//! can be cut and restored.
+//!
+//! The simulation runs on a single threaded runtime with a paused clock: time only advances when
+//! every task is waiting, and then jumps to the next timer. Scenarios therefore run as fast as the
+//! CPU allows, no matter how much latency is simulated, and the link behaviour is the same for
+//! every run with the same seed.
+//!
+//! ```no_run
+//! use sremp_client::domain::{UiCommand, UiEvent};
+//! use sremp_sim::Simulation;
+//!
+//! let mut sim = Simulation::new(42);
+//! let alice = sim.spawn_node([10, 0, 0, 1], "alice");
+//! let bob = sim.spawn_node([10, 0, 0, 2], "bob");
+//! sim.run(async {
+//!     let addr = bob.listen(4000).await;
+//!     alice.command(UiCommand::Connect(addr)).await;
+//!     bob.connected_to(&alice.id()).await;
+//!     alice.connected_to(&bob.id()).await;
+//!
+//!     alice
+//!         .command(UiCommand::SendMessage(bob.id(), alice.message("hi")))
+//!         .await;
+//!     bob.wait_for(|e| match e {
+//!         UiEvent::IncomingMessage(_, _, msg) => Some(msg.text.clone()),
+//!         _ => None,
+//!     })
+//!     .await;
+//! });
+//! ```
+
+use async_channel::Sender;
+use sremp_client::domain::{ClientDomain, UiCommand, UiEvent};
+use sremp_core::{domain::priority::PriorityReceiver, identity::UserIdentity};
+use std::{future::Future, net::IpAddr, path::Path};
+
+mod link;
+pub use link::*;
+
+mod network;
+pub use network::*;
+
+mod node;
+pub use node::*;
+
+mod peer;
+pub use peer::SimPeer;
+
+mod stream;
+pub use stream::SimStream;
+
+/// A [`SimNetwork`] with the nodes on it and the runtime they run on
+#[derive(Debug)]
+pub struct Simulation {
+    network: SimNetwork,
+    rt: tokio::runtime::Runtime,
+}
+
+impl Simulation {
+    /// Creates a new [`Simulation`], all randomness of the links is derived from `seed`.
+    pub fn new(seed: u64) -> Self {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .start_paused(true)
+            .build()
+            .expect("could not build the simulation runtime");
+        Self {
+            network: SimNetwork::new(seed),
+            rt,
+        }
+    }
+
+    #[inline]
+    pub fn network(&self) -> &SimNetwork {
+        &self.network
+    }
+
+    /// Starts a new node on the host with address `ip`.
+    pub fn spawn_node(&mut self, ip: impl Into<IpAddr>, username: &str) -> SimNode {
+        let user = UserIdentity::create(username).expect("could not create identity");
+        self.spawn_node_with(ip, user)
+    }
+
+    /// Starts a new node on the host with address `ip` that uses an existing identity.
+    pub fn spawn_node_with(&mut self, ip: impl Into<IpAddr>, user: UserIdentity) -> SimNode {
+        SimNode::spawn(
+            &self.network,
+            ip.into(),
+            user,
+            ClientDomain::new(),
+            &mut self.rt,
+        )
+    }
+
+    /// Starts a new node with the identity `user` whose client keeps its state in the file at
+    /// `state`, see [`ClientDomain::load`].
+    pub fn spawn_node_with_state(
+        &mut self,
+        ip: impl Into<IpAddr>,
+        user: UserIdentity,
+        state: &Path,
+    ) -> SimNode {
+        let client = ClientDomain::load(state).expect("could not load the client state");
+        SimNode::spawn(&self.network, ip.into(), user, client, &mut self.rt)
+    }
+
+    /// Starts a network domain without a client on the host with address `ip`, see [`SimPeer`].
+    pub fn spawn_peer(&mut self, ip: impl Into<IpAddr>, username: &str) -> SimPeer {
+        let user = UserIdentity::create(username).expect("could not create identity");
+        SimPeer::spawn(&self.network, ip.into(), user, &mut self.rt)
+    }
+
+    /// Starts the domains of a client on the host with address `ip` without a [`SimNode`], for
+    /// frontends that drive the [`ClientDomain`](sremp_client::domain::ClientDomain) themselves.
+    ///
+    /// No identity is set, that is up to the frontend.
+    pub fn spawn_client(
+        &mut self,
+        ip: impl Into<IpAddr>,
+    ) -> (Sender<UiCommand>, PriorityReceiver<UiEvent>) {
+        let (commands, events, _) =
+            spawn_domains(&self.network, ip.into(), ClientDomain::new(), &mut self.rt);
+        (commands, events)
+    }
+
+    /// Runs a scenario to completion, driving all nodes while it waits.
+    pub fn run<F: Future>(&mut self, scenario: F) -> F::Output {
+        self.rt.block_on(scenario)
+    }
+}
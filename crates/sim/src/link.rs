@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+use rand::Rng;
+
+/// Properties of the virtual link between two hosts of a [`SimNetwork`](crate::SimNetwork).
+///
+/// Like TCP, the streams over a link are reliable and ordered. Loss and reordering of segments do
+/// not corrupt the stream, but delay it: a lost segment is retransmitted after
+/// [`retransmit_timeout`](Self::retransmit_timeout), and segments that arrive out of order are
+/// held back until the segments before them have arrived.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinkConfig {
+    /// One-way delay of each segment
+    pub latency: Duration,
+    /// Random extra delay of each segment, between zero and this
+    pub jitter: Duration,
+    /// Probability that a segment is lost and needs to be retransmitted
+    pub loss: f64,
+    /// Probability that a segment arrives after the segments sent after it
+    pub reorder: f64,
+    /// Delay until a lost segment is sent again
+    pub retransmit_timeout: Duration,
+}
+
+impl LinkConfig {
+    /// A link without delay and loss
+    pub const PERFECT: Self = Self {
+        latency: Duration::ZERO,
+        jitter: Duration::ZERO,
+        loss: 0.0,
+        reorder: 0.0,
+        retransmit_timeout: Duration::from_millis(200),
+    };
+
+    /// Creates a [`LinkConfig`] with the given latency and nothing else.
+    pub fn with_latency(latency: Duration) -> Self {
+        Self {
+            latency,
+            ..Self::PERFECT
+        }
+    }
+
+    /// Draws the time it takes a segment to cross this link.
+    pub(crate) fn segment_delay(&self, rng: &mut impl Rng) -> Duration {
+        let mut delay = self.latency;
+        if !self.jitter.is_zero() {
+            delay += rng.gen_range(Duration::ZERO..=self.jitter);
+        }
+        // NOTE: a retransmitted segment may be lost again
+        while self.loss > 0.0 && rng.gen_bool(self.loss.min(0.99)) {
+            delay += self.retransmit_timeout;
+        }
+        if self.reorder > 0.0 && rng.gen_bool(self.reorder.min(1.0)) {
+            // overtaken by the next segments, which then have to wait for it
+            delay += self.latency.max(Duration::from_millis(1));
+        }
+        delay
+    }
+}
+
+impl Default for LinkConfig {
+    fn default() -> Self {
+        Self::PERFECT
+    }
+}
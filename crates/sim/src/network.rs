@@ -0,0 +1,247 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use rand::{SeedableRng, rngs::StdRng};
+use sremp_core::net::transport::{BoxFuture, BoxedStream, Transport, TransportListener};
+
+use crate::{
+    link::LinkConfig,
+    stream::{ConnectionHandle, SimStream},
+};
+
+/// First port that is given out for listeners bound to port 0 and for the local end of
+/// connections
+const FIRST_EPHEMERAL_PORT: u16 = 49152;
+
+/// A virtual network connecting any number of hosts in one process.
+///
+/// Hosts are identified by their [`IpAddr`] and reach the network through a [`SimTransport`]. All
+/// randomness of the links comes from one generator seeded in [`SimNetwork::new`], and all delays
+/// use the tokio clock, so a simulation on a runtime with a paused clock is reproducible.
+#[derive(Debug, Clone)]
+pub struct SimNetwork {
+    inner: Arc<Mutex<NetworkState>>,
+}
+
+#[derive(Debug)]
+struct NetworkState {
+    rng: StdRng,
+    default_link: LinkConfig,
+    links: HashMap<(IpAddr, IpAddr), LinkConfig>,
+    partitions: HashSet<(IpAddr, IpAddr)>,
+    listeners: HashMap<SocketAddr, async_channel::Sender<(BoxedStream, SocketAddr)>>,
+    connections: Vec<ConnectionHandle>,
+    next_port: u16,
+}
+
+/// [`Transport`] of one host in a [`SimNetwork`]
+#[derive(Debug, Clone)]
+pub struct SimTransport {
+    network: SimNetwork,
+    ip: IpAddr,
+}
+
+/// Listener of a [`SimTransport`], stops listening when dropped
+#[derive(Debug)]
+pub struct SimListener {
+    network: SimNetwork,
+    local: SocketAddr,
+    incoming: async_channel::Receiver<(BoxedStream, SocketAddr)>,
+}
+
+impl SimNetwork {
+    /// Creates an empty network, links use the seed for their randomness.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(NetworkState {
+                rng: StdRng::seed_from_u64(seed),
+                default_link: LinkConfig::default(),
+                links: HashMap::new(),
+                partitions: HashSet::new(),
+                listeners: HashMap::new(),
+                connections: Vec::new(),
+                next_port: FIRST_EPHEMERAL_PORT,
+            })),
+        }
+    }
+
+    /// Returns the [`Transport`] for the host with this address.
+    pub fn transport(&self, ip: impl Into<IpAddr>) -> SimTransport {
+        SimTransport {
+            network: self.clone(),
+            ip: ip.into(),
+        }
+    }
+
+    /// Sets the link used between hosts that have no link of their own.
+    pub fn set_default_link(&self, link: LinkConfig) {
+        self.state().default_link = link;
+    }
+
+    /// Sets the link between two hosts, in both directions.
+    pub fn set_link(&self, a: impl Into<IpAddr>, b: impl Into<IpAddr>, link: LinkConfig) {
+        self.state()
+            .links
+            .insert(host_pair(a.into(), b.into()), link);
+    }
+
+    /// Cuts the link between two hosts.
+    ///
+    /// This acts like an outage that lasts long enough for the connections to time out: all
+    /// connections between the hosts are reset, and new connections fail until
+    /// [`heal`](Self::heal) is called.
+    pub fn partition(&self, a: impl Into<IpAddr>, b: impl Into<IpAddr>) {
+        let pair = host_pair(a.into(), b.into());
+        let mut state = self.state();
+        log::info!("Partitioning {} from {}", pair.0, pair.1);
+        state.partitions.insert(pair);
+        state.connections.retain(|c| {
+            if host_pair(c.ends.0.ip(), c.ends.1.ip()) == pair {
+                c.reset();
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    /// Restores the link between two hosts after a [`partition`](Self::partition).
+    pub fn heal(&self, a: impl Into<IpAddr>, b: impl Into<IpAddr>) {
+        let pair = host_pair(a.into(), b.into());
+        log::info!("Healing the link between {} and {}", pair.0, pair.1);
+        self.state().partitions.remove(&pair);
+    }
+
+    /// Returns the number of connections that are currently open.
+    pub fn open_connections(&self) -> usize {
+        let mut state = self.state();
+        state.connections.retain(|c| !c.is_closed());
+        state.connections.len()
+    }
+
+    pub(crate) fn segment_delay(&self, from: IpAddr, to: IpAddr) -> Duration {
+        let mut state = self.state();
+        let link = state.link(from, to);
+        link.segment_delay(&mut state.rng)
+    }
+
+    #[inline]
+    fn state(&self) -> MutexGuard<'_, NetworkState> {
+        self.inner
+            .lock()
+            .expect("simulated network state is poisoned")
+    }
+
+    async fn connect(&self, from: IpAddr, remote: SocketAddr) -> io::Result<BoxedStream> {
+        let latency = {
+            let state = self.state();
+            if state.partitions.contains(&host_pair(from, remote.ip())) {
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+            state.link(from, remote.ip()).latency
+        };
+        // NOTE: the stream is only usable after one round trip
+        tokio::time::sleep(latency * 2).await;
+
+        let mut state = self.state();
+        let Some(listener) = state.listeners.get(&remote).cloned() else {
+            return Err(io::ErrorKind::ConnectionRefused.into());
+        };
+        let local = SocketAddr::new(from, state.ephemeral_port());
+        let (ours, theirs, handle) = SimStream::pair(self, local, remote);
+        if listener.try_send((Box::new(theirs), local)).is_err() {
+            return Err(io::ErrorKind::ConnectionRefused.into());
+        }
+        state.connections.push(handle);
+        log::debug!("Simulated connection {local} -> {remote} established");
+        Ok(Box::new(ours))
+    }
+
+    fn bind(&self, ip: IpAddr, local: SocketAddr) -> io::Result<SimListener> {
+        let mut state = self.state();
+        // NOTE: listening on the unspecified address means listening on the address of the host
+        let ip = if local.ip().is_unspecified() {
+            ip
+        } else {
+            local.ip()
+        };
+        let port = match local.port() {
+            0 => state.ephemeral_port(),
+            p => p,
+        };
+        let local = SocketAddr::new(ip, port);
+        if state.listeners.contains_key(&local) {
+            return Err(io::ErrorKind::AddrInUse.into());
+        }
+        let (tx, rx) = async_channel::unbounded();
+        state.listeners.insert(local, tx);
+        Ok(SimListener {
+            network: self.clone(),
+            local,
+            incoming: rx,
+        })
+    }
+}
+
+impl NetworkState {
+    fn link(&self, a: IpAddr, b: IpAddr) -> LinkConfig {
+        self.links
+            .get(&host_pair(a, b))
+            .copied()
+            .unwrap_or(self.default_link)
+    }
+
+    fn ephemeral_port(&mut self) -> u16 {
+        let port = self.next_port;
+        self.next_port = self
+            .next_port
+            .checked_add(1)
+            .unwrap_or(FIRST_EPHEMERAL_PORT);
+        port
+    }
+}
+
+impl Transport for SimTransport {
+    fn connect(&self, remote: SocketAddr) -> BoxFuture<'_, io::Result<BoxedStream>> {
+        Box::pin(self.network.connect(self.ip, remote))
+    }
+
+    fn bind(&self, local: SocketAddr) -> BoxFuture<'_, io::Result<Box<dyn TransportListener>>> {
+        Box::pin(async move {
+            let listener = self.network.bind(self.ip, local)?;
+            Ok(Box::new(listener) as Box<dyn TransportListener>)
+        })
+    }
+}
+
+impl TransportListener for SimListener {
+    fn accept(&self) -> BoxFuture<'_, io::Result<(BoxedStream, SocketAddr)>> {
+        Box::pin(async move {
+            self.incoming
+                .recv()
+                .await
+                .map_err(|_| io::ErrorKind::NotConnected.into())
+        })
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.local)
+    }
+}
+
+impl Drop for SimListener {
+    fn drop(&mut self) {
+        self.network.state().listeners.remove(&self.local);
+    }
+}
+
+/// Links are symmetric, so the pair is stored in a fixed order
+#[inline]
+fn host_pair(a: IpAddr, b: IpAddr) -> (IpAddr, IpAddr) {
+    if a <= b { (a, b) } else { (b, a) }
+}
@@ -0,0 +1,193 @@
+use std::{
+    fmt::Display,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_channel::Sender;
+use sremp_client::{
+    domain::{ClientDomain, UI_COMMAND_CAPACITY, UI_EVENT_BULK_CAPACITY, UiCommand, UiEvent},
+    error::ClientResult,
+};
+use sremp_core::{
+    chat::messages::{Message, SharedMessage},
+    domain::{
+        NET_COMMAND_CAPACITY, NET_EVENT_BULK_CAPACITY, NetworkDomain,
+        priority::{PriorityReceiver, priority_channel},
+    },
+    identity::{ContactId, UserIdentity},
+};
+
+use crate::network::SimNetwork;
+
+/// How long [`SimNode`] waits for an event before the scenario is considered stuck.
+///
+/// This is virtual time, waiting for it takes no real time on a paused clock.
+pub const EVENT_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// A complete client in a simulation: a [`NetworkDomain`] on a simulated host and the
+/// [`ClientDomain`] on top of it. The scenario takes the role of the UI.
+#[derive(Debug)]
+pub struct SimNode {
+    pub ip: IpAddr,
+    pub user: Arc<UserIdentity>,
+    commands: Sender<UiCommand>,
+    events: PriorityReceiver<UiEvent>,
+    history: Mutex<Vec<UiEvent>>,
+    client: tokio::task::JoinHandle<ClientResult<()>>,
+}
+
+impl SimNode {
+    /// Starts the domains of a new node with the identity `user`, on top of the state in `client`.
+    pub(crate) fn spawn(
+        network: &SimNetwork,
+        ip: IpAddr,
+        user: UserIdentity,
+        client: ClientDomain,
+        rt: &mut tokio::runtime::Runtime,
+    ) -> Self {
+        let (ui_cmd_tx, ui_evt_rx, client) = spawn_domains(network, ip, client, rt);
+        let user = Arc::new(user);
+        ui_cmd_tx
+            .send_blocking(UiCommand::SetIdentity(Some(user.clone())))
+            .expect("client domain has stopped");
+
+        Self {
+            ip,
+            user,
+            commands: ui_cmd_tx,
+            events: ui_evt_rx,
+            history: Mutex::new(Vec::new()),
+            client,
+        }
+    }
+
+    /// Stops the node like a frontend that quits, and waits until its [`ClientDomain`] has
+    /// stopped and saved its state.
+    pub async fn stop(self) {
+        self.commands.close();
+        let drain = async { while self.events.recv().await.is_ok() {} };
+        tokio::select! {
+            biased;
+            stopped = self.client => stopped
+                .expect("client domain has panicked")
+                .expect("client domain has failed"),
+            () = drain => (),
+        }
+    }
+
+    #[inline]
+    pub fn id(&self) -> ContactId {
+        self.user.identity.id()
+    }
+
+    /// Creates a chat message written by this node.
+    pub fn message(&self, text: impl Display) -> SharedMessage {
+        Message::new(text, chrono::Utc::now(), self.id()).into()
+    }
+
+    /// Sends a command to the [`ClientDomain`], like the UI would.
+    pub async fn command(&self, command: UiCommand) {
+        self.commands
+            .send(command)
+            .await
+            .expect("client domain has stopped");
+    }
+
+    /// Waits for the next [`UiEvent`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if no event arrives within [`EVENT_TIMEOUT`].
+    pub async fn next_event(&self) -> UiEvent {
+        let event = tokio::time::timeout(EVENT_TIMEOUT, self.events.recv())
+            .await
+            .unwrap_or_else(|_| panic!("{self} has not received an event in {EVENT_TIMEOUT:?}"))
+            .expect("client domain has stopped");
+        log::debug!("{self} received ui event: {event}");
+        self.history
+            .lock()
+            .expect("event history is poisoned")
+            .push(event.clone());
+        event
+    }
+
+    /// Skips events until `f` returns [`Some`] for one of them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an event takes longer than [`EVENT_TIMEOUT`].
+    pub async fn wait_for<T>(&self, mut f: impl FnMut(&UiEvent) -> Option<T>) -> T {
+        loop {
+            if let Some(t) = f(&self.next_event().await) {
+                return t;
+            }
+        }
+    }
+
+    /// Returns the number of events that wait to be received.
+    pub fn pending_events(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Starts listening on `port` and returns the address peers can connect to.
+    pub async fn listen(&self, port: u16) -> SocketAddr {
+        self.command(UiCommand::StartListener(SocketAddr::new(self.ip, port)))
+            .await;
+        self.wait_for(|e| match e {
+            UiEvent::ListenerStarted(addr) => Some(*addr),
+            _ => None,
+        })
+        .await
+    }
+
+    /// Waits until a connection with `peer` is established and returns its remote address.
+    pub async fn connected_to(&self, peer: &ContactId) -> SocketAddr {
+        self.wait_for(|e| match e {
+            UiEvent::ConnectionEstablished(addr, id, _) if id == peer => Some(*addr),
+            _ => None,
+        })
+        .await
+    }
+
+    /// Returns all events received so far, in order.
+    pub fn history(&self) -> Vec<UiEvent> {
+        self.history
+            .lock()
+            .expect("event history is poisoned")
+            .clone()
+    }
+}
+
+/// Starts a [`NetworkDomain`] on the simulated host `ip` and the `client` on top of it, returns
+/// the channels of the UI side and the task of the client.
+pub(crate) fn spawn_domains(
+    network: &SimNetwork,
+    ip: IpAddr,
+    client: ClientDomain,
+    rt: &mut tokio::runtime::Runtime,
+) -> (
+    Sender<UiCommand>,
+    PriorityReceiver<UiEvent>,
+    tokio::task::JoinHandle<ClientResult<()>>,
+) {
+    let (net_cmd_tx, net_cmd_rx) = async_channel::bounded(NET_COMMAND_CAPACITY);
+    let (net_evt_tx, net_evt_rx) = priority_channel(NET_EVENT_BULK_CAPACITY);
+    let (ui_cmd_tx, ui_cmd_rx) = async_channel::bounded(UI_COMMAND_CAPACITY);
+    let (ui_evt_tx, ui_evt_rx) = priority_channel(UI_EVENT_BULK_CAPACITY);
+
+    NetworkDomain::with_transport(Arc::new(network.transport(ip)))
+        .start(net_cmd_rx, net_evt_tx, rt)
+        .expect("could not start the network domain");
+    let client = client
+        .start(net_cmd_tx, net_evt_rx, ui_cmd_rx, ui_evt_tx, rt)
+        .expect("could not start the client domain");
+    (ui_cmd_tx, ui_evt_rx, client)
+}
+
+impl Display for SimNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.user.identity.username(), self.ip)
+    }
+}
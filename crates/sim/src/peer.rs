@@ -0,0 +1,104 @@
+use std::{
+    fmt::Display,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
+use async_channel::Sender;
+use sremp_core::{
+    domain::{
+        NET_COMMAND_CAPACITY, NET_EVENT_BULK_CAPACITY, NetworkCommand, NetworkDomain, NetworkEvent,
+        priority::{PriorityReceiver, priority_channel},
+    },
+    identity::{ContactId, UserIdentity},
+};
+
+use crate::{network::SimNetwork, node::EVENT_TIMEOUT};
+
+/// A [`NetworkDomain`] on a simulated host without a client on top of it. The scenario drives
+/// it directly, so it can send what no client would, like malformed envelopes.
+#[derive(Debug)]
+pub struct SimPeer {
+    pub ip: IpAddr,
+    pub user: Arc<UserIdentity>,
+    commands: Sender<NetworkCommand>,
+    events: PriorityReceiver<NetworkEvent>,
+}
+
+impl SimPeer {
+    pub(crate) fn spawn(
+        network: &SimNetwork,
+        ip: IpAddr,
+        user: UserIdentity,
+        rt: &mut tokio::runtime::Runtime,
+    ) -> Self {
+        let (net_cmd_tx, net_cmd_rx) = async_channel::bounded(NET_COMMAND_CAPACITY);
+        let (net_evt_tx, net_evt_rx) = priority_channel(NET_EVENT_BULK_CAPACITY);
+        NetworkDomain::with_transport(Arc::new(network.transport(ip)))
+            .start(net_cmd_rx, net_evt_tx, rt)
+            .expect("could not start the network domain");
+
+        let user = Arc::new(user);
+        net_cmd_tx
+            .send_blocking(NetworkCommand::SetIdentity(Some(user.clone())))
+            .expect("network domain has stopped");
+        Self {
+            ip,
+            user,
+            commands: net_cmd_tx,
+            events: net_evt_rx,
+        }
+    }
+
+    #[inline]
+    pub fn id(&self) -> ContactId {
+        self.user.identity.id()
+    }
+
+    /// Sends a command to the [`NetworkDomain`], like a client would.
+    pub async fn command(&self, command: NetworkCommand) {
+        self.commands
+            .send(command)
+            .await
+            .expect("network domain has stopped");
+    }
+
+    /// Skips events until `f` returns [`Some`] for one of them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an event takes longer than [`EVENT_TIMEOUT`].
+    pub async fn wait_for<T>(&self, mut f: impl FnMut(&NetworkEvent) -> Option<T>) -> T {
+        loop {
+            let event = tokio::time::timeout(EVENT_TIMEOUT, self.events.recv())
+                .await
+                .unwrap_or_else(|_| panic!("{self} has not received an event in {EVENT_TIMEOUT:?}"))
+                .expect("network domain has stopped");
+            log::debug!("{self} received network event: {event}");
+            if let Some(t) = f(&event) {
+                return t;
+            }
+        }
+    }
+
+    /// Connects to `remote` and waits until the connection is established.
+    pub async fn connect(&self, remote: SocketAddr) {
+        self.command(NetworkCommand::Connect(remote)).await;
+        self.wait_for(|e| match e {
+            NetworkEvent::ConnectionEstablished(addr, ..) if *addr == remote => Some(()),
+            _ => None,
+        })
+        .await
+    }
+}
+
+impl Display for SimPeer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({}, network only)",
+            self.user.identity.username(),
+            self.ip
+        )
+    }
+}
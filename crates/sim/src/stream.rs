@@ -0,0 +1,195 @@
+use std::{
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    task::{Context, Poll},
+};
+
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    sync::mpsc,
+    time::Instant,
+};
+
+use crate::network::SimNetwork;
+
+/// What a stream receives from the link: data, or the error the connection broke with
+pub(crate) type Delivery = Result<Vec<u8>, io::ErrorKind>;
+
+/// One end of a virtual connection, the simulated counterpart of a TCP stream.
+#[derive(Debug)]
+pub struct SimStream {
+    local: SocketAddr,
+    peer: SocketAddr,
+    incoming: mpsc::UnboundedReceiver<Delivery>,
+    pending: Vec<u8>,
+    pos: usize,
+    outgoing: Option<mpsc::UnboundedSender<(Instant, Vec<u8>)>>,
+    reset: Arc<AtomicBool>,
+}
+
+/// Handles the network keeps of each open connection, so it can reset it
+#[derive(Debug)]
+pub(crate) struct ConnectionHandle {
+    pub(crate) ends: (SocketAddr, SocketAddr),
+    pub(crate) reset: Arc<AtomicBool>,
+    pub(crate) deliver: [mpsc::WeakUnboundedSender<Delivery>; 2],
+}
+
+impl SimStream {
+    /// Creates both ends of a new connection, with a task for each direction that delays the
+    /// data according to the link between the ends.
+    pub(crate) fn pair(
+        network: &SimNetwork,
+        a: SocketAddr,
+        b: SocketAddr,
+    ) -> (Self, Self, ConnectionHandle) {
+        let reset = Arc::new(AtomicBool::new(false));
+        let (a_out, a_link) = mpsc::unbounded_channel();
+        let (b_out, b_link) = mpsc::unbounded_channel();
+        let (to_a, a_in) = mpsc::unbounded_channel();
+        let (to_b, b_in) = mpsc::unbounded_channel();
+
+        let handle = ConnectionHandle {
+            ends: (a, b),
+            reset: reset.clone(),
+            deliver: [to_a.downgrade(), to_b.downgrade()],
+        };
+        tokio::spawn(pump(network.clone(), a, b, a_link, to_b, reset.clone()));
+        tokio::spawn(pump(network.clone(), b, a, b_link, to_a, reset.clone()));
+
+        let end = |local, peer, incoming, outgoing| Self {
+            local,
+            peer,
+            incoming,
+            pending: Vec::new(),
+            pos: 0,
+            outgoing: Some(outgoing),
+            reset: reset.clone(),
+        };
+        (end(a, b, a_in, a_out), end(b, a, b_in, b_out), handle)
+    }
+
+    #[inline]
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local
+    }
+
+    #[inline]
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer
+    }
+}
+
+impl ConnectionHandle {
+    /// Breaks the connection, both ends get a [`io::ErrorKind::ConnectionReset`].
+    pub(crate) fn reset(&self) {
+        self.reset.store(true, Ordering::Relaxed);
+        for deliver in &self.deliver {
+            if let Some(deliver) = deliver.upgrade() {
+                _ = deliver.send(Err(io::ErrorKind::ConnectionReset));
+            }
+        }
+    }
+
+    #[inline]
+    pub(crate) fn is_closed(&self) -> bool {
+        self.deliver.iter().all(|d| d.strong_count() == 0)
+    }
+}
+
+/// Moves the segments written on one end to the other end, after the delay of the link.
+async fn pump(
+    network: SimNetwork,
+    from: SocketAddr,
+    to: SocketAddr,
+    mut link: mpsc::UnboundedReceiver<(Instant, Vec<u8>)>,
+    deliver: mpsc::UnboundedSender<Delivery>,
+    reset: Arc<AtomicBool>,
+) {
+    let mut last_delivery = Instant::now();
+    while let Some((sent, segment)) = link.recv().await {
+        // NOTE: the stream is ordered, a segment is never delivered before the one in front of it
+        let delivery = (sent + network.segment_delay(from.ip(), to.ip())).max(last_delivery);
+        last_delivery = delivery;
+        tokio::time::sleep_until(delivery).await;
+        if reset.load(Ordering::Relaxed) || deliver.send(Ok(segment)).is_err() {
+            return;
+        }
+    }
+    // the sending end was shut down or dropped, `deliver` is dropped here and the receiving end
+    // reads EOF
+}
+
+impl AsyncRead for SimStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        loop {
+            if self.pos < self.pending.len() {
+                let len = usize::min(buf.remaining(), self.pending.len() - self.pos);
+                buf.put_slice(&self.pending[self.pos..self.pos + len]);
+                self.pos += len;
+                return Poll::Ready(Ok(()));
+            }
+            match self.incoming.poll_recv(cx) {
+                Poll::Ready(Some(Ok(segment))) => {
+                    self.pending = segment;
+                    self.pos = 0;
+                }
+                Poll::Ready(Some(Err(kind))) => return Poll::Ready(Err(kind.into())),
+                // EOF
+                Poll::Ready(None) => return Poll::Ready(Ok(())),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl AsyncWrite for SimStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        if self.reset.load(Ordering::Relaxed) {
+            return Poll::Ready(Err(io::ErrorKind::ConnectionReset.into()));
+        }
+        let Some(outgoing) = &self.outgoing else {
+            return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+        };
+        match outgoing.send((Instant::now(), buf.to_vec())) {
+            Ok(()) => Poll::Ready(Ok(buf.len())),
+            Err(_) => Poll::Ready(Err(io::ErrorKind::BrokenPipe.into())),
+        }
+    }
+
+    /// Sends all buffers as one segment, like a single write to a socket
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let segment: Vec<u8> = bufs.iter().flat_map(|b| b.iter().copied()).collect();
+        self.poll_write(cx, &segment)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        true
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.outgoing = None;
+        Poll::Ready(Ok(()))
+    }
+}
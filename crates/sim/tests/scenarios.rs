@@ -0,0 +1,922 @@
+use std::time::Duration;
+
+use chrono::TimeDelta;
+use sremp_client::domain::{
+    UI_EVENT_BULK_CAPACITY, UiCommand, UiEvent, outbox::DeliveryState, tofu::PeerStatus,
+};
+use sremp_core::domain::{NET_EVENT_BULK_CAPACITY, NetworkCommand, NetworkEvent};
+use sremp_core::identity::{
+    IdentityStatement, PairingCode, ShortAuthString, Succession, Trust, UserIdentity,
+    invitation::Invitation,
+};
+use sremp_core::net::envelope::Envelope;
+use sremp_sim::{LinkConfig, SimNetwork, SimNode, Simulation};
+
+const ALICE: [u8; 4] = [10, 0, 0, 1];
+const BOB: [u8; 4] = [10, 0, 0, 2];
+const ALICE_PHONE: [u8; 4] = [10, 0, 0, 3];
+const ROGUE: [u8; 4] = [10, 0, 0, 4];
+const CAROL: [u8; 4] = [10, 0, 0, 5];
+
+fn lossy_link() -> LinkConfig {
+    LinkConfig {
+        latency: Duration::from_millis(40),
+        jitter: Duration::from_millis(10),
+        loss: 0.05,
+        reorder: 0.05,
+        ..LinkConfig::PERFECT
+    }
+}
+
+/// Connects `from` to `to` and waits until both sides see the connection.
+async fn connect(from: &SimNode, to: &SimNode, port: u16) -> std::net::SocketAddr {
+    from.command(UiCommand::Connect((to.ip, port).into())).await;
+    to.connected_to(&from.id()).await;
+    from.connected_to(&to.id()).await
+}
+
+async fn receive_texts(node: &SimNode, count: usize) -> Vec<String> {
+    let mut texts = Vec::with_capacity(count);
+    while texts.len() < count {
+        texts.push(
+            node.wait_for(|e| match e {
+                UiEvent::IncomingMessage(_, _, msg) => Some(msg.text.clone()),
+                _ => None,
+            })
+            .await,
+        );
+    }
+    texts
+}
+
+#[test]
+fn messages_survive_lossy_link_and_reconnect() {
+    let mut sim = Simulation::new(1);
+    sim.network().set_link(ALICE, BOB, lossy_link());
+    let alice = sim.spawn_node(ALICE, "alice");
+    let bob = sim.spawn_node(BOB, "bob");
+    let network = sim.network().clone();
+
+    sim.run(async {
+        bob.listen(4000).await;
+        connect(&alice, &bob, 4000).await;
+        alice
+            .command(UiCommand::TrustContact(bob.id(), Trust::Trusted))
+            .await;
+        bob.command(UiCommand::TrustContact(alice.id(), Trust::Trusted))
+            .await;
+
+        let sent: Vec<String> = (0..100).map(|i| format!("message {i}")).collect();
+        for text in &sent {
+            alice
+                .command(UiCommand::SendMessage(bob.id(), alice.message(text)))
+                .await;
+        }
+        assert_eq!(receive_texts(&bob, sent.len()).await, sent);
+
+        partition(&network, &alice, &bob).await;
+
+        connect(&alice, &bob, 4000).await;
+        bob.command(UiCommand::SendMessage(
+            alice.id(),
+            bob.message("welcome back"),
+        ))
+        .await;
+        assert_eq!(receive_texts(&alice, 1).await, ["welcome back"]);
+    });
+}
+
+/// Cuts the link between the nodes, waits until both notice and restores it.
+async fn partition(network: &SimNetwork, a: &SimNode, b: &SimNode) {
+    network.partition(a.ip, b.ip);
+    for (node, peer) in [(a, b), (b, a)] {
+        node.wait_for(|e| match e {
+            UiEvent::ConnectionLost(_, id) if *id == peer.id() => Some(()),
+            _ => None,
+        })
+        .await;
+    }
+    network.heal(a.ip, b.ip);
+}
+
+#[test]
+fn connecting_across_partition_fails() {
+    let mut sim = Simulation::new(2);
+    let alice = sim.spawn_node(ALICE, "alice");
+    let bob = sim.spawn_node(BOB, "bob");
+    let network = sim.network().clone();
+
+    sim.run(async {
+        let addr = bob.listen(4000).await;
+        network.partition(ALICE, BOB);
+        alice.command(UiCommand::Connect(addr)).await;
+        alice
+            .wait_for(|e| match e {
+                UiEvent::ConnectionFailed(remote, _) if *remote == addr => Some(()),
+                _ => None,
+            })
+            .await;
+
+        network.heal(ALICE, BOB);
+        connect(&alice, &bob, 4000).await;
+    });
+}
+
+#[test]
+fn identity_update_reaches_connected_peer() {
+    let mut sim = Simulation::new(3);
+    let alice = sim.spawn_node(ALICE, "alice");
+    let bob = sim.spawn_node(BOB, "bob");
+
+    sim.run(async {
+        bob.listen(4000).await;
+        connect(&alice, &bob, 4000).await;
+
+        let mut updated = (*alice.user).clone();
+        let mut key = updated.identity_key.clone();
+        updated.identity.set_username("alice2", &mut key).unwrap();
+        alice
+            .command(UiCommand::SetIdentity(Some(updated.into())))
+            .await;
+
+        bob.wait_for(|e| match e {
+            UiEvent::SetKnownIdentities(known) => known
+                .get(&alice.id())
+                .filter(|c| c.username() == "alice2")
+                .map(|c| assert_eq!(c.version(), 1)),
+            _ => None,
+        })
+        .await;
+    });
+}
+
+#[test]
+fn same_seed_same_timing() {
+    fn scenario(seed: u64) -> Duration {
+        let mut sim = Simulation::new(seed);
+        sim.network().set_default_link(lossy_link());
+        let alice = sim.spawn_node(ALICE, "alice");
+        let bob = sim.spawn_node(BOB, "bob");
+        sim.run(async {
+            bob.listen(4000).await;
+            let start = tokio::time::Instant::now();
+            connect(&alice, &bob, 4000).await;
+            for i in 0..20 {
+                alice
+                    .command(UiCommand::SendMessage(bob.id(), alice.message(i)))
+                    .await;
+            }
+            receive_texts(&bob, 20).await;
+            start.elapsed()
+        })
+    }
+
+    assert_eq!(scenario(7), scenario(7));
+}
+
+#[test]
+fn connection_stats_measure_traffic_and_rtt() {
+    let mut sim = Simulation::new(4);
+    let latency = Duration::from_millis(50);
+    sim.network()
+        .set_default_link(LinkConfig::with_latency(latency));
+    let alice = sim.spawn_node(ALICE, "alice");
+    let bob = sim.spawn_node(BOB, "bob");
+
+    sim.run(async {
+        bob.listen(4000).await;
+        let remote = connect(&alice, &bob, 4000).await;
+        alice
+            .command(UiCommand::SendMessage(bob.id(), alice.message("hi")))
+            .await;
+        receive_texts(&bob, 1).await;
+        tokio::time::sleep(Duration::from_secs(1)).await;
+
+        alice.command(UiCommand::QueryConnectionStats).await;
+        let stats = alice
+            .wait_for(|e| match e {
+                UiEvent::ConnectionStats(stats) => Some(stats.clone()),
+                _ => None,
+            })
+            .await;
+        assert_eq!(stats.len(), 1);
+        let stats = &stats[0];
+        assert_eq!(stats.remote, remote);
+        assert_eq!(stats.contact, bob.id());
+        assert!(stats.handshake_duration >= latency * 2);
+        assert!(stats.frames_out >= 2 && stats.bytes_out > 0);
+        assert!(stats.frames_in >= 1 && stats.bytes_in > 0);
+        let rtt = stats.rtt.expect("peer has not answered a ping");
+        assert!(rtt >= latency * 2, "rtt {rtt:?} is below the link latency");
+    });
+}
+
+#[test]
+fn slow_ui_pushes_back_and_control_events_overtake() {
+    let mut sim = Simulation::new(5);
+    let alice = sim.spawn_node(ALICE, "alice");
+    let bob = sim.spawn_node(BOB, "bob");
+
+    sim.run(async {
+        bob.listen(4000).await;
+        alice.command(UiCommand::Connect((BOB, 4000).into())).await;
+        let remote = bob.connected_to(&alice.id()).await;
+        alice.connected_to(&bob.id()).await;
+
+        // bob does not look at his events while alice sends far more than fits into his queues
+        let count = 2 * UI_EVENT_BULK_CAPACITY + NET_EVENT_BULK_CAPACITY;
+        let send = async {
+            for i in 0..count {
+                alice
+                    .command(UiCommand::SendMessage(bob.id(), alice.message(i)))
+                    .await;
+            }
+        };
+        // alice herself keeps up, or her own queue would hold her back
+        let drain = async {
+            loop {
+                alice.next_event().await;
+            }
+        };
+        tokio::select! {
+            () = send => (),
+            () = drain => unreachable!(),
+        }
+        tokio::time::sleep(Duration::from_secs(30)).await;
+        let backlog = bob.pending_events();
+        assert!(
+            backlog <= UI_EVENT_BULK_CAPACITY + 8,
+            "{backlog} events are queued"
+        );
+
+        bob.command(UiCommand::Disconnect(remote)).await;
+        // messages that were already on their way may come first, the rest is overtaken
+        let mut before = 0;
+        loop {
+            match bob.next_event().await {
+                UiEvent::ConnectionLost(..) => break,
+                UiEvent::IncomingMessage(..) => before += 1,
+                other => panic!("unexpected event: {other}"),
+            }
+            // let the other tasks run, receiving from a full queue does not yield
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert!(
+            before <= 2,
+            "{before} messages came before the lost connection"
+        );
+        let rest = bob.pending_events();
+        assert!(
+            rest >= UI_EVENT_BULK_CAPACITY / 2,
+            "only {rest} events are queued"
+        );
+    });
+}
+
+#[test]
+fn outbox_delivers_messages_written_while_offline() {
+    let mut sim = Simulation::new(7);
+    let alice = sim.spawn_node(ALICE, "alice");
+    let bob = sim.spawn_node(BOB, "bob");
+
+    sim.run(async {
+        let sent: Vec<String> = (0..10).map(|i| format!("offline {i}")).collect();
+        for text in &sent {
+            alice
+                .command(UiCommand::SendMessage(bob.id(), alice.message(text)))
+                .await;
+            alice
+                .wait_for(|e| match e {
+                    UiEvent::DeliveryStateChanged(_, msg, DeliveryState::Pending)
+                        if msg.text == *text =>
+                    {
+                        Some(())
+                    }
+                    _ => None,
+                })
+                .await;
+        }
+
+        bob.listen(4000).await;
+        connect(&alice, &bob, 4000).await;
+        assert_eq!(receive_texts(&bob, sent.len()).await, sent);
+
+        let mut confirmed = 0;
+        while confirmed < sent.len() {
+            alice
+                .wait_for(|e| match e {
+                    UiEvent::DeliveryStateChanged(_, msg, DeliveryState::Sent) => {
+                        assert!(msg.flags.sent());
+                        Some(())
+                    }
+                    _ => None,
+                })
+                .await;
+            confirmed += 1;
+        }
+    });
+}
+
+#[test]
+fn outbox_survives_a_restart() {
+    let state = std::env::temp_dir().join(format!("sremp-sim-{}.state", std::process::id()));
+    let alice_user = UserIdentity::create("alice").unwrap();
+    let mut sim = Simulation::new(18);
+    let alice = sim.spawn_node_with_state(ALICE, alice_user.clone(), &state);
+    let bob = sim.spawn_node(BOB, "bob");
+
+    let bob_id = bob.id();
+    sim.run(async {
+        alice
+            .command(UiCommand::SendMessage(bob_id, alice.message("before")))
+            .await;
+        alice
+            .wait_for(|e| match e {
+                UiEvent::DeliveryStateChanged(_, _, DeliveryState::Pending) => Some(()),
+                _ => None,
+            })
+            .await;
+        alice.stop().await;
+    });
+
+    let alice = sim.spawn_node_with_state(ALICE_PHONE, alice_user, &state);
+    sim.run(async {
+        bob.listen(4000).await;
+        connect(&alice, &bob, 4000).await;
+        assert_eq!(receive_texts(&bob, 1).await, ["before"]);
+    });
+    std::fs::remove_file(&state).unwrap();
+}
+
+#[test]
+fn contact_stays_reachable_over_second_connection() {
+    let mut sim = Simulation::new(8);
+    let alice = sim.spawn_node(ALICE, "alice");
+    let bob = sim.spawn_node(BOB, "bob");
+
+    sim.run(async {
+        bob.listen(4000).await;
+        alice.listen(5000).await;
+        let first = connect(&alice, &bob, 4000).await;
+        connect(&bob, &alice, 5000).await;
+
+        alice
+            .command(UiCommand::SendMessage(bob.id(), alice.message("both")))
+            .await;
+        assert_eq!(receive_texts(&bob, 1).await, ["both"]);
+
+        alice.command(UiCommand::Disconnect(first)).await;
+        alice
+            .wait_for(|e| match e {
+                UiEvent::ConnectionLost(remote, _) if *remote == first => Some(()),
+                _ => None,
+            })
+            .await;
+
+        let sent: Vec<String> = (0..10).map(|i| format!("second {i}")).collect();
+        for text in &sent {
+            alice
+                .command(UiCommand::SendMessage(bob.id(), alice.message(text)))
+                .await;
+        }
+        assert_eq!(receive_texts(&bob, sent.len()).await, sent);
+    });
+}
+
+#[test]
+fn certified_device_connects_as_same_contact() {
+    let mut sim = Simulation::new(9);
+    let mut alice_user = UserIdentity::create("alice").unwrap();
+    let phone_user = alice_user.new_device("phone").unwrap();
+    assert_eq!(alice_user.identity, phone_user.identity);
+    // knows the identity of alice, but its noise key was never certified
+    let rogue_user = UserIdentity {
+        noise_key: UserIdentity::create("rogue").unwrap().noise_key,
+        ..alice_user.clone()
+    };
+    let alice = sim.spawn_node_with(ALICE, alice_user);
+    let phone = sim.spawn_node_with(ALICE_PHONE, phone_user);
+    let rogue = sim.spawn_node_with(ROGUE, rogue_user);
+    let bob = sim.spawn_node(BOB, "bob");
+
+    sim.run(async {
+        bob.listen(4000).await;
+        connect(&alice, &bob, 4000).await;
+        connect(&phone, &bob, 4000).await;
+        assert_eq!(phone.id(), alice.id());
+
+        phone
+            .command(UiCommand::SendMessage(
+                bob.id(),
+                phone.message("from the phone"),
+            ))
+            .await;
+        assert_eq!(receive_texts(&bob, 1).await, ["from the phone"]);
+
+        rogue
+            .command(UiCommand::Connect((bob.ip, 4000).into()))
+            .await;
+        rogue
+            .wait_for(|e| match e {
+                UiEvent::ConnectionLost(..) | UiEvent::ConnectionFailed(..) => Some(()),
+                _ => None,
+            })
+            .await;
+        let established = bob
+            .history()
+            .iter()
+            .filter(|e| matches!(e, UiEvent::ConnectionEstablished(..)))
+            .count();
+        assert_eq!(established, 2);
+    });
+}
+
+#[test]
+fn removed_device_is_refused() {
+    let mut sim = Simulation::new(17);
+    let mut alice_user = UserIdentity::create("alice").unwrap();
+    let phone_user = alice_user.new_device("phone").unwrap();
+    let phone_key = alice_user.identity.devices()[0].noise_key();
+    let alice = sim.spawn_node_with(ALICE, alice_user);
+    let phone = sim.spawn_node_with(ALICE_PHONE, phone_user);
+    let bob = sim.spawn_node(BOB, "bob");
+
+    sim.run(async {
+        bob.listen(4000).await;
+        connect(&alice, &bob, 4000).await;
+        let phone_addr = connect(&phone, &bob, 4000).await;
+        phone.command(UiCommand::Disconnect(phone_addr)).await;
+
+        let mut updated = (*alice.user).clone();
+        let mut key = updated.identity_key.clone();
+        assert!(
+            updated
+                .identity
+                .remove_device(&phone_key, &mut key)
+                .unwrap()
+        );
+        let version = updated.identity.version();
+        alice
+            .command(UiCommand::SetIdentity(Some(updated.into())))
+            .await;
+        bob.wait_for(|e| match e {
+            UiEvent::SetKnownIdentities(known) => known
+                .get(&alice.id())
+                .filter(|c| c.version() == version)
+                .map(|_| ()),
+            _ => None,
+        })
+        .await;
+
+        // the phone still presents the version of the identity that lists it
+        phone
+            .command(UiCommand::Connect((bob.ip, 4000).into()))
+            .await;
+        bob.wait_for(|e| match e {
+            UiEvent::ConnectionFailed(..) => Some(()),
+            _ => None,
+        })
+        .await;
+        phone
+            .wait_for(|e| match e {
+                UiEvent::ConnectionLost(..) | UiEvent::ConnectionFailed(..) => Some(()),
+                _ => None,
+            })
+            .await;
+        let established = bob
+            .history()
+            .iter()
+            .filter(|e| matches!(e, UiEvent::ConnectionEstablished(..)))
+            .count();
+        assert_eq!(established, 2);
+    });
+}
+
+#[test]
+fn device_with_an_older_version_of_the_identity_connects() {
+    let mut sim = Simulation::new(21);
+    let mut alice_user = UserIdentity::create("alice").unwrap();
+    let phone_user = alice_user.new_device("phone").unwrap();
+    let alice = sim.spawn_node_with(ALICE, alice_user);
+    let phone = sim.spawn_node_with(ALICE_PHONE, phone_user);
+    let bob = sim.spawn_node(BOB, "bob");
+
+    sim.run(async {
+        bob.listen(4000).await;
+        connect(&alice, &bob, 4000).await;
+
+        // the laptop renames the identity, the phone still has its own copy
+        let mut renamed = (*alice.user).clone();
+        let mut key = renamed.identity_key.clone();
+        renamed.identity.set_username("alice2", &mut key).unwrap();
+        let version = renamed.identity.version();
+        alice
+            .command(UiCommand::SetIdentity(Some(renamed.into())))
+            .await;
+        bob.wait_for(|e| match e {
+            UiEvent::SetKnownIdentities(known) => known
+                .get(&alice.id())
+                .filter(|c| c.version() == version)
+                .map(|_| ()),
+            _ => None,
+        })
+        .await;
+
+        connect(&phone, &bob, 4000).await;
+        phone
+            .command(UiCommand::SendMessage(
+                bob.id(),
+                phone.message("from the phone"),
+            ))
+            .await;
+        assert_eq!(receive_texts(&bob, 1).await, ["from the phone"]);
+        // the older copy of the phone does not undo the rename
+        let known = bob
+            .history()
+            .into_iter()
+            .rev()
+            .find_map(|e| match e {
+                UiEvent::SetKnownIdentities(known) => Some(known),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(known[&alice.id()].username(), "alice2");
+    });
+}
+
+#[test]
+fn succession_migrates_trust_and_revocation_blocks_it() {
+    let mut sim = Simulation::new(10);
+    let alice_user = UserIdentity::create("alice").unwrap();
+    let (successor_user, succession) = alice_user.create_successor().unwrap();
+    let successor_id = successor_user.id();
+    let carol_user = UserIdentity::create("carol").unwrap();
+    let revocation = carol_user.revoke("lost my laptop").unwrap();
+    let (carol_successor, carol_succession) = carol_user.create_successor().unwrap();
+    let carol_successor_id = carol_successor.id();
+    let alice = sim.spawn_node_with(ALICE, alice_user);
+    let carol = sim.spawn_node_with(CAROL, carol_user);
+    let bob = sim.spawn_node(BOB, "bob");
+
+    sim.run(async {
+        bob.listen(4000).await;
+        connect(&alice, &bob, 4000).await;
+        connect(&carol, &bob, 4000).await;
+        bob.command(UiCommand::TrustContact(alice.id(), Trust::Trusted))
+            .await;
+        bob.command(UiCommand::TrustContact(carol.id(), Trust::Trusted))
+            .await;
+
+        alice
+            .command(UiCommand::PublishStatement(
+                IdentityStatement::Succession(succession).into(),
+            ))
+            .await;
+        bob.wait_for(|e| match e {
+            UiEvent::SetKnownIdentities(known) => known.get(&successor_id).map(|successor| {
+                assert_eq!(successor.trust, Trust::Trusted);
+                assert_eq!(known[&alice.id()].trust, Trust::Revoked);
+                assert_eq!(known[&alice.id()].successor, Some(successor_id.clone()));
+            }),
+            _ => None,
+        })
+        .await;
+
+        carol
+            .command(UiCommand::PublishStatement(
+                IdentityStatement::Revocation(revocation).into(),
+            ))
+            .await;
+        carol
+            .command(UiCommand::PublishStatement(
+                IdentityStatement::Succession(carol_succession).into(),
+            ))
+            .await;
+        // the revocation is applied, the succession after it is not
+        bob.wait_for(|e| match e {
+            UiEvent::SetKnownIdentities(known) => known
+                .get(&carol.id())
+                .filter(|c| c.trust == Trust::Revoked)
+                .map(|c| assert_eq!(c.successor, None)),
+            _ => None,
+        })
+        .await;
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        while bob.pending_events() > 0 {
+            bob.next_event().await;
+        }
+        assert!(bob.history().iter().all(|e| match e {
+            UiEvent::SetKnownIdentities(known) => !known.contains_key(&carol_successor_id),
+            _ => true,
+        }));
+    });
+}
+
+async fn verification_started(node: &SimNode, peer: &SimNode) -> ShortAuthString {
+    node.wait_for(|e| match e {
+        UiEvent::VerificationStarted(id, sas) if *id == peer.id() => Some(*sas),
+        _ => None,
+    })
+    .await
+}
+
+async fn verification_finished(node: &SimNode, peer: &SimNode) -> Trust {
+    node.wait_for(|e| match e {
+        UiEvent::VerificationFinished(id, trust) if *id == peer.id() => Some(*trust),
+        _ => None,
+    })
+    .await
+}
+
+#[test]
+fn short_auth_string_verifies_contact_or_marks_it_suspicious() {
+    let mut sim = Simulation::new(11);
+    let alice = sim.spawn_node(ALICE, "alice");
+    let bob = sim.spawn_node(BOB, "bob");
+
+    sim.run(async {
+        bob.listen(4000).await;
+        connect(&alice, &bob, 4000).await;
+
+        alice.command(UiCommand::StartVerification(bob.id())).await;
+        let sas = verification_started(&alice, &bob).await;
+        assert_eq!(verification_started(&bob, &alice).await, sas);
+
+        alice
+            .command(UiCommand::ConfirmVerification(bob.id(), true))
+            .await;
+        bob.command(UiCommand::ConfirmVerification(alice.id(), true))
+            .await;
+        assert_eq!(verification_finished(&alice, &bob).await, Trust::Verified);
+        assert_eq!(verification_finished(&bob, &alice).await, Trust::Verified);
+
+        // one mismatch is enough, the other side does not need to answer
+        bob.command(UiCommand::StartVerification(alice.id())).await;
+        verification_started(&alice, &bob).await;
+        alice
+            .command(UiCommand::ConfirmVerification(bob.id(), false))
+            .await;
+        assert_eq!(verification_finished(&alice, &bob).await, Trust::Suspicious);
+        assert_eq!(verification_finished(&bob, &alice).await, Trust::Suspicious);
+    });
+}
+
+#[test]
+fn invitation_pins_identity_and_tries_endpoints_in_order() {
+    let mut sim = Simulation::new(12);
+    let alice = sim.spawn_node(ALICE, "alice");
+    let bob = sim.spawn_node(BOB, "bob");
+    let carol = sim.spawn_node(CAROL, "carol");
+
+    sim.run(async {
+        let bob_addr = bob.listen(4000).await;
+        let carol_addr = carol.listen(4000).await;
+        let nobody = std::net::SocketAddr::from((ROGUE, 4000));
+
+        // the first endpoint does not answer, the second is bob
+        let invitation =
+            Invitation::create(&bob.user, vec![nobody, bob_addr], TimeDelta::minutes(10)).unwrap();
+        let invitation = Invitation::from_uri(&invitation.to_uri().unwrap()).unwrap();
+        alice
+            .command(UiCommand::AcceptInvitation(invitation.into()))
+            .await;
+        assert_eq!(alice.connected_to(&bob.id()).await, bob_addr);
+
+        // carol answers on an endpoint of the invitation, but she is not bob
+        let invitation =
+            Invitation::create(&bob.user, vec![carol_addr], TimeDelta::minutes(10)).unwrap();
+        alice
+            .command(UiCommand::AcceptInvitation(invitation.into()))
+            .await;
+        alice
+            .wait_for(|e| match e {
+                UiEvent::ConnectionFailed(remote, _) if *remote == carol_addr => Some(()),
+                UiEvent::ConnectionEstablished(_, id, _) if *id == carol.id() => {
+                    panic!("connected to carol with an invitation of bob")
+                }
+                _ => None,
+            })
+            .await;
+    });
+}
+
+async fn pairing_finished(node: &SimNode, peer: &SimNode) -> bool {
+    node.wait_for(|e| match e {
+        UiEvent::PairingFinished(id, paired) if *id == peer.id() => Some(*paired),
+        _ => None,
+    })
+    .await
+}
+
+#[test]
+fn pairing_code_verifies_contact_only_if_both_entered_it() {
+    let mut sim = Simulation::new(13);
+    let alice = sim.spawn_node(ALICE, "alice");
+    let bob = sim.spawn_node(BOB, "bob");
+
+    sim.run(async {
+        bob.listen(4000).await;
+        connect(&alice, &bob, 4000).await;
+
+        let code = PairingCode::generate();
+        alice
+            .command(UiCommand::StartPairing(bob.id(), code.clone()))
+            .await;
+        bob.wait_for(|e| {
+            matches!(e, UiEvent::PairingRequested(id) if *id == alice.id()).then_some(())
+        })
+        .await;
+        // the code is read out loud, so case and separators may differ
+        let spoken = code.to_string().to_uppercase().replace('-', " ");
+        bob.command(UiCommand::StartPairing(alice.id(), spoken.parse().unwrap()))
+            .await;
+        assert!(pairing_finished(&alice, &bob).await);
+        assert!(pairing_finished(&bob, &alice).await);
+
+        // a typo fails on both sides
+        alice
+            .command(UiCommand::StartPairing(
+                bob.id(),
+                "1-abandon-ability".parse().unwrap(),
+            ))
+            .await;
+        bob.wait_for(|e| {
+            matches!(e, UiEvent::PairingRequested(id) if *id == alice.id()).then_some(())
+        })
+        .await;
+        bob.command(UiCommand::StartPairing(
+            alice.id(),
+            "1-abandon-able".parse().unwrap(),
+        ))
+        .await;
+        assert!(!pairing_finished(&alice, &bob).await);
+        assert!(!pairing_finished(&bob, &alice).await);
+    });
+}
+
+async fn peer_status(node: &SimNode, peer: &SimNode) -> PeerStatus {
+    node.wait_for(|e| match e {
+        UiEvent::ConnectionEstablished(_, id, status) if *id == peer.id() => Some(status.clone()),
+        _ => None,
+    })
+    .await
+}
+
+#[test]
+fn connections_are_classified_for_trust_on_first_use() {
+    let mut sim = Simulation::new(14);
+    let alice = sim.spawn_node(ALICE, "alice");
+    let bob = sim.spawn_node(BOB, "bob");
+    let mallory = sim.spawn_node(ROGUE, "B0b");
+
+    sim.run(async {
+        alice.listen(4000).await;
+        bob.command(UiCommand::Connect((alice.ip, 4000).into()))
+            .await;
+        assert_eq!(peer_status(&alice, &bob).await, PeerStatus::New);
+        let remote = bob.connected_to(&alice.id()).await;
+        alice
+            .command(UiCommand::TrustContact(bob.id(), Trust::Trusted))
+            .await;
+        bob.command(UiCommand::Disconnect(remote)).await;
+        alice
+            .wait_for(|e| {
+                matches!(e, UiEvent::ConnectionLost(_, id) if *id == bob.id()).then_some(())
+            })
+            .await;
+
+        bob.command(UiCommand::Connect((alice.ip, 4000).into()))
+            .await;
+        assert_eq!(peer_status(&alice, &bob).await, PeerStatus::Trusted);
+
+        mallory
+            .command(UiCommand::Connect((alice.ip, 4000).into()))
+            .await;
+        assert_eq!(
+            peer_status(&alice, &mallory).await,
+            PeerStatus::KeyChanged {
+                address_of: None,
+                name_of: vec![bob.id()],
+            }
+        );
+        alice
+            .command(UiCommand::TrustContact(mallory.id(), Trust::Rejected))
+            .await;
+        mallory
+            .command(UiCommand::Connect((alice.ip, 4000).into()))
+            .await;
+        assert_eq!(peer_status(&alice, &mallory).await, PeerStatus::Rejected);
+    });
+}
+
+#[test]
+fn endorsement_is_shown_to_contacts_of_the_endorser() {
+    let mut sim = Simulation::new(15);
+    let alice = sim.spawn_node(ALICE, "alice");
+    let bob = sim.spawn_node(BOB, "bob");
+    let carol = sim.spawn_node(CAROL, "carol");
+
+    sim.run(async {
+        bob.listen(4000).await;
+        carol.listen(4000).await;
+        connect(&alice, &bob, 4000).await;
+        connect(&alice, &carol, 4000).await;
+        carol
+            .command(UiCommand::TrustContact(alice.id(), Trust::Trusted))
+            .await;
+
+        alice.command(UiCommand::Endorse(bob.id(), true)).await;
+        // bob presents the endorsement to carol, who has not met him yet
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        connect(&bob, &carol, 4000).await;
+        carol
+            .wait_for(|e| match e {
+                UiEvent::SetKnownIdentities(known) => known
+                    .vouched_for_by(&bob.id())
+                    .iter()
+                    .any(|endorser| endorser.id() == alice.id())
+                    .then_some(()),
+                _ => None,
+            })
+            .await;
+
+        alice.command(UiCommand::Endorse(bob.id(), false)).await;
+        carol
+            .wait_for(|e| match e {
+                UiEvent::SetKnownIdentities(known) => {
+                    known.vouched_for_by(&bob.id()).is_empty().then_some(())
+                }
+                _ => None,
+            })
+            .await;
+    });
+}
+
+#[test]
+fn malformed_message_does_not_stop_the_client() {
+    let mut sim = Simulation::new(19);
+    let alice = sim.spawn_node(ALICE, "alice");
+    let bob = sim.spawn_node(BOB, "bob");
+    let rogue = sim.spawn_peer(ROGUE, "rogue");
+
+    sim.run(async {
+        let addr = alice.listen(4000).await;
+        // not MessagePack at all
+        rogue.connect(addr).await;
+        alice.connected_to(&rogue.id()).await;
+        rogue
+            .command(NetworkCommand::SendToContact(
+                alice.id(),
+                Envelope::ChatMessage(vec![0xc1, 0xff, 0x00].into()).into(),
+            ))
+            .await;
+        rogue
+            .wait_for(|e| match e {
+                NetworkEvent::EnvelopeSent(..) => Some(()),
+                _ => None,
+            })
+            .await;
+
+        connect(&bob, &alice, 4000).await;
+        bob.command(UiCommand::SendMessage(
+            alice.id(),
+            bob.message("still there?"),
+        ))
+        .await;
+        assert_eq!(receive_texts(&alice, 1).await, ["still there?"]);
+    });
+}
+
+#[test]
+fn invalid_statement_is_reported_back() {
+    let mut sim = Simulation::new(20);
+    let alice_user = UserIdentity::create("alice").unwrap();
+    let mut key = alice_user.identity_key.clone();
+    // an identity can not succeed itself
+    let statement = IdentityStatement::Succession(
+        Succession::create(&mut key.clone(), alice_user.identity.clone(), &mut key).unwrap(),
+    );
+    let alice = sim.spawn_node_with(ALICE, alice_user);
+    let bob = sim.spawn_node(BOB, "bob");
+
+    sim.run(async {
+        alice.listen(4000).await;
+        alice
+            .command(UiCommand::PublishStatement(statement.into()))
+            .await;
+        alice
+            .wait_for(|e| match e {
+                UiEvent::StatementRejected(..) => Some(()),
+                _ => None,
+            })
+            .await;
+
+        connect(&bob, &alice, 4000).await;
+        bob.command(UiCommand::SendMessage(
+            alice.id(),
+            bob.message("still there?"),
+        ))
+        .await;
+        assert_eq!(receive_texts(&alice, 1).await, ["still there?"]);
+    });
+}